@@ -0,0 +1,739 @@
+//! Anomaly detection: the latest window of executions of every project, and
+//! of every agent and model within it, against a rolling baseline of the
+//! windows before it; and the health of every workflow that ran in it.
+//!
+//! Only the window is read execution by execution. Baselines come from the
+//! hourly rollups, plus the partial hours at their edges, and satisfaction
+//! from the feedback given, so a check costs the same however busy the
+//! baseline days were.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use super::queries::percentile;
+use super::query_builder::QueryBuilder;
+use super::rollups::{Durations, Plan, ROLLUP_SECS};
+use super::significance::two_proportion_test;
+use super::types::HealthStatus;
+use crate::storage::{AlertMetric, AlertScope, Connection, feedback_sql, params};
+
+/// Fewest rated executions on each side for a satisfaction comparison
+const MIN_RATED: i64 = 5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalySettings {
+    /// Length of the window checked, and of each baseline window for cost
+    pub window_secs: i64,
+    /// History before the window the baseline is drawn from
+    pub baseline_secs: i64,
+    /// Fewest executions in both the window and the baseline for a group
+    /// to be checked
+    pub min_requests: i64,
+    /// Standard deviations (cost) or z-score (error rate, satisfaction) a
+    /// window must be off by
+    pub z_threshold: f64,
+    /// Smallest relative increase of cost and p95 latency, in percent
+    pub min_change_pct: f64,
+}
+
+/// One execution as the detector sees it
+#[derive(Debug, Clone)]
+pub struct ExecutionSample {
+    pub project: String,
+    pub agent: Option<String>,
+    pub model: Option<String>,
+    pub created_at: i64,
+    pub cost_usd: f64,
+    pub duration_ms: Option<i64>,
+    pub success: bool,
+    /// Requester's thumbs up or down, if they gave exactly one
+    pub satisfied: Option<bool>,
+}
+
+/// A metric of a project, agent, model or workflow outside its baseline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub project: String,
+    pub scope: AlertScope,
+    pub target: Option<String>,
+    pub metric: AlertMetric,
+    pub baseline: f64,
+    pub value: f64,
+}
+
+pub fn query_execution_samples(
+    conn: &dyn Connection,
+    since: i64,
+    until: i64,
+) -> Result<Vec<ExecutionSample>> {
+    let sql = format!(
+        "SELECT e.project, e.agent, e.model, e.created_at, COALESCE(e.cost_usd, 0), e.duration_ms,
+                CASE WHEN e.response != '' THEN 1 ELSE 0 END, {}, {}
+         FROM executions e {}
+         WHERE e.created_at >= ?1 AND e.created_at < ?2
+         GROUP BY e.id, e.project, e.agent, e.model, e.created_at, e.cost_usd, e.duration_ms, e.response",
+        feedback_sql::POSITIVE_DISTINCT,
+        feedback_sql::NEGATIVE_DISTINCT,
+        feedback_sql::FEEDBACK_JOIN_VERIFIED,
+    );
+    let samples = conn
+        .query_map(&sql, params![since, until], |row| {
+            let success: i64 = row.get(6)?;
+            let (positive, negative): (i64, i64) = (row.get(7)?, row.get(8)?);
+            Ok(ExecutionSample {
+                project: row.get(0)?,
+                agent: row.get(1)?,
+                model: row.get(2)?,
+                created_at: row.get(3)?,
+                cost_usd: row.get(4)?,
+                duration_ms: row.get(5)?,
+                success: success == 1,
+                satisfied: match (positive > 0, negative > 0) {
+                    (true, false) => Some(true),
+                    (false, true) => Some(false),
+                    _ => None,
+                },
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(samples)
+}
+
+/// Metrics that left their baseline in the window ending at `now`.
+pub fn detect_anomalies(
+    conn: &dyn Connection,
+    now: i64,
+    settings: &AnomalySettings,
+) -> Result<Vec<Anomaly>> {
+    let window_start = now - settings.window_secs;
+    let since = window_start - settings.baseline_secs;
+    let samples = query_execution_samples(conn, window_start, now)?;
+    let baselines = query_baselines(conn, since, window_start, settings)?;
+    let mut anomalies = find_anomalies(&samples, &baselines, settings);
+    anomalies.extend(find_unhealthy_workflows(
+        conn,
+        since,
+        window_start,
+        now,
+        settings,
+    )?);
+    Ok(anomalies)
+}
+
+/// Workflows whose runs in the window leave them degraded or critical, with
+/// their success rate before and during the window. Workflow runs belong to
+/// no project.
+fn find_unhealthy_workflows(
+    conn: &dyn Connection,
+    since: i64,
+    window_start: i64,
+    now: i64,
+    settings: &AnomalySettings,
+) -> Result<Vec<Anomaly>> {
+    let rows = conn
+        .query_map(
+            "SELECT workflow_name, CASE WHEN created_at >= ?2 THEN 1 ELSE 0 END, COUNT(*),
+                    SUM(CASE WHEN status = 'success' THEN 1 ELSE 0 END)
+             FROM workflow_executions
+             WHERE created_at >= ?1 AND created_at < ?3
+             GROUP BY 1, 2 ORDER BY 1",
+            params![since, window_start, now],
+            |row| {
+                let in_window: i64 = row.get(1)?;
+                Ok((
+                    row.get::<String>(0)?,
+                    in_window == 1,
+                    (row.get::<i64>(3)?, row.get::<i64>(2)?),
+                ))
+            },
+        )?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut runs: HashMap<&str, [(i64, i64); 2]> = HashMap::new();
+    for (name, in_window, counts) in &rows {
+        runs.entry(name).or_default()[*in_window as usize] = *counts;
+    }
+    let mut anomalies: Vec<Anomaly> = runs
+        .into_iter()
+        .filter(|(_, [_, window])| window.1 >= settings.min_requests)
+        .filter(|(_, [_, window])| {
+            HealthStatus::from_success_rate(rate(*window)) != HealthStatus::Healthy
+        })
+        .map(|(name, [before, window])| Anomaly {
+            project: String::new(),
+            scope: AlertScope::Workflow,
+            target: Some(name.to_string()),
+            metric: AlertMetric::Health,
+            // A workflow with no earlier runs had nothing to fall from
+            baseline: if before.1 > 0 { rate(before) } else { 100.0 },
+            value: rate(window),
+        })
+        .collect();
+    anomalies.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(anomalies)
+}
+
+type GroupKey = (String, AlertScope, Option<String>);
+
+/// What a group did over the baseline
+#[derive(Debug, Default, Clone)]
+pub(super) struct Baseline {
+    /// Spend of each baseline window as long as the checked one, the most
+    /// recent first
+    spend: Vec<f64>,
+    requests: i64,
+    failures: i64,
+    durations: Durations,
+    /// Executions their requester rated, and how many of those satisfied
+    rated: i64,
+    satisfied: i64,
+}
+
+/// Baselines of every project, and of every agent and model in it, over
+/// the baseline windows ending at `until`
+pub(super) struct Baselines {
+    groups: HashMap<GroupKey, Baseline>,
+    until: i64,
+    window_secs: i64,
+    windows: usize,
+}
+
+impl Baselines {
+    fn new(until: i64, settings: &AnomalySettings) -> Self {
+        Self {
+            groups: HashMap::new(),
+            until,
+            window_secs: settings.window_secs,
+            windows: (settings.baseline_secs / settings.window_secs).max(1) as usize,
+        }
+    }
+
+    /// Applies `f` to the project's baseline and to its agent's and model's.
+    fn add(
+        &mut self,
+        project: &str,
+        agent: Option<&str>,
+        model: Option<&str>,
+        f: impl Fn(&mut Baseline),
+    ) {
+        let keys = [
+            Some((project.to_string(), AlertScope::Project, None)),
+            agent
+                .filter(|a| !a.is_empty())
+                .map(|a| (project.to_string(), AlertScope::Agent, Some(a.to_string()))),
+            model
+                .filter(|m| !m.is_empty())
+                .map(|m| (project.to_string(), AlertScope::Model, Some(m.to_string()))),
+        ];
+        for key in keys.into_iter().flatten() {
+            let windows = self.windows;
+            let baseline = self.groups.entry(key).or_insert_with(|| Baseline {
+                spend: vec![0.0; windows],
+                ..Baseline::default()
+            });
+            f(baseline);
+        }
+    }
+
+    /// Spend of `[start, end)` shared among the windows it overlaps, in
+    /// proportion to the overlap; a single execution has `start == end`.
+    fn spend_shares(&self, start: i64, end: i64, cost: f64) -> Vec<(usize, f64)> {
+        let window = |ts: i64| ((self.until - 1 - ts) / self.window_secs) as usize;
+        if end <= start {
+            return vec![(window(start), cost)];
+        }
+        (window(end - 1)..=window(start))
+            .filter(|&i| i < self.windows)
+            .map(|i| {
+                let to = self.until - i as i64 * self.window_secs;
+                let from = to - self.window_secs;
+                let overlap = end.min(to) - start.max(from);
+                (i, cost * overlap as f64 / (end - start) as f64)
+            })
+            .collect()
+    }
+
+    fn add_sample(&mut self, sample: &ExecutionSample) {
+        let shares = self.spend_shares(sample.created_at, sample.created_at, sample.cost_usd);
+        self.add(
+            &sample.project,
+            sample.agent.as_deref(),
+            sample.model.as_deref(),
+            |b| {
+                for &(i, cost) in &shares {
+                    if let Some(spend) = b.spend.get_mut(i) {
+                        *spend += cost;
+                    }
+                }
+                b.requests += 1;
+                b.failures += !sample.success as i64;
+                if let Some(duration) = sample.duration_ms {
+                    b.durations.add(duration);
+                }
+                if let Some(satisfied) = sample.satisfied {
+                    b.rated += 1;
+                    b.satisfied += satisfied as i64;
+                }
+            },
+        );
+    }
+}
+
+/// Baselines of `[since, until)`: whole hours from the rollups, the partial
+/// hours at either end from `executions`, and satisfaction from the
+/// feedback requesters gave.
+fn query_baselines(
+    conn: &dyn Connection,
+    since: i64,
+    until: i64,
+    settings: &AnomalySettings,
+) -> Result<Baselines> {
+    let mut baselines = Baselines::new(until, settings);
+    let plan = Plan::between(since, until);
+
+    if let Some((first, last)) = plan.rollups {
+        let qb = QueryBuilder::new(conn.dialect()).buckets(first, last);
+        let sql = format!(
+            "SELECT bucket_start, project, agent, model, SUM(requests), SUM(successful), SUM(cost_usd)
+             FROM execution_rollups WHERE {}
+             GROUP BY bucket_start, project, agent, model",
+            qb.where_clause()
+        );
+        for row in conn.query_map(&sql, qb.params(), |row| {
+            Ok((
+                row.get::<i64>(0)?,
+                row.get::<String>(1)?,
+                row.get::<String>(2)?,
+                row.get::<String>(3)?,
+                row.get::<i64>(4)?,
+                row.get::<i64>(5)?,
+                row.get::<f64>(6)?,
+            ))
+        })? {
+            let (bucket, project, agent, model, requests, successful, cost) = row?;
+            let shares = baselines.spend_shares(bucket, bucket + ROLLUP_SECS, cost);
+            baselines.add(&project, Some(&agent), Some(&model), |b| {
+                for &(i, cost) in &shares {
+                    b.spend[i] += cost;
+                }
+                b.requests += requests;
+                b.failures += requests - successful;
+            });
+        }
+
+        let sql = format!(
+            "SELECT project, agent, model, bin, SUM(hits) FROM execution_rollup_latency
+             WHERE {} GROUP BY project, agent, model, bin",
+            qb.where_clause()
+        );
+        for row in conn.query_map(&sql, qb.params(), |row| {
+            Ok((
+                row.get::<String>(0)?,
+                row.get::<String>(1)?,
+                row.get::<String>(2)?,
+                row.get::<i64>(3)?,
+                row.get::<i64>(4)?,
+            ))
+        })? {
+            let (project, agent, model, bin, hits) = row?;
+            baselines.add(&project, Some(&agent), Some(&model), |b| {
+                b.durations.add_bin(bin, hits)
+            });
+        }
+    }
+
+    for (start, end) in plan.raw {
+        let qb = QueryBuilder::new(conn.dialect()).between(start, end);
+        let sql = format!(
+            "SELECT project, agent, model, created_at, COALESCE(cost_usd, 0), duration_ms,
+                    CASE WHEN response != '' THEN 1 ELSE 0 END
+             FROM executions WHERE {}",
+            qb.where_clause()
+        );
+        for row in conn.query_map(&sql, qb.params(), |row| {
+            let success: i64 = row.get(6)?;
+            Ok(ExecutionSample {
+                project: row.get(0)?,
+                agent: row.get(1)?,
+                model: row.get(2)?,
+                created_at: row.get(3)?,
+                cost_usd: row.get(4)?,
+                duration_ms: row.get(5)?,
+                success: success == 1,
+                // Counted from the feedback below
+                satisfied: None,
+            })
+        })? {
+            baselines.add_sample(&row?);
+        }
+    }
+
+    // Starts from the feedback, which can't predate the execution it rates
+    let sql = format!(
+        "SELECT e.project, e.agent, e.model, {}, {}
+         FROM reactions r JOIN executions e ON e.id = r.execution_id AND r.user_id = e.requester
+         WHERE r.category = 'feedback' AND r.created_at >= ?1
+           AND e.created_at >= ?1 AND e.created_at < ?2
+         GROUP BY e.id, e.project, e.agent, e.model",
+        feedback_sql::POSITIVE_DISTINCT,
+        feedback_sql::NEGATIVE_DISTINCT,
+    );
+    for row in conn.query_map(&sql, params![since, until], |row| {
+        Ok((
+            row.get::<String>(0)?,
+            row.get::<Option<String>>(1)?,
+            row.get::<Option<String>>(2)?,
+            row.get::<i64>(3)?,
+            row.get::<i64>(4)?,
+        ))
+    })? {
+        let (project, agent, model, positive, negative) = row?;
+        let satisfied = match (positive > 0, negative > 0) {
+            (true, false) => true,
+            (false, true) => false,
+            _ => continue,
+        };
+        baselines.add(&project, agent.as_deref(), model.as_deref(), |b| {
+            b.rated += 1;
+            b.satisfied += satisfied as i64;
+        });
+    }
+    Ok(baselines)
+}
+
+/// Metrics of the window's groups outside their baselines.
+fn find_anomalies(
+    samples: &[ExecutionSample],
+    baselines: &Baselines,
+    settings: &AnomalySettings,
+) -> Vec<Anomaly> {
+    let mut windows: HashMap<GroupKey, Vec<&ExecutionSample>> = HashMap::new();
+    for sample in samples {
+        let keys = [
+            Some((sample.project.clone(), AlertScope::Project, None)),
+            sample
+                .agent
+                .clone()
+                .filter(|a| !a.is_empty())
+                .map(|a| (sample.project.clone(), AlertScope::Agent, Some(a))),
+            sample
+                .model
+                .clone()
+                .filter(|m| !m.is_empty())
+                .map(|m| (sample.project.clone(), AlertScope::Model, Some(m))),
+        ];
+        for key in keys.into_iter().flatten() {
+            windows.entry(key).or_default().push(sample);
+        }
+    }
+
+    let empty = Baseline::default();
+    let mut anomalies: Vec<Anomaly> = windows
+        .into_iter()
+        .flat_map(|((project, scope, target), window)| {
+            let baseline = baselines
+                .groups
+                .get(&(project.clone(), scope, target.clone()));
+            check_group(baseline.unwrap_or(&empty), &window, settings)
+                .into_iter()
+                .map(move |(metric, baseline, value)| Anomaly {
+                    project: project.clone(),
+                    scope,
+                    target: target.clone(),
+                    metric,
+                    baseline,
+                    value,
+                })
+        })
+        .collect();
+    anomalies.sort_by(|a, b| {
+        (&a.project, a.scope.as_str(), &a.target, a.metric.as_str()).cmp(&(
+            &b.project,
+            b.scope.as_str(),
+            &b.target,
+            b.metric.as_str(),
+        ))
+    });
+    anomalies
+}
+
+/// Metrics of one group outside the baseline, with the baseline and
+/// window values.
+fn check_group(
+    base: &Baseline,
+    window: &[&ExecutionSample],
+    settings: &AnomalySettings,
+) -> Vec<(AlertMetric, f64, f64)> {
+    if base.requests < settings.min_requests || (window.len() as i64) < settings.min_requests {
+        return Vec::new();
+    }
+    let increased =
+        |baseline: f64, value: f64| value > baseline * (1.0 + settings.min_change_pct / 100.0);
+    let mut found = Vec::new();
+
+    // Cost of the window against the spend of each baseline window of the
+    // same length, idle ones included
+    let windows = base.spend.len().max(1) as f64;
+    let mean = base.spend.iter().sum::<f64>() / windows;
+    let std_dev = (base.spend.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / windows).sqrt();
+    let cost: f64 = window.iter().map(|s| s.cost_usd).sum();
+    if cost > mean + settings.z_threshold * std_dev && increased(mean, cost) {
+        found.push((AlertMetric::Cost, mean, cost));
+    }
+
+    let mut durations: Vec<i64> = window.iter().filter_map(|s| s.duration_ms).collect();
+    durations.sort_unstable();
+    if let (Some(before), Some(now)) = (
+        base.durations.clone().percentile(95),
+        percentile(&durations, 95),
+    ) && increased(before as f64, now as f64)
+    {
+        found.push((AlertMetric::Latency, before as f64, now as f64));
+    }
+
+    let before = (base.failures, base.requests);
+    let now = (
+        window.iter().filter(|s| !s.success).count() as i64,
+        window.len() as i64,
+    );
+    if two_proportion_test(before, now).is_some_and(|t| t.z >= settings.z_threshold) {
+        found.push((AlertMetric::ErrorRate, rate(before), rate(now)));
+    }
+
+    let rated: Vec<bool> = window.iter().filter_map(|s| s.satisfied).collect();
+    let before = (base.satisfied, base.rated);
+    let now = (
+        rated.iter().filter(|&&s| s).count() as i64,
+        rated.len() as i64,
+    );
+    if before.1 >= MIN_RATED
+        && now.1 >= MIN_RATED
+        && two_proportion_test(before, now).is_some_and(|t| t.z <= -settings.z_threshold)
+    {
+        found.push((AlertMetric::Satisfaction, rate(before), rate(now)));
+    }
+    found
+}
+
+fn rate((hits, total): (i64, i64)) -> f64 {
+    if total > 0 {
+        hits as f64 / total as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Execution, Storage};
+
+    const HOUR: i64 = 3600;
+
+    fn sample(
+        agent: &str,
+        created_at: i64,
+        cost_usd: f64,
+        duration_ms: i64,
+        success: bool,
+    ) -> ExecutionSample {
+        ExecutionSample {
+            project: "demo".into(),
+            agent: Some(agent.into()),
+            model: Some("sonnet".into()),
+            created_at,
+            cost_usd,
+            duration_ms: Some(duration_ms),
+            success,
+            satisfied: None,
+        }
+    }
+
+    #[test]
+    fn test_flags_metrics_outside_baseline() {
+        let settings = AnomalySettings {
+            window_secs: HOUR,
+            baseline_secs: 24 * HOUR,
+            min_requests: 5,
+            z_threshold: 3.0,
+            min_change_pct: 50.0,
+        };
+        let window_start = 100 * HOUR;
+        let mut samples = Vec::new();
+        // Both agents run steadily for a day: 5 requests an hour at $0.10
+        // and 1s, a thumbs up on each and one failure every other hour
+        for hour in 1..=24 {
+            for i in 0..5 {
+                for agent in ["ops", "support"] {
+                    let mut s = sample(
+                        agent,
+                        window_start - hour * HOUR + i * 60,
+                        0.1,
+                        1000,
+                        !(hour % 2 == 0 && i == 0),
+                    );
+                    s.satisfied = Some(true);
+                    samples.push(s);
+                }
+            }
+        }
+        // support stays the same; ops costs ten times as much, takes five
+        // times as long, fails half the time and gets thumbs down
+        for i in 0..5 {
+            samples.push(sample("support", window_start + i * 60, 0.1, 1000, true));
+        }
+        for i in 0..12 {
+            let mut s = sample("ops", window_start + i * 60, 1.0, 5000, i % 2 == 0);
+            s.satisfied = Some(false);
+            samples.push(s);
+        }
+
+        let (before, window): (Vec<_>, Vec<_>) = samples
+            .into_iter()
+            .partition(|s| s.created_at < window_start);
+        let mut baselines = Baselines::new(window_start, &settings);
+        for s in &before {
+            baselines.add_sample(s);
+        }
+
+        let anomalies = find_anomalies(&window, &baselines, &settings);
+        let ops: Vec<_> = anomalies
+            .iter()
+            .filter(|a| a.scope == AlertScope::Agent && a.target.as_deref() == Some("ops"))
+            .map(|a| a.metric)
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                AlertMetric::Cost,
+                AlertMetric::ErrorRate,
+                AlertMetric::Latency,
+                AlertMetric::Satisfaction
+            ]
+        );
+        let cost = anomalies
+            .iter()
+            .find(|a| a.scope == AlertScope::Agent && a.metric == AlertMetric::Cost)
+            .unwrap();
+        assert!((cost.baseline - 0.5).abs() < 1e-9);
+        assert!((cost.value - 12.0).abs() < 1e-9);
+        assert!(
+            !anomalies
+                .iter()
+                .any(|a| a.target.as_deref() == Some("support"))
+        );
+        // ops drags the project and the model up with it
+        assert!(
+            anomalies
+                .iter()
+                .any(|a| a.scope == AlertScope::Project && a.metric == AlertMetric::Cost)
+        );
+        assert!(
+            anomalies
+                .iter()
+                .any(|a| a.scope == AlertScope::Model && a.metric == AlertMetric::Cost)
+        );
+
+        // Too few executions in the window to judge
+        let quiet: Vec<_> = window
+            .into_iter()
+            .filter(|s| s.created_at < window_start + 2 * 60)
+            .collect();
+        assert!(find_anomalies(&quiet, &baselines, &settings).is_empty());
+    }
+
+    #[test]
+    fn test_baselines_from_rollups_match_raw_executions() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("anomaly.db")).unwrap();
+        let settings = AnomalySettings {
+            window_secs: HOUR,
+            baseline_secs: 3 * 24 * HOUR,
+            min_requests: 5,
+            z_threshold: 3.0,
+            min_change_pct: 50.0,
+        };
+        // Neither end of the baseline falls on an hour
+        let until = 1_750_001_234;
+        let since = until - settings.baseline_secs;
+        for i in 0..700 {
+            storage
+                .save(&Execution {
+                    id: format!("e{}", i),
+                    project: "demo".into(),
+                    source: None,
+                    requester: Some("alice".into()),
+                    agent: Some(["ops", "support", ""][i as usize % 3].into()),
+                    instruction: None,
+                    user_message: String::new(),
+                    user_context: None,
+                    response: if i % 9 == 0 {
+                        String::new()
+                    } else {
+                        "ok".into()
+                    },
+                    structured_output: None,
+                    model: Some("sonnet".into()),
+                    cost_usd: Some(0.01 * (i % 5) as f64),
+                    input_tokens: None,
+                    output_tokens: None,
+                    cache_read_tokens: None,
+                    cache_creation_tokens: None,
+                    duration_ms: (i % 13 != 0).then_some(40 + i * 7919 % 30_000),
+                    duration_api_ms: None,
+                    session_id: None,
+                    metadata: None,
+                    agent_revision: None,
+                    allowed_tools: None,
+                    disallowed_tools: None,
+                    rerun_of: None,
+                    experiment_id: None,
+                    variant: None,
+                    run_id: None,
+                    created_at: since - 3_000 + i * 401,
+                })
+                .unwrap();
+            if i % 4 == 0 {
+                let reaction = if i % 3 == 0 { "-1" } else { "+1" };
+                storage
+                    .upsert_reaction(&format!("e{}", i), "alice", reaction)
+                    .unwrap();
+            }
+        }
+
+        storage
+            .with_connection(|conn| {
+                let rolled = query_baselines(conn, since, until, &settings)?;
+                let mut raw = Baselines::new(until, &settings);
+                for s in query_execution_samples(conn, since, until)? {
+                    raw.add_sample(&s);
+                }
+                assert_eq!(rolled.groups.len(), 4);
+                assert_eq!(raw.groups.len(), 4);
+                for (key, raw) in &raw.groups {
+                    let rolled = &rolled.groups[key];
+                    assert!(raw.requests > 0);
+                    assert!(raw.rated > 0);
+                    assert_eq!(
+                        (
+                            rolled.requests,
+                            rolled.failures,
+                            rolled.rated,
+                            rolled.satisfied
+                        ),
+                        (raw.requests, raw.failures, raw.rated, raw.satisfied)
+                    );
+                    // Hours are spread over the windows they overlap, so
+                    // only the total is exact
+                    let spend = |b: &Baseline| b.spend.iter().sum::<f64>();
+                    assert!((spend(rolled) - spend(raw)).abs() < 1e-9);
+                    let p95 = |b: &Baseline| b.durations.clone().percentile(95).unwrap();
+                    let (rolled, raw) = (p95(rolled), p95(raw));
+                    assert!(((rolled - raw).abs() as f64) <= raw as f64 * 0.01 + 1.0);
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -1,6 +1,15 @@
+mod anomaly;
 mod queries;
 mod query_builder;
+mod range;
+mod rollups;
+mod significance;
 mod types;
+mod workflows;
 
+pub use anomaly::*;
 pub use queries::*;
+pub use range::*;
+pub use rollups::*;
 pub use types::*;
+pub use workflows::*;
@@ -1,25 +1,26 @@
+use std::collections::{BTreeMap, HashMap};
+
 use anyhow::Result;
 use chrono::{TimeZone, Utc};
-use rusqlite::{Connection, params};
 
 use super::query_builder::QueryBuilder;
+use super::range::{TimeRange, bucket_start};
+use super::rollups::{Plan, query_durations, query_totals};
+use super::significance::{Sample, mean_difference_test, two_proportion_test};
 use super::types::*;
-use crate::storage::feedback_sql;
+use crate::storage::{Connection, Experiment, ExperimentVariant, Value, feedback_sql, params};
 
 pub fn get_overview_stats(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<OverviewStats> {
     let now = Utc::now().timestamp();
-    let (period_start, period_end) = calculate_period_bounds(now, period);
-    let (prev_start, prev_end) = calculate_previous_period_bounds(period_start, period_end);
-
-    let current = query_period_stats(conn, period_start, period_end, project, source)?;
-    let previous = query_period_stats(conn, prev_start, prev_end, project, source)?;
-    let percentiles = query_duration_percentiles(conn, period_start, period_end, project, source)?;
-    let pending_feedback = query_pending_feedback(conn, period_start, period_end, project, source)?;
+    let current = query_period_stats(conn, range, project, source)?;
+    let previous = query_period_stats(conn, &range.previous(now), project, source)?;
+    let percentiles = query_duration_percentiles(conn, range, project, source)?;
+    let pending_feedback = query_pending_feedback(conn, range, project, source)?;
 
     let summary = SummaryStats {
         total_requests: current.total_requests,
@@ -55,9 +56,9 @@ pub fn get_overview_stats(
     };
 
     Ok(OverviewStats {
-        period: period.to_string(),
-        period_start: format_timestamp(period_start),
-        period_end: format_timestamp(period_end),
+        period: range.label.clone(),
+        period_start: range.format(range.start),
+        period_end: range.format(range.end_at(now)),
         summary,
         feedback,
         comparison: calculate_comparison(&current, &previous),
@@ -65,61 +66,51 @@ pub fn get_overview_stats(
 }
 
 pub fn get_timeseries(
-    conn: &Connection,
+    conn: &dyn Connection,
     granularity: Granularity,
-    from: i64,
-    to: i64,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
     model: Option<&str>,
 ) -> Result<TimeSeriesResponse> {
+    let to = range.end_at(Utc::now().timestamp());
     let actual_granularity = match granularity {
-        Granularity::Auto => Granularity::from_range(to - from),
+        Granularity::Auto => Granularity::from_range(to - range.start),
         g => g,
     };
 
-    let bucket_seconds = match actual_granularity {
-        Granularity::Hour | Granularity::Auto => 3600,
-        Granularity::Day => 86400,
-        Granularity::Week => 604800,
-    };
-
     Ok(TimeSeriesResponse {
         granularity: actual_granularity.as_str().to_string(),
-        from: format_timestamp(from),
-        to: format_timestamp(to),
-        data: query_timeseries_data(conn, from, to, bucket_seconds, project, source, model)?,
+        from: range.format(range.start),
+        to: range.format(to),
+        timezone: range.tz.to_string(),
+        data: query_timeseries_data(conn, range, actual_granularity, project, source, model)?,
     })
 }
 
 pub fn get_model_stats(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<ModelsResponse> {
-    let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
-
     Ok(ModelsResponse {
-        period: period.to_string(),
-        models: query_model_breakdown(conn, period_start, project, source)?,
+        period: range.label.clone(),
+        models: query_model_breakdown(conn, range, project, source)?,
     })
 }
 
 pub fn get_error_stats(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<ErrorsResponse> {
-    let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
     let (total_requests, total_errors, errors) =
-        query_error_breakdown(conn, period_start, project, source)?;
+        query_error_breakdown(conn, range, project, source)?;
 
     Ok(ErrorsResponse {
-        period: period.to_string(),
+        period: range.label.clone(),
         total_errors,
         error_rate: safe_percentage(total_errors, total_requests),
         errors,
@@ -127,82 +118,138 @@ pub fn get_error_stats(
 }
 
 pub fn get_source_stats(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
 ) -> Result<SourcesResponse> {
-    let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
-
     Ok(SourcesResponse {
-        period: period.to_string(),
-        sources: query_source_breakdown(conn, period_start, project)?,
+        period: range.label.clone(),
+        sources: query_source_breakdown(conn, range, project)?,
     })
 }
 
 pub fn get_requester_stats(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
     limit: i64,
 ) -> Result<RequestersResponse> {
-    let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
-
     Ok(RequestersResponse {
-        period: period.to_string(),
-        requesters: query_requester_breakdown(conn, period_start, project, source, limit)?,
+        period: range.label.clone(),
+        requesters: query_requester_breakdown(conn, range, project, source, limit)?,
     })
 }
 
-pub fn get_workflow_stats(conn: &Connection, period: Period) -> Result<WorkflowsResponse> {
+/// Per-agent volume, quality, cost and latency, with the routing methods
+/// that sent requests to each agent and a comparison against the previous
+/// period.
+pub fn get_agent_stats(
+    conn: &dyn Connection,
+    range: &TimeRange,
+    project: Option<&str>,
+    source: Option<&str>,
+) -> Result<AgentsResponse> {
     let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
+    let current = query_agent_breakdown(conn, range, project, source)?;
+    let previous: HashMap<String, PeriodStats> =
+        query_agent_breakdown(conn, &range.previous(now), project, source)?
+            .into_iter()
+            .collect();
+    let mut durations = query_durations(conn, &Plan::new(range, now), project, source, true)?;
+    let mut routing = query_agent_routing(conn, range, project, source)?;
+    let total_requests: i64 = current.iter().map(|(_, s)| s.total_requests).sum();
+
+    let agents = current
+        .into_iter()
+        .map(|(agent, stats)| {
+            let mut durations = durations.remove(&agent).unwrap_or_default();
+            let total_feedback = stats.positive_feedback + stats.negative_feedback;
+            let comparison = calculate_comparison(
+                &stats,
+                previous.get(&agent).unwrap_or(&PeriodStats::default()),
+            );
+            AgentStats {
+                requests: stats.total_requests,
+                percentage: safe_percentage(stats.total_requests, total_requests),
+                cost_usd: stats.total_cost_usd,
+                cost_per_request: if stats.total_requests > 0 {
+                    stats.total_cost_usd / stats.total_requests as f64
+                } else {
+                    0.0
+                },
+                success_rate: safe_percentage(stats.successful_requests, stats.total_requests),
+                failure_rate: safe_percentage(
+                    stats.total_requests - stats.successful_requests,
+                    stats.total_requests,
+                ),
+                satisfaction_rate: if total_feedback > 0 {
+                    Some(stats.positive_feedback as f64 / total_feedback as f64 * 100.0)
+                } else {
+                    None
+                },
+                avg_duration_ms: stats.avg_duration_ms,
+                p50_duration_ms: durations.percentile(50),
+                p95_duration_ms: durations.percentile(95),
+                routing: routing.remove(&agent).unwrap_or_default(),
+                comparison,
+                agent,
+            }
+        })
+        .collect();
+
+    Ok(AgentsResponse {
+        period: range.label.clone(),
+        period_start: range.format(range.start),
+        period_end: range.format(range.end_at(now)),
+        agents,
+    })
+}
 
+pub fn get_workflow_stats(conn: &dyn Connection, range: &TimeRange) -> Result<WorkflowsResponse> {
     Ok(WorkflowsResponse {
-        period: period.to_string(),
-        workflows: query_workflow_stats(conn, period_start)?,
+        period: range.label.clone(),
+        workflows: query_workflow_stats(conn, range, None)?,
     })
 }
 
 pub fn record_workflow_execution(
-    conn: &Connection,
+    conn: &dyn Connection,
     req: &RecordWorkflowRequest,
 ) -> Result<RecordWorkflowResponse> {
     let now = Utc::now();
-    conn.execute(
-        "INSERT INTO workflow_executions (workflow_name, execution_id, status, duration_ms, metadata, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    // Older workflows report the failure in their metadata
+    let error = req.error.clone().or_else(|| {
+        req.metadata
+            .as_ref()
+            .and_then(|m| m.get("error"))
+            .and_then(|e| e.as_str())
+            .map(str::to_string)
+    });
+    let id: i64 = conn.query_row(
+        "INSERT INTO workflow_executions (workflow_name, execution_id, run_id, status, duration_ms, error, metadata, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         RETURNING id",
         params![
             req.workflow,
             req.execution_id,
+            req.run_id,
             req.status.as_str(),
             req.duration_ms,
+            error,
             req.metadata.as_ref().map(|m| m.to_string()),
             now.timestamp(),
         ],
+        |row| row.get(0),
     )?;
 
     Ok(RecordWorkflowResponse {
-        id: conn.last_insert_rowid(),
+        id,
         recorded_at: now.to_rfc3339(),
     })
 }
 
-fn calculate_period_bounds(now: i64, period: Period) -> (i64, i64) {
-    match period.seconds() {
-        Some(secs) => (now - secs, now),
-        None => (0, now),
-    }
-}
-
-fn calculate_previous_period_bounds(start: i64, end: i64) -> (i64, i64) {
-    let duration = end - start;
-    (start - duration, start)
-}
-
-fn format_timestamp(ts: i64) -> String {
+pub(super) fn format_timestamp(ts: i64) -> String {
     Utc.timestamp_opt(ts, 0)
         .single()
         .map(|dt| dt.to_rfc3339())
@@ -232,48 +279,18 @@ struct PeriodStats {
 }
 
 fn query_period_stats(
-    conn: &Connection,
-    start: i64,
-    end: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<PeriodStats> {
-    let qb = QueryBuilder::new()
-        .time_range(start, end)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source);
 
-    let exec_sql = format!(
-        "SELECT COUNT(*), COALESCE(SUM(CASE WHEN response != '' THEN 1 ELSE 0 END), 0),
-                COALESCE(SUM(cost_usd), 0), COALESCE(SUM(input_tokens), 0),
-                COALESCE(SUM(output_tokens), 0), COALESCE(SUM(cache_read_tokens), 0),
-                COALESCE(SUM(cache_creation_tokens), 0), COALESCE(AVG(duration_ms), 0)
-         FROM executions WHERE {}",
-        qb.where_clause()
-    );
-
-    let mut stmt = conn.prepare(&exec_sql)?;
-    let (total, successful, cost, input, output, cache_read, cache_creation, avg_dur): (
-        i64,
-        i64,
-        f64,
-        i64,
-        i64,
-        i64,
-        i64,
-        f64,
-    ) = stmt.query_row(qb.params().as_slice(), |row| {
-        Ok((
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            row.get(3)?,
-            row.get(4)?,
-            row.get(5)?,
-            row.get(6)?,
-            row.get(7)?,
-        ))
-    })?;
+    let now = Utc::now().timestamp();
+    let totals = query_totals(conn, &Plan::new(range, now), project, source)?;
 
     let fb_sql = format!(
         "SELECT {}, {}
@@ -287,20 +304,19 @@ fn query_period_stats(
         qb.where_aliased("e")
     );
 
-    let mut stmt = conn.prepare(&fb_sql)?;
-    let (positive, negative): (i64, i64) = stmt
-        .query_row(qb.params().as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))
+    let (positive, negative): (i64, i64) = conn
+        .query_row(&fb_sql, qb.params(), |row| Ok((row.get(0)?, row.get(1)?)))
         .unwrap_or((0, 0));
 
     Ok(PeriodStats {
-        total_requests: total,
-        successful_requests: successful,
-        total_cost_usd: cost,
-        total_input_tokens: input,
-        total_output_tokens: output,
-        cache_read_tokens: cache_read,
-        cache_creation_tokens: cache_creation,
-        avg_duration_ms: avg_dur,
+        total_requests: totals.requests,
+        successful_requests: totals.successful,
+        total_cost_usd: totals.cost_usd,
+        total_input_tokens: totals.input_tokens,
+        total_output_tokens: totals.output_tokens,
+        cache_read_tokens: totals.cache_read_tokens,
+        cache_creation_tokens: totals.cache_creation_tokens,
+        avg_duration_ms: totals.avg_duration_ms(),
         positive_feedback: positive,
         negative_feedback: negative,
     })
@@ -315,50 +331,38 @@ struct DurationPercentiles {
 }
 
 fn query_duration_percentiles(
-    conn: &Connection,
-    start: i64,
-    end: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<DurationPercentiles> {
-    let qb = QueryBuilder::new()
-        .time_range(start, end)
-        .optional("project", project)
-        .optional("source", source);
-
-    let sql = format!(
-        "SELECT duration_ms FROM executions WHERE {} AND duration_ms IS NOT NULL ORDER BY duration_ms",
-        qb.where_clause()
-    );
-
-    let mut stmt = conn.prepare(&sql)?;
-    let durations: Vec<i64> = stmt
-        .query_map(qb.params().as_slice(), |row| row.get(0))?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    if durations.is_empty() {
-        return Ok(DurationPercentiles::default());
-    }
+    let now = Utc::now().timestamp();
+    let mut durations = query_durations(conn, &Plan::new(range, now), project, source, false)?
+        .remove("")
+        .unwrap_or_default();
 
-    let len = durations.len();
     Ok(DurationPercentiles {
-        p50: Some(durations[len * 50 / 100]),
-        p90: Some(durations[len * 90 / 100]),
-        p95: Some(durations[len * 95 / 100]),
-        p99: Some(durations[len.saturating_sub(1).min(len * 99 / 100)]),
+        p50: durations.percentile(50),
+        p90: durations.percentile(90),
+        p95: durations.percentile(95),
+        p99: durations.percentile(99),
     })
 }
 
+/// Nearest-rank percentile of ascending `sorted` values.
+pub(super) fn percentile(sorted: &[i64], pct: usize) -> Option<i64> {
+    let last = sorted.len().checked_sub(1)?;
+    Some(sorted[last.min(sorted.len() * pct / 100)])
+}
+
 fn query_pending_feedback(
-    conn: &Connection,
-    start: i64,
-    end: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<i64> {
-    let qb = QueryBuilder::new()
-        .time_range(start, end)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source);
 
@@ -368,9 +372,7 @@ fn query_pending_feedback(
         qb.where_aliased("e")
     );
 
-    let mut stmt = conn.prepare(&sql)?;
-    stmt.query_row(qb.params().as_slice(), |row| row.get(0))
-        .map_err(Into::into)
+    conn.query_row(&sql, qb.params(), |row| row.get(0))
 }
 
 fn calculate_comparison(current: &PeriodStats, previous: &PeriodStats) -> ComparisonStats {
@@ -405,72 +407,122 @@ fn calculate_comparison(current: &PeriodStats, previous: &PeriodStats) -> Compar
     }
 }
 
+/// Slot the database groups by before slots are folded into local buckets.
+/// Every UTC offset is a multiple of 15 minutes, so a slot never straddles a
+/// local hour, day or week boundary.
+const TIMESERIES_SLOT_SECONDS: i64 = 900;
+
+#[derive(Default)]
+struct BucketTotals {
+    requests: i64,
+    successful: i64,
+    cost_usd: f64,
+    input_tokens: i64,
+    output_tokens: i64,
+    duration_sum: f64,
+    duration_count: i64,
+    positive_feedback: i64,
+    negative_feedback: i64,
+}
+
 fn query_timeseries_data(
-    conn: &Connection,
-    from: i64,
-    to: i64,
-    bucket_seconds: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
+    granularity: Granularity,
     project: Option<&str>,
     source: Option<&str>,
     model: Option<&str>,
 ) -> Result<Vec<TimeSeriesPoint>> {
-    let qb = QueryBuilder::new()
-        .time_range(from, to)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source)
         .optional("model", model);
 
     let sql = format!(
         "SELECT
-            (e.created_at / {bucket}) * {bucket} as bucket_ts,
+            (e.created_at / {slot}) * {slot} as slot_ts,
             COUNT(DISTINCT e.id) as requests,
             COUNT(DISTINCT CASE WHEN e.response != '' THEN e.id END) as successful,
-            COALESCE(SUM(e.cost_usd), 0) / MAX(1, COUNT(*) / COUNT(DISTINCT e.id)) as cost,
-            COALESCE(SUM(e.input_tokens), 0) / MAX(1, COUNT(*) / COUNT(DISTINCT e.id)) as input,
-            COALESCE(SUM(e.output_tokens), 0) / MAX(1, COUNT(*) / COUNT(DISTINCT e.id)) as output,
-            COALESCE(AVG(e.duration_ms), 0) as avg_duration,
+            COALESCE(SUM(e.cost_usd), 0) / {dedup} as cost,
+            COALESCE(SUM(e.input_tokens), 0) / {dedup} as input,
+            COALESCE(SUM(e.output_tokens), 0) / {dedup} as output,
+            COALESCE(SUM(e.duration_ms), 0) as duration_sum,
+            COUNT(e.duration_ms) as duration_count,
             {positive}, {negative}
         FROM executions e
         {join}
         WHERE {where_clause}
-        GROUP BY bucket_ts ORDER BY bucket_ts",
-        bucket = bucket_seconds,
+        GROUP BY slot_ts ORDER BY slot_ts",
+        slot = TIMESERIES_SLOT_SECONDS,
         positive = feedback_sql::POSITIVE_DISTINCT,
         negative = feedback_sql::NEGATIVE_DISTINCT,
         join = feedback_sql::FEEDBACK_JOIN_VERIFIED,
+        dedup = qb.at_least_one("COUNT(*) / COUNT(DISTINCT e.id)"),
         where_clause = qb.where_aliased("e")
     );
 
-    let mut stmt = conn.prepare(&sql)?;
-    let data: Vec<TimeSeriesPoint> = stmt
-        .query_map(qb.params().as_slice(), |row| {
-            Ok(TimeSeriesPoint {
-                timestamp: format_timestamp(row.get::<_, i64>(0)?),
+    let mut buckets: BTreeMap<i64, BucketTotals> = BTreeMap::new();
+    for row in conn.query_map(&sql, qb.params(), |row| {
+        Ok((
+            row.get::<i64>(0)?,
+            BucketTotals {
                 requests: row.get(1)?,
                 successful: row.get(2)?,
                 cost_usd: row.get(3)?,
                 input_tokens: row.get(4)?,
                 output_tokens: row.get(5)?,
-                avg_duration_ms: row.get(6)?,
-                p95_duration_ms: None,
-                positive_feedback: row.get(7)?,
-                negative_feedback: row.get(8)?,
-            })
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
+                duration_sum: row.get(6)?,
+                duration_count: row.get(7)?,
+                positive_feedback: row.get(8)?,
+                negative_feedback: row.get(9)?,
+            },
+        ))
+    })? {
+        let (slot, totals) = row?;
+        let bucket = buckets
+            .entry(bucket_start(granularity, slot, range.tz))
+            .or_default();
+        bucket.requests += totals.requests;
+        bucket.successful += totals.successful;
+        bucket.cost_usd += totals.cost_usd;
+        bucket.input_tokens += totals.input_tokens;
+        bucket.output_tokens += totals.output_tokens;
+        bucket.duration_sum += totals.duration_sum;
+        bucket.duration_count += totals.duration_count;
+        bucket.positive_feedback += totals.positive_feedback;
+        bucket.negative_feedback += totals.negative_feedback;
+    }
 
-    Ok(data)
+    Ok(buckets
+        .into_iter()
+        .map(|(start, totals)| TimeSeriesPoint {
+            timestamp: range.format(start),
+            requests: totals.requests,
+            successful: totals.successful,
+            cost_usd: totals.cost_usd,
+            input_tokens: totals.input_tokens,
+            output_tokens: totals.output_tokens,
+            avg_duration_ms: if totals.duration_count > 0 {
+                totals.duration_sum / totals.duration_count as f64
+            } else {
+                0.0
+            },
+            p95_duration_ms: None,
+            positive_feedback: totals.positive_feedback,
+            negative_feedback: totals.negative_feedback,
+        })
+        .collect())
 }
 
 fn query_model_breakdown(
-    conn: &Connection,
-    start: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<Vec<ModelStats>> {
-    let qb = QueryBuilder::new()
-        .since(start)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source)
         .not_empty("model");
@@ -479,7 +531,7 @@ fn query_model_breakdown(
         "SELECT
             e.model,
             COUNT(DISTINCT e.id) as requests,
-            COALESCE(SUM(e.cost_usd), 0) / MAX(1, COUNT(*) / COUNT(DISTINCT e.id)) as cost,
+            COALESCE(SUM(e.cost_usd), 0) / {dedup} as cost,
             COALESCE(AVG(e.input_tokens), 0) as avg_input,
             COALESCE(AVG(e.output_tokens), 0) as avg_output,
             COALESCE(AVG(e.duration_ms), 0) as avg_duration,
@@ -491,22 +543,22 @@ fn query_model_breakdown(
         positive = feedback_sql::POSITIVE_DISTINCT,
         negative = feedback_sql::NEGATIVE_DISTINCT,
         join = feedback_sql::FEEDBACK_JOIN_VERIFIED,
+        dedup = qb.at_least_one("COUNT(*) / COUNT(DISTINCT e.id)"),
         where_clause = qb.where_aliased("e")
     );
 
-    let mut stmt = conn.prepare(&sql)?;
-    let rows: Vec<_> = stmt
-        .query_map(qb.params().as_slice(), |row| {
+    let rows: Vec<_> = conn
+        .query_map(&sql, qb.params(), |row| {
             Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, i64>(1)?,
-                row.get::<_, f64>(2)?,
-                row.get::<_, f64>(3)?,
-                row.get::<_, f64>(4)?,
-                row.get::<_, f64>(5)?,
-                row.get::<_, i64>(6)?,
-                row.get::<_, i64>(7)?,
-                row.get::<_, i64>(8)?,
+                row.get::<String>(0)?,
+                row.get::<i64>(1)?,
+                row.get::<f64>(2)?,
+                row.get::<f64>(3)?,
+                row.get::<f64>(4)?,
+                row.get::<f64>(5)?,
+                row.get::<i64>(6)?,
+                row.get::<i64>(7)?,
+                row.get::<i64>(8)?,
             ))
         })?
         .filter_map(|r| r.ok())
@@ -577,13 +629,13 @@ fn model_display_name(model: &str) -> String {
 }
 
 fn query_error_breakdown(
-    conn: &Connection,
-    start: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
 ) -> Result<(i64, i64, Vec<ErrorBreakdown>)> {
-    let qb = QueryBuilder::new()
-        .since(start)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source);
 
@@ -592,7 +644,7 @@ fn query_error_breakdown(
 
     let total_requests: i64 = conn.query_row(
         &format!("SELECT COUNT(*) FROM executions WHERE {}", base_where),
-        params.as_slice(),
+        params,
         |row| row.get(0),
     )?;
 
@@ -601,15 +653,15 @@ fn query_error_breakdown(
             "SELECT COUNT(*) FROM executions WHERE {} AND response = ''",
             base_where
         ),
-        params.as_slice(),
+        params,
         |row| row.get(0),
     )?;
 
     let timeout_threshold = 300_000i64;
     let mut errors = Vec::new();
 
-    let mut qb2 = QueryBuilder::new()
-        .since(start)
+    let mut qb2 = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source);
 
@@ -617,12 +669,12 @@ fn query_error_breakdown(
     qb2.add_param(timeout_threshold);
 
     let sql = format!(
-        "SELECT COUNT(*) FROM executions WHERE {} AND response = '' AND duration_ms >= ?{}",
+        "SELECT COUNT(*) FROM executions WHERE {} AND response = '' AND duration_ms >= {}",
         qb2.where_clause(),
-        next_idx
+        qb2.placeholder(next_idx)
     );
 
-    let timeout_count: i64 = conn.query_row(&sql, qb2.params().as_slice(), |row| row.get(0))?;
+    let timeout_count: i64 = conn.query_row(&sql, qb2.params(), |row| row.get(0))?;
 
     if timeout_count > 0 {
         errors.push(ErrorBreakdown {
@@ -649,19 +701,19 @@ fn query_error_breakdown(
 }
 
 fn query_source_breakdown(
-    conn: &Connection,
-    start: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
 ) -> Result<Vec<SourceStats>> {
-    let qb = QueryBuilder::new()
-        .since(start)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project);
 
     let sql = format!(
         "SELECT
             COALESCE(e.source, 'unknown') as src,
             COUNT(DISTINCT e.id) as requests,
-            COALESCE(SUM(e.cost_usd), 0) / MAX(1, COUNT(*) / COUNT(DISTINCT e.id)) as cost,
+            COALESCE(SUM(e.cost_usd), 0) / {dedup} as cost,
             COALESCE(AVG(e.duration_ms), 0) as avg_duration,
             COUNT(DISTINCT CASE WHEN e.response != '' THEN e.id END) as successful,
             {positive}, {negative},
@@ -672,21 +724,21 @@ fn query_source_breakdown(
         positive = feedback_sql::POSITIVE_DISTINCT,
         negative = feedback_sql::NEGATIVE_DISTINCT,
         join = feedback_sql::FEEDBACK_JOIN_VERIFIED,
+        dedup = qb.at_least_one("COUNT(*) / COUNT(DISTINCT e.id)"),
         where_clause = qb.where_aliased("e")
     );
 
-    let mut stmt = conn.prepare(&sql)?;
-    let rows: Vec<_> = stmt
-        .query_map(qb.params().as_slice(), |row| {
+    let rows: Vec<_> = conn
+        .query_map(&sql, qb.params(), |row| {
             Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, i64>(1)?,
-                row.get::<_, f64>(2)?,
-                row.get::<_, f64>(3)?,
-                row.get::<_, i64>(4)?,
-                row.get::<_, i64>(5)?,
-                row.get::<_, i64>(6)?,
-                row.get::<_, i64>(7)?,
+                row.get::<String>(0)?,
+                row.get::<i64>(1)?,
+                row.get::<f64>(2)?,
+                row.get::<f64>(3)?,
+                row.get::<i64>(4)?,
+                row.get::<i64>(5)?,
+                row.get::<i64>(6)?,
+                row.get::<i64>(7)?,
             ))
         })?
         .filter_map(|r| r.ok())
@@ -728,14 +780,14 @@ fn query_source_breakdown(
 }
 
 fn query_requester_breakdown(
-    conn: &Connection,
-    start: i64,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     source: Option<&str>,
     limit: i64,
 ) -> Result<Vec<RequesterStats>> {
-    let qb = QueryBuilder::new()
-        .since(start)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("source", source)
         .not_empty("requester");
@@ -743,9 +795,9 @@ fn query_requester_breakdown(
     let sql = format!(
         "SELECT
             e.requester,
-            e.source,
+            MAX(e.source),
             COUNT(DISTINCT e.id) as requests,
-            COALESCE(SUM(e.cost_usd), 0) / MAX(1, COUNT(*) / COUNT(DISTINCT e.id)) as cost,
+            COALESCE(SUM(e.cost_usd), 0) / {dedup} as cost,
             COALESCE(AVG(e.duration_ms), 0) as avg_duration,
             COUNT(DISTINCT CASE WHEN e.response != '' THEN e.id END) as successful,
             {positive}, {negative},
@@ -756,13 +808,13 @@ fn query_requester_breakdown(
         positive = feedback_sql::POSITIVE_DISTINCT,
         negative = feedback_sql::NEGATIVE_DISTINCT,
         join = feedback_sql::FEEDBACK_JOIN_VERIFIED,
+        dedup = qb.at_least_one("COUNT(*) / COUNT(DISTINCT e.id)"),
         where_clause = qb.where_aliased("e"),
         limit = limit
     );
 
-    let mut stmt = conn.prepare(&sql)?;
-    let requesters: Vec<RequesterStats> = stmt
-        .query_map(qb.params().as_slice(), |row| {
+    let requesters: Vec<RequesterStats> = conn
+        .query_map(&sql, qb.params(), |row| {
             let requester: String = row.get(0)?;
             let source: Option<String> = row.get(1)?;
             let requests: i64 = row.get(2)?;
@@ -795,8 +847,115 @@ fn query_requester_breakdown(
     Ok(requesters)
 }
 
-fn query_workflow_stats(conn: &Connection, start: i64) -> Result<Vec<WorkflowStats>> {
-    let sql = "SELECT
+/// Period totals of each agent, busiest first.
+fn query_agent_breakdown(
+    conn: &dyn Connection,
+    range: &TimeRange,
+    project: Option<&str>,
+    source: Option<&str>,
+) -> Result<Vec<(String, PeriodStats)>> {
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
+        .optional("project", project)
+        .optional("source", source)
+        .not_empty("agent");
+
+    let sql = format!(
+        "SELECT
+            e.agent,
+            COUNT(DISTINCT e.id) as requests,
+            COUNT(DISTINCT CASE WHEN e.response != '' THEN e.id END) as successful,
+            COALESCE(SUM(e.cost_usd), 0) / {dedup} as cost,
+            COALESCE(AVG(e.duration_ms), 0) as avg_duration,
+            {positive}, {negative}
+        FROM executions e
+        {join}
+        WHERE {where_clause} GROUP BY e.agent ORDER BY requests DESC",
+        positive = feedback_sql::POSITIVE_DISTINCT,
+        negative = feedback_sql::NEGATIVE_DISTINCT,
+        join = feedback_sql::FEEDBACK_JOIN_VERIFIED,
+        dedup = qb.at_least_one("COUNT(*) / COUNT(DISTINCT e.id)"),
+        where_clause = qb.where_aliased("e")
+    );
+
+    let agents = conn
+        .query_map(&sql, qb.params(), |row| {
+            Ok((
+                row.get::<String>(0)?,
+                PeriodStats {
+                    total_requests: row.get(1)?,
+                    successful_requests: row.get(2)?,
+                    total_cost_usd: row.get(3)?,
+                    avg_duration_ms: row.get(4)?,
+                    positive_feedback: row.get(5)?,
+                    negative_feedback: row.get(6)?,
+                    ..Default::default()
+                },
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(agents)
+}
+
+/// Classification methods that routed to each agent, most frequent first.
+fn query_agent_routing(
+    conn: &dyn Connection,
+    range: &TimeRange,
+    project: Option<&str>,
+    source: Option<&str>,
+) -> Result<HashMap<String, Vec<RoutingMethodStats>>> {
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
+        .optional("project", project)
+        .optional("source", source);
+
+    let sql = format!(
+        "SELECT agent, method, COUNT(*) FROM classification_logs
+         WHERE {} GROUP BY agent, method ORDER BY agent, COUNT(*) DESC, method",
+        qb.where_clause()
+    );
+
+    let mut routing: HashMap<String, Vec<RoutingMethodStats>> = HashMap::new();
+    for row in conn.query_map(&sql, qb.params(), |row| {
+        Ok((
+            row.get::<String>(0)?,
+            row.get::<String>(1)?,
+            row.get::<i64>(2)?,
+        ))
+    })? {
+        let (agent, method, count) = row?;
+        routing.entry(agent).or_default().push(RoutingMethodStats {
+            method,
+            count,
+            percentage: 0.0,
+        });
+    }
+    for methods in routing.values_mut() {
+        let total: i64 = methods.iter().map(|m| m.count).sum();
+        for method in methods.iter_mut() {
+            method.percentage = safe_percentage(method.count, total);
+        }
+    }
+    Ok(routing)
+}
+
+/// Most common failure reasons kept per workflow
+const TOP_FAILURE_REASONS: usize = 5;
+
+pub(super) fn query_workflow_stats(
+    conn: &dyn Connection,
+    range: &TimeRange,
+    workflow: Option<&str>,
+) -> Result<Vec<WorkflowStats>> {
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
+        .optional("workflow_name", workflow);
+    let mut durations = query_workflow_durations(conn, &qb)?;
+    let mut failures = query_workflow_failures(conn, &qb)?;
+    let sql = format!(
+        "SELECT
         workflow_name,
         COUNT(*) as total,
         COALESCE(SUM(CASE WHEN status = 'success' THEN 1 ELSE 0 END), 0) as successful,
@@ -806,12 +965,13 @@ fn query_workflow_stats(conn: &Connection, start: i64) -> Result<Vec<WorkflowSta
         (SELECT status FROM workflow_executions w2
          WHERE w2.workflow_name = workflow_executions.workflow_name
          ORDER BY created_at DESC LIMIT 1) as last_status
-    FROM workflow_executions WHERE created_at >= ?1
-    GROUP BY workflow_name ORDER BY total DESC";
+    FROM workflow_executions WHERE {}
+    GROUP BY workflow_name ORDER BY total DESC",
+        qb.where_clause()
+    );
 
-    let mut stmt = conn.prepare(sql)?;
-    let workflows: Vec<WorkflowStats> = stmt
-        .query_map(params![start], |row| {
+    let workflows: Vec<WorkflowStats> = conn
+        .query_map(&sql, qb.params(), |row| {
             let name: String = row.get(0)?;
             let total: i64 = row.get(1)?;
             let successful: i64 = row.get(2)?;
@@ -820,6 +980,8 @@ fn query_workflow_stats(conn: &Connection, start: i64) -> Result<Vec<WorkflowSta
             let last_exec: Option<i64> = row.get(5)?;
             let last_status: Option<String> = row.get(6)?;
             let success_rate = safe_percentage(successful, total);
+            let durations = durations.remove(&name).unwrap_or_default();
+            let failure_reasons = failures.remove(&name).unwrap_or_default();
 
             Ok(WorkflowStats {
                 display_name: workflow_display_name(&name),
@@ -830,9 +992,12 @@ fn query_workflow_stats(conn: &Connection, start: i64) -> Result<Vec<WorkflowSta
                 failed,
                 success_rate,
                 avg_duration_ms: avg_duration,
-                p95_duration_ms: None,
+                p50_duration_ms: percentile(&durations, 50),
+                p95_duration_ms: percentile(&durations, 95),
+                p99_duration_ms: percentile(&durations, 99),
                 last_execution: last_exec.map(format_timestamp),
                 last_status,
+                failure_reasons,
                 metadata: None,
             })
         })?
@@ -842,6 +1007,59 @@ fn query_workflow_stats(conn: &Connection, start: i64) -> Result<Vec<WorkflowSta
     Ok(workflows)
 }
 
+/// Sorted run durations of each workflow
+fn query_workflow_durations(
+    conn: &dyn Connection,
+    qb: &QueryBuilder,
+) -> Result<HashMap<String, Vec<i64>>> {
+    let sql = format!(
+        "SELECT workflow_name, duration_ms FROM workflow_executions
+         WHERE {} AND duration_ms IS NOT NULL
+         ORDER BY workflow_name, duration_ms",
+        qb.where_clause()
+    );
+    let mut durations: HashMap<String, Vec<i64>> = HashMap::new();
+    for row in conn.query_map(&sql, qb.params(), |row| {
+        Ok((row.get::<String>(0)?, row.get::<i64>(1)?))
+    })? {
+        let (name, duration) = row?;
+        durations.entry(name).or_default().push(duration);
+    }
+    Ok(durations)
+}
+
+/// Failed runs of each workflow grouped by error, or by status when the
+/// run reported none, most common first
+fn query_workflow_failures(
+    conn: &dyn Connection,
+    qb: &QueryBuilder,
+) -> Result<HashMap<String, Vec<FailureReason>>> {
+    let sql = format!(
+        "SELECT workflow_name, COALESCE(NULLIF(error, ''), status), COUNT(*)
+         FROM workflow_executions
+         WHERE {} AND status != 'success'
+         GROUP BY 1, 2 ORDER BY 3 DESC, 2",
+        qb.where_clause()
+    );
+    let mut failures: HashMap<String, Vec<FailureReason>> = HashMap::new();
+    for row in conn.query_map(&sql, qb.params(), |row| {
+        Ok((
+            row.get::<String>(0)?,
+            FailureReason {
+                reason: row.get(1)?,
+                count: row.get(2)?,
+            },
+        ))
+    })? {
+        let (name, reason) = row?;
+        let reasons = failures.entry(name).or_default();
+        if reasons.len() < TOP_FAILURE_REASONS {
+            reasons.push(reason);
+        }
+    }
+    Ok(failures)
+}
+
 fn workflow_display_name(name: &str) -> String {
     match name {
         "slack-mention-handler" => "Slack Mention".to_string(),
@@ -864,33 +1082,29 @@ fn workflow_display_name(name: &str) -> String {
 }
 
 pub fn get_classify_stats(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
 ) -> Result<ClassifyStatsResponse> {
-    let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
-
-    let qb = QueryBuilder::new()
-        .since(period_start)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project);
 
     let total_sql = format!(
         "SELECT COUNT(*), COALESCE(AVG(duration_ms), 0) FROM classification_logs WHERE {}",
         qb.where_clause()
     );
-    let mut stmt = conn.prepare(&total_sql)?;
-    let (total, avg_duration): (i64, f64) =
-        stmt.query_row(qb.params().as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let (total, avg_duration): (i64, f64) = conn.query_row(&total_sql, qb.params(), |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
 
     let agents_sql = format!(
         "SELECT agent, COUNT(*), AVG(confidence), AVG(duration_ms)
          FROM classification_logs WHERE {} GROUP BY agent ORDER BY COUNT(*) DESC",
         qb.where_clause()
     );
-    let mut stmt = conn.prepare(&agents_sql)?;
-    let agents: Vec<AgentClassifyStats> = stmt
-        .query_map(qb.params().as_slice(), |row| {
+    let agents: Vec<AgentClassifyStats> = conn
+        .query_map(&agents_sql, qb.params(), |row| {
             let agent: String = row.get(0)?;
             let count: i64 = row.get(1)?;
             let avg_confidence: f64 = row.get(2)?;
@@ -911,9 +1125,8 @@ pub fn get_classify_stats(
          FROM classification_logs WHERE {} GROUP BY method ORDER BY COUNT(*) DESC",
         qb.where_clause()
     );
-    let mut stmt = conn.prepare(&methods_sql)?;
-    let methods: Vec<MethodClassifyStats> = stmt
-        .query_map(qb.params().as_slice(), |row| {
+    let methods: Vec<MethodClassifyStats> = conn
+        .query_map(&methods_sql, qb.params(), |row| {
             let method: String = row.get(0)?;
             let count: i64 = row.get(1)?;
             let avg_dur: f64 = row.get(2)?;
@@ -927,30 +1140,123 @@ pub fn get_classify_stats(
         .filter_map(|r| r.ok())
         .collect();
 
+    let method = |name: &str| methods.iter().find(|m| m.method == name);
+    let (cache_hits, cache_saved_ms) = match (method("cache"), method("llm")) {
+        (Some(cache), Some(llm)) => (
+            cache.count,
+            (llm.avg_duration_ms - cache.avg_duration_ms).max(0.0) * cache.count as f64,
+        ),
+        (Some(cache), None) => (cache.count, 0.0),
+        _ => (0, 0.0),
+    };
+
     Ok(ClassifyStatsResponse {
-        period: period.to_string(),
+        period: range.label.clone(),
         total_classifications: total,
         avg_duration_ms: avg_duration,
         agents,
         methods,
+        cache_hits,
+        cache_saved_ms,
+    })
+}
+
+pub fn get_agent_revision_stats(
+    conn: &dyn Connection,
+    project: &str,
+    agent: &str,
+) -> Result<AgentRevisionStatsResponse> {
+    let sql = format!(
+        "SELECT
+            e.agent_revision,
+            COUNT(DISTINCT e.id) as requests,
+            COUNT(DISTINCT CASE WHEN e.response != '' THEN e.id END) as successful,
+            COALESCE(SUM(e.cost_usd), 0) / {dedup} as cost,
+            COALESCE(AVG(e.duration_ms), 0) as avg_duration,
+            {positive}, {negative},
+            MIN(e.created_at), MAX(e.created_at)
+        FROM executions e
+        {join}
+        WHERE e.project = ?1 AND e.agent = ?2
+        GROUP BY e.agent_revision ORDER BY e.agent_revision",
+        positive = feedback_sql::POSITIVE_DISTINCT,
+        negative = feedback_sql::NEGATIVE_DISTINCT,
+        join = feedback_sql::FEEDBACK_JOIN_VERIFIED,
+        dedup = conn
+            .dialect()
+            .greatest("1", "COUNT(*) / COUNT(DISTINCT e.id)"),
+    );
+
+    let mut revisions: Vec<RevisionQualityStats> = conn
+        .query_map(&sql, params![project, agent], |row| {
+            let requests: i64 = row.get(1)?;
+            let successful: i64 = row.get(2)?;
+            let cost: f64 = row.get(3)?;
+            let positive: i64 = row.get(5)?;
+            let negative: i64 = row.get(6)?;
+            let total_feedback = positive + negative;
+            Ok(RevisionQualityStats {
+                revision: row.get(0)?,
+                requests,
+                success_rate: safe_percentage(successful, requests),
+                satisfaction_rate: if total_feedback > 0 {
+                    Some(positive as f64 / total_feedback as f64 * 100.0)
+                } else {
+                    None
+                },
+                cost_per_request: if requests > 0 {
+                    cost / requests as f64
+                } else {
+                    0.0
+                },
+                avg_duration_ms: row.get(4)?,
+                classifications: 0,
+                avg_confidence: None,
+                first_seen: format_timestamp(row.get::<i64>(7)?),
+                last_seen: format_timestamp(row.get::<i64>(8)?),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let classifications: Vec<(Option<i64>, i64, f64)> = conn
+        .query_map(
+            "SELECT agent_revision, COUNT(*), AVG(confidence)
+         FROM classification_logs WHERE project = ?1 AND agent = ?2
+         GROUP BY agent_revision",
+            params![project, agent],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    for (revision, count, avg_confidence) in classifications {
+        if let Some(stats) = revisions.iter_mut().find(|r| r.revision == revision) {
+            stats.classifications = count;
+            stats.avg_confidence = Some(avg_confidence);
+        }
+    }
+
+    Ok(AgentRevisionStatsResponse {
+        project: project.to_string(),
+        agent: agent.to_string(),
+        revisions,
     })
 }
 
 pub fn get_classify_logs(
-    conn: &Connection,
-    period: Period,
+    conn: &dyn Connection,
+    range: &TimeRange,
     project: Option<&str>,
     agent: Option<&str>,
     method: Option<&str>,
     page: i64,
     limit: i64,
 ) -> Result<ClassifyLogsResponse> {
-    let now = Utc::now().timestamp();
-    let (period_start, _) = calculate_period_bounds(now, period);
     let offset = (page - 1) * limit;
 
-    let qb = QueryBuilder::new()
-        .since(period_start)
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
         .optional("project", project)
         .optional("agent", agent)
         .optional("method", method);
@@ -959,25 +1265,24 @@ pub fn get_classify_logs(
         "SELECT COUNT(*) FROM classification_logs WHERE {}",
         qb.where_clause()
     );
-    let total: i64 = conn.query_row(&count_sql, qb.params_refs().as_slice(), |row| row.get(0))?;
+    let total: i64 = conn.query_row(&count_sql, qb.params(), |row| row.get(0))?;
 
     let logs_sql = format!(
         "SELECT id, substr(text, 1, 100), agent, model, confidence, method,
-                matched_keyword, reasoning, duration_ms, project, source, requester, created_at
-         FROM classification_logs WHERE {} ORDER BY created_at DESC LIMIT ?{} OFFSET ?{}",
+                matched_keyword, reasoning, duration_ms, project, source, requester, created_at,
+                llm_error
+         FROM classification_logs WHERE {} ORDER BY created_at DESC LIMIT {} OFFSET {}",
         qb.where_clause(),
-        qb.params_len() + 1,
-        qb.params_len() + 2
+        qb.placeholder(qb.params_len() + 1),
+        qb.placeholder(qb.params_len() + 2)
     );
 
     let mut params = qb.into_params();
-    params.push(Box::new(limit));
-    params.push(Box::new(offset));
-    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    params.push(Value::Integer(limit));
+    params.push(Value::Integer(offset));
 
-    let mut stmt = conn.prepare(&logs_sql)?;
-    let logs: Vec<ClassifyLogEntry> = stmt
-        .query_map(params_refs.as_slice(), |row| {
+    let logs: Vec<ClassifyLogEntry> = conn
+        .query_map(&logs_sql, &params, |row| {
             Ok(ClassifyLogEntry {
                 id: row.get(0)?,
                 text_preview: row.get(1)?,
@@ -991,7 +1296,8 @@ pub fn get_classify_logs(
                 project: row.get(9)?,
                 source: row.get(10)?,
                 requester: row.get(11)?,
-                created_at: format_timestamp(row.get::<_, i64>(12)?),
+                llm_error: row.get(13)?,
+                created_at: format_timestamp(row.get::<i64>(12)?),
             })
         })?
         .filter_map(|r| r.ok())
@@ -1007,3 +1313,144 @@ pub fn get_classify_logs(
         total_pages,
     })
 }
+
+/// Compares an experiment's variants, each against the first one (the
+/// control): failure rate and satisfaction with a two-proportion test, cost
+/// and latency with a test on the means.
+pub fn get_experiment_results(
+    conn: &dyn Connection,
+    experiment: &Experiment,
+) -> Result<ExperimentResultsResponse> {
+    struct Totals {
+        requests: i64,
+        failures: i64,
+        cost: Sample,
+        duration: Sample,
+        positive: i64,
+        negative: i64,
+    }
+
+    let mut totals: HashMap<String, Totals> = conn
+        .query_map(
+            "SELECT
+                variant,
+                COUNT(*),
+                SUM(CASE WHEN response != '' THEN 0 ELSE 1 END),
+                COUNT(cost_usd),
+                COALESCE(SUM(cost_usd), 0),
+                COALESCE(SUM(cost_usd * cost_usd), 0),
+                COUNT(duration_ms),
+                COALESCE(SUM(CAST(duration_ms AS DOUBLE PRECISION)), 0),
+                COALESCE(SUM(CAST(duration_ms AS DOUBLE PRECISION) * duration_ms), 0)
+            FROM executions
+            WHERE experiment_id = ?1 AND variant IS NOT NULL
+            GROUP BY variant",
+            params![experiment.id],
+            |row| {
+                Ok((
+                    row.get::<String>(0)?,
+                    Totals {
+                        requests: row.get(1)?,
+                        failures: row.get(2)?,
+                        cost: Sample::from_sums(row.get(3)?, row.get(4)?, row.get(5)?),
+                        duration: Sample::from_sums(row.get(6)?, row.get(7)?, row.get(8)?),
+                        positive: 0,
+                        negative: 0,
+                    },
+                ))
+            },
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let feedback: Vec<(String, i64, i64)> = conn
+        .query_map(
+            &format!(
+                "SELECT e.variant, {}, {}
+                FROM executions e
+                {}
+                WHERE e.experiment_id = ?1 AND e.variant IS NOT NULL
+                GROUP BY e.variant",
+                feedback_sql::POSITIVE_DISTINCT,
+                feedback_sql::NEGATIVE_DISTINCT,
+                feedback_sql::FEEDBACK_JOIN_VERIFIED,
+            ),
+            params![experiment.id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (variant, positive, negative) in feedback {
+        if let Some(t) = totals.get_mut(&variant) {
+            t.positive = positive;
+            t.negative = negative;
+        }
+    }
+
+    let empty = || Totals {
+        requests: 0,
+        failures: 0,
+        cost: Sample::from_sums(0, 0.0, 0.0),
+        duration: Sample::from_sums(0, 0.0, 0.0),
+        positive: 0,
+        negative: 0,
+    };
+    let variants: Vec<(&ExperimentVariant, Totals)> = experiment
+        .variants
+        .iter()
+        .map(|v| (v, totals.remove(&v.name).unwrap_or_else(empty)))
+        .collect();
+
+    let control = variants.first().map(|(_, t)| t);
+    let stats = variants
+        .iter()
+        .enumerate()
+        .map(|(i, (variant, t))| {
+            let vs_control = control.filter(|_| i > 0).map(|c| VariantComparison {
+                satisfaction: two_proportion_test(
+                    (c.positive, c.positive + c.negative),
+                    (t.positive, t.positive + t.negative),
+                ),
+                failure_rate: two_proportion_test(
+                    (c.failures, c.requests),
+                    (t.failures, t.requests),
+                ),
+                cost: mean_difference_test(c.cost, t.cost),
+                latency: mean_difference_test(c.duration, t.duration),
+            });
+            let total_feedback = t.positive + t.negative;
+            VariantStats {
+                variant: variant.name.clone(),
+                weight: variant.weight,
+                requests: t.requests,
+                failures: t.failures,
+                failure_rate: safe_percentage(t.failures, t.requests),
+                positive_feedback: t.positive,
+                negative_feedback: t.negative,
+                satisfaction_rate: (total_feedback > 0)
+                    .then(|| t.positive as f64 / total_feedback as f64 * 100.0),
+                cost_per_request: if t.requests > 0 {
+                    t.cost.mean * t.cost.n as f64 / t.requests as f64
+                } else {
+                    0.0
+                },
+                avg_duration_ms: t.duration.mean,
+                vs_control,
+            }
+        })
+        .collect();
+
+    Ok(ExperimentResultsResponse {
+        experiment_id: experiment.id.clone(),
+        name: experiment.name.clone(),
+        project: experiment.project_id.clone(),
+        agent: experiment.agent.clone(),
+        status: experiment.status.clone(),
+        control: experiment
+            .variants
+            .first()
+            .map(|v| v.name.clone())
+            .unwrap_or_default(),
+        variants: stats,
+    })
+}
@@ -1,25 +1,43 @@
-use rusqlite::ToSql;
+use super::range::TimeRange;
+use crate::storage::{Dialect, ToValue, Value};
 
 pub struct QueryBuilder {
+    dialect: Dialect,
     conditions: Vec<String>,
-    params: Vec<Box<dyn ToSql>>,
+    params: Vec<Value>,
 }
 
 impl QueryBuilder {
-    pub fn new() -> Self {
+    pub fn new(dialect: Dialect) -> Self {
         Self {
+            dialect,
             conditions: Vec::new(),
             params: Vec::new(),
         }
     }
 
-    pub fn time_range(mut self, start: i64, end: i64) -> Self {
-        self.add_time_range(start, end);
+    /// Restricts `created_at` to `range`.
+    pub fn within(self, range: &TimeRange) -> Self {
+        self.between(range.start, range.end)
+    }
+
+    /// Restricts `created_at` to `[start, end)`, or to `start` onwards.
+    pub fn between(mut self, start: i64, end: Option<i64>) -> Self {
+        match end {
+            Some(end) => self.add_time_range(start, end),
+            None => self.add_start_time(start),
+        }
         self
     }
 
-    pub fn since(mut self, start: i64) -> Self {
-        self.add_start_time(start);
+    /// Restricts rollup `bucket_start` to `[start, end)`.
+    pub fn buckets(mut self, start: i64, end: i64) -> Self {
+        let idx = self.add_param(start);
+        self.conditions
+            .push(format!("bucket_start >= {}", self.placeholder(idx)));
+        let idx = self.add_param(end);
+        self.conditions
+            .push(format!("bucket_start < {}", self.placeholder(idx)));
         self
     }
 
@@ -34,25 +52,25 @@ impl QueryBuilder {
     }
 
     pub fn add_time_range(&mut self, start: i64, end: i64) {
+        let idx = self.add_param(start);
         self.conditions
-            .push(format!("created_at >= ?{}", self.params.len() + 1));
-        self.params.push(Box::new(start));
+            .push(format!("created_at >= {}", self.placeholder(idx)));
+        let idx = self.add_param(end);
         self.conditions
-            .push(format!("created_at < ?{}", self.params.len() + 1));
-        self.params.push(Box::new(end));
+            .push(format!("created_at < {}", self.placeholder(idx)));
     }
 
     pub fn add_start_time(&mut self, start: i64) {
+        let idx = self.add_param(start);
         self.conditions
-            .push(format!("created_at >= ?{}", self.params.len() + 1));
-        self.params.push(Box::new(start));
+            .push(format!("created_at >= {}", self.placeholder(idx)));
     }
 
     pub fn add_optional(&mut self, field: &str, value: Option<&str>) {
         if let Some(v) = value {
+            let idx = self.add_param(v);
             self.conditions
-                .push(format!("{} = ?{}", field, self.params.len() + 1));
-            self.params.push(Box::new(v.to_string()));
+                .push(format!("{} = {}", field, self.placeholder(idx)));
         }
     }
 
@@ -61,11 +79,21 @@ impl QueryBuilder {
             .push(format!("{} IS NOT NULL AND {} != ''", field, field));
     }
 
-    pub fn add_param(&mut self, value: impl ToSql + 'static) -> usize {
-        self.params.push(Box::new(value));
+    pub fn add_param(&mut self, value: impl ToValue) -> usize {
+        self.params.push(value.to_value());
         self.params.len()
     }
 
+    /// Placeholder for the 1-based parameter `idx` in this builder's dialect.
+    pub fn placeholder(&self, idx: usize) -> String {
+        self.dialect.placeholder(idx)
+    }
+
+    /// `MAX(1, expr)` with the dialect's scalar maximum.
+    pub fn at_least_one(&self, expr: &str) -> String {
+        self.dialect.greatest("1", expr)
+    }
+
     pub fn where_clause(&self) -> String {
         if self.conditions.is_empty() {
             "1=1".to_string()
@@ -83,13 +111,8 @@ impl QueryBuilder {
             .replace("requester", &format!("{}.requester", alias))
     }
 
-    pub fn params(&self) -> Vec<&dyn ToSql> {
-        self.params.iter().map(|p| p.as_ref()).collect()
-    }
-
-    #[inline]
-    pub fn params_refs(&self) -> Vec<&dyn ToSql> {
-        self.params()
+    pub fn params(&self) -> &[Value] {
+        &self.params
     }
 
     pub fn next_index(&self) -> usize {
@@ -100,13 +123,7 @@ impl QueryBuilder {
         self.params.len()
     }
 
-    pub fn into_params(self) -> Vec<Box<dyn ToSql>> {
+    pub fn into_params(self) -> Vec<Value> {
         self.params
     }
 }
-
-impl Default for QueryBuilder {
-    fn default() -> Self {
-        Self::new()
-    }
-}
@@ -0,0 +1,318 @@
+//! Time windows of stats queries: periods relative to now, calendar periods
+//! and explicit bounds, resolved in an IANA timezone so local days, weeks and
+//! months start at local midnight even across DST changes.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{
+    DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
+
+use super::types::{Granularity, Period};
+
+/// Calendar unit that periods and buckets align to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarUnit {
+    Day,
+    Week,
+    Month,
+}
+
+/// Resolved window of a stats query
+#[derive(Debug, Clone)]
+pub struct TimeRange {
+    pub start: i64,
+    /// Exclusive end; open ranges run up to now
+    pub end: Option<i64>,
+    pub tz: Tz,
+    /// Period name, or `custom` for explicit bounds
+    pub label: String,
+    /// Set for calendar periods, which compare against the previous unit
+    unit: Option<CalendarUnit>,
+}
+
+impl TimeRange {
+    /// Window of `period` as of `now`.
+    pub fn period(period: Period, now: i64, tz: Tz) -> Self {
+        let (start, end, unit) = match period.calendar() {
+            Some((unit, offset)) => {
+                let (start, end) = calendar_bounds(unit, now, offset, tz);
+                (start, (end <= now).then_some(end), Some(unit))
+            }
+            None => (period.seconds().map_or(0, |secs| now - secs), None, None),
+        };
+        Self {
+            start,
+            end,
+            tz,
+            label: period.to_string(),
+            unit,
+        }
+    }
+
+    /// Resolves a stats query. `from`/`to` take precedence over `period`;
+    /// without `from` the window starts `period` before `to`. Bounds are
+    /// RFC 3339 timestamps, local `YYYY-MM-DDTHH:MM[:SS]` times, epoch
+    /// seconds or dates, a `to` date including that whole day. `tz` falls
+    /// back to `default_tz`, then UTC.
+    pub fn resolve(
+        period: Period,
+        from: Option<&str>,
+        to: Option<&str>,
+        tz: Option<&str>,
+        default_tz: &str,
+    ) -> Result<Self> {
+        let tz = match tz.filter(|s| !s.is_empty()) {
+            Some(name) => name
+                .parse::<Tz>()
+                .map_err(|_| anyhow!("unknown timezone '{}'", name))?,
+            None => default_tz.parse().unwrap_or(chrono_tz::UTC),
+        };
+        let now = Utc::now().timestamp();
+        if from.is_none() && to.is_none() {
+            return Ok(Self::period(period, now, tz));
+        }
+
+        let end = to.map(|v| parse_bound(v, tz, true)).transpose()?;
+        let start = match from {
+            Some(v) => parse_bound(v, tz, false)?,
+            None => period.seconds().map_or(0, |secs| end.unwrap_or(now) - secs),
+        };
+        if start >= end.unwrap_or(now) {
+            bail!("from must be before to");
+        }
+        Ok(Self {
+            start,
+            end,
+            tz,
+            label: "custom".to_string(),
+            unit: None,
+        })
+    }
+
+    pub fn end_at(&self, now: i64) -> i64 {
+        self.end.unwrap_or(now)
+    }
+
+    /// Window to compare against: the same length just before this one, or
+    /// for calendar periods the previous unit up to the same elapsed time,
+    /// so this month so far is compared with the same days of last month.
+    pub fn previous(&self, now: i64) -> Self {
+        let length = self.end_at(now) - self.start;
+        let start = match self.unit {
+            Some(unit) => calendar_bounds(unit, self.start, -1, self.tz).0,
+            None => self.start - length,
+        };
+        Self {
+            start,
+            end: Some((start + length).min(self.start)),
+            tz: self.tz,
+            label: "previous".to_string(),
+            unit: self.unit,
+        }
+    }
+
+    /// RFC 3339 timestamp in the range's timezone.
+    pub fn format(&self, ts: i64) -> String {
+        DateTime::from_timestamp(ts, 0)
+            .map(|dt| dt.with_timezone(&self.tz).to_rfc3339())
+            .unwrap_or_default()
+    }
+}
+
+fn parse_bound(value: &str, tz: Tz, is_end: bool) -> Result<i64> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp());
+    }
+    if let Ok(ts) = value.parse::<i64>() {
+        return Ok(ts);
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(value, format) {
+            return tz
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|dt| dt.timestamp())
+                .ok_or_else(|| anyhow!("'{}' does not exist in {}", value, tz));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if is_end { date + Days::new(1) } else { date };
+        return Ok(local_midnight(date, tz));
+    }
+    bail!("invalid time '{}'", value)
+}
+
+/// First instant of `date` in `tz`. Midnight can fall in a DST gap, in
+/// which case the day starts at the first local time after it.
+fn local_midnight(date: NaiveDate, tz: Tz) -> i64 {
+    let midnight = date.and_time(NaiveTime::MIN);
+    (0..3)
+        .find_map(|hours| {
+            tz.from_local_datetime(&(midnight + chrono::Duration::hours(hours)))
+                .earliest()
+        })
+        .map(|dt| dt.timestamp())
+        .unwrap_or_else(|| midnight.and_utc().timestamp())
+}
+
+fn local_date(ts: i64, tz: Tz) -> NaiveDate {
+    DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .with_timezone(&tz)
+        .date_naive()
+}
+
+fn shift(unit: CalendarUnit, date: NaiveDate, units: i32) -> NaiveDate {
+    let shifted = match unit {
+        CalendarUnit::Day | CalendarUnit::Week => {
+            let days = if unit == CalendarUnit::Week { 7 } else { 1 } * i64::from(units);
+            date.checked_add_signed(chrono::Duration::days(days))
+        }
+        CalendarUnit::Month if units < 0 => {
+            date.checked_sub_months(Months::new(units.unsigned_abs()))
+        }
+        CalendarUnit::Month => date.checked_add_months(Months::new(units as u32)),
+    };
+    shifted.unwrap_or(date)
+}
+
+/// Local start and end of the calendar `unit` containing `ts`, moved by
+/// `offset` units (-1 for the previous one). Weeks start on Monday.
+pub fn calendar_bounds(unit: CalendarUnit, ts: i64, offset: i32, tz: Tz) -> (i64, i64) {
+    let today = local_date(ts, tz);
+    let current = match unit {
+        CalendarUnit::Day => today,
+        CalendarUnit::Week => today - Days::new(u64::from(today.weekday().num_days_from_monday())),
+        CalendarUnit::Month => today.with_day(1).unwrap_or(today),
+    };
+    let start = shift(unit, current, offset);
+    let end = shift(unit, start, 1);
+    (local_midnight(start, tz), local_midnight(end, tz))
+}
+
+/// Start of the local `granularity` bucket containing `ts`.
+pub fn bucket_start(granularity: Granularity, ts: i64, tz: Tz) -> i64 {
+    match granularity {
+        Granularity::Hour | Granularity::Auto => {
+            let local = DateTime::from_timestamp(ts, 0)
+                .unwrap_or_default()
+                .with_timezone(&tz);
+            ts - i64::from(local.minute() * 60 + local.second())
+        }
+        Granularity::Day => calendar_bounds(CalendarUnit::Day, ts, 0, tz).0,
+        Granularity::Week => calendar_bounds(CalendarUnit::Week, ts, 0, tz).0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar_periods_and_buckets() {
+        let seoul: Tz = "Asia/Seoul".parse().unwrap();
+        let local = |y, m, d| {
+            seoul
+                .with_ymd_and_hms(y, m, d, 0, 0, 0)
+                .unwrap()
+                .timestamp()
+        };
+        // Wednesday 2025-01-15 01:00 in Seoul, still the 14th in UTC
+        let now = Utc
+            .with_ymd_and_hms(2025, 1, 14, 16, 0, 0)
+            .unwrap()
+            .timestamp();
+
+        let today = TimeRange::period(Period::Today, now, seoul);
+        assert_eq!((today.start, today.end), (local(2025, 1, 15), None));
+        let last_week = TimeRange::period(Period::LastWeek, now, seoul);
+        assert_eq!(
+            (last_week.start, last_week.end),
+            (local(2025, 1, 6), Some(local(2025, 1, 13)))
+        );
+        let last_month = TimeRange::period(Period::LastMonth, now, seoul);
+        assert_eq!(
+            (last_month.start, last_month.end),
+            (local(2024, 12, 1), Some(local(2025, 1, 1)))
+        );
+        // This month so far against the same days of December
+        let previous = TimeRange::period(Period::ThisMonth, now, seoul).previous(now);
+        assert_eq!(
+            (previous.start, previous.end),
+            (
+                local(2024, 12, 1),
+                Some(now - (local(2025, 1, 1) - local(2024, 12, 1)))
+            )
+        );
+
+        assert_eq!(
+            bucket_start(Granularity::Day, now, seoul),
+            local(2025, 1, 15)
+        );
+        assert_eq!(
+            bucket_start(Granularity::Day, now, chrono_tz::UTC),
+            Utc.with_ymd_and_hms(2025, 1, 14, 0, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+
+        // Day buckets follow DST: 23 hours when New York springs forward,
+        // and hours stay aligned to a +05:45 offset
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        let noon = Utc
+            .with_ymd_and_hms(2025, 3, 9, 17, 0, 0)
+            .unwrap()
+            .timestamp();
+        let (start, end) = calendar_bounds(CalendarUnit::Day, noon, 0, new_york);
+        assert_eq!(end - start, 23 * 3600);
+        let kathmandu: Tz = "Asia/Kathmandu".parse().unwrap();
+        assert_eq!(
+            bucket_start(Granularity::Hour, noon, kathmandu),
+            noon - 45 * 60
+        );
+    }
+
+    #[test]
+    fn test_resolve_explicit_bounds() {
+        let range = TimeRange::resolve(
+            Period::Hours24,
+            Some("2025-01-01"),
+            Some("2025-01-31"),
+            Some("Asia/Seoul"),
+            "UTC",
+        )
+        .unwrap();
+        assert_eq!(range.label, "custom");
+        assert_eq!(range.format(range.start), "2025-01-01T00:00:00+09:00");
+        assert_eq!(
+            range.end.map(|end| range.format(end)).as_deref(),
+            Some("2025-02-01T00:00:00+09:00")
+        );
+
+        let range = TimeRange::resolve(
+            Period::Days7,
+            None,
+            Some("2025-01-08T00:00:00Z"),
+            None,
+            "Asia/Seoul",
+        )
+        .unwrap();
+        assert_eq!(range.end.unwrap() - range.start, 7 * 86400);
+        assert_eq!(range.tz, chrono_tz::Asia::Seoul);
+
+        assert!(TimeRange::resolve(Period::All, None, None, Some("Mars/Base"), "UTC").is_err());
+        assert!(
+            TimeRange::resolve(
+                Period::All,
+                Some("2025-02-01"),
+                Some("2025-01-01"),
+                None,
+                "UTC"
+            )
+            .is_err()
+        );
+        assert!(TimeRange::resolve(Period::All, Some("yesterday"), None, None, "UTC").is_err());
+    }
+}
@@ -0,0 +1,436 @@
+//! Hourly execution rollups: totals and a latency sketch per project,
+//! source, model and agent, added to by every save. Ranges spanning days
+//! read their whole hours from the rollups and only the partial hours at
+//! either end from `executions`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+
+use super::queries::percentile;
+use super::query_builder::QueryBuilder;
+use super::range::TimeRange;
+use crate::storage::{Connection, Row};
+
+/// Width of a rollup bucket
+pub const ROLLUP_SECS: i64 = 3600;
+
+/// Fewest whole hours a range must span for rollups to be read
+const MIN_ROLLUP_HOURS: i64 = 48;
+
+/// Relative error of percentiles read from latency sketches
+const SKETCH_ACCURACY: f64 = 0.01;
+
+/// Start of the rollup bucket holding `ts`.
+pub fn rollup_bucket(ts: i64) -> i64 {
+    ts - ts.rem_euclid(ROLLUP_SECS)
+}
+
+fn sketch_gamma() -> f64 {
+    (1.0 + SKETCH_ACCURACY) / (1.0 - SKETCH_ACCURACY)
+}
+
+/// Latency sketch bin of a duration. Bin 0 holds zero durations; bin `k`
+/// holds `(gamma^(k-2), gamma^(k-1)]`, every value of which is within
+/// `SKETCH_ACCURACY` of the bin's representative value.
+pub fn latency_bin(duration_ms: i64) -> i64 {
+    if duration_ms <= 0 {
+        return 0;
+    }
+    ((duration_ms as f64).ln() / sketch_gamma().ln()).ceil() as i64 + 1
+}
+
+fn bin_value(bin: i64) -> i64 {
+    if bin <= 0 {
+        return 0;
+    }
+    let gamma = sketch_gamma();
+    (2.0 * gamma.powi((bin - 1) as i32) / (gamma + 1.0)).round() as i64
+}
+
+/// Where each part of a range is read from
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Plan {
+    /// Whole hours read from rollups
+    pub(super) rollups: Option<(i64, i64)>,
+    /// Spans read from `executions`, open-ended when without an end
+    pub(super) raw: Vec<(i64, Option<i64>)>,
+}
+
+impl Plan {
+    /// Reads the whole hours of `range` from rollups when it spans enough
+    /// of them.
+    pub(super) fn new(range: &TimeRange, now: i64) -> Self {
+        Self::split(range.start, range.end, range.end_at(now))
+    }
+
+    /// Plan for `[start, end)`.
+    pub(super) fn between(start: i64, end: i64) -> Self {
+        Self::split(start, Some(end), end)
+    }
+
+    fn split(start: i64, end: Option<i64>, until: i64) -> Self {
+        let first = rollup_bucket(start + ROLLUP_SECS - 1);
+        let last = rollup_bucket(until);
+        if last - first < MIN_ROLLUP_HOURS * ROLLUP_SECS {
+            return Self {
+                rollups: None,
+                raw: vec![(start, end)],
+            };
+        }
+        let mut raw = Vec::new();
+        if start < first {
+            raw.push((start, Some(first)));
+        }
+        if end != Some(last) {
+            raw.push((last, end));
+        }
+        Self {
+            rollups: Some((first, last)),
+            raw,
+        }
+    }
+
+    /// Reads all of `range` from `executions`.
+    #[cfg(test)]
+    pub(super) fn raw(range: &TimeRange) -> Self {
+        Self {
+            rollups: None,
+            raw: vec![(range.start, range.end)],
+        }
+    }
+}
+
+fn filtered(conn: &dyn Connection, project: Option<&str>, source: Option<&str>) -> QueryBuilder {
+    QueryBuilder::new(conn.dialect())
+        .optional("project", project)
+        .optional("source", source)
+}
+
+/// Execution totals of a range
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(super) struct Totals {
+    pub requests: i64,
+    pub successful: i64,
+    pub cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub duration_sum: i64,
+    pub duration_count: i64,
+}
+
+impl Totals {
+    fn add(&mut self, other: &Totals) {
+        self.requests += other.requests;
+        self.successful += other.successful;
+        self.cost_usd += other.cost_usd;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.duration_sum += other.duration_sum;
+        self.duration_count += other.duration_count;
+    }
+
+    pub(super) fn avg_duration_ms(&self) -> f64 {
+        if self.duration_count > 0 {
+            self.duration_sum as f64 / self.duration_count as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+pub(super) fn query_totals(
+    conn: &dyn Connection,
+    plan: &Plan,
+    project: Option<&str>,
+    source: Option<&str>,
+) -> Result<Totals> {
+    let map_totals = |row: &Row| {
+        Ok(Totals {
+            requests: row.get(0)?,
+            successful: row.get(1)?,
+            cost_usd: row.get(2)?,
+            input_tokens: row.get(3)?,
+            output_tokens: row.get(4)?,
+            cache_read_tokens: row.get(5)?,
+            cache_creation_tokens: row.get(6)?,
+            duration_sum: row.get(7)?,
+            duration_count: row.get(8)?,
+        })
+    };
+
+    let mut totals = Totals::default();
+    if let Some((start, end)) = plan.rollups {
+        let qb = filtered(conn, project, source).buckets(start, end);
+        let sql = format!(
+            "SELECT COALESCE(SUM(requests), 0), COALESCE(SUM(successful), 0),
+                    COALESCE(SUM(cost_usd), 0), COALESCE(SUM(input_tokens), 0),
+                    COALESCE(SUM(output_tokens), 0), COALESCE(SUM(cache_read_tokens), 0),
+                    COALESCE(SUM(cache_creation_tokens), 0), COALESCE(SUM(duration_sum), 0),
+                    COALESCE(SUM(duration_count), 0)
+             FROM execution_rollups WHERE {}",
+            qb.where_clause()
+        );
+        totals.add(&conn.query_row(&sql, qb.params(), map_totals)?);
+    }
+    for &(start, end) in &plan.raw {
+        let qb = filtered(conn, project, source).between(start, end);
+        let sql = format!(
+            "SELECT COUNT(*), COALESCE(SUM(CASE WHEN response != '' THEN 1 ELSE 0 END), 0),
+                    COALESCE(SUM(cost_usd), 0), COALESCE(SUM(input_tokens), 0),
+                    COALESCE(SUM(output_tokens), 0), COALESCE(SUM(cache_read_tokens), 0),
+                    COALESCE(SUM(cache_creation_tokens), 0), COALESCE(SUM(duration_ms), 0),
+                    COUNT(duration_ms)
+             FROM executions WHERE {}",
+            qb.where_clause()
+        );
+        totals.add(&conn.query_row(&sql, qb.params(), map_totals)?);
+    }
+    Ok(totals)
+}
+
+/// Execution durations of a range. Percentiles are exact while every
+/// duration was read from `executions`, and within `SKETCH_ACCURACY` once
+/// sketch bins are involved.
+#[derive(Debug, Default, Clone)]
+pub(super) struct Durations {
+    values: Vec<i64>,
+    bins: BTreeMap<i64, i64>,
+}
+
+impl Durations {
+    pub(super) fn add(&mut self, duration_ms: i64) {
+        self.values.push(duration_ms);
+    }
+
+    pub(super) fn add_bin(&mut self, bin: i64, hits: i64) {
+        *self.bins.entry(bin).or_default() += hits;
+    }
+
+    /// Nearest-rank percentile, like `queries::percentile`.
+    pub(super) fn percentile(&mut self, pct: usize) -> Option<i64> {
+        if self.bins.is_empty() {
+            self.values.sort_unstable();
+            return percentile(&self.values, pct);
+        }
+        for value in self.values.drain(..) {
+            *self.bins.entry(latency_bin(value)).or_default() += 1;
+        }
+        let total: i64 = self.bins.values().sum();
+        let rank = (total - 1).min(total * pct as i64 / 100);
+        let mut seen = 0;
+        for (&bin, &hits) in &self.bins {
+            seen += hits;
+            if seen > rank {
+                return Some(bin_value(bin));
+            }
+        }
+        None
+    }
+}
+
+/// Durations of each agent's executions, or of all executions under `""`
+/// unless `by_agent`.
+pub(super) fn query_durations(
+    conn: &dyn Connection,
+    plan: &Plan,
+    project: Option<&str>,
+    source: Option<&str>,
+    by_agent: bool,
+) -> Result<HashMap<String, Durations>> {
+    let group = if by_agent { ", agent" } else { "" };
+    let key = |row: &Row, idx: usize| -> Result<String> {
+        if by_agent {
+            row.get(idx)
+        } else {
+            Ok(String::new())
+        }
+    };
+    let with_agent = |qb: QueryBuilder| {
+        if by_agent { qb.not_empty("agent") } else { qb }
+    };
+
+    let mut durations: HashMap<String, Durations> = HashMap::new();
+    if let Some((start, end)) = plan.rollups {
+        let qb = with_agent(filtered(conn, project, source).buckets(start, end));
+        let sql = format!(
+            "SELECT bin, SUM(hits){group} FROM execution_rollup_latency
+             WHERE {} GROUP BY bin{group}",
+            qb.where_clause()
+        );
+        for row in conn.query_map(&sql, qb.params(), |row| {
+            Ok((key(row, 2)?, row.get::<i64>(0)?, row.get::<i64>(1)?))
+        })? {
+            let (key, bin, hits) = row?;
+            *durations
+                .entry(key)
+                .or_default()
+                .bins
+                .entry(bin)
+                .or_default() += hits;
+        }
+    }
+    for &(start, end) in &plan.raw {
+        let qb = with_agent(filtered(conn, project, source).between(start, end));
+        let sql = format!(
+            "SELECT duration_ms{group} FROM executions
+             WHERE {} AND duration_ms IS NOT NULL",
+            qb.where_clause()
+        );
+        for row in conn.query_map(&sql, qb.params(), |row| {
+            Ok((key(row, 1)?, row.get::<i64>(0)?))
+        })? {
+            let (key, duration) = row?;
+            durations.entry(key).or_default().values.push(duration);
+        }
+    }
+    Ok(durations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::Period;
+    use crate::storage::{Execution, Storage};
+
+    const NOW: i64 = 1_750_001_234;
+
+    fn execution(i: i64) -> Execution {
+        Execution {
+            id: format!("e{}", i),
+            project: "demo".into(),
+            source: Some(if i % 2 == 0 { "slack" } else { "api" }.into()),
+            requester: None,
+            agent: Some(["ops", "support", ""][i as usize % 3].into()),
+            instruction: None,
+            user_message: String::new(),
+            user_context: None,
+            response: if i % 9 == 0 {
+                String::new()
+            } else {
+                "ok".into()
+            },
+            structured_output: None,
+            model: Some("sonnet".into()),
+            cost_usd: Some(0.01 * (i % 5) as f64),
+            input_tokens: Some(100 + i),
+            output_tokens: Some(i % 50),
+            cache_read_tokens: Some(i % 7),
+            cache_creation_tokens: None,
+            duration_ms: (i % 13 != 0).then_some(40 + i * 7919 % 30_000),
+            duration_api_ms: None,
+            session_id: None,
+            metadata: None,
+            agent_revision: None,
+            allowed_tools: None,
+            disallowed_tools: None,
+            rerun_of: None,
+            experiment_id: None,
+            variant: None,
+            run_id: None,
+            // Five days of executions, a little under 15 minutes apart
+            created_at: NOW - 5 * 86400 + i * 863,
+        }
+    }
+
+    fn assert_close(rollup: Option<i64>, raw: Option<i64>) {
+        let (rollup, raw) = (rollup.unwrap(), raw.unwrap());
+        assert!(
+            ((rollup - raw).abs() as f64) <= raw as f64 * SKETCH_ACCURACY + 1.0,
+            "{} vs {}",
+            rollup,
+            raw
+        );
+    }
+
+    #[test]
+    fn test_rollups_match_raw_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("rollups.db")).unwrap();
+        for i in 0..500 {
+            storage.save(&execution(i)).unwrap();
+        }
+
+        let open = TimeRange::period(Period::Days7, NOW, chrono_tz::UTC);
+        let closed = TimeRange::resolve(
+            Period::All,
+            Some(&(NOW - 4 * 86400 - 1_111).to_string()),
+            Some(&(NOW - 3_333).to_string()),
+            None,
+            "UTC",
+        )
+        .unwrap();
+        let compare = |storage: &Storage| {
+            storage.with_connection(|conn| {
+                for range in [&open, &closed] {
+                    let plan = Plan::new(range, NOW);
+                    assert!(plan.rollups.is_some());
+                    for source in [None, Some("slack")] {
+                        let rolled = query_totals(conn, &plan, Some("demo"), source)?;
+                        let raw = query_totals(conn, &Plan::raw(range), Some("demo"), source)?;
+                        assert!(rolled.requests > 0);
+                        assert!((rolled.cost_usd - raw.cost_usd).abs() < 1e-9);
+                        assert_eq!(
+                            Totals {
+                                cost_usd: 0.0,
+                                ..rolled
+                            },
+                            Totals {
+                                cost_usd: 0.0,
+                                ..raw
+                            }
+                        );
+
+                        for by_agent in [false, true] {
+                            let mut rolled =
+                                query_durations(conn, &plan, Some("demo"), source, by_agent)?;
+                            let mut raw = query_durations(
+                                conn,
+                                &Plan::raw(range),
+                                Some("demo"),
+                                source,
+                                by_agent,
+                            )?;
+                            let mut keys: Vec<_> = rolled.keys().cloned().collect();
+                            keys.sort();
+                            let mut raw_keys: Vec<_> = raw.keys().cloned().collect();
+                            raw_keys.sort();
+                            assert_eq!(keys, raw_keys);
+                            for key in keys {
+                                let (rolled, raw) =
+                                    (rolled.get_mut(&key).unwrap(), raw.get_mut(&key).unwrap());
+                                for pct in [0, 50, 90, 95, 99, 100] {
+                                    assert_close(rolled.percentile(pct), raw.percentile(pct));
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            })
+        };
+        compare(&storage).unwrap();
+
+        // Rebuilding from scratch lands on the same rollups
+        assert_eq!(storage.rebuild_rollups().unwrap(), 500);
+        compare(&storage).unwrap();
+    }
+
+    #[test]
+    fn test_sketch_bins_stay_within_accuracy() {
+        for duration in (1..5_000).chain((5_000..10_000_000).step_by(997)) {
+            let value = bin_value(latency_bin(duration));
+            assert!(
+                ((value - duration).abs() as f64) <= duration as f64 * SKETCH_ACCURACY + 0.5,
+                "{} -> {}",
+                duration,
+                value
+            );
+        }
+        assert_eq!(bin_value(latency_bin(0)), 0);
+    }
+}
@@ -0,0 +1,124 @@
+use serde::Serialize;
+
+/// Two-sided p-value below which a difference counts as significant
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// Difference between a variant and the control, with a two-sided z-test
+#[derive(Debug, Clone, Serialize)]
+pub struct SignificanceTest {
+    /// Variant minus control, in the metric's unit
+    pub difference: f64,
+    pub z: f64,
+    pub p_value: f64,
+    pub significant: bool,
+}
+
+impl SignificanceTest {
+    fn new(difference: f64, se: f64) -> Option<Self> {
+        if se.is_nan() || se <= 0.0 {
+            return None;
+        }
+        let z = difference / se;
+        let p_value = (2.0 * (1.0 - normal_cdf(z.abs()))).clamp(0.0, 1.0);
+        Some(Self {
+            difference,
+            z,
+            p_value,
+            significant: p_value < SIGNIFICANCE_LEVEL,
+        })
+    }
+}
+
+/// Mean and sample variance of a metric
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub n: i64,
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl Sample {
+    /// From a count, sum and sum of squares.
+    pub fn from_sums(n: i64, sum: f64, sum_squares: f64) -> Self {
+        let mean = if n > 0 { sum / n as f64 } else { 0.0 };
+        let variance = if n > 1 {
+            ((sum_squares - n as f64 * mean * mean) / (n - 1) as f64).max(0.0)
+        } else {
+            0.0
+        };
+        Self { n, mean, variance }
+    }
+}
+
+/// Pooled two-proportion z-test of `successes / trials`; the difference is
+/// in percentage points.
+pub fn two_proportion_test(control: (i64, i64), variant: (i64, i64)) -> Option<SignificanceTest> {
+    let ((s1, n1), (s2, n2)) = (control, variant);
+    if n1 <= 0 || n2 <= 0 {
+        return None;
+    }
+    let (n1, n2) = (n1 as f64, n2 as f64);
+    let (p1, p2) = (s1 as f64 / n1, s2 as f64 / n2);
+    let pooled = (s1 + s2) as f64 / (n1 + n2);
+    let se = (pooled * (1.0 - pooled) * (1.0 / n1 + 1.0 / n2)).sqrt();
+    SignificanceTest::new((p2 - p1) * 100.0, se * 100.0)
+}
+
+/// Welch's test of a difference in means, with the normal approximation;
+/// needs at least two observations per side.
+pub fn mean_difference_test(control: Sample, variant: Sample) -> Option<SignificanceTest> {
+    if control.n < 2 || variant.n < 2 {
+        return None;
+    }
+    let se = (control.variance / control.n as f64 + variant.variance / variant.n as f64).sqrt();
+    SignificanceTest::new(variant.mean - control.mean, se)
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function (Numerical Recipes, error below 1.2e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_significance_tests() {
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-3);
+
+        // 60% vs 40% satisfaction on 200 ratings each is clearly different
+        let test = two_proportion_test((80, 200), (120, 200)).unwrap();
+        assert!((test.difference - 20.0).abs() < 1e-9);
+        assert!(test.significant && test.p_value < 0.001);
+        // ... on 10 ratings each it is not
+        assert!(!two_proportion_test((4, 10), (6, 10)).unwrap().significant);
+        assert!(two_proportion_test((0, 0), (3, 5)).is_none());
+
+        let control = Sample::from_sums(4, 10.0, 30.0);
+        assert_eq!((control.mean, control.variance), (2.5, 5.0 / 3.0));
+        let variant = Sample {
+            n: 50,
+            mean: 2.6,
+            variance: 1.0,
+        };
+        assert!(!mean_difference_test(control, variant).unwrap().significant);
+        assert!(mean_difference_test(Sample::from_sums(1, 1.0, 1.0), variant).is_none());
+    }
+}
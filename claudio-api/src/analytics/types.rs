@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 
+use super::range::CalendarUnit;
+use super::significance::SignificanceTest;
+
 /// Period for statistics queries
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Period {
     #[serde(rename = "1h")]
     Hour1,
@@ -17,10 +20,16 @@ pub enum Period {
     Days90,
     #[serde(rename = "all")]
     All,
+    Today,
+    Yesterday,
+    ThisWeek,
+    LastWeek,
+    ThisMonth,
+    LastMonth,
 }
 
 impl Period {
-    /// Returns the number of seconds for this period
+    /// Returns the number of seconds for periods relative to now
     pub fn seconds(&self) -> Option<i64> {
         match self {
             Period::Hour1 => Some(3600),
@@ -28,7 +37,20 @@ impl Period {
             Period::Days7 => Some(604800),
             Period::Days30 => Some(2592000),
             Period::Days90 => Some(7776000),
-            Period::All => None,
+            _ => None,
+        }
+    }
+
+    /// Calendar unit and offset from the current one for calendar periods
+    pub fn calendar(&self) -> Option<(CalendarUnit, i32)> {
+        match self {
+            Period::Today => Some((CalendarUnit::Day, 0)),
+            Period::Yesterday => Some((CalendarUnit::Day, -1)),
+            Period::ThisWeek => Some((CalendarUnit::Week, 0)),
+            Period::LastWeek => Some((CalendarUnit::Week, -1)),
+            Period::ThisMonth => Some((CalendarUnit::Month, 0)),
+            Period::LastMonth => Some((CalendarUnit::Month, -1)),
+            _ => None,
         }
     }
 
@@ -41,8 +63,33 @@ impl Period {
             Period::Days30 => "30d",
             Period::Days90 => "90d",
             Period::All => "all",
+            Period::Today => "today",
+            Period::Yesterday => "yesterday",
+            Period::ThisWeek => "this_week",
+            Period::LastWeek => "last_week",
+            Period::ThisMonth => "this_month",
+            Period::LastMonth => "last_month",
         }
     }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [
+            Period::Hour1,
+            Period::Hours24,
+            Period::Days7,
+            Period::Days30,
+            Period::Days90,
+            Period::All,
+            Period::Today,
+            Period::Yesterday,
+            Period::ThisWeek,
+            Period::LastWeek,
+            Period::ThisMonth,
+            Period::LastMonth,
+        ]
+        .into_iter()
+        .find(|p| p.as_str() == s)
+    }
 }
 
 impl std::fmt::Display for Period {
@@ -157,6 +204,8 @@ pub struct TimeSeriesResponse {
     pub granularity: String,
     pub from: String,
     pub to: String,
+    /// IANA timezone the buckets are aligned to
+    pub timezone: String,
     pub data: Vec<TimeSeriesPoint>,
 }
 
@@ -264,15 +313,29 @@ pub struct WorkflowStats {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub avg_duration_ms: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub p50_duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub p95_duration_ms: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub p99_duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_execution: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_status: Option<String>,
+    /// Most common reasons runs failed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failure_reasons: Vec<FailureReason>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Error reported by failed workflow runs, or their status if none
+#[derive(Debug, Serialize)]
+pub struct FailureReason {
+    pub reason: String,
+    pub count: i64,
+}
+
 /// Workflow stats response
 #[derive(Debug, Serialize)]
 pub struct WorkflowsResponse {
@@ -280,6 +343,71 @@ pub struct WorkflowsResponse {
     pub workflows: Vec<WorkflowStats>,
 }
 
+/// Recent runs of one workflow
+#[derive(Debug, Serialize)]
+pub struct WorkflowRunsResponse {
+    pub workflow: String,
+    pub period: String,
+    /// Health, latency percentiles and failure reasons over the period;
+    /// absent when the workflow did not run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<WorkflowStats>,
+    pub runs: Vec<WorkflowRun>,
+}
+
+/// One recorded workflow run with everything that carried its run id
+#[derive(Debug, Serialize)]
+pub struct WorkflowRun {
+    pub id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: String,
+    /// Executions made by the run, or the one it reported
+    pub executions: Vec<RunExecution>,
+    pub classifications: Vec<ClassifyLogEntry>,
+    /// Slack events forwarded to start the run
+    pub deliveries: Vec<RunDelivery>,
+}
+
+/// Execution linked to a workflow run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunExecution {
+    pub id: String,
+    pub project: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    pub created_at: String,
+}
+
+/// Webhook delivery that started a workflow run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunDelivery {
+    pub endpoint: String,
+    pub event_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: String,
+}
+
 /// Source statistics
 #[derive(Debug, Serialize)]
 pub struct SourceStats {
@@ -320,15 +448,57 @@ pub struct RequestersResponse {
     pub requesters: Vec<RequesterStats>,
 }
 
+/// Agent statistics
+#[derive(Debug, Serialize)]
+pub struct AgentStats {
+    pub agent: String,
+    pub requests: i64,
+    pub percentage: f64,
+    pub cost_usd: f64,
+    pub cost_per_request: f64,
+    pub success_rate: f64,
+    pub failure_rate: f64,
+    pub satisfaction_rate: Option<f64>,
+    pub avg_duration_ms: f64,
+    pub p50_duration_ms: Option<i64>,
+    pub p95_duration_ms: Option<i64>,
+    /// How the period's classifications routed to this agent were decided
+    pub routing: Vec<RoutingMethodStats>,
+    /// Change against the preceding period of the same length
+    pub comparison: ComparisonStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoutingMethodStats {
+    pub method: String,
+    pub count: i64,
+    pub percentage: f64,
+}
+
+/// Agents response
+#[derive(Debug, Serialize)]
+pub struct AgentsResponse {
+    pub period: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub agents: Vec<AgentStats>,
+}
+
 /// Request to record workflow execution
 #[derive(Debug, Deserialize)]
 pub struct RecordWorkflowRequest {
     pub workflow: String,
     #[serde(default)]
     pub execution_id: Option<String>,
+    /// Run id the Slack bridge handed to the workflow
+    #[serde(default)]
+    pub run_id: Option<String>,
     pub status: WorkflowStatus,
     #[serde(default)]
     pub duration_ms: Option<i64>,
+    /// Why the run failed; defaults to `metadata.error`
+    #[serde(default)]
+    pub error: Option<String>,
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
 }
@@ -348,6 +518,11 @@ pub struct ClassifyStatsResponse {
     pub avg_duration_ms: f64,
     pub agents: Vec<AgentClassifyStats>,
     pub methods: Vec<MethodClassifyStats>,
+    /// Classifications answered from the cache instead of the LLM
+    pub cache_hits: i64,
+    /// LLM time the cache hits avoided, from the period's average llm and
+    /// cache durations
+    pub cache_saved_ms: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -368,7 +543,7 @@ pub struct MethodClassifyStats {
 }
 
 /// Classification log entry
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ClassifyLogEntry {
     pub id: i64,
     pub text_preview: String,
@@ -382,6 +557,7 @@ pub struct ClassifyLogEntry {
     pub project: Option<String>,
     pub source: Option<String>,
     pub requester: Option<String>,
+    pub llm_error: Option<String>,
     pub created_at: String,
 }
 
@@ -394,3 +570,71 @@ pub struct ClassifyLogsResponse {
     pub limit: i64,
     pub total_pages: i64,
 }
+
+/// Execution quality for a single agent revision
+#[derive(Debug, Serialize)]
+pub struct RevisionQualityStats {
+    /// `None` for executions recorded before revision tracking
+    pub revision: Option<i64>,
+    pub requests: i64,
+    pub success_rate: f64,
+    pub satisfaction_rate: Option<f64>,
+    pub cost_per_request: f64,
+    pub avg_duration_ms: f64,
+    pub classifications: i64,
+    pub avg_confidence: Option<f64>,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// Agent revision comparison response
+#[derive(Debug, Serialize)]
+pub struct AgentRevisionStatsResponse {
+    pub project: String,
+    pub agent: String,
+    pub revisions: Vec<RevisionQualityStats>,
+}
+
+/// Variant compared against the experiment's control
+#[derive(Debug, Serialize)]
+pub struct VariantComparison {
+    /// Satisfaction rate difference, in percentage points
+    pub satisfaction: Option<SignificanceTest>,
+    /// Failure rate difference, in percentage points
+    pub failure_rate: Option<SignificanceTest>,
+    /// Cost per request difference, in USD
+    pub cost: Option<SignificanceTest>,
+    /// Average duration difference, in milliseconds
+    pub latency: Option<SignificanceTest>,
+}
+
+/// Execution quality for a single experiment variant
+#[derive(Debug, Serialize)]
+pub struct VariantStats {
+    pub variant: String,
+    pub weight: u32,
+    pub requests: i64,
+    pub failures: i64,
+    pub failure_rate: f64,
+    pub positive_feedback: i64,
+    pub negative_feedback: i64,
+    pub satisfaction_rate: Option<f64>,
+    pub cost_per_request: f64,
+    pub avg_duration_ms: f64,
+    /// `None` for the control
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vs_control: Option<VariantComparison>,
+}
+
+/// Experiment variant comparison response
+#[derive(Debug, Serialize)]
+pub struct ExperimentResultsResponse {
+    pub experiment_id: String,
+    pub name: String,
+    pub project: String,
+    pub agent: String,
+    pub status: String,
+    /// Variant the others are tested against (the first one)
+    pub control: String,
+    pub variants: Vec<VariantStats>,
+}
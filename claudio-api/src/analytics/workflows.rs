@@ -0,0 +1,223 @@
+//! Workflow runs: each run n8n reported, joined with the executions,
+//! classifications and webhook deliveries that carried its run id.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use super::queries::{format_timestamp, query_workflow_stats};
+use super::query_builder::QueryBuilder;
+use super::range::TimeRange;
+use super::types::*;
+use crate::storage::{Connection, Value};
+
+/// `IN (...)` over `values`, numbered after the `offset` params before it
+fn in_list(qb: &QueryBuilder, offset: usize, values: usize) -> String {
+    (offset + 1..=offset + values)
+        .map(|i| qb.placeholder(i))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn text_params<'a>(values: impl IntoIterator<Item = &'a String>) -> Vec<Value> {
+    values.into_iter().map(|v| Value::Text(v.clone())).collect()
+}
+
+/// Latest runs of `workflow` in the range, newest first.
+pub fn get_workflow_runs(
+    conn: &dyn Connection,
+    workflow: &str,
+    range: &TimeRange,
+    status: Option<WorkflowStatus>,
+    limit: i64,
+) -> Result<WorkflowRunsResponse> {
+    let stats = query_workflow_stats(conn, range, Some(workflow))?
+        .into_iter()
+        .next();
+
+    let qb = QueryBuilder::new(conn.dialect())
+        .within(range)
+        .optional("workflow_name", Some(workflow))
+        .optional("status", status.as_ref().map(WorkflowStatus::as_str));
+    let sql = format!(
+        "SELECT id, run_id, execution_id, status, duration_ms, error, metadata, created_at
+         FROM workflow_executions WHERE {} ORDER BY created_at DESC, id DESC LIMIT {}",
+        qb.where_clause(),
+        qb.placeholder(qb.params_len() + 1)
+    );
+    let mut params = qb.params().to_vec();
+    params.push(Value::Integer(limit));
+    let rows: Vec<(Option<String>, WorkflowRun)> = conn
+        .query_map(&sql, &params, |row| {
+            let metadata: Option<String> = row.get(6)?;
+            Ok((
+                row.get::<Option<String>>(2)?,
+                WorkflowRun {
+                    id: row.get(0)?,
+                    run_id: row.get(1)?,
+                    status: row.get(3)?,
+                    duration_ms: row.get(4)?,
+                    error: row.get(5)?,
+                    metadata: metadata.and_then(|m| serde_json::from_str(&m).ok()),
+                    created_at: format_timestamp(row.get::<i64>(7)?),
+                    executions: Vec::new(),
+                    classifications: Vec::new(),
+                    deliveries: Vec::new(),
+                },
+            ))
+        })?
+        .collect::<Result<_>>()?;
+    let (execution_ids, mut runs): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+
+    let run_ids: Vec<String> = runs.iter().filter_map(|r| r.run_id.clone()).collect();
+    let reported: Vec<String> = execution_ids.iter().flatten().cloned().collect();
+    let executions = query_run_executions(conn, &run_ids, &reported)?;
+    let classifications = query_run_classifications(conn, &run_ids)?;
+    let deliveries = query_run_deliveries(conn, &run_ids)?;
+
+    for (run, execution_id) in runs.iter_mut().zip(execution_ids) {
+        run.executions = executions
+            .iter()
+            .filter(|(run_id, e)| {
+                (run.run_id.is_some() && *run_id == run.run_id)
+                    || execution_id.as_ref() == Some(&e.id)
+            })
+            .map(|(_, e)| e.clone())
+            .collect();
+        if let Some(run_id) = &run.run_id {
+            // n8n may report a run more than once, e.g. an error then a retry
+            run.classifications = classifications.get(run_id).cloned().unwrap_or_default();
+            run.deliveries = deliveries.get(run_id).cloned().unwrap_or_default();
+        }
+    }
+
+    Ok(WorkflowRunsResponse {
+        workflow: workflow.to_string(),
+        period: range.label.clone(),
+        stats,
+        runs,
+    })
+}
+
+/// Executions carrying one of `run_ids` or reported as one of `ids`, with
+/// their run id
+fn query_run_executions(
+    conn: &dyn Connection,
+    run_ids: &[String],
+    ids: &[String],
+) -> Result<Vec<(Option<String>, RunExecution)>> {
+    if run_ids.is_empty() && ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let qb = QueryBuilder::new(conn.dialect());
+    let mut conditions = Vec::new();
+    if !run_ids.is_empty() {
+        conditions.push(format!("run_id IN ({})", in_list(&qb, 0, run_ids.len())));
+    }
+    if !ids.is_empty() {
+        conditions.push(format!(
+            "id IN ({})",
+            in_list(&qb, run_ids.len(), ids.len())
+        ));
+    }
+    let sql = format!(
+        "SELECT id, run_id, project, agent, model, response != '', cost_usd, duration_ms, created_at
+         FROM executions WHERE {} ORDER BY created_at",
+        conditions.join(" OR ")
+    );
+    let mut params = text_params(run_ids);
+    params.extend(text_params(ids));
+    conn.query_map(&sql, &params, |row| {
+        Ok((
+            row.get(1)?,
+            RunExecution {
+                id: row.get(0)?,
+                project: row.get(2)?,
+                agent: row.get(3)?,
+                model: row.get(4)?,
+                success: row.get(5)?,
+                cost_usd: row.get(6)?,
+                duration_ms: row.get(7)?,
+                created_at: format_timestamp(row.get::<i64>(8)?),
+            },
+        ))
+    })?
+    .collect()
+}
+
+fn query_run_classifications(
+    conn: &dyn Connection,
+    run_ids: &[String],
+) -> Result<HashMap<String, Vec<ClassifyLogEntry>>> {
+    let mut by_run: HashMap<String, Vec<ClassifyLogEntry>> = HashMap::new();
+    if run_ids.is_empty() {
+        return Ok(by_run);
+    }
+    let qb = QueryBuilder::new(conn.dialect());
+    let sql = format!(
+        "SELECT id, substr(text, 1, 100), agent, model, confidence, method,
+                matched_keyword, reasoning, duration_ms, project, source, requester, created_at,
+                llm_error, run_id
+         FROM classification_logs WHERE run_id IN ({}) ORDER BY created_at",
+        in_list(&qb, 0, run_ids.len())
+    );
+    for row in conn.query_map(&sql, &text_params(run_ids), |row| {
+        Ok((
+            row.get::<String>(14)?,
+            ClassifyLogEntry {
+                id: row.get(0)?,
+                text_preview: row.get(1)?,
+                agent: row.get(2)?,
+                model: row.get(3)?,
+                confidence: row.get(4)?,
+                method: row.get(5)?,
+                matched_keyword: row.get(6)?,
+                reasoning: row.get(7)?,
+                duration_ms: row.get(8)?,
+                project: row.get(9)?,
+                source: row.get(10)?,
+                requester: row.get(11)?,
+                llm_error: row.get(13)?,
+                created_at: format_timestamp(row.get::<i64>(12)?),
+            },
+        ))
+    })? {
+        let (run_id, log) = row?;
+        by_run.entry(run_id).or_default().push(log);
+    }
+    Ok(by_run)
+}
+
+fn query_run_deliveries(
+    conn: &dyn Connection,
+    run_ids: &[String],
+) -> Result<HashMap<String, Vec<RunDelivery>>> {
+    let mut by_run: HashMap<String, Vec<RunDelivery>> = HashMap::new();
+    if run_ids.is_empty() {
+        return Ok(by_run);
+    }
+    let qb = QueryBuilder::new(conn.dialect());
+    let sql = format!(
+        "SELECT run_id, endpoint, event_type, channel, status_code, error, duration_ms, created_at
+         FROM webhook_deliveries WHERE run_id IN ({}) ORDER BY created_at, id",
+        in_list(&qb, 0, run_ids.len())
+    );
+    for row in conn.query_map(&sql, &text_params(run_ids), |row| {
+        Ok((
+            row.get::<String>(0)?,
+            RunDelivery {
+                endpoint: row.get(1)?,
+                event_type: row.get(2)?,
+                channel: row.get(3)?,
+                status_code: row.get(4)?,
+                error: row.get(5)?,
+                duration_ms: row.get(6)?,
+                created_at: format_timestamp(row.get::<i64>(7)?),
+            },
+        ))
+    })? {
+        let (run_id, delivery) = row?;
+        by_run.entry(run_id).or_default().push(delivery);
+    }
+    Ok(by_run)
+}
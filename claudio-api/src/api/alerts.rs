@@ -0,0 +1,181 @@
+//! Anomaly alerts: each check compares the latest window of executions with
+//! its rolling baseline, opens an alert for every metric that left it and
+//! resolves the open ones that returned, POSTing each change to the
+//! configured webhook.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::analytics::{self, Anomaly, AnomalySettings, HealthStatus};
+use crate::api::webhook;
+use crate::config::{AlertConfig, Config};
+use crate::storage::{
+    Alert, AlertFilter, AlertMetric, AlertScope, AlertStatus, AsyncStorage, Storage,
+};
+
+/// Webhook payload for an opened or resolved alert
+#[derive(Debug, Serialize)]
+struct AlertEvent {
+    event: &'static str,
+    message: String,
+    #[serde(flatten)]
+    alert: Alert,
+}
+
+fn settings(config: &AlertConfig) -> AnomalySettings {
+    AnomalySettings {
+        window_secs: config.window_secs,
+        baseline_secs: config.baseline_days * 86400,
+        min_requests: config.min_requests,
+        z_threshold: config.z_threshold,
+        min_change_pct: config.min_change_pct,
+    }
+}
+
+fn same_subject(alert: &Alert, anomaly: &Anomaly) -> bool {
+    alert.project == anomaly.project
+        && alert.scope == anomaly.scope
+        && alert.target == anomaly.target
+        && alert.metric == anomaly.metric
+}
+
+fn format_value(metric: AlertMetric, value: f64) -> String {
+    match metric {
+        AlertMetric::Cost => format!("${:.2}", value),
+        AlertMetric::Latency => format!("{:.0} ms", value),
+        AlertMetric::ErrorRate | AlertMetric::Satisfaction | AlertMetric::Health => {
+            format!("{:.1}%", value)
+        }
+    }
+}
+
+fn describe(alert: &Alert) -> String {
+    let what = match alert.metric {
+        AlertMetric::Cost => "Cost spike",
+        AlertMetric::Latency => "p95 latency jump",
+        AlertMetric::ErrorRate => "Error rate increase",
+        AlertMetric::Satisfaction => "Satisfaction drop",
+        AlertMetric::Health => match HealthStatus::from_success_rate(alert.value) {
+            HealthStatus::Critical => "Critical success rate",
+            _ => "Degraded success rate",
+        },
+    };
+    let subject = match (alert.scope, alert.target.as_deref()) {
+        (AlertScope::Workflow, Some(target)) => format!("workflow '{}'", target),
+        (AlertScope::Project, _) | (_, None) => format!("project '{}'", alert.project),
+        (scope, Some(target)) => format!(
+            "{} '{}' in project '{}'",
+            scope.as_str(),
+            target,
+            alert.project
+        ),
+    };
+    let message = format!(
+        "{} for {}: {} against a baseline of {}",
+        what,
+        subject,
+        format_value(alert.metric, alert.value),
+        format_value(alert.metric, alert.baseline)
+    );
+    match alert.status {
+        AlertStatus::Open => message,
+        AlertStatus::Resolved => format!("Resolved: {}", message),
+    }
+}
+
+/// Opens, refreshes and resolves alerts to match the anomalies found now.
+/// Returns the alerts that opened or resolved.
+fn reconcile(storage: &Storage, anomalies: &[Anomaly], now: i64) -> Result<Vec<AlertEvent>> {
+    let open = storage.list_alerts(
+        &AlertFilter {
+            status: Some(AlertStatus::Open),
+            ..AlertFilter::default()
+        },
+        i64::MAX,
+    )?;
+    let mut events = Vec::new();
+    for anomaly in anomalies {
+        match open.iter().find(|a| same_subject(a, anomaly)) {
+            Some(alert) => storage.update_alert(alert.id, anomaly.baseline, anomaly.value, now)?,
+            // Another instance may have opened it first
+            None => {
+                if let Some(alert) = storage.open_alert(anomaly, now)? {
+                    events.push(AlertEvent {
+                        event: "alert.opened",
+                        message: describe(&alert),
+                        alert,
+                    });
+                }
+            }
+        }
+    }
+    for alert in open {
+        if anomalies.iter().any(|a| same_subject(&alert, a)) {
+            continue;
+        }
+        if storage.resolve_alert(alert.id, now)? {
+            let alert = Alert {
+                status: AlertStatus::Resolved,
+                ended_at: Some(now),
+                updated_at: now,
+                ..alert
+            };
+            events.push(AlertEvent {
+                event: "alert.resolved",
+                message: describe(&alert),
+                alert,
+            });
+        }
+    }
+    Ok(events)
+}
+
+/// Runs one anomaly check as of now.
+async fn check(storage: &AsyncStorage) -> Result<Vec<AlertEvent>> {
+    let now = Utc::now().timestamp();
+    let settings = settings(&Config::global().alerts);
+    let anomalies = storage
+        .with_connection(move |conn| analytics::detect_anomalies(conn, now, &settings))
+        .await?;
+    storage
+        .write("reconcile_alerts", move |s| reconcile(s, &anomalies, now))
+        .await
+}
+
+async fn notify(events: Vec<AlertEvent>) {
+    let webhook = Config::global().alerts.webhook.as_deref();
+    for event in events {
+        tracing::warn!(
+            alert = event.alert.id,
+            event = event.event,
+            "{}",
+            event.message
+        );
+        let Some(url) = webhook else {
+            continue;
+        };
+        webhook::notify(url, &event, "alert").await;
+    }
+}
+
+/// Starts the background anomaly checks, unless disabled.
+pub fn spawn_detector(storage: AsyncStorage) {
+    let interval_secs = Config::global().alerts.check_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match check(&storage).await {
+                Ok(events) => notify(events).await,
+                Err(e) => tracing::warn!(error = %e, "Failed to check for anomalies"),
+            }
+        }
+    });
+}
@@ -0,0 +1,254 @@
+//! Cost budgets: checked before every execution and re-evaluated after it,
+//! posting an alert to the configured webhook the first time a period's
+//! usage crosses each threshold.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use tracing::Instrument;
+
+use crate::analytics::{self, CalendarUnit};
+use crate::api::error::ApiError;
+use crate::api::routes::AppState;
+use crate::api::types::ChatCompletionRequest;
+use crate::api::webhook;
+use crate::config::Config;
+use crate::storage::{Budget, BudgetAction, BudgetPeriod, Storage};
+
+/// A budget's spend in its current period
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub period_start: i64,
+    pub period_end: i64,
+    pub spent_usd: f64,
+    pub remaining_usd: f64,
+    pub usage_percent: f64,
+    pub exceeded: bool,
+}
+
+/// Webhook payload for a crossed threshold
+#[derive(Debug, Serialize)]
+pub struct BudgetAlert {
+    pub event: &'static str,
+    pub threshold: u32,
+    #[serde(flatten)]
+    pub status: BudgetStatus,
+}
+
+fn timezone() -> Tz {
+    Config::global()
+        .defaults
+        .timezone
+        .parse()
+        .unwrap_or(chrono_tz::UTC)
+}
+
+/// Start and end of the period containing `now`, at local midnight.
+pub fn period_bounds(period: BudgetPeriod, now: DateTime<Utc>, tz: Tz) -> (i64, i64) {
+    let unit = match period {
+        BudgetPeriod::Daily => CalendarUnit::Day,
+        BudgetPeriod::Weekly => CalendarUnit::Week,
+        BudgetPeriod::Monthly => CalendarUnit::Month,
+    };
+    analytics::calendar_bounds(unit, now.timestamp(), 0, tz)
+}
+
+/// Current status of each budget, plus alerts for thresholds crossed for
+/// the first time this period.
+fn evaluate(
+    storage: &Storage,
+    budgets: Vec<Budget>,
+    thresholds: &[u32],
+) -> Result<(Vec<BudgetStatus>, Vec<BudgetAlert>)> {
+    let now = Utc::now();
+    let tz = timezone();
+    let mut statuses = Vec::with_capacity(budgets.len());
+    let mut alerts = Vec::new();
+    for budget in budgets {
+        let (period_start, period_end) = period_bounds(budget.period, now, tz);
+        let spent_usd = storage.budget_spend(&budget, period_start)?;
+        let usage_percent = if budget.limit_usd > 0.0 {
+            spent_usd / budget.limit_usd * 100.0
+        } else {
+            100.0
+        };
+        let status = BudgetStatus {
+            period_start,
+            period_end,
+            spent_usd,
+            remaining_usd: (budget.limit_usd - spent_usd).max(0.0),
+            usage_percent,
+            exceeded: spent_usd >= budget.limit_usd,
+            budget,
+        };
+        // Only the highest newly crossed threshold is sent
+        let crossed: Vec<u32> = thresholds
+            .iter()
+            .copied()
+            .filter(|&t| usage_percent >= f64::from(t))
+            .collect();
+        let mut highest = None;
+        for threshold in crossed {
+            if storage.record_budget_alert(&status.budget.id, period_start, threshold, spent_usd)? {
+                highest = Some(threshold);
+            }
+        }
+        if let Some(threshold) = highest {
+            alerts.push(BudgetAlert {
+                event: "budget_threshold",
+                threshold,
+                status: status.clone(),
+            });
+        }
+        statuses.push(status);
+    }
+    Ok((statuses, alerts))
+}
+
+/// Status of every budget in a project.
+pub async fn project_status(state: &AppState, project_id: String) -> Result<Vec<BudgetStatus>> {
+    state
+        .storage
+        .run(move |s| {
+            let budgets = s.list_budgets(&project_id)?;
+            Ok(evaluate(s, budgets, &[])?.0)
+        })
+        .await
+}
+
+async fn check(state: &AppState, req: &ChatCompletionRequest) -> Result<Vec<BudgetStatus>> {
+    let Some(project_id) = req.project.clone() else {
+        return Ok(Vec::new());
+    };
+    let agent = req.agent.clone();
+    let requester = req.requester.clone();
+    let (statuses, alerts) = state
+        .storage
+        .run(move |s| {
+            let budgets =
+                s.applicable_budgets(&project_id, agent.as_deref(), requester.as_deref())?;
+            evaluate(s, budgets, &Config::global().budgets.alert_thresholds)
+        })
+        .await?;
+    if !alerts.is_empty() {
+        tokio::spawn(send_alerts(alerts).in_current_span());
+    }
+    Ok(statuses)
+}
+
+/// Applies the action of every exhausted budget covering `req`: the request
+/// is rejected if any of them rejects, otherwise it runs on a downgraded
+/// model or just logs a warning. Budgets that can't be read don't block.
+pub async fn enforce(state: &AppState, req: &mut ChatCompletionRequest) -> Result<(), ApiError> {
+    let statuses = match check(state, req).await {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to check budgets");
+            return Ok(());
+        }
+    };
+    let exceeded: Vec<&BudgetStatus> = statuses.iter().filter(|s| s.exceeded).collect();
+
+    if let Some(status) = exceeded
+        .iter()
+        .find(|s| s.budget.action == BudgetAction::Reject)
+    {
+        return Err(ApiError::budget_exceeded(describe(status)));
+    }
+    if let Some((status, model)) = exceeded.iter().find_map(|s| {
+        (s.budget.action == BudgetAction::Downgrade)
+            .then_some(s.budget.downgrade_model.as_ref())
+            .flatten()
+            .map(|m| (s, m))
+    }) {
+        tracing::info!(budget = %status.budget.id, model = %model, "{}, downgrading model", describe(status));
+        req.model = Some(model.clone());
+    }
+    for status in exceeded
+        .iter()
+        .filter(|s| s.budget.action == BudgetAction::Warn)
+    {
+        tracing::warn!(budget = %status.budget.id, "{}", describe(status));
+    }
+    Ok(())
+}
+
+/// Re-evaluates the budgets covering a finished request so thresholds it
+/// pushed usage over alert right away rather than on the next request.
+pub fn after_execution(state: &AppState, req: ChatCompletionRequest) {
+    let state = state.clone();
+    tokio::spawn(
+        async move {
+            if let Err(e) = check(&state, &req).await {
+                tracing::warn!(error = %e, "Failed to check budgets");
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+fn describe(status: &BudgetStatus) -> String {
+    let budget = &status.budget;
+    let scope = match budget.target {
+        Some(ref target) => format!("{} '{}'", budget.scope.as_str(), target),
+        None => format!("project '{}'", budget.project_id),
+    };
+    format!(
+        "{} budget for {} exhausted (${:.2} of ${:.2})",
+        budget.period.as_str(),
+        scope,
+        status.spent_usd,
+        budget.limit_usd
+    )
+}
+
+async fn send_alerts(alerts: Vec<BudgetAlert>) {
+    let webhook = Config::global().budgets.alert_webhook.as_deref();
+    for alert in alerts {
+        tracing::warn!(
+            budget = %alert.status.budget.id,
+            threshold = alert.threshold,
+            spent_usd = alert.status.spent_usd,
+            "Budget threshold crossed"
+        );
+        let Some(url) = webhook else {
+            continue;
+        };
+        webhook::notify(url, &alert, "budget alert").await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_period_bounds_follow_local_calendar() {
+        let tz: Tz = "Asia/Seoul".parse().unwrap();
+        // Wednesday 2025-01-15 01:00 in Seoul
+        let now = Utc.with_ymd_and_hms(2025, 1, 14, 16, 0, 0).unwrap();
+        let local = |y, m, d| tz.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().timestamp();
+
+        assert_eq!(
+            period_bounds(BudgetPeriod::Daily, now, tz),
+            (local(2025, 1, 15), local(2025, 1, 16))
+        );
+        assert_eq!(
+            period_bounds(BudgetPeriod::Weekly, now, tz),
+            (local(2025, 1, 13), local(2025, 1, 20))
+        );
+        assert_eq!(
+            period_bounds(BudgetPeriod::Monthly, now, tz),
+            (local(2025, 1, 1), local(2025, 2, 1))
+        );
+
+        // A 23-hour day when clocks spring forward
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 3, 9, 12, 0, 0).unwrap();
+        let (start, end) = period_bounds(BudgetPeriod::Daily, now, tz);
+        assert_eq!(end - start, 23 * 3600);
+    }
+}
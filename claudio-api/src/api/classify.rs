@@ -1,12 +1,17 @@
+use std::cmp::Ordering;
+
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use tracing::Instrument;
 
-use crate::api::types::ChatCompletionRequest;
+use crate::api::classify_cache;
+use crate::api::types::{ChatCompletionRequest, ChatCompletionResponse, ExecutionStatus};
 use crate::claude::ClaudeExecutor;
 use crate::config::Config;
-use crate::storage::{Agent, Project};
+use crate::plugins::semantic::{self, SemanticMatch};
+use crate::storage::{Agent, Keyword, Project};
 
 static REGEX_CACHE: Lazy<DashMap<String, Option<Regex>>> = Lazy::new(DashMap::new);
 
@@ -16,6 +21,7 @@ pub struct ClassifySettings {
     pub fallback_agent: String,
     pub model: String,
     pub timeout: u32,
+    pub clarify_threshold: f64,
 }
 
 impl Default for ClassifySettings {
@@ -24,6 +30,7 @@ impl Default for ClassifySettings {
             fallback_agent: "general".into(),
             model: "haiku".into(),
             timeout: 30,
+            clarify_threshold: 0.0,
         }
     }
 }
@@ -34,6 +41,7 @@ impl From<&Project> for ClassifySettings {
             fallback_agent: project.fallback_agent.clone(),
             model: project.classify_model.clone(),
             timeout: project.classify_timeout as u32,
+            clarify_threshold: project.clarify_threshold,
         }
     }
 }
@@ -62,13 +70,31 @@ pub struct ClassifyResponse {
     /// Classification reasoning
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning: Option<String>,
-    /// Classification method (keyword, semantic, llm, fallback)
+    /// Classification method (keyword, semantic, llm, cache, fallback, clarification)
     pub method: String,
     /// Matched keyword (if method is keyword)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub matched_keyword: Option<String>,
     /// Classification duration in milliseconds
     pub duration_ms: u64,
+    /// Revision of the selected agent at classification time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_revision: Option<i64>,
+    /// Why the LLM stage fell back: a failed call or a reply that didn't
+    /// match the classification schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_error: Option<String>,
+    /// Ranked candidates, best first (only when requested with `top_k`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<ClassifyCandidate>,
+    /// Confidence was below the project's clarify threshold; ask the user
+    /// with `clarification` instead of routing to `agent`
+    pub needs_clarification: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clarification: Option<Clarification>,
+    /// Routed to the project's fallback agent for lack of a match
+    #[serde(skip)]
+    pub fallback: bool,
 }
 
 /// Build ClassifyResponse from Agent
@@ -98,6 +124,12 @@ fn build_response(
         method: method.into(),
         matched_keyword,
         duration_ms,
+        agent_revision: Some(agent.revision),
+        llm_error: None,
+        candidates: Vec::new(),
+        needs_clarification: false,
+        clarification: None,
+        fallback: false,
     }
 }
 
@@ -115,6 +147,137 @@ fn build_fallback_response(settings: &ClassifySettings, duration_ms: u64) -> Cla
         method: "fallback".into(),
         matched_keyword: None,
         duration_ms,
+        agent_revision: None,
+        llm_error: None,
+        candidates: Vec::new(),
+        needs_clarification: false,
+        clarification: None,
+        fallback: true,
+    }
+}
+
+/// LLM classification behind the decision cache. `classify` only runs on a
+/// miss, and its decision is cached under the message's key, the fallback
+/// agent included, so repeated alerts skip the LLM either way. The exchange
+/// with the model is returned when there was one.
+async fn llm_stage<'a, F, Fut>(
+    text: &str,
+    project_id: &str,
+    agents: &[Agent],
+    sorted_agents: &[&'a Agent],
+    settings: &ClassifySettings,
+    start: std::time::Instant,
+    classify: F,
+) -> (ClassifyResponse, Option<LlmExchange>)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = LlmClassification<'a>>,
+{
+    let cache = classify_cache::global();
+    let key = cache
+        .key(text, project_id, agents, settings)
+        .instrument(tracing::info_span!("classify.cache"))
+        .await;
+    let cached = key
+        .as_ref()
+        .and_then(|key| cache.get(key))
+        .filter(|hit| hit.fallback || sorted_agents.iter().any(|a| a.name == hit.agent));
+    if let Some(hit) = cached {
+        let reasoning = Some(format!(
+            "Cached llm decision from {}s ago for \"{}\"{}",
+            hit.age.as_secs(),
+            hit.matched_text,
+            hit.reasoning
+                .map(|r| format!(": {}", r))
+                .unwrap_or_default()
+        ));
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let response = match sorted_agents.iter().find(|a| a.name == hit.agent) {
+            Some(agent) if !hit.fallback => {
+                build_response(agent, hit.confidence, reasoning, "cache", None, duration_ms)
+            }
+            _ => ClassifyResponse {
+                confidence: hit.confidence,
+                reasoning,
+                method: "cache".into(),
+                ..build_fallback_response(settings, duration_ms)
+            },
+        };
+        return (response, None);
+    }
+
+    let llm = classify().await;
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let mut exchange = LlmExchange {
+        model: settings.model.clone(),
+        prompt: llm.prompt,
+        status: llm.response.status,
+        reply: llm.response.result,
+        structured_output: llm.response.structured_output,
+        error: llm.response.error.map(|e| e.message),
+        parse_error: None,
+        parsed_agent: None,
+        confidence: None,
+    };
+    let response = match llm.parsed {
+        Ok(decision) => {
+            let response = decision.response(settings, duration_ms);
+            exchange.parsed_agent = Some(response.agent.clone());
+            exchange.confidence = Some(response.confidence);
+            if let Some(key) = key {
+                cache.insert(
+                    key,
+                    &response.agent,
+                    response.confidence,
+                    response.reasoning.clone(),
+                    response.fallback,
+                );
+            }
+            response
+        }
+        Err(e) => {
+            if let LlmError::Unparsable(_) = e {
+                exchange.parse_error = Some(e.to_string());
+            }
+            let mut response = build_fallback_response(settings, duration_ms);
+            response.llm_error = Some(e.to_string());
+            response
+        }
+    };
+    (response, Some(exchange))
+}
+
+/// Classification stages, in the order they are tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Keyword,
+    Semantic,
+    Llm,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Keyword, Stage::Semantic, Stage::Llm];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Keyword => "keyword",
+            Stage::Semantic => "semantic",
+            Stage::Llm => "llm",
+        }
+    }
+}
+
+impl std::str::FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "keyword" => Ok(Stage::Keyword),
+            "semantic" => Ok(Stage::Semantic),
+            "llm" => Ok(Stage::Llm),
+            other => anyhow::bail!("unknown classify stage '{}'", other),
+        }
     }
 }
 
@@ -124,150 +287,1090 @@ pub async fn classify_with_agents(
     agents: &[Agent],
     settings: &ClassifySettings,
 ) -> ClassifyResponse {
+    classify_with_stages(text, project_id, agents, settings, &Stage::ALL).await
+}
+
+/// Like [`classify_with_agents`], but only tries `stages`; anything they
+/// don't match falls back to the project's fallback agent.
+pub async fn classify_with_stages(
+    text: &str,
+    project_id: &str,
+    agents: &[Agent],
+    settings: &ClassifySettings,
+    stages: &[Stage],
+) -> ClassifyResponse {
+    run_pipeline(text, project_id, agents, settings, stages, false, None)
+        .await
+        .0
+}
+
+/// Classifies with every stage and attaches up to `top_k` ranked candidates.
+/// When the decision's confidence is below the project's clarify threshold,
+/// the response asks which of the candidates was meant instead.
+pub async fn classify_with_candidates(
+    text: &str,
+    project_id: &str,
+    agents: &[Agent],
+    settings: &ClassifySettings,
+    top_k: usize,
+) -> ClassifyResponse {
+    let (mut response, candidates) =
+        run_pipeline(text, project_id, agents, settings, &Stage::ALL, true, None).await;
+    clarify(&mut response, &candidates, agents, settings, top_k);
+    response.candidates = candidates.into_iter().take(top_k).collect();
+    response
+}
+
+/// Turns a decision below the project's clarify threshold into a question
+/// offering the best `candidates`.
+fn clarify(
+    response: &mut ClassifyResponse,
+    candidates: &[ClassifyCandidate],
+    agents: &[Agent],
+    settings: &ClassifySettings,
+    top_k: usize,
+) {
+    if settings.clarify_threshold > 0.0
+        && response.confidence < settings.clarify_threshold
+        && !candidates.is_empty()
+    {
+        let choices: Vec<ClarificationChoice> = candidates
+            .iter()
+            .take(CLARIFY_CHOICES.max(top_k))
+            .map(|c| ClarificationChoice {
+                agent: c.agent.clone(),
+                description: agents
+                    .iter()
+                    .find(|a| a.name == c.agent)
+                    .map(|a| a.description.clone())
+                    .unwrap_or_default(),
+                score: c.score,
+            })
+            .collect();
+        response.reasoning = Some(format!(
+            "Confidence {:.2} is below the clarify threshold {:.2} (best guess: {})",
+            response.confidence, settings.clarify_threshold, response.agent
+        ));
+        response.method = "clarification".into();
+        response.needs_clarification = true;
+        response.clarification = Some(Clarification::new(choices));
+    }
+}
+
+/// Runs the stages in order; the first that matches decides. With `rank`,
+/// keyword and semantic candidates are collected even after a decision and
+/// returned best first, the decided agent leading. With `trace`, every
+/// requested stage runs up to the LLM and records what it saw.
+#[tracing::instrument(
+    name = "classify",
+    skip_all,
+    fields(project = project_id, agents = agents.len(), method = tracing::field::Empty)
+)]
+async fn run_pipeline(
+    text: &str,
+    project_id: &str,
+    agents: &[Agent],
+    settings: &ClassifySettings,
+    stages: &[Stage],
+    rank: bool,
+    mut trace: Option<&mut Trace>,
+) -> (ClassifyResponse, Vec<ClassifyCandidate>) {
     let start = std::time::Instant::now();
-    let text_lower = text.to_lowercase();
+    let sorted_agents = sort_by_priority(agents);
+    let mut decision: Option<(Stage, ClassifyResponse)> = None;
+    let mut candidates = Vec::new();
 
-    // Sort agents by priority (highest first)
-    let mut sorted_agents: Vec<_> = agents.iter().collect();
-    sorted_agents.sort_by(|a, b| b.priority.cmp(&a.priority));
-
-    // 1. Keyword matching (fastest) - supports /regex/ patterns
-    for agent in &sorted_agents {
-        for keyword in &agent.keywords {
-            let matched = match_keyword(keyword, text, &text_lower);
-
-            if matched {
-                return build_response(
-                    agent,
-                    0.95,
-                    Some(format!("Matched '{}' → {}", keyword, agent.name)),
-                    "keyword",
-                    Some(keyword.clone()),
-                    start.elapsed().as_millis() as u64,
-                );
-            }
+    // 1. Keyword scoring (fastest) - supports /regex/ patterns
+    if stages.contains(&Stage::Keyword) {
+        let _span = tracing::info_span!("classify.keyword").entered();
+        let scores = keyword_scores(&sorted_agents, text);
+        if let Some(best) = keyword_winner(&scores) {
+            let response = keyword_response(best, start.elapsed().as_millis() as u64);
+            decision = Some((Stage::Keyword, response));
+        }
+        if rank {
+            candidates.extend(
+                scores
+                    .iter()
+                    .filter(|s| s.excluded_by.is_none() && s.score >= KEYWORD_MIN_SCORE)
+                    .map(|s| ClassifyCandidate {
+                        agent: s.agent.name.clone(),
+                        score: keyword_confidence(s.score),
+                        method: "keyword".into(),
+                    }),
+            );
+        }
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.keyword(&scores, decision.is_some());
         }
+    } else if let Some(trace) = trace.as_deref_mut() {
+        trace.skip(Stage::Keyword, "not requested");
     }
 
     // 2. Semantic routing with priority adjustment
-    let global_config = Config::global();
-    if global_config.semantic_search.enabled {
-        let semantic_matches =
-            crate::plugins::semantic::classify_for_project(text, Some(project_id));
-
-        // Apply priority adjustment to each match and find the best one
-        // Priority bonus: priority/1000 (e.g., priority 100 → +10%, priority 20 → +2%)
-        let best_match = semantic_matches
-            .into_iter()
-            .filter_map(|m| {
-                let agent = sorted_agents.iter().find(|a| a.id == m.agent)?;
-                let priority_bonus = agent.priority.max(0) as f64 / 1000.0;
-                let adjusted_score = m.score * (1.0 + priority_bonus);
-                Some((agent, m, adjusted_score))
+    if !stages.contains(&Stage::Semantic) {
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.skip(Stage::Semantic, "not requested");
+        }
+    } else if decision.is_none() || rank || trace.is_some() {
+        let matches = semantic_candidates(text, project_id, &sorted_agents)
+            .instrument(tracing::info_span!("classify.semantic"))
+            .await;
+        match matches {
+            Some(matches) => {
+                let outcome = match matches.first() {
+                    Some(best) if decision.is_none() => {
+                        let response = semantic_response(best, start.elapsed().as_millis() as u64);
+                        decision = Some((Stage::Semantic, response));
+                        StageOutcome::Decided
+                    }
+                    Some(_) => StageOutcome::Superseded,
+                    None => StageOutcome::NoMatch,
+                };
+                if rank {
+                    candidates.extend(matches.iter().map(|(agent, _, adjusted)| {
+                        ClassifyCandidate {
+                            agent: agent.name.clone(),
+                            score: adjusted.min(1.0),
+                            method: "semantic".into(),
+                        }
+                    }));
+                }
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.semantic(&matches, outcome);
+                }
+            }
+            None => {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.skip(Stage::Semantic, "semantic search is disabled");
+                }
+            }
+        }
+    }
+
+    // 3. LLM classification fallback
+    let response = match decision {
+        Some((stage, response)) => {
+            if let Some(trace) = trace.as_deref_mut() {
+                if stages.contains(&Stage::Llm) {
+                    trace.skip(Stage::Llm, format!("decided by {} stage", stage.as_str()));
+                } else {
+                    trace.skip(Stage::Llm, "not requested");
+                }
+            }
+            response
+        }
+        None if stages.contains(&Stage::Llm) => {
+            let (response, exchange) = llm_stage(
+                text,
+                project_id,
+                agents,
+                &sorted_agents,
+                settings,
+                start,
+                || {
+                    llm_classify(text, &sorted_agents, settings)
+                        .instrument(tracing::info_span!("classify.llm"))
+                },
+            )
+            .await;
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.llm(&response, exchange);
+            }
+            response
+        }
+        None => {
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.skip(Stage::Llm, "not requested");
+                trace.fallback_reason = Some("no requested stage matched".to_string());
+            }
+            build_fallback_response(settings, start.elapsed().as_millis() as u64)
+        }
+    };
+
+    tracing::Span::current().record("method", response.method.as_str());
+
+    let mut ranked = Vec::new();
+    if rank {
+        // A cached fallback is no more a candidate than a fresh one
+        if !response.fallback {
+            ranked.push(ClassifyCandidate {
+                agent: response.agent.clone(),
+                score: response.confidence,
+                method: response.method.clone(),
+            });
+        }
+        candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        for candidate in candidates {
+            if !ranked.iter().any(|c| c.agent == candidate.agent) {
+                ranked.push(candidate);
+            }
+        }
+    }
+    (response, ranked)
+}
+
+/// Choices offered when asking for clarification, unless `top_k` asks for more
+const CLARIFY_CHOICES: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassifyCandidate {
+    pub agent: String,
+    /// Confidence the candidate's stage would report
+    pub score: f64,
+    /// Stage that produced the candidate (keyword, semantic, llm)
+    pub method: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClarificationChoice {
+    pub agent: String,
+    pub description: String,
+    pub score: f64,
+}
+
+/// Question to send back instead of routing, pre-rendered for Slack.
+#[derive(Debug, Serialize)]
+pub struct Clarification {
+    pub question: String,
+    pub choices: Vec<ClarificationChoice>,
+    /// Question and numbered choices as Slack mrkdwn
+    pub mrkdwn: String,
+    /// Block Kit blocks with one button per choice; the button value is the
+    /// agent name
+    pub blocks: serde_json::Value,
+}
+
+/// `action_id` prefix of the choice buttons in [`Clarification::blocks`]
+pub const CLARIFY_ACTION_PREFIX: &str = "claudio_clarify_";
+
+impl Clarification {
+    fn new(choices: Vec<ClarificationChoice>) -> Self {
+        let question = "I'm not sure which agent should handle this. Did you mean one of these?";
+
+        let mut mrkdwn = format!("*{}*", question);
+        for (i, choice) in choices.iter().enumerate() {
+            mrkdwn.push_str(&format!("\n{}. *{}*", i + 1, choice.agent));
+            if !choice.description.is_empty() {
+                mrkdwn.push_str(&format!(" — {}", choice.description));
+            }
+        }
+
+        let buttons: Vec<serde_json::Value> = choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                serde_json::json!({
+                    "type": "button",
+                    "text": {"type": "plain_text", "text": choice.agent, "emoji": true},
+                    "value": choice.agent,
+                    "action_id": format!("{}{}", CLARIFY_ACTION_PREFIX, i),
+                })
             })
-            .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+            .collect();
+        let blocks = serde_json::json!([
+            {"type": "section", "text": {"type": "mrkdwn", "text": mrkdwn}},
+            {"type": "actions", "block_id": "claudio_clarify", "elements": buttons},
+        ]);
 
-        if let Some((agent, semantic_match, adjusted_score)) = best_match {
-            return build_response(
+        Self {
+            question: question.to_string(),
+            choices,
+            mrkdwn,
+            blocks,
+        }
+    }
+}
+
+/// Dry-run breakdown of a classification: every candidate each stage saw,
+/// plus the decision a classify request would make.
+#[derive(Debug, Serialize)]
+pub struct ClassifyExplanation {
+    pub decision: ClassifyResponse,
+    pub stages: Vec<StageExplanation>,
+    /// Why the fallback agent was chosen (if it was)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageOutcome {
+    /// This stage picked the agent
+    Decided,
+    /// Had candidates, but an earlier stage already decided
+    Superseded,
+    NoMatch,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StageExplanation {
+    pub stage: Stage,
+    pub outcome: StageOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keyword_matches: Vec<KeywordCandidate>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub semantic_candidates: Vec<SemanticCandidate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm: Option<LlmExchange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeywordCandidate {
+    pub agent: String,
+    pub priority: i32,
+    pub score: f64,
+    pub matched: Vec<Keyword>,
+    /// Negative keyword that removed this agent from the stage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded_by: Option<Keyword>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SemanticCandidate {
+    pub agent: String,
+    pub priority: i32,
+    pub matched_example: String,
+    pub raw_score: f64,
+    pub adjusted_score: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LlmExchange {
+    pub model: String,
+    pub prompt: String,
+    pub status: ExecutionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<String>,
+    /// Schema-constrained reply, when the CLI returned one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_output: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why a completed reply couldn't be used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<String>,
+    /// Agent the reply resolved to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parsed_agent: Option<String>,
+    /// Model's own confidence in `parsed_agent`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl StageExplanation {
+    fn new(stage: Stage, outcome: StageOutcome) -> Self {
+        Self {
+            stage,
+            outcome,
+            note: None,
+            keyword_matches: Vec::new(),
+            semantic_candidates: Vec::new(),
+            llm: None,
+        }
+    }
+
+    fn skipped(stage: Stage, note: impl Into<String>) -> Self {
+        Self {
+            note: Some(note.into()),
+            ..Self::new(stage, StageOutcome::Skipped)
+        }
+    }
+}
+
+/// What each stage of a traced [`run_pipeline`] saw
+#[derive(Default)]
+struct Trace {
+    stages: Vec<StageExplanation>,
+    fallback_reason: Option<String>,
+}
+
+impl Trace {
+    fn skip(&mut self, stage: Stage, note: impl Into<String>) {
+        self.stages.push(StageExplanation::skipped(stage, note));
+    }
+
+    fn keyword(&mut self, scores: &[KeywordScore], decided: bool) {
+        let outcome = if decided {
+            StageOutcome::Decided
+        } else {
+            StageOutcome::NoMatch
+        };
+        let mut stage = StageExplanation::new(Stage::Keyword, outcome);
+        stage.note = Some(format!(
+            "agents need a score of at least {} that no negative keyword vetoes",
+            KEYWORD_MIN_SCORE
+        ));
+        stage.keyword_matches = scores
+            .iter()
+            .map(|s| KeywordCandidate {
+                agent: s.agent.name.clone(),
+                priority: s.agent.priority,
+                score: s.score,
+                matched: s.matched.iter().map(|k| (*k).clone()).collect(),
+                excluded_by: s.excluded_by.cloned(),
+            })
+            .collect();
+        self.stages.push(stage);
+    }
+
+    fn semantic(&mut self, matches: &[(&Agent, SemanticMatch, f64)], outcome: StageOutcome) {
+        let semantic = &Config::global().semantic_search;
+        let mut stage = StageExplanation::new(Stage::Semantic, outcome);
+        stage.note = Some(format!(
+            "top {} matches with score >= {}; adjusted = raw * (1 + priority / 1000)",
+            semantic.top_k, semantic.min_score
+        ));
+        stage.semantic_candidates = matches
+            .iter()
+            .map(|(agent, m, adjusted)| SemanticCandidate {
+                agent: agent.name.clone(),
+                priority: agent.priority,
+                matched_example: m.matched_example.clone(),
+                raw_score: m.score,
+                adjusted_score: *adjusted,
+            })
+            .collect();
+        self.stages.push(stage);
+    }
+
+    /// `exchange` is `None` when the decision came from the cache.
+    fn llm(&mut self, response: &ClassifyResponse, exchange: Option<LlmExchange>) {
+        let outcome = if response.fallback {
+            StageOutcome::NoMatch
+        } else {
+            StageOutcome::Decided
+        };
+        let mut stage = StageExplanation::new(Stage::Llm, outcome);
+        if exchange.is_none() {
+            stage.note = Some("cached decision; the LLM wasn't called".to_string());
+        }
+        if response.fallback {
+            self.fallback_reason = Some(match (&response.llm_error, &exchange) {
+                (Some(e), _) => format!("LLM {}", e),
+                (None, Some(_)) => "LLM picked the fallback agent".to_string(),
+                (None, None) => "cached LLM decision picked the fallback agent".to_string(),
+            });
+        }
+        stage.llm = exchange;
+        self.stages.push(stage);
+    }
+}
+
+/// Runs the pipeline a classify request does, cache and clarify threshold
+/// included, without stopping at the first match, so keyword and semantic
+/// candidates are always listed. The LLM is only called when no earlier
+/// stage decided.
+pub async fn explain_with_stages(
+    text: &str,
+    project_id: &str,
+    agents: &[Agent],
+    settings: &ClassifySettings,
+    stages: &[Stage],
+) -> ClassifyExplanation {
+    let mut trace = Trace::default();
+    let rank = settings.clarify_threshold > 0.0;
+    let (mut decision, candidates) = run_pipeline(
+        text,
+        project_id,
+        agents,
+        settings,
+        stages,
+        rank,
+        Some(&mut trace),
+    )
+    .await;
+    clarify(&mut decision, &candidates, agents, settings, 0);
+    ClassifyExplanation {
+        decision,
+        stages: trace.stages,
+        fallback_reason: trace.fallback_reason,
+    }
+}
+
+fn sort_by_priority(agents: &[Agent]) -> Vec<&Agent> {
+    // Highest priority first
+    let mut sorted: Vec<_> = agents.iter().collect();
+    sorted.sort_by_key(|a| std::cmp::Reverse(a.priority));
+    sorted
+}
+
+/// Minimum keyword score for the keyword stage to decide. Keywords weighted
+/// below it only count together with other matches.
+const KEYWORD_MIN_SCORE: f64 = 1.0;
+
+struct KeywordScore<'a> {
+    agent: &'a Agent,
+    /// Sum of the weights of `matched`
+    score: f64,
+    /// Heaviest first
+    matched: Vec<&'a Keyword>,
+    excluded_by: Option<&'a Keyword>,
+}
+
+/// Scores every agent with at least one keyword match: vetoed agents last,
+/// then highest score first. `agents` comes in priority order and the sort
+/// is stable, so priority breaks ties.
+fn keyword_scores<'a>(agents: &[&'a Agent], text: &str) -> Vec<KeywordScore<'a>> {
+    let text_lower = text.to_lowercase();
+    let mut scores: Vec<KeywordScore> = agents
+        .iter()
+        .filter_map(|agent| {
+            let mut matched: Vec<&Keyword> = agent
+                .keywords
+                .iter()
+                .filter(|k| match_keyword(k, text, &text_lower))
+                .collect();
+            if matched.is_empty() {
+                return None;
+            }
+            matched.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(Ordering::Equal));
+            let excluded_by = agent
+                .negative_keywords
+                .iter()
+                .find(|k| match_keyword(k, text, &text_lower));
+            Some(KeywordScore {
+                agent,
+                score: matched.iter().map(|k| k.weight).sum(),
+                matched,
+                excluded_by,
+            })
+        })
+        .collect();
+    scores.sort_by(|a, b| {
+        a.excluded_by
+            .is_some()
+            .cmp(&b.excluded_by.is_some())
+            .then(b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal))
+    });
+    scores
+}
+
+fn keyword_winner<'s, 'a>(scores: &'s [KeywordScore<'a>]) -> Option<&'s KeywordScore<'a>> {
+    scores
+        .first()
+        .filter(|s| s.excluded_by.is_none() && s.score >= KEYWORD_MIN_SCORE)
+}
+
+/// 0.9 for a single full-weight match, approaching 0.99 as the score grows.
+fn keyword_confidence(score: f64) -> f64 {
+    (1.0 - 0.1 / score.max(KEYWORD_MIN_SCORE)).min(0.99)
+}
+
+fn keyword_response(best: &KeywordScore, duration_ms: u64) -> ClassifyResponse {
+    let patterns = best
+        .matched
+        .iter()
+        .map(|k| format!("'{}'", k.pattern))
+        .collect::<Vec<_>>()
+        .join(", ");
+    build_response(
+        best.agent,
+        keyword_confidence(best.score),
+        Some(format!(
+            "Matched {} (score: {:.2}) → {}",
+            patterns, best.score, best.agent.name
+        )),
+        "keyword",
+        best.matched.first().map(|k| k.pattern.clone()),
+        duration_ms,
+    )
+}
+
+/// Semantic matches with the priority bonus applied, best first. `None` when
+/// semantic search is disabled.
+async fn semantic_candidates<'a>(
+    text: &str,
+    project_id: &str,
+    agents: &[&'a Agent],
+) -> Option<Vec<(&'a Agent, SemanticMatch, f64)>> {
+    if !Config::global().semantic_search.enabled {
+        return None;
+    }
+    let semantic_matches = semantic::classify_for_project(text, Some(project_id)).await;
+
+    // Priority bonus: priority/1000 (e.g., priority 100 → +10%, priority 20 → +2%)
+    let mut candidates: Vec<_> = semantic_matches
+        .into_iter()
+        .filter_map(|m| {
+            let agent = *agents.iter().find(|a| a.id == m.agent)?;
+            let priority_bonus = agent.priority.max(0) as f64 / 1000.0;
+            let adjusted_score = m.score * (1.0 + priority_bonus);
+            Some((agent, m, adjusted_score))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal));
+    Some(candidates)
+}
+
+fn semantic_response(
+    (agent, semantic_match, adjusted_score): &(&Agent, SemanticMatch, f64),
+    duration_ms: u64,
+) -> ClassifyResponse {
+    build_response(
+        agent,
+        adjusted_score.min(1.0), // Cap at 1.0
+        Some(format!(
+            "Semantic match (raw: {:.2}, adjusted: {:.2}, priority: {}) → '{}'",
+            semantic_match.score, adjusted_score, agent.priority, semantic_match.matched_example
+        )),
+        "semantic",
+        None,
+        duration_ms,
+    )
+}
+
+/// Confidence of an LLM decision whose reply left it out
+const LLM_DEFAULT_CONFIDENCE: f64 = 0.8;
+
+/// Agent picked by the LLM stage
+struct LlmDecision<'a> {
+    /// `None` when the model picked the fallback agent
+    agent: Option<&'a Agent>,
+    confidence: f64,
+    reasoning: Option<String>,
+}
+
+impl LlmDecision<'_> {
+    fn response(self, settings: &ClassifySettings, duration_ms: u64) -> ClassifyResponse {
+        match self.agent {
+            Some(agent) => build_response(
                 agent,
-                adjusted_score.min(1.0), // Cap at 1.0
-                Some(format!(
-                    "Semantic match (raw: {:.2}, adjusted: {:.2}, priority: {}) → '{}'",
-                    semantic_match.score,
-                    adjusted_score,
-                    agent.priority,
-                    semantic_match.matched_example
-                )),
-                "semantic",
+                self.confidence,
+                self.reasoning,
+                "llm",
                 None,
-                start.elapsed().as_millis() as u64,
-            );
+                duration_ms,
+            ),
+            None => ClassifyResponse {
+                confidence: self.confidence,
+                reasoning: self.reasoning,
+                ..build_fallback_response(settings, duration_ms)
+            },
         }
     }
+}
 
-    // 3. LLM classification fallback
-    let agent_list = sorted_agents
+/// Why the LLM stage produced no decision
+#[derive(Debug)]
+enum LlmError {
+    /// The call itself failed or timed out
+    Request(String),
+    /// The reply didn't match the classification schema
+    Unparsable(String),
+}
+
+impl std::fmt::Display for LlmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LlmError::Request(e) => write!(f, "request failed: {}", e),
+            LlmError::Unparsable(e) => write!(f, "unparsable reply: {}", e),
+        }
+    }
+}
+
+struct LlmClassification<'a> {
+    prompt: String,
+    response: ChatCompletionResponse,
+    parsed: Result<LlmDecision<'a>, LlmError>,
+}
+
+/// JSON schema the classifier reply must follow: one of `names`, with the
+/// model's confidence and a short reason.
+fn classification_schema(names: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "agent": {"type": "string", "enum": names},
+            "confidence": {"type": "number", "minimum": 0, "maximum": 1},
+            "reasoning": {"type": "string"}
+        },
+        "required": ["agent", "confidence", "reasoning"],
+        "additionalProperties": false
+    })
+}
+
+async fn llm_classify<'a>(
+    text: &str,
+    agents: &[&'a Agent],
+    settings: &ClassifySettings,
+) -> LlmClassification<'a> {
+    let listed: Vec<&Agent> = agents
         .iter()
+        .copied()
         .filter(|a| !a.description.is_empty() && a.name != settings.fallback_agent)
+        .collect();
+    let agent_list = listed
+        .iter()
         .map(|a| format!("- {}: {}", a.name, a.description))
         .collect::<Vec<_>>()
         .join("\n");
+    let mut names: Vec<&str> = listed.iter().map(|a| a.name.as_str()).collect();
+    names.push(&settings.fallback_agent);
 
-    let classification_prompt = format!(
+    let prompt = format!(
         "Classify the request into the most appropriate agent.\n\n\
          Request: {}\n\n\
          Available agents:\n{}\n\
          - {}: General tasks not matching above categories\n\n\
-         Respond ONLY with JSON: {{\"agent\": \"NAME\", \"reasoning\": \"brief reason\"}}",
+         Answer with the agent name, your confidence between 0 and 1, and a brief reason.",
         text, agent_list, settings.fallback_agent
     );
 
     let req = ChatCompletionRequest {
-        user_message: classification_prompt,
+        user_message: prompt.clone(),
         model: Some(settings.model.clone()),
         timeout: Some(settings.timeout as u64),
-        working_dir: Some(global_config.defaults.isolated_dir.clone()),
+        working_dir: Some(Config::global().defaults.isolated_dir.clone()),
+        output_schema: Some(classification_schema(&names)),
         ..Default::default()
     };
 
     let response = ClaudeExecutor::execute(req).await;
-    let duration_ms = start.elapsed().as_millis() as u64;
+    let parsed = parse_llm_reply(&response, agents, settings);
+    LlmClassification {
+        prompt,
+        response,
+        parsed,
+    }
+}
 
-    if response.status.is_success()
-        && let Some(result) = response.result
-        && let Some((agent, reasoning)) = parse_classification_db(&result, &sorted_agents)
-    {
-        return build_response(agent, 0.80, reasoning, "llm", None, duration_ms);
+/// Reads the schema-constrained reply. Agent names must match exactly; a
+/// reply without structured output is only accepted if it is that JSON
+/// object as a whole.
+fn parse_llm_reply<'a>(
+    response: &ChatCompletionResponse,
+    agents: &[&'a Agent],
+    settings: &ClassifySettings,
+) -> Result<LlmDecision<'a>, LlmError> {
+    if !response.status.is_success() {
+        let reason = response
+            .error
+            .as_ref()
+            .map(|e| e.message.clone())
+            .unwrap_or_else(|| response.status.to_string());
+        return Err(LlmError::Request(reason));
+    }
+
+    #[derive(Deserialize)]
+    struct Reply {
+        agent: String,
+        confidence: Option<f64>,
+        reasoning: Option<String>,
     }
 
-    build_fallback_response(settings, duration_ms)
+    let reply: Reply = match (&response.structured_output, &response.result) {
+        (Some(value), _) => serde_json::from_value(value.clone())
+            .map_err(|e| LlmError::Unparsable(e.to_string()))?,
+        (None, Some(result)) => serde_json::from_str(result.trim())
+            .map_err(|_| LlmError::Unparsable("no structured output".to_string()))?,
+        (None, None) => return Err(LlmError::Unparsable("empty reply".to_string())),
+    };
+
+    let agent = match agents.iter().find(|a| a.name == reply.agent) {
+        Some(agent) => Some(*agent),
+        None if reply.agent == settings.fallback_agent => None,
+        None => {
+            return Err(LlmError::Unparsable(format!(
+                "unknown agent '{}'",
+                reply.agent
+            )));
+        }
+    };
+    Ok(LlmDecision {
+        agent,
+        confidence: reply
+            .confidence
+            .filter(|c| c.is_finite())
+            .map_or(LLM_DEFAULT_CONFIDENCE, |c| c.clamp(0.0, 1.0)),
+        reasoning: reply.reasoning,
+    })
 }
 
-fn match_keyword(keyword: &str, text: &str, text_lower: &str) -> bool {
-    if keyword.starts_with('/') && keyword.ends_with('/') && keyword.len() > 2 {
-        let pattern = &keyword[1..keyword.len() - 1];
+/// `\b` only fits next to word characters: around the `+` of "c++" it would
+/// demand a letter follow, so edges like that match as they are.
+fn whole_word_regex(pattern: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let boundary = |word: bool| if word { r"\b" } else { "" };
+    format!(
+        "(?i){}{}{}",
+        boundary(is_word(pattern.chars().next())),
+        regex::escape(pattern),
+        boundary(is_word(pattern.chars().next_back()))
+    )
+}
 
+fn match_keyword(keyword: &Keyword, text: &str, text_lower: &str) -> bool {
+    let pattern = &keyword.pattern;
+    if keyword.is_regex() {
         let entry = REGEX_CACHE
-            .entry(keyword.to_string())
-            .or_insert_with(|| Regex::new(&format!("(?i){}", pattern)).ok());
+            .entry(pattern.clone())
+            .or_insert_with(|| Regex::new(&format!("(?i){}", &pattern[1..pattern.len() - 1])).ok());
+
+        entry.as_ref().is_some_and(|re| re.is_match(text))
+    } else if keyword.whole_word {
+        // Cached under a key that can't collide with a /regex/ keyword
+        let entry = REGEX_CACHE
+            .entry(format!("word:{}", pattern))
+            .or_insert_with(|| Regex::new(&whole_word_regex(pattern)).ok());
 
         entry.as_ref().is_some_and(|re| re.is_match(text))
     } else {
-        text_lower.contains(&keyword.to_lowercase())
+        text_lower.contains(&pattern.to_lowercase())
     }
 }
 
-fn parse_classification_db<'a>(
-    text: &str,
-    agents: &'a [&Agent],
-) -> Option<(&'a Agent, Option<String>)> {
-    let json_start = text.find('{')?;
-    let json_end = text.rfind('}')?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if json_start >= json_end {
-        return find_agent_by_name(text, agents);
+    fn agent(name: &str, priority: i32, keywords: serde_json::Value) -> Agent {
+        serde_json::from_value(serde_json::json!({
+            "id": name,
+            "project_id": "demo",
+            "name": name,
+            "description": "",
+            "model": "haiku",
+            "priority": priority,
+            "keywords": keywords,
+            "examples": [],
+            "timeout": 300,
+            "static_response": false,
+            "revision": 1,
+            "created_at": 0,
+            "updated_at": 0,
+        }))
+        .unwrap()
     }
 
-    let json_str = text.get(json_start..=json_end)?;
+    fn winner(agents: &[Agent], text: &str) -> Option<String> {
+        let sorted = sort_by_priority(agents);
+        keyword_winner(&keyword_scores(&sorted, text)).map(|s| s.agent.name.clone())
+    }
 
-    #[derive(Deserialize)]
-    struct ParsedResult {
-        agent: String,
-        reasoning: Option<String>,
+    #[test]
+    fn test_keyword_scoring() {
+        let mut triage = agent(
+            "triage",
+            90,
+            serde_json::json!([{"pattern": "error", "weight": 0.5}, "incident"]),
+        );
+        let deploy = agent(
+            "deploy",
+            10,
+            serde_json::json!(["deploy", {"pattern": "error", "weight": 0.5}]),
+        );
+        let agents = vec![triage.clone(), deploy.clone()];
+
+        // A light keyword alone doesn't decide; combined weights beat priority
+        assert_eq!(winner(&agents, "I see an error"), None);
+        assert_eq!(
+            winner(&agents, "deploy failed with an error").as_deref(),
+            Some("deploy")
+        );
+        // Equal scores go to the higher priority agent
+        assert_eq!(
+            winner(&agents, "deploy during the incident").as_deref(),
+            Some("triage")
+        );
+
+        triage.negative_keywords = vec![Keyword::from("deploy")];
+        let agents = vec![triage, deploy];
+        assert_eq!(
+            winner(&agents, "deploy during the incident").as_deref(),
+            Some("deploy")
+        );
     }
 
-    if let Ok(parsed) = serde_json::from_str::<ParsedResult>(json_str)
-        && let Some(agent) = agents.iter().find(|a| a.name == parsed.agent)
-    {
-        return Some((agent, parsed.reasoning));
+    #[tokio::test]
+    async fn test_low_confidence_asks_for_clarification() {
+        let agents = vec![
+            agent("triage", 90, serde_json::json!(["incident"])),
+            agent("deploy", 10, serde_json::json!(["deploy", "rollout"])),
+        ];
+        let settings = ClassifySettings {
+            clarify_threshold: 0.92,
+            ..Default::default()
+        };
+        let text = "deploy during the incident";
+
+        let response = classify_with_candidates(text, "demo", &agents, &settings, 5).await;
+        assert!(response.needs_clarification);
+        assert_eq!(response.method, "clarification");
+        let ranked: Vec<&str> = response
+            .candidates
+            .iter()
+            .map(|c| c.agent.as_str())
+            .collect();
+        assert_eq!(ranked, vec!["triage", "deploy"]);
+        let clarification = response.clarification.unwrap();
+        assert_eq!(clarification.choices.len(), 2);
+        assert!(clarification.mrkdwn.contains("1. *triage*"));
+        assert_eq!(clarification.blocks[1]["elements"][1]["value"], "deploy");
+
+        // Explaining goes through the same pipeline and threshold
+        let explained = explain_with_stages(text, "demo", &agents, &settings, &Stage::ALL).await;
+        assert!(explained.decision.needs_clarification);
+        assert_eq!(explained.decision.agent, "triage");
+        let outcomes: Vec<_> = explained.stages.iter().map(|s| s.outcome).collect();
+        assert_eq!(outcomes[0], StageOutcome::Decided);
+        assert_eq!(outcomes[2], StageOutcome::Skipped);
+
+        // Two full matches clear the threshold and route directly
+        let response =
+            classify_with_candidates("deploy the rollout", "demo", &agents, &settings, 0).await;
+        assert!(!response.needs_clarification);
+        assert_eq!(response.agent, "deploy");
+        assert!(response.candidates.is_empty());
     }
 
-    find_agent_by_name(text, agents)
-}
+    #[test]
+    fn test_whole_word_keywords() {
+        let agents = vec![agent(
+            "ops",
+            50,
+            serde_json::json!([{"pattern": "CI", "whole_word": true}]),
+        )];
+        assert_eq!(winner(&agents, "rerun ci please").as_deref(), Some("ops"));
+        assert_eq!(winner(&agents, "decide on a city"), None);
 
-fn find_agent_by_name<'a>(text: &str, agents: &'a [&Agent]) -> Option<(&'a Agent, Option<String>)> {
-    agents
-        .iter()
-        .find(|a| text.contains(&a.name))
-        .map(|agent| (*agent, None))
+        let agents = vec![agent(
+            "dev",
+            50,
+            serde_json::json!([
+                {"pattern": "c++", "whole_word": true},
+                {"pattern": ".net", "whole_word": true}
+            ]),
+        )];
+        assert_eq!(winner(&agents, "c++ build failed").as_deref(), Some("dev"));
+        assert_eq!(winner(&agents, "upgrade to .NET 8").as_deref(), Some("dev"));
+        assert_eq!(winner(&agents, "abc++ build failed"), None);
+        assert_eq!(winner(&agents, "check the .network tab"), None);
+
+        let plain = Keyword::from("deploy");
+        assert_eq!(serde_json::to_value(&plain).unwrap(), "deploy");
+        assert_eq!(plain.weight, 1.0);
+        assert_eq!(keyword_confidence(1.0), 0.9);
+    }
+
+    fn llm_reply(structured: Option<serde_json::Value>, result: &str) -> ChatCompletionResponse {
+        ChatCompletionResponse {
+            id: "r1".into(),
+            status: ExecutionStatus::Completed,
+            created: 0,
+            result: Some(result.into()),
+            structured_output: structured,
+            claude_response: None,
+            error: None,
+            project: "demo".into(),
+            duration_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_llm_reply_requires_exact_agent_names() {
+        let agents = vec![
+            agent("ops", 50, serde_json::json!([])),
+            agent("devops", 50, serde_json::json!([])),
+        ];
+        let sorted = sort_by_priority(&agents);
+        let settings = ClassifySettings::default();
+
+        let reply = llm_reply(
+            Some(serde_json::json!({"agent": "devops", "confidence": 1.4, "reasoning": "infra"})),
+            "",
+        );
+        let decision = parse_llm_reply(&reply, &sorted, &settings).unwrap();
+        assert_eq!(
+            (decision.agent.unwrap().name.as_str(), decision.confidence),
+            ("devops", 1.0)
+        );
+
+        // Free text naming an agent is no longer scraped for a substring match
+        let reply = llm_reply(None, "I'd pick devops: {\"agent\": \"devops\"}");
+        assert!(matches!(
+            parse_llm_reply(&reply, &sorted, &settings),
+            Err(LlmError::Unparsable(_))
+        ));
+        let reply = llm_reply(Some(serde_json::json!({"agent": "dev"})), "");
+        assert!(matches!(
+            parse_llm_reply(&reply, &sorted, &settings),
+            Err(LlmError::Unparsable(_))
+        ));
+
+        // The fallback agent keeps the model's confidence and reasoning
+        let reply = llm_reply(
+            None,
+            r#"{"agent": "general", "confidence": 0.6, "reasoning": "small talk"}"#,
+        );
+        let fallback = parse_llm_reply(&reply, &sorted, &settings)
+            .unwrap()
+            .response(&settings, 0);
+        assert_eq!(
+            (
+                fallback.agent.as_str(),
+                fallback.method.as_str(),
+                fallback.confidence,
+                fallback.reasoning.as_deref()
+            ),
+            ("general", "fallback", 0.6, Some("small talk"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_decisions_are_cached() {
+        let agents = vec![agent("ops", 50, serde_json::json!([]))];
+        let sorted = sort_by_priority(&agents);
+        let settings = ClassifySettings::default();
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let classify = || async {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let response = llm_reply(None, r#"{"agent": "general", "confidence": 0.6}"#);
+            let parsed = parse_llm_reply(&response, &sorted, &settings);
+            LlmClassification {
+                prompt: String::new(),
+                response,
+                parsed,
+            }
+        };
+
+        let text = "Heartbeat 17 from fallback-cache-test";
+        let start = std::time::Instant::now();
+        let (first, exchange) = llm_stage(
+            text,
+            "fallback-cache",
+            &agents,
+            &sorted,
+            &settings,
+            start,
+            classify,
+        )
+        .await;
+        assert_eq!(
+            (first.agent.as_str(), first.method.as_str()),
+            ("general", "fallback")
+        );
+        assert!(exchange.is_some());
+        let (again, exchange) = llm_stage(
+            "heartbeat 42 from fallback-cache-test",
+            "fallback-cache",
+            &agents,
+            &sorted,
+            &settings,
+            start,
+            classify,
+        )
+        .await;
+        assert_eq!(
+            (again.agent.as_str(), again.method.as_str()),
+            ("general", "cache")
+        );
+        assert!(exchange.is_none());
+        assert_eq!((first.confidence, again.confidence), (0.6, 0.6));
+        assert!(first.fallback && again.fallback);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
 }
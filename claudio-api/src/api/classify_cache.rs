@@ -0,0 +1,311 @@
+//! Reuses LLM routing decisions for repeated messages, so bot alerts that
+//! only differ in numbers or whitespace don't each pay for an LLM call.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+
+use crate::api::classify::ClassifySettings;
+use crate::config::{ClassifyCacheConfig, Config};
+use crate::plugins::semantic;
+use crate::storage::Agent;
+
+static CACHE: Lazy<ClassifyCache> =
+    Lazy::new(|| ClassifyCache::new(&Config::global().classify_cache));
+
+/// Decision taken from the cache
+#[derive(Debug, Clone)]
+pub struct CachedDecision {
+    pub agent: String,
+    pub confidence: f64,
+    pub reasoning: Option<String>,
+    /// `agent` is the project's fallback agent, picked for lack of a match
+    pub fallback: bool,
+    /// Time since the decision was cached
+    pub age: Duration,
+    /// Normalized text of the cached message
+    pub matched_text: String,
+}
+
+/// Where a message's decision is cached: its project, the agent set it was
+/// classified against and its normalized text (plus embedding for
+/// near-duplicate lookups).
+pub struct CacheKey {
+    project_id: String,
+    fingerprint: u64,
+    text: String,
+    vector: Option<Vec<f32>>,
+}
+
+struct CacheEntry {
+    agent: String,
+    confidence: f64,
+    reasoning: Option<String>,
+    fallback: bool,
+    vector: Option<Vec<f32>>,
+    stored_at: Instant,
+}
+
+struct ProjectCache {
+    fingerprint: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+pub struct ClassifyCache {
+    projects: DashMap<String, ProjectCache>,
+    ttl: Duration,
+    max_entries: usize,
+    similarity: f64,
+}
+
+impl ClassifyCache {
+    pub fn new(config: &ClassifyCacheConfig) -> Self {
+        Self {
+            projects: DashMap::new(),
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_entries: config.max_entries,
+            similarity: config.similarity,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    /// `None` when the cache is disabled or the text normalizes to nothing.
+    pub async fn key(
+        &self,
+        text: &str,
+        project_id: &str,
+        agents: &[Agent],
+        settings: &ClassifySettings,
+    ) -> Option<CacheKey> {
+        if !self.enabled() {
+            return None;
+        }
+        let text = normalize(text);
+        if text.is_empty() {
+            return None;
+        }
+        let vector = if self.similarity > 0.0 {
+            semantic::embed_text(&text).await
+        } else {
+            None
+        };
+        Some(CacheKey {
+            project_id: project_id.to_string(),
+            fingerprint: fingerprint(agents, settings),
+            text,
+            vector,
+        })
+    }
+
+    /// Exact normalized-text hit first, then the most similar near-duplicate
+    /// above the similarity threshold. Entries cached against another agent
+    /// set are dropped.
+    pub fn get(&self, key: &CacheKey) -> Option<CachedDecision> {
+        let mut project = self.projects.get_mut(&key.project_id)?;
+        if project.fingerprint != key.fingerprint {
+            project.fingerprint = key.fingerprint;
+            project.entries.clear();
+            return None;
+        }
+        let ttl = self.ttl;
+        project.entries.retain(|_, e| e.stored_at.elapsed() < ttl);
+
+        let (text, entry) = match project.entries.get_key_value(&key.text) {
+            Some(hit) => hit,
+            None => {
+                let query = key.vector.as_ref()?;
+                project
+                    .entries
+                    .iter()
+                    .filter_map(|(text, e)| {
+                        let score = dot(query, e.vector.as_ref()?);
+                        (score >= self.similarity).then_some((score, text, e))
+                    })
+                    .max_by(|a, b| a.0.total_cmp(&b.0))
+                    .map(|(_, text, e)| (text, e))?
+            }
+        };
+        Some(CachedDecision {
+            agent: entry.agent.clone(),
+            confidence: entry.confidence,
+            reasoning: entry.reasoning.clone(),
+            fallback: entry.fallback,
+            age: entry.stored_at.elapsed(),
+            matched_text: text.clone(),
+        })
+    }
+
+    pub fn insert(
+        &self,
+        key: CacheKey,
+        agent: &str,
+        confidence: f64,
+        reasoning: Option<String>,
+        fallback: bool,
+    ) {
+        let mut project = self
+            .projects
+            .entry(key.project_id)
+            .or_insert_with(|| ProjectCache {
+                fingerprint: key.fingerprint,
+                entries: HashMap::new(),
+            });
+        if project.fingerprint != key.fingerprint {
+            project.fingerprint = key.fingerprint;
+            project.entries.clear();
+        }
+        if project.entries.len() >= self.max_entries
+            && !project.entries.contains_key(&key.text)
+            && let Some(oldest) = project
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.stored_at)
+                .map(|(text, _)| text.clone())
+        {
+            project.entries.remove(&oldest);
+        }
+        project.entries.insert(
+            key.text,
+            CacheEntry {
+                agent: agent.to_string(),
+                confidence,
+                reasoning,
+                fallback,
+                vector: key.vector,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn invalidate(&self, project_id: &str) {
+        self.projects.remove(project_id);
+    }
+}
+
+pub fn global() -> &'static ClassifyCache {
+    &CACHE
+}
+
+/// Drops a project's cached decisions after its agents changed.
+pub fn invalidate_project(project_id: &str) {
+    CACHE.invalidate(project_id);
+}
+
+/// Lowercases, replaces digit runs with `#` and collapses whitespace, so
+/// alerts differing only in ids, counts or timestamps share an entry.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut in_digits = false;
+    for word in text.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !in_digits {
+                    normalized.push('#');
+                }
+                in_digits = true;
+            } else {
+                normalized.extend(c.to_lowercase());
+                in_digits = false;
+            }
+        }
+        in_digits = false;
+    }
+    normalized
+}
+
+/// Identifies the agent set and settings a decision was made against; any
+/// agent revision or classify setting change yields a new fingerprint.
+fn fingerprint(agents: &[Agent], settings: &ClassifySettings) -> u64 {
+    let mut revisions: Vec<(&str, i64)> =
+        agents.iter().map(|a| (a.id.as_str(), a.revision)).collect();
+    revisions.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    revisions.hash(&mut hasher);
+    settings.model.hash(&mut hasher);
+    settings.fallback_agent.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| f64::from(*x) * f64::from(*y))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize) -> ClassifyCache {
+        ClassifyCache::new(&ClassifyCacheConfig {
+            ttl_secs: 60,
+            max_entries,
+            similarity: 0.0,
+        })
+    }
+
+    #[test]
+    fn test_normalize_masks_numbers_and_whitespace() {
+        assert_eq!(
+            normalize("  CPU   usage 97% on host-12\n(alert #4411)"),
+            "cpu usage #% on host-# (alert ##)"
+        );
+        assert_eq!(normalize("Disk 80%"), normalize("disk   91%"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_until_agents_change() {
+        let cache = cache(2);
+        let settings = ClassifySettings::default();
+        let key = cache
+            .key("Disk 91% on db-1", "demo", &[], &settings)
+            .await
+            .unwrap();
+        assert!(cache.get(&key).is_none());
+        cache.insert(key, "ops", 0.8, Some("disk alert".into()), false);
+
+        let key = cache
+            .key("disk 80% on db-2", "demo", &[], &settings)
+            .await
+            .unwrap();
+        let hit = cache.get(&key).unwrap();
+        assert_eq!(
+            (hit.agent.as_str(), hit.confidence, hit.fallback),
+            ("ops", 0.8, false)
+        );
+        let other = cache
+            .key("disk 80% on db-2", "other", &[], &settings)
+            .await
+            .unwrap();
+        assert!(cache.get(&other).is_none());
+
+        // The oldest entry is evicted once the project is full
+        for text in ["first", "second"] {
+            let key = cache.key(text, "demo", &[], &settings).await.unwrap();
+            cache.insert(key, "general", 0.8, None, true);
+        }
+        assert!(cache.get(&key).is_none());
+        let key = cache.key("second", "demo", &[], &settings).await.unwrap();
+        assert!(cache.get(&key).unwrap().fallback);
+
+        let changed = ClassifySettings {
+            model: "sonnet".into(),
+            ..ClassifySettings::default()
+        };
+        let key = cache.key("second", "demo", &[], &changed).await.unwrap();
+        assert!(cache.get(&key).is_none());
+        let key = cache.key("second", "demo", &[], &settings).await.unwrap();
+        assert!(cache.get(&key).is_none());
+    }
+}
@@ -5,6 +5,8 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::storage::Overloaded;
+
 #[derive(Debug, Serialize)]
 pub struct ApiError {
     pub code: ErrorCode,
@@ -16,9 +18,12 @@ pub struct ApiError {
 #[derive(Debug, Clone, Copy, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorCode {
+    BadRequest,
     NotFound,
     Forbidden,
     RateLimited,
+    BudgetExceeded,
+    Unavailable,
     Internal,
 }
 
@@ -31,6 +36,14 @@ impl ApiError {
         }
     }
 
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            code: ErrorCode::BadRequest,
+            message: message.into(),
+            status_code: StatusCode::BAD_REQUEST,
+        }
+    }
+
     pub fn forbidden(message: impl Into<String>) -> Self {
         Self {
             code: ErrorCode::Forbidden,
@@ -47,6 +60,22 @@ impl ApiError {
         }
     }
 
+    pub fn budget_exceeded(message: impl Into<String>) -> Self {
+        Self {
+            code: ErrorCode::BudgetExceeded,
+            message: message.into(),
+            status_code: StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self {
+            code: ErrorCode::Unavailable,
+            message: message.into(),
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
     pub fn internal() -> Self {
         Self {
             code: ErrorCode::Internal,
@@ -64,6 +93,10 @@ impl IntoResponse for ApiError {
 
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
+        if let Some(overloaded) = err.downcast_ref::<Overloaded>() {
+            tracing::warn!(error = %overloaded, "Rejecting request");
+            return Self::unavailable(overloaded.to_string());
+        }
         tracing::error!(error = %err, "Internal error");
         Self::internal()
     }
@@ -0,0 +1,322 @@
+//! Offline routing evaluation: replays a project's labeled eval cases through
+//! the classifier and scores the result against the previous run.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::api::classify::{self, ClassifySettings, Stage};
+use crate::storage::{AsyncStorage, EvalCase, EvalCaseResult, EvalRun, Project};
+
+/// Cases classified at once; the LLM stage spawns a CLI process per case.
+const EVAL_CONCURRENCY: usize = 4;
+
+/// Cases taken from each seed source when no limit is given.
+pub const DEFAULT_SEED_LIMIT: i64 = 200;
+
+#[derive(Debug, Serialize)]
+pub struct MethodStats {
+    /// Cases decided by this method
+    pub cases: i64,
+    pub correct: i64,
+    /// Share of all cases decided by this method
+    pub coverage: f64,
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvalReport {
+    #[serde(flatten)]
+    pub run: EvalRun,
+    /// expected agent → predicted agent → count
+    pub confusion: BTreeMap<String, BTreeMap<String, i64>>,
+    pub methods: BTreeMap<String, MethodStats>,
+    /// Accuracy over verified cases only; logged labels just replay the
+    /// classifier's own decisions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_accuracy: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_run_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_accuracy: Option<f64>,
+    /// Cases the previous run got right and this one gets wrong
+    pub regressions: Vec<EvalCaseResult>,
+    /// Cases the previous run got wrong and this one gets right
+    pub fixed: Vec<EvalCaseResult>,
+}
+
+impl EvalReport {
+    pub fn new(run: EvalRun, previous: Option<EvalRun>) -> Self {
+        let total = run.results.len().max(1) as f64;
+
+        let mut confusion: BTreeMap<String, BTreeMap<String, i64>> = BTreeMap::new();
+        let mut methods: BTreeMap<String, MethodStats> = BTreeMap::new();
+        for result in &run.results {
+            *confusion
+                .entry(result.expected.clone())
+                .or_default()
+                .entry(result.predicted.clone())
+                .or_default() += 1;
+
+            let stats = methods.entry(result.method.clone()).or_insert(MethodStats {
+                cases: 0,
+                correct: 0,
+                coverage: 0.0,
+                accuracy: 0.0,
+            });
+            stats.cases += 1;
+            stats.correct += result.correct as i64;
+        }
+        for stats in methods.values_mut() {
+            stats.coverage = stats.cases as f64 / total;
+            stats.accuracy = stats.correct as f64 / stats.cases as f64;
+        }
+        let verified: Vec<bool> = run
+            .results
+            .iter()
+            .filter(|r| r.verified)
+            .map(|r| r.correct)
+            .collect();
+        let verified_accuracy = (!verified.is_empty())
+            .then(|| verified.iter().filter(|&&c| c).count() as f64 / verified.len() as f64);
+
+        let previous_correct: HashMap<&str, bool> = previous
+            .iter()
+            .flat_map(|p| &p.results)
+            .map(|r| (r.case_id.as_str(), r.correct))
+            .collect();
+        let (mut regressions, mut fixed) = (Vec::new(), Vec::new());
+        for result in &run.results {
+            match previous_correct.get(result.case_id.as_str()) {
+                Some(true) if !result.correct => regressions.push(result.clone()),
+                Some(false) if result.correct => fixed.push(result.clone()),
+                _ => {}
+            }
+        }
+
+        Self {
+            run,
+            confusion,
+            methods,
+            verified_accuracy,
+            previous_run_id: previous.as_ref().map(|p| p.id.clone()),
+            previous_accuracy: previous.as_ref().map(|p| p.accuracy),
+            regressions,
+            fixed,
+        }
+    }
+}
+
+/// Classifies every case with `stages`, stores the run and reports it against
+/// the last run over the same stages.
+pub async fn run_eval(
+    storage: &AsyncStorage,
+    project: &Project,
+    cases: Vec<EvalCase>,
+    stages: Vec<Stage>,
+) -> Result<EvalReport> {
+    let pid = project.id.clone();
+    let agents = storage.run(move |s| s.list_agents(&pid)).await?;
+    let settings = ClassifySettings::from(project);
+
+    let results: Vec<EvalCaseResult> = futures::stream::iter(cases)
+        .map(|case| {
+            let (agents, settings, stages) = (&agents, &settings, &stages);
+            async move {
+                let response = classify::classify_with_stages(
+                    &case.text,
+                    &project.id,
+                    agents,
+                    settings,
+                    stages,
+                )
+                .await;
+                EvalCaseResult {
+                    correct: response.agent == case.expected_agent,
+                    verified: case.verified,
+                    case_id: case.id,
+                    text: case.text,
+                    expected: case.expected_agent,
+                    predicted: response.agent,
+                    method: response.method,
+                }
+            }
+        })
+        .buffered(EVAL_CONCURRENCY)
+        .collect()
+        .await;
+
+    let correct = results.iter().filter(|r| r.correct).count() as i64;
+    let run = EvalRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_id: project.id.clone(),
+        stages: stages.iter().map(|s| s.as_str().to_string()).collect(),
+        total: results.len() as i64,
+        correct,
+        accuracy: correct as f64 / results.len().max(1) as f64,
+        results,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    let (pid, stage_names) = (run.project_id.clone(), run.stages.clone());
+    let previous = storage
+        .run(move |s| s.last_eval_run(&pid, &stage_names))
+        .await?;
+    let saved = run.clone();
+    storage.run(move |s| s.save_eval_run(&saved)).await?;
+
+    Ok(EvalReport::new(run, previous))
+}
+
+/// Parses a comma-separated stage list; empty means every stage.
+pub fn parse_stages(list: Option<&str>) -> Result<Vec<Stage>> {
+    let stages = match list.filter(|s| !s.trim().is_empty()) {
+        Some(list) => list
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Stage>>>()?,
+        None => Stage::ALL.to_vec(),
+    };
+    // Keep pipeline order so runs with the same stages compare regardless of
+    // how they were listed
+    Ok(Stage::ALL
+        .into_iter()
+        .filter(|s| stages.contains(s))
+        .collect())
+}
+
+/// `claudio-api eval <project_id> [--stages keyword,semantic,llm] [--seed]`
+///
+/// Prints the report as JSON and exits non-zero when the run has regressions.
+pub async fn run_cli(storage: &AsyncStorage, args: &[String]) -> Result<()> {
+    let mut project_id = None;
+    let mut stages = None;
+    let mut seed = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stages" => stages = Some(args.next().context("--stages needs a value")?.clone()),
+            "--seed" => seed = true,
+            other if project_id.is_none() && !other.starts_with("--") => {
+                project_id = Some(other.to_string())
+            }
+            other => anyhow::bail!("unexpected argument '{}'", other),
+        }
+    }
+    let project_id = project_id
+        .context("usage: claudio-api eval <project_id> [--stages keyword,semantic,llm] [--seed]")?;
+    let stages = parse_stages(stages.as_deref())?;
+
+    let pid = project_id.clone();
+    let project = storage
+        .run(move |s| s.get_project(&pid))
+        .await?
+        .with_context(|| format!("project '{}' not found", project_id))?;
+
+    if seed {
+        let pid = project.id.clone();
+        let added = storage
+            .run(move |s| s.seed_eval_cases(&pid, DEFAULT_SEED_LIMIT))
+            .await?;
+        eprintln!("Seeded {} eval cases", added);
+    }
+
+    let pid = project.id.clone();
+    let cases = storage.run(move |s| s.list_eval_cases(&pid)).await?;
+    if cases.is_empty() {
+        anyhow::bail!(
+            "project '{}' has no eval cases; run with --seed",
+            project.id
+        );
+    }
+
+    let report = run_eval(storage, &project, cases, stages).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.regressions.is_empty() {
+        anyhow::bail!(
+            "{} regressions since the last run",
+            report.regressions.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(case_id: &str, expected: &str, predicted: &str, method: &str) -> EvalCaseResult {
+        EvalCaseResult {
+            case_id: case_id.to_string(),
+            text: format!("text {}", case_id),
+            expected: expected.to_string(),
+            predicted: predicted.to_string(),
+            method: method.to_string(),
+            correct: expected == predicted,
+            verified: case_id != "d",
+        }
+    }
+
+    fn run(id: &str, results: Vec<EvalCaseResult>) -> EvalRun {
+        let correct = results.iter().filter(|r| r.correct).count() as i64;
+        EvalRun {
+            id: id.to_string(),
+            project_id: "demo".to_string(),
+            stages: vec!["keyword".to_string()],
+            total: results.len() as i64,
+            correct,
+            accuracy: correct as f64 / results.len() as f64,
+            results,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_report_against_previous_run() {
+        let previous = run(
+            "old",
+            vec![
+                result("a", "ops", "ops", "keyword"),
+                result("b", "docs", "general", "fallback"),
+            ],
+        );
+        let current = run(
+            "new",
+            vec![
+                result("a", "ops", "docs", "keyword"),
+                result("b", "docs", "docs", "keyword"),
+                result("c", "ops", "ops", "keyword"),
+                result("d", "docs", "general", "fallback"),
+            ],
+        );
+
+        let report = EvalReport::new(current, Some(previous));
+
+        assert_eq!(report.previous_run_id.as_deref(), Some("old"));
+        assert_eq!(report.regressions.len(), 1);
+        assert_eq!(report.regressions[0].case_id, "a");
+        assert_eq!(report.fixed.len(), 1);
+        assert_eq!(report.fixed[0].case_id, "b");
+
+        assert_eq!(report.confusion["ops"]["docs"], 1);
+        assert_eq!(report.confusion["docs"]["general"], 1);
+        let keyword = &report.methods["keyword"];
+        assert_eq!((keyword.cases, keyword.correct), (3, 2));
+        assert_eq!(keyword.coverage, 0.75);
+        assert_eq!(report.methods["fallback"].accuracy, 0.0);
+        // d's label came from a log
+        assert_eq!(report.verified_accuracy, Some(2.0 / 3.0));
+    }
+
+    #[test]
+    fn test_parse_stages_keeps_pipeline_order() {
+        assert_eq!(
+            parse_stages(Some("llm, keyword")).unwrap(),
+            vec![Stage::Keyword, Stage::Llm]
+        );
+        assert_eq!(parse_stages(None).unwrap(), Stage::ALL.to_vec());
+        assert!(parse_stages(Some("regex")).is_err());
+    }
+}
@@ -0,0 +1,442 @@
+//! Tabular exports of executions and stats breakdowns as CSV, NDJSON or
+//! Parquet. Rows are flat JSON objects; an [`Encoder`] turns each batch of
+//! them into the next chunk of the file, so large exports stream instead of
+//! being built in memory. Parquet gets one row group per batch.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+use axum::body::Body;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+/// Download response for an export named `name`.
+pub fn attachment(format: ExportFormat, name: &str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", name, format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Integer,
+    Real,
+    Text,
+}
+
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnKind,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, kind: ColumnKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+        }
+    }
+}
+
+/// Columns named in a comma-separated `columns` parameter, in that order,
+/// or every available column without one.
+pub fn select_columns(available: Vec<Column>, selection: Option<&str>) -> Result<Vec<Column>> {
+    let Some(selection) = selection.filter(|s| !s.trim().is_empty()) else {
+        return Ok(available);
+    };
+    selection
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            available
+                .iter()
+                .find(|c| c.name == name)
+                .cloned()
+                .ok_or_else(|| anyhow!("unknown column '{}'", name))
+        })
+        .collect()
+}
+
+/// Flattens nested objects into dotted columns, so `comparison` becomes
+/// `comparison.requests_change_pct` and so on. Arrays are kept as JSON text.
+pub fn flatten(value: &Value) -> Map<String, Value> {
+    fn walk(prefix: &str, value: &Value, out: &mut Map<String, Value>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    let name = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    walk(&name, value, out);
+                }
+            }
+            Value::Array(_) => {
+                out.insert(prefix.to_string(), Value::String(value.to_string()));
+            }
+            _ => {
+                out.insert(prefix.to_string(), value.clone());
+            }
+        }
+    }
+    let mut out = Map::new();
+    walk("", value, &mut out);
+    out
+}
+
+/// Columns of flattened `rows`, typed by the values they hold: integers
+/// widen to reals, anything else is text.
+pub fn infer_columns(rows: &[Map<String, Value>]) -> Vec<Column> {
+    let mut kinds: BTreeMap<&str, Option<ColumnKind>> = BTreeMap::new();
+    for row in rows {
+        for (name, value) in row {
+            let kind = match value {
+                Value::Null => None,
+                Value::Number(n) if n.is_i64() => Some(ColumnKind::Integer),
+                Value::Number(_) => Some(ColumnKind::Real),
+                _ => Some(ColumnKind::Text),
+            };
+            let entry = kinds.entry(name).or_insert(None);
+            *entry = match (*entry, kind) {
+                (current, None) => current,
+                (None, kind) => kind,
+                (Some(a), Some(b)) if a == b => Some(a),
+                (Some(ColumnKind::Integer), Some(ColumnKind::Real))
+                | (Some(ColumnKind::Real), Some(ColumnKind::Integer)) => Some(ColumnKind::Real),
+                _ => Some(ColumnKind::Text),
+            };
+        }
+    }
+    kinds
+        .into_iter()
+        .map(|(name, kind)| Column::new(name, kind.unwrap_or(ColumnKind::Text)))
+        .collect()
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn csv_field(value: Option<&Value>) -> String {
+    let field = value.and_then(text).unwrap_or_default();
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+enum Output {
+    Csv { header_written: bool },
+    Ndjson,
+    Parquet(Box<SerializedFileWriter<Vec<u8>>>),
+}
+
+/// Writes rows in one format, a batch at a time.
+pub struct Encoder {
+    columns: Vec<Column>,
+    output: Output,
+}
+
+impl Encoder {
+    pub fn new(format: ExportFormat, columns: Vec<Column>) -> Result<Self> {
+        if columns.is_empty() {
+            bail!("no columns to export");
+        }
+        let output = match format {
+            ExportFormat::Csv => Output::Csv {
+                header_written: false,
+            },
+            ExportFormat::Ndjson => Output::Ndjson,
+            ExportFormat::Parquet => {
+                let fields = columns
+                    .iter()
+                    .map(|column| {
+                        let (physical, logical) = match column.kind {
+                            ColumnKind::Integer => (PhysicalType::INT64, None),
+                            ColumnKind::Real => (PhysicalType::DOUBLE, None),
+                            ColumnKind::Text => {
+                                (PhysicalType::BYTE_ARRAY, Some(LogicalType::String))
+                            }
+                        };
+                        Type::primitive_type_builder(&column.name, physical)
+                            .with_repetition(Repetition::OPTIONAL)
+                            .with_logical_type(logical)
+                            .build()
+                            .map(Arc::new)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let schema = Type::group_type_builder("export")
+                    .with_fields(fields)
+                    .build()?;
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Output::Parquet(Box::new(SerializedFileWriter::new(
+                    Vec::new(),
+                    Arc::new(schema),
+                    Arc::new(properties),
+                )?))
+            }
+        };
+        Ok(Self { columns, output })
+    }
+
+    /// Encodes `rows`, returning the bytes to send next.
+    pub fn encode(&mut self, rows: &[Map<String, Value>]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self.output {
+            Output::Csv {
+                ref mut header_written,
+            } => {
+                if !*header_written {
+                    let header: Vec<String> = self
+                        .columns
+                        .iter()
+                        .map(|c| csv_field(Some(&Value::String(c.name.clone()))))
+                        .collect();
+                    out.extend_from_slice(header.join(",").as_bytes());
+                    out.push(b'\n');
+                    *header_written = true;
+                }
+                for row in rows {
+                    let fields: Vec<String> = self
+                        .columns
+                        .iter()
+                        .map(|c| csv_field(row.get(&c.name)))
+                        .collect();
+                    out.extend_from_slice(fields.join(",").as_bytes());
+                    out.push(b'\n');
+                }
+            }
+            Output::Ndjson => {
+                for row in rows {
+                    out.push(b'{');
+                    for (i, column) in self.columns.iter().enumerate() {
+                        if i > 0 {
+                            out.push(b',');
+                        }
+                        serde_json::to_writer(&mut out, &column.name)?;
+                        out.push(b':');
+                        serde_json::to_writer(
+                            &mut out,
+                            row.get(&column.name).unwrap_or(&Value::Null),
+                        )?;
+                    }
+                    out.extend_from_slice(b"}\n");
+                }
+            }
+            Output::Parquet(ref mut writer) => {
+                if rows.is_empty() {
+                    return Ok(out);
+                }
+                let mut group = writer.next_row_group()?;
+                for column in &self.columns {
+                    let mut writer = group
+                        .next_column()?
+                        .ok_or_else(|| anyhow!("no writer for column '{}'", column.name))?;
+                    let values = rows.iter().map(|row| row.get(&column.name));
+                    let levels = |present: &[bool]| -> Vec<i16> {
+                        present.iter().map(|&p| i16::from(p)).collect()
+                    };
+                    match column.kind {
+                        ColumnKind::Integer => {
+                            let cells: Vec<Option<i64>> =
+                                values.map(|v| v.and_then(Value::as_i64)).collect();
+                            let present: Vec<bool> = cells.iter().map(Option::is_some).collect();
+                            let data: Vec<i64> = cells.into_iter().flatten().collect();
+                            writer.typed::<Int64Type>().write_batch(
+                                &data,
+                                Some(&levels(&present)),
+                                None,
+                            )?;
+                        }
+                        ColumnKind::Real => {
+                            let cells: Vec<Option<f64>> =
+                                values.map(|v| v.and_then(Value::as_f64)).collect();
+                            let present: Vec<bool> = cells.iter().map(Option::is_some).collect();
+                            let data: Vec<f64> = cells.into_iter().flatten().collect();
+                            writer.typed::<DoubleType>().write_batch(
+                                &data,
+                                Some(&levels(&present)),
+                                None,
+                            )?;
+                        }
+                        ColumnKind::Text => {
+                            let cells: Vec<Option<String>> =
+                                values.map(|v| v.and_then(text)).collect();
+                            let present: Vec<bool> = cells.iter().map(Option::is_some).collect();
+                            let data: Vec<ByteArray> = cells
+                                .into_iter()
+                                .flatten()
+                                .map(|s| ByteArray::from(s.into_bytes()))
+                                .collect();
+                            writer.typed::<ByteArrayType>().write_batch(
+                                &data,
+                                Some(&levels(&present)),
+                                None,
+                            )?;
+                        }
+                    }
+                    writer.close()?;
+                }
+                group.close()?;
+                writer.flush()?;
+                out = std::mem::take(writer.inner_mut());
+            }
+        }
+        Ok(out)
+    }
+
+    /// Ends the file, returning its last bytes.
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        match self.output {
+            Output::Csv {
+                header_written: false,
+            } => self.encode(&[]),
+            Output::Parquet(ref mut writer) => {
+                writer.finish()?;
+                Ok(std::mem::take(writer.inner_mut()))
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use serde_json::json;
+
+    fn rows() -> Vec<Map<String, Value>> {
+        [
+            json!({"agent": "ops", "requests": 3, "cost": 1.5, "note": "a, \"b\""}),
+            json!({"agent": "dev", "requests": null, "cost": 2, "note": null}),
+        ]
+        .iter()
+        .map(flatten)
+        .collect()
+    }
+
+    fn encode_all(format: ExportFormat, columns: Vec<Column>) -> Vec<u8> {
+        let rows = rows();
+        let mut encoder = Encoder::new(format, columns).unwrap();
+        let mut out = encoder.encode(&rows[..1]).unwrap();
+        out.extend(encoder.encode(&rows[1..]).unwrap());
+        out.extend(encoder.finish().unwrap());
+        out
+    }
+
+    #[test]
+    fn test_encode_formats() {
+        let columns = infer_columns(&rows());
+        let kinds: Vec<_> = columns.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("agent", ColumnKind::Text),
+                ("cost", ColumnKind::Real),
+                ("note", ColumnKind::Text),
+                ("requests", ColumnKind::Integer),
+            ]
+        );
+
+        let selected = select_columns(columns.clone(), Some("requests, agent,note")).unwrap();
+        let csv = encode_all(ExportFormat::Csv, selected);
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "requests,agent,note\n3,ops,\"a, \"\"b\"\"\"\n,dev,\n"
+        );
+        assert!(select_columns(columns.clone(), Some("agent,prompt")).is_err());
+
+        let selected = select_columns(columns.clone(), Some("agent,requests")).unwrap();
+        let ndjson = encode_all(ExportFormat::Ndjson, selected);
+        assert_eq!(
+            String::from_utf8(ndjson).unwrap(),
+            "{\"agent\":\"ops\",\"requests\":3}\n{\"agent\":\"dev\",\"requests\":null}\n"
+        );
+
+        let parquet = encode_all(ExportFormat::Parquet, columns);
+        let reader = SerializedFileReader::new(bytes::Bytes::from(parquet)).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 2);
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "{agent: \"ops\", cost: 1.5, note: \"a, \"b\"\", requests: 3}",
+                "{agent: \"dev\", cost: 2.0, note: null, requests: null}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flatten_nested_breakdown() {
+        let row = flatten(&json!({
+            "agent": "ops",
+            "comparison": {"requests_change_pct": 100.0},
+            "routing": [{"method": "keyword", "count": 2}],
+        }));
+        assert_eq!(row["comparison.requests_change_pct"], json!(100.0));
+        assert_eq!(
+            row["routing"],
+            json!("[{\"count\":2,\"method\":\"keyword\"}]")
+        );
+    }
+}
@@ -2,27 +2,39 @@ use axum::{
     Json,
     extract::{Path, State},
 };
+use serde::Serialize;
 
+use crate::api::classify_cache;
 use crate::api::error::{ApiError, ApiResult};
 use crate::api::handlers::DeleteResponse;
 use crate::api::routes::AppState;
+use crate::plugins::semantic::{self, ProjectIndexStatus};
 use crate::storage::{Agent, CreateAgent, UpdateAgent};
 
+#[derive(Serialize)]
+pub struct SyncAgentsResponse {
+    /// Examples in the index after the sync
+    pub indexed: usize,
+}
+
 pub async fn list_agents(
     State(state): State<AppState>,
     Path(project_id): Path<String>,
 ) -> ApiResult<Vec<Agent>> {
     state
         .storage
-        .list_agents(&project_id)
+        .run(move |s| s.list_agents(&project_id))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 pub async fn get_agent(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Agent> {
+    let lookup = id.clone();
     state
         .storage
-        .get_agent(&id)?
+        .run(move |s| s.get_agent(&lookup))
+        .await?
         .map(Json)
         .ok_or_else(|| ApiError::not_found("Agent", &id))
 }
@@ -32,11 +44,14 @@ pub async fn create_agent(
     Path(project_id): Path<String>,
     Json(input): Json<CreateAgent>,
 ) -> ApiResult<Agent> {
-    let agent = state.storage.create_agent(&project_id, input)?;
+    let pid = project_id.clone();
+    let agent = state
+        .storage
+        .run(move |s| s.create_agent(&pid, input))
+        .await?;
 
-    if let Ok(agents) = state.storage.list_agents(&project_id) {
-        let _ = crate::plugins::semantic::sync_agents(&project_id, &agents);
-    }
+    semantic::enqueue_agent(&agent.project_id, &agent.id);
+    classify_cache::invalidate_project(&agent.project_id);
 
     Ok(Json(agent))
 }
@@ -46,35 +61,56 @@ pub async fn update_agent(
     Path(id): Path<String>,
     Json(input): Json<UpdateAgent>,
 ) -> ApiResult<Agent> {
-    let project_id = state.storage.get_agent(&id)?.map(|a| a.project_id);
-
-    let result = state
+    let lookup = id.clone();
+    let agent = state
         .storage
-        .update_agent(&id, &input)?
+        .run(move |s| s.update_agent(&lookup, &input))
+        .await?
         .ok_or_else(|| ApiError::not_found("Agent", &id))?;
 
-    if let Some(pid) = project_id
-        && let Ok(agents) = state.storage.list_agents(&pid)
-    {
-        let _ = crate::plugins::semantic::sync_agents(&pid, &agents);
-    }
+    semantic::enqueue_agent(&agent.project_id, &agent.id);
+    classify_cache::invalidate_project(&agent.project_id);
 
-    Ok(Json(result))
+    Ok(Json(agent))
 }
 
 pub async fn delete_agent(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> ApiResult<DeleteResponse> {
-    let project_id = state.storage.get_agent(&id)?.map(|a| a.project_id);
-
-    let deleted = state.storage.delete_agent(&id)?;
+    let lookup = id.clone();
+    let (project_id, deleted) = state
+        .storage
+        .run(move |s| {
+            let project_id = s.get_agent(&lookup)?.map(|a| a.project_id);
+            Ok((project_id, s.delete_agent(&lookup)?))
+        })
+        .await?;
 
-    if let Some(pid) = project_id
-        && let Ok(agents) = state.storage.list_agents(&pid)
-    {
-        let _ = crate::plugins::semantic::sync_agents(&pid, &agents);
+    if let Some(pid) = project_id {
+        semantic::enqueue_agent(&pid, &id);
+        classify_cache::invalidate_project(&pid);
     }
 
     Ok(Json(DeleteResponse { deleted }))
 }
+
+/// Re-indexes every agent of a project for semantic routing.
+pub async fn sync_agents(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> ApiResult<SyncAgentsResponse> {
+    let pid = project_id.clone();
+    let agents = state.storage.run(move |s| s.list_agents(&pid)).await?;
+    let indexed = semantic::sync_agents(&project_id, &agents).await?;
+    Ok(Json(SyncAgentsResponse { indexed }))
+}
+
+pub async fn get_index_status(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> ApiResult<ProjectIndexStatus> {
+    let pid = project_id.clone();
+    let agents = state.storage.run(move |s| s.list_agents(&pid)).await?;
+    Ok(Json(semantic::index_status(&project_id, &agents)))
+}
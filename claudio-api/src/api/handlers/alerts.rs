@@ -0,0 +1,52 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::Deserialize;
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::routes::AppState;
+use crate::storage::{Alert, AlertFilter, AlertMetric, AlertScope, AlertStatus};
+
+#[derive(Debug, Deserialize)]
+pub struct ListAlertsQuery {
+    pub status: Option<AlertStatus>,
+    pub project: Option<String>,
+    pub scope: Option<AlertScope>,
+    pub metric: Option<AlertMetric>,
+    #[serde(default = "default_alerts_limit")]
+    pub limit: i64,
+}
+
+fn default_alerts_limit() -> i64 {
+    50
+}
+
+/// Anomaly alerts, most recently started first.
+pub async fn list_alerts(
+    State(state): State<AppState>,
+    Query(query): Query<ListAlertsQuery>,
+) -> ApiResult<Vec<Alert>> {
+    let filter = AlertFilter {
+        status: query.status,
+        project: query.project,
+        scope: query.scope,
+        metric: query.metric,
+    };
+    let limit = query.limit.clamp(1, 500);
+    state
+        .storage
+        .run(move |s| s.list_alerts(&filter, limit))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn get_alert(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<Alert> {
+    state
+        .storage
+        .run(move |s| s.get_alert(id))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Alert", &id.to_string()))
+}
@@ -0,0 +1,136 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use crate::api::budget::{self, BudgetStatus};
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::handlers::DeleteResponse;
+use crate::api::routes::AppState;
+use crate::storage::{Budget, BudgetAction, BudgetScope, CreateBudget, UpdateBudget};
+
+fn validate_limit(limit_usd: f64) -> Result<(), ApiError> {
+    if !limit_usd.is_finite() || limit_usd <= 0.0 {
+        return Err(ApiError::bad_request("limit_usd must be above 0"));
+    }
+    Ok(())
+}
+
+fn validate_action(action: BudgetAction, downgrade_model: Option<&str>) -> Result<(), ApiError> {
+    if action == BudgetAction::Downgrade && downgrade_model.is_none_or(|m| m.trim().is_empty()) {
+        return Err(ApiError::bad_request(
+            "the downgrade action needs a downgrade_model",
+        ));
+    }
+    Ok(())
+}
+
+pub async fn list_budgets(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> ApiResult<Vec<Budget>> {
+    state
+        .storage
+        .run(move |s| s.list_budgets(&project_id))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn create_budget(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Json(mut input): Json<CreateBudget>,
+) -> ApiResult<Budget> {
+    validate_limit(input.limit_usd)?;
+    validate_action(input.action, input.downgrade_model.as_deref())?;
+    input.target = input
+        .target
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    if input.scope != BudgetScope::Project && input.target.is_none() {
+        return Err(ApiError::bad_request(format!(
+            "{} budgets need a target",
+            input.scope.as_str()
+        )));
+    }
+
+    let lookup = project_id.clone();
+    state
+        .storage
+        .run(move |s| s.get_project(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project", &project_id))?;
+    if input.scope == BudgetScope::Agent {
+        let (pid, name) = (project_id.clone(), input.target.clone().unwrap_or_default());
+        let agent = state
+            .storage
+            .run(move |s| s.get_agent_by_name(&pid, &name))
+            .await?
+            .ok_or_else(|| {
+                ApiError::bad_request(format!(
+                    "agent '{}' does not exist in project '{}'",
+                    input.target.as_deref().unwrap_or_default(),
+                    project_id
+                ))
+            })?;
+        input.target = Some(agent.name);
+    }
+
+    state
+        .storage
+        .run(move |s| s.create_budget(&project_id, &input))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn update_budget(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<UpdateBudget>,
+) -> ApiResult<Budget> {
+    let lookup = id.clone();
+    let budget = state
+        .storage
+        .run(move |s| s.get_budget(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Budget", &id))?;
+
+    if let Some(limit_usd) = input.limit_usd {
+        validate_limit(limit_usd)?;
+    }
+    validate_action(
+        input.action.unwrap_or(budget.action),
+        input
+            .downgrade_model
+            .as_deref()
+            .or(budget.downgrade_model.as_deref()),
+    )?;
+
+    state
+        .storage
+        .run(move |s| s.update_budget(&id, &input))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Budget", &budget.id))
+}
+
+pub async fn delete_budget(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state.storage.run(move |s| s.delete_budget(&id)).await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+/// Current period spend of every budget in a project.
+pub async fn get_budget_status(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> ApiResult<Vec<BudgetStatus>> {
+    budget::project_status(&state, project_id)
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
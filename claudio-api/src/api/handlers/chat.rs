@@ -6,13 +6,17 @@ use chrono::Utc;
 use chrono_tz::Tz;
 use serde::Deserialize;
 
-use crate::api::classify::{self, ClassifyResponse, ClassifySettings};
+use crate::api::budget;
+use crate::api::classify::{self, ClassifyExplanation, ClassifyResponse, ClassifySettings};
 use crate::api::error::{ApiError, ApiResult};
+use crate::api::eval;
 use crate::api::routes::AppState;
-use crate::api::types::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::api::types::{ChatCompletionRequest, ChatCompletionResponse, ExecutionStatus};
 use crate::claude::ClaudeExecutor;
 use crate::config::Config;
-use crate::storage::{ClassificationLog, Execution};
+use crate::metrics;
+use crate::storage::{Agent, ClassificationLog, Execution, ExperimentVariant};
+use crate::telemetry;
 
 #[derive(Deserialize)]
 pub struct ProjectClassifyRequest {
@@ -21,6 +25,12 @@ pub struct ProjectClassifyRequest {
     pub source: Option<String>,
     #[serde(default)]
     pub requester: Option<String>,
+    /// Number of ranked candidates to include in the response
+    #[serde(default)]
+    pub top_k: usize,
+    /// Workflow run asking, linking the classification log to it
+    #[serde(default)]
+    pub run_id: Option<String>,
 }
 
 pub async fn classify_project(
@@ -28,22 +38,105 @@ pub async fn classify_project(
     Path(project_id): Path<String>,
     Json(req): Json<ProjectClassifyRequest>,
 ) -> ApiResult<ClassifyResponse> {
-    let project = state
+    let pid = project_id.clone();
+    let (project, agents) = state
         .storage
-        .get_project(&project_id)?
-        .ok_or_else(|| ApiError::not_found("Project", &project_id))?;
+        .run(move |s| Ok((s.get_project(&pid)?, s.list_agents(&pid)?)))
+        .await?;
+    let project = project.ok_or_else(|| ApiError::not_found("Project", &project_id))?;
+
+    if agents.is_empty() {
+        return Err(ApiError::not_found("Agents for project", &project_id));
+    }
+
+    let settings = ClassifySettings::from(&project);
+    let response = if req.top_k > 0 || settings.clarify_threshold > 0.0 {
+        classify::classify_with_candidates(&req.text, &project_id, &agents, &settings, req.top_k)
+            .await
+    } else {
+        classify::classify_with_agents(&req.text, &project_id, &agents, &settings).await
+    };
+    save_classification_log(
+        &state,
+        req.text,
+        project_id,
+        req.source,
+        req.requester,
+        req.run_id,
+        &response,
+    )
+    .await;
 
-    let agents = state.storage.list_agents(&project_id)?;
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct ExplainClassifyRequest {
+    pub text: String,
+    /// Comma-separated subset of keyword, semantic, llm; all when omitted
+    #[serde(default)]
+    pub stages: Option<String>,
+    /// Record the decision in classification logs like a real classify call
+    #[serde(default)]
+    pub log: bool,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub requester: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+pub async fn explain_classify_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Json(req): Json<ExplainClassifyRequest>,
+) -> ApiResult<ClassifyExplanation> {
+    let stages = eval::parse_stages(req.stages.as_deref())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    let pid = project_id.clone();
+    let (project, agents) = state
+        .storage
+        .run(move |s| Ok((s.get_project(&pid)?, s.list_agents(&pid)?)))
+        .await?;
+    let project = project.ok_or_else(|| ApiError::not_found("Project", &project_id))?;
 
     if agents.is_empty() {
         return Err(ApiError::not_found("Agents for project", &project_id));
     }
 
     let settings = ClassifySettings::from(&project);
-    let response = classify::classify_with_agents(&req.text, &project_id, &agents, &settings).await;
+    let explanation =
+        classify::explain_with_stages(&req.text, &project_id, &agents, &settings, &stages).await;
+
+    if req.log {
+        save_classification_log(
+            &state,
+            req.text,
+            project_id,
+            req.source,
+            req.requester,
+            req.run_id,
+            &explanation.decision,
+        )
+        .await;
+    }
 
+    Ok(Json(explanation))
+}
+
+async fn save_classification_log(
+    state: &AppState,
+    text: String,
+    project_id: String,
+    source: Option<String>,
+    requester: Option<String>,
+    run_id: Option<String>,
+    response: &ClassifyResponse,
+) {
     let log = ClassificationLog {
-        text: req.text,
+        text,
         agent: response.agent.clone(),
         model: response.model.clone(),
         confidence: response.confidence,
@@ -52,15 +145,24 @@ pub async fn classify_project(
         reasoning: response.reasoning.clone(),
         duration_ms: response.duration_ms as i64,
         project: Some(project_id),
-        source: req.source,
-        requester: req.requester,
+        source,
+        requester,
+        agent_revision: response.agent_revision,
+        llm_error: response.llm_error.clone(),
+        run_id,
     };
 
-    if let Err(e) = state.storage.save_classification(&log) {
-        tracing::error!(agent = %log.agent, error = %e, "Failed to save classification log");
-    }
+    metrics::CLASSIFICATIONS.inc(&[&log.method]);
+    metrics::CLASSIFY_DURATION.observe(&[&log.method], response.duration_ms as f64 / 1000.0);
 
-    Ok(Json(response))
+    let agent = log.agent.clone();
+    if let Err(e) = state
+        .storage
+        .write("save_classification", move |s| s.save_classification(&log))
+        .await
+    {
+        tracing::error!(agent = %agent, error = %e, "Failed to save classification log");
+    }
 }
 
 pub async fn chat_project(
@@ -70,9 +172,11 @@ pub async fn chat_project(
 ) -> ApiResult<ChatCompletionResponse> {
     let original_user_message = req.user_message.clone();
 
+    let pid = project_id.clone();
     let project = state
         .storage
-        .get_project(&project_id)?
+        .run(move |s| s.get_project(&pid))
+        .await?
         .ok_or_else(|| ApiError::not_found("Project", &project_id))?;
 
     if state
@@ -85,9 +189,34 @@ pub async fn chat_project(
 
     let mut agent_tools: Option<Vec<String>> = None;
     let mut agent_working_dir: Option<String> = None;
-    if let Some(ref agent_name) = req.agent
-        && let Ok(Some(agent)) = state.storage.get_agent_by_name(&project_id, agent_name)
-    {
+    let mut agent_revision: Option<i64> = None;
+    let selected_agent = match req.agent.clone() {
+        Some(agent_name) => {
+            let pid = project_id.clone();
+            state
+                .storage
+                .run(move |s| s.get_agent_by_name(&pid, &agent_name))
+                .await?
+        }
+        None => None,
+    };
+    let mut experiment = None;
+    if let Some(mut agent) = selected_agent {
+        agent_revision = Some(agent.revision);
+        if let Some((experiment_id, variant)) =
+            assign_experiment_variant(&state, &agent, req.requester.clone()).await
+        {
+            if variant.instruction.is_some() {
+                agent.instruction = variant.instruction;
+            }
+            if let Some(model) = variant.model {
+                agent.model = model;
+            }
+            if variant.tools.is_some() {
+                agent.tools = variant.tools;
+            }
+            experiment = Some((experiment_id, variant.name));
+        }
         if let Some(ref agent_instruction) = agent.instruction {
             req.instruction = match req.instruction {
                 Some(ref req_instruction) => {
@@ -115,20 +244,7 @@ pub async fn chat_project(
             Some(agent_working_dir.unwrap_or_else(|| config.defaults.isolated_dir.clone()));
     }
     if req.allowed_tools.is_none() {
-        req.allowed_tools = match (agent_tools, project.allowed_tools.clone()) {
-            (Some(agent), Some(proj)) => {
-                let mut merged = agent;
-                for tool in proj {
-                    if !merged.contains(&tool) {
-                        merged.push(tool);
-                    }
-                }
-                Some(merged)
-            }
-            (Some(agent), None) => Some(agent),
-            (None, Some(proj)) => Some(proj),
-            (None, None) => None,
-        };
+        req.allowed_tools = merge_tools(agent_tools, project.allowed_tools.clone());
     }
     if req.disallowed_tools.is_none() {
         req.disallowed_tools = project.disallowed_tools.clone();
@@ -139,8 +255,11 @@ pub async fn chat_project(
 
     let mut user_context_snapshot: Option<String> = None;
     if project.enable_user_context
-        && let Some(ref requester) = req.requester
-        && let Ok(ctx) = state.storage.get_user_context(requester)
+        && let Some(requester) = req.requester.clone()
+        && let Ok(ctx) = state
+            .storage
+            .run(move |s| s.get_user_context(&requester))
+            .await
     {
         let formatted = format_user_context(&ctx, None);
         if !formatted.is_empty() {
@@ -158,11 +277,102 @@ pub async fn chat_project(
 
     req.project = Some(project_id.clone());
 
+    let record = ExecutionRecord {
+        instruction: instruction_snapshot,
+        user_message: original_user_message,
+        user_context: user_context_snapshot,
+        agent_revision,
+        rerun_of: None,
+        experiment,
+    };
+    execute_and_save(&state, req, record).await.map(Json)
+}
+
+/// Variant of the running experiment on `agent` that this request gets, if
+/// any. Lookup failures run the agent as configured.
+async fn assign_experiment_variant(
+    state: &AppState,
+    agent: &Agent,
+    requester: Option<String>,
+) -> Option<(String, ExperimentVariant)> {
+    let project_id = agent.project_id.clone();
+    let name = agent.name.clone();
+    let assigned = state
+        .storage
+        .run(move |s| {
+            let Some(experiment) = s.running_experiment(&project_id, &name)? else {
+                return Ok(None);
+            };
+            let variant = s.assign_variant(&experiment, requester.as_deref())?;
+            Ok(variant.map(|v| (experiment.id, v)))
+        })
+        .await;
+    match assigned {
+        Ok(assigned) => assigned,
+        Err(e) => {
+            tracing::warn!(agent = %agent.name, error = %e, "Failed to assign experiment variant");
+            None
+        }
+    }
+}
+
+/// Agent tools followed by any project tools the agent doesn't already list.
+pub(crate) fn merge_tools(
+    agent: Option<Vec<String>>,
+    project: Option<Vec<String>>,
+) -> Option<Vec<String>> {
+    match (agent, project) {
+        (Some(agent), Some(proj)) => {
+            let mut merged = agent;
+            for tool in proj {
+                if !merged.contains(&tool) {
+                    merged.push(tool);
+                }
+            }
+            Some(merged)
+        }
+        (Some(agent), None) => Some(agent),
+        (None, Some(proj)) => Some(proj),
+        (None, None) => None,
+    }
+}
+
+/// What gets stored alongside an execution but is no longer in the request
+/// once the user message has been structured.
+pub(crate) struct ExecutionRecord {
+    pub instruction: Option<String>,
+    pub user_message: String,
+    pub user_context: Option<String>,
+    pub agent_revision: Option<i64>,
+    pub rerun_of: Option<String>,
+    /// Experiment and variant the agent configuration came from
+    pub experiment: Option<(String, String)>,
+}
+
+/// Checks the request's budgets, runs it through the executor and stores the
+/// execution. A failed save is logged rather than failing the request.
+pub(crate) async fn execute_and_save(
+    state: &AppState,
+    mut req: ChatCompletionRequest,
+    record: ExecutionRecord,
+) -> Result<ChatCompletionResponse, ApiError> {
+    budget::enforce(state, &mut req).await?;
+    // Identifies the budgets to re-check once the cost is known
+    let budget_scope = ChatCompletionRequest {
+        project: req.project.clone(),
+        agent: req.agent.clone(),
+        requester: req.requester.clone(),
+        ..Default::default()
+    };
+
     let source = req.source.clone();
     let requester = req.requester.clone();
     let agent = req.agent.clone();
     let model_for_execution = req.model.clone();
-    let metadata_str = req.metadata.as_ref().map(|m| m.to_string());
+    let allowed_tools = req.allowed_tools.clone();
+    let disallowed_tools = req.disallowed_tools.clone();
+    let run_id = req.run_id.clone();
+    let metadata_str = metadata_with_trace(req.metadata.as_ref());
 
     let response = ClaudeExecutor::execute(req).await;
 
@@ -179,9 +389,9 @@ pub async fn chat_project(
         source,
         requester,
         agent,
-        instruction: instruction_snapshot,
-        user_message: original_user_message,
-        user_context: user_context_snapshot,
+        instruction: record.instruction,
+        user_message: record.user_message,
+        user_context: record.user_context,
         response: response.result.clone().unwrap_or_default(),
         structured_output: response.structured_output.as_ref().map(|v| v.to_string()),
         model: actual_model,
@@ -219,17 +429,70 @@ pub async fn chat_project(
             .as_ref()
             .map(|c| c.session_id.clone()),
         metadata: metadata_str,
+        agent_revision: record.agent_revision,
+        allowed_tools,
+        disallowed_tools,
+        rerun_of: record.rerun_of,
+        experiment_id: record.experiment.as_ref().map(|(id, _)| id.clone()),
+        variant: record.experiment.map(|(_, variant)| variant),
+        run_id,
         created_at: response.created,
     };
 
-    if let Err(e) = state.storage.save(&execution) {
+    record_execution_metrics(&execution, response.status);
+    let has_cost = execution.cost_usd.is_some_and(|c| c > 0.0);
+    if let Err(e) = state
+        .storage
+        .write("save_execution", move |s| s.save(&execution))
+        .await
+    {
         tracing::error!(execution_id = %response.id, error = %e, "Failed to save execution");
+    } else if has_cost {
+        budget::after_execution(state, budget_scope);
     }
 
-    Ok(Json(response))
+    Ok(response)
+}
+
+/// Serialized request metadata with the current trace id added, so an
+/// execution can be looked up in the tracing backend. Metadata that isn't an
+/// object, or already has a `trace_id`, is stored as given.
+fn metadata_with_trace(metadata: Option<&serde_json::Value>) -> Option<String> {
+    let Some(trace_id) = telemetry::current_trace_id() else {
+        return metadata.map(|m| m.to_string());
+    };
+    match metadata {
+        Some(serde_json::Value::Object(map)) => {
+            let mut map = map.clone();
+            map.entry("trace_id").or_insert(trace_id.into());
+            Some(serde_json::Value::Object(map).to_string())
+        }
+        Some(other) => Some(other.to_string()),
+        None => Some(serde_json::json!({ "trace_id": trace_id }).to_string()),
+    }
+}
+
+fn record_execution_metrics(execution: &Execution, status: ExecutionStatus) {
+    let project = execution.project.as_str();
+    let agent = execution.agent.as_deref().unwrap_or("");
+    let model = execution.model.as_deref().unwrap_or("");
+    metrics::EXECUTIONS.inc(&[project, agent, model, &status.to_string()]);
+    for (kind, tokens) in [
+        ("input", execution.input_tokens),
+        ("output", execution.output_tokens),
+        ("cache_read", execution.cache_read_tokens),
+        ("cache_creation", execution.cache_creation_tokens),
+    ] {
+        if let Some(tokens) = tokens.filter(|&t| t > 0) {
+            metrics::TOKENS.inc_by(&[project, model, kind], tokens as f64);
+        }
+    }
+    if let Some(cost) = execution.cost_usd {
+        metrics::COST.inc_by(&[project, model], cost);
+    }
 }
 
-fn format_structured_message(
+pub(crate) fn format_structured_message(
     instruction: Option<&str>,
     user_context: Option<&str>,
     user_message: &str,
@@ -0,0 +1,143 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::eval::{self, DEFAULT_SEED_LIMIT, EvalReport};
+use crate::api::handlers::DeleteResponse;
+use crate::api::routes::AppState;
+use crate::storage::{CreateEvalCase, EvalCase, EvalRun, Project};
+
+#[derive(Deserialize)]
+pub struct SeedQuery {
+    /// Cases taken from each source
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct SeedResponse {
+    pub added: usize,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RunEvalRequest {
+    /// Comma-separated subset of keyword, semantic, llm; all when omitted
+    #[serde(default)]
+    pub stages: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EvalRunsQuery {
+    #[serde(default = "default_runs_limit")]
+    pub limit: i64,
+}
+
+fn default_runs_limit() -> i64 {
+    20
+}
+
+pub async fn list_eval_cases(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> ApiResult<Vec<EvalCase>> {
+    state
+        .storage
+        .run(move |s| s.list_eval_cases(&project_id))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn add_eval_case(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Json(input): Json<CreateEvalCase>,
+) -> ApiResult<EvalCase> {
+    if input.text.trim().is_empty() || input.expected_agent.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            "text and expected_agent must not be empty",
+        ));
+    }
+    load_project(&state, &project_id).await?;
+
+    state
+        .storage
+        .run(move |s| s.add_eval_case(&project_id, &input, "manual"))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::bad_request("an eval case with this text already exists"))
+}
+
+pub async fn delete_eval_case(
+    State(state): State<AppState>,
+    Path((project_id, case_id)): Path<(String, String)>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state
+        .storage
+        .run(move |s| s.delete_eval_case(&project_id, &case_id))
+        .await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+pub async fn seed_eval_cases(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<SeedQuery>,
+) -> ApiResult<SeedResponse> {
+    load_project(&state, &project_id).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_SEED_LIMIT);
+    let added = state
+        .storage
+        .run(move |s| s.seed_eval_cases(&project_id, limit))
+        .await?;
+    Ok(Json(SeedResponse { added }))
+}
+
+pub async fn run_eval(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    body: Option<Json<RunEvalRequest>>,
+) -> ApiResult<EvalReport> {
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let stages = eval::parse_stages(req.stages.as_deref())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let project = load_project(&state, &project_id).await?;
+
+    let pid = project_id.clone();
+    let cases = state.storage.run(move |s| s.list_eval_cases(&pid)).await?;
+    if cases.is_empty() {
+        return Err(ApiError::bad_request(
+            "project has no eval cases; add or seed some first",
+        ));
+    }
+
+    eval::run_eval(&state.storage, &project, cases, stages)
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn list_eval_runs(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<EvalRunsQuery>,
+) -> ApiResult<Vec<EvalRun>> {
+    state
+        .storage
+        .run(move |s| s.list_eval_runs(&project_id, query.limit))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+async fn load_project(state: &AppState, project_id: &str) -> Result<Project, ApiError> {
+    let lookup = project_id.to_string();
+    state
+        .storage
+        .run(move |s| s.get_project(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project", project_id))
+}
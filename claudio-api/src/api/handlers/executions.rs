@@ -1,16 +1,34 @@
+use std::sync::Arc;
+
 use axum::{
     Json,
+    body::{Body, Bytes},
     extract::{Path, Query, State},
+    response::Response,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::api::error::{ApiError, ApiResult};
+use crate::api::export::{self, Column, ColumnKind, Encoder, ExportFormat};
 use crate::api::routes::AppState;
 use crate::storage::{
-    Execution, ExecutionDetail, ExecutionFilter, ExecutionListResponse, FilterOptions,
-    ReactionResult, ReactionSummary,
+    Execution, ExecutionDetail, ExecutionExport, ExecutionFilter, ExecutionListResponse,
+    FilterOptions, ReactionResult, ReactionSummary,
 };
 
+/// Executions fetched and encoded per chunk of an export
+const EXPORT_BATCH: i64 = 1000;
+
+/// Message bodies, which exports redact unless asked not to
+const BODY_COLUMNS: &[&str] = &[
+    "instruction",
+    "user_message",
+    "user_context",
+    "response",
+    "structured_output",
+];
+
 #[derive(Serialize)]
 pub struct UpdateResponse {
     pub success: bool,
@@ -42,11 +60,14 @@ pub async fn add_reaction(
     Path(execution_id): Path<String>,
     Json(req): Json<AddReactionRequest>,
 ) -> ApiResult<ReactionResponse> {
-    validate_feedback_permission(&state, &execution_id, &req.user_id, &req.reaction)?;
+    validate_feedback_permission(&state, &execution_id, &req.user_id, &req.reaction).await?;
 
     let result = state
         .storage
-        .upsert_reaction(&execution_id, &req.user_id, &req.reaction)?;
+        .write("upsert_reaction", move |s| {
+            s.upsert_reaction(&execution_id, &req.user_id, &req.reaction)
+        })
+        .await?;
 
     let result_str = match result {
         ReactionResult::Created => "created",
@@ -67,7 +88,8 @@ pub async fn remove_reaction(
 ) -> ApiResult<ReactionResponse> {
     let deleted = state
         .storage
-        .delete_reaction(&execution_id, &req.user_id, &req.reaction)?;
+        .run(move |s| s.delete_reaction(&execution_id, &req.user_id, &req.reaction))
+        .await?;
 
     Ok(Json(ReactionResponse {
         success: true,
@@ -81,19 +103,27 @@ pub async fn get_reactions(
 ) -> ApiResult<ReactionSummary> {
     state
         .storage
-        .get_reaction_summary(&id)
+        .run(move |s| s.get_reaction_summary(&id))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
-fn validate_feedback_permission(
+async fn validate_feedback_permission(
     state: &AppState,
     execution_id: &str,
     user_id: &str,
     reaction: &str,
 ) -> Result<(), ApiError> {
-    if crate::storage::is_feedback_reaction(reaction)
-        && let Ok(Some(exec)) = state.storage.get_execution_by_id(execution_id)
+    if !crate::storage::is_feedback_reaction(reaction) {
+        return Ok(());
+    }
+
+    let execution_id = execution_id.to_string();
+    if let Ok(Some(exec)) = state
+        .storage
+        .run(move |s| s.get_execution_by_id(&execution_id))
+        .await
         && exec.requester.as_deref() != Some(user_id)
     {
         return Err(ApiError::forbidden(
@@ -119,14 +149,13 @@ pub struct ExecutionResponse {
 pub async fn lookup_execution(
     State(state): State<AppState>,
     Query(query): Query<LookupQuery>,
-) -> Json<ExecutionResponse> {
+) -> ApiResult<ExecutionResponse> {
     let execution = state
         .storage
-        .find_by_metadata_ref(&query.source, &query.ref_key, &query.ref_value)
-        .ok()
-        .flatten();
+        .run(move |s| s.find_by_metadata_ref(&query.source, &query.ref_key, &query.ref_value))
+        .await?;
 
-    Json(ExecutionResponse { execution })
+    Ok(Json(ExecutionResponse { execution }))
 }
 
 #[derive(Deserialize)]
@@ -151,7 +180,8 @@ pub async fn get_recent_executions(
     let limit = query.limit.min(50);
     let executions = state
         .storage
-        .get_recent_executions(limit)
+        .run(move |s| s.get_recent_executions(limit))
+        .await
         .unwrap_or_default();
     Json(RecentResponse { executions })
 }
@@ -184,6 +214,12 @@ pub struct ListExecutionsQuery {
     pub search: Option<String>,
     #[serde(default)]
     pub failed_only: Option<bool>,
+    /// Comma-separated tags, all of which must be present
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Saved view whose filter fills any parameter not given here
+    #[serde(default)]
+    pub view: Option<String>,
 }
 
 fn default_page() -> i64 {
@@ -194,10 +230,12 @@ fn default_list_limit() -> i64 {
     20
 }
 
-pub async fn list_executions(
-    State(state): State<AppState>,
-    Query(query): Query<ListExecutionsQuery>,
-) -> ApiResult<ExecutionListResponse> {
+/// Filter of a list or export request, with parameters it leaves unset
+/// taken from its saved view.
+async fn resolve_filter(
+    state: &AppState,
+    query: ListExecutionsQuery,
+) -> Result<ExecutionFilter, ApiError> {
     let filter = ExecutionFilter {
         project: query.project,
         source: query.source,
@@ -210,18 +248,161 @@ pub async fn list_executions(
         to: query.to,
         search: query.search,
         failed_only: query.failed_only,
+        tags: query
+            .tag
+            .as_deref()
+            .map(|t| {
+                t.split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default(),
     };
 
+    match query.view {
+        Some(view_id) => {
+            let lookup = view_id.clone();
+            let view = state
+                .storage
+                .run(move |s| s.get_view(&lookup))
+                .await?
+                .ok_or_else(|| ApiError::not_found("View", &view_id))?;
+            Ok(filter.or(view.filter))
+        }
+        None => Ok(filter),
+    }
+}
+
+pub async fn list_executions(
+    State(state): State<AppState>,
+    Query(query): Query<ListExecutionsQuery>,
+) -> ApiResult<ExecutionListResponse> {
     let page = query.page.max(1);
     let limit = query.limit.clamp(1, 100);
+    let filter = resolve_filter(&state, query).await?;
 
     state
         .storage
-        .get_executions(&filter, page, limit)
+        .run(move |s| s.get_executions(&filter, page, limit))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
+#[derive(Deserialize)]
+pub struct ExportExecutionsQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Comma-separated columns to export, in order
+    #[serde(default)]
+    pub columns: Option<String>,
+    /// Replace message bodies with `[redacted]`
+    #[serde(default = "default_redact")]
+    pub redact: bool,
+}
+
+fn default_redact() -> bool {
+    true
+}
+
+fn execution_columns() -> Vec<Column> {
+    use ColumnKind::{Integer, Real, Text};
+    [
+        ("id", Text),
+        ("project", Text),
+        ("agent", Text),
+        ("source", Text),
+        ("requester", Text),
+        ("channel", Text),
+        ("model", Text),
+        ("session_id", Text),
+        ("agent_revision", Integer),
+        ("experiment_id", Text),
+        ("variant", Text),
+        ("rerun_of", Text),
+        ("cost_usd", Real),
+        ("input_tokens", Integer),
+        ("output_tokens", Integer),
+        ("cache_read_tokens", Integer),
+        ("cache_creation_tokens", Integer),
+        ("duration_ms", Integer),
+        ("duration_api_ms", Integer),
+        ("feedback", Integer),
+        ("tags", Text),
+        ("metadata", Text),
+        ("instruction", Text),
+        ("user_message", Text),
+        ("user_context", Text),
+        ("response", Text),
+        ("structured_output", Text),
+        ("created_at", Integer),
+    ]
+    .into_iter()
+    .map(|(name, kind)| Column::new(name, kind))
+    .collect()
+}
+
+fn execution_row(execution: &ExecutionExport, redact: bool) -> serde_json::Map<String, Value> {
+    let mut row = export::flatten(&serde_json::to_value(execution).unwrap_or_default());
+    if redact {
+        for column in BODY_COLUMNS {
+            if let Some(value) = row.get_mut(*column).filter(|v| !v.is_null()) {
+                *value = Value::String("[redacted]".to_string());
+            }
+        }
+    }
+    row
+}
+
+/// Streams the executions matching a list filter, oldest first, fetching
+/// and encoding them a batch at a time.
+pub async fn export_executions(
+    State(state): State<AppState>,
+    Query(export): Query<ExportExecutionsQuery>,
+    Query(query): Query<ListExecutionsQuery>,
+) -> Result<Response, ApiError> {
+    let filter = Arc::new(resolve_filter(&state, query).await?);
+    let columns = export::select_columns(execution_columns(), export.columns.as_deref())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let encoder =
+        Encoder::new(export.format, columns).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let redact = export.redact;
+
+    let storage = state.storage.clone();
+    let chunks = futures::stream::try_unfold(
+        (Some(encoder), None::<(i64, String)>),
+        move |(encoder, after)| {
+            let storage = storage.clone();
+            let filter = filter.clone();
+            async move {
+                let Some(mut encoder) = encoder else {
+                    return Ok(None);
+                };
+                let batch = storage
+                    .run(move |s| s.export_executions(&filter, after, EXPORT_BATCH))
+                    .await
+                    .inspect_err(|e| tracing::error!(error = %e, "Execution export failed"))?;
+                let rows: Vec<_> = batch.iter().map(|e| execution_row(e, redact)).collect();
+                let mut chunk = encoder.encode(&rows)?;
+                if (batch.len() as i64) < EXPORT_BATCH {
+                    chunk.extend(encoder.finish()?);
+                    return Ok::<_, anyhow::Error>(Some((Bytes::from(chunk), (None, None))));
+                }
+                let next = batch.last().map(|e| (e.created_at, e.id.clone()));
+                Ok(Some((Bytes::from(chunk), (Some(encoder), next))))
+            }
+        },
+    );
+
+    Ok(export::attachment(
+        export.format,
+        "executions",
+        Body::from_stream(chunks),
+    ))
+}
+
 #[derive(Serialize)]
 pub struct ExecutionDetailResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -234,7 +415,8 @@ pub async fn get_execution_detail(
 ) -> ApiResult<ExecutionDetailResponse> {
     state
         .storage
-        .get_execution_by_id(&id)
+        .run(move |s| s.get_execution_by_id(&id))
+        .await
         .map(|execution| Json(ExecutionDetailResponse { execution }))
         .map_err(Into::into)
 }
@@ -244,7 +426,11 @@ pub async fn update_execution(
     Path(id): Path<String>,
     Json(updates): Json<serde_json::Value>,
 ) -> Json<UpdateResponse> {
-    match state.storage.update_metadata(&id, &updates) {
+    match state
+        .storage
+        .run(move |s| s.update_metadata(&id, &updates))
+        .await
+    {
         Ok(true) => Json(UpdateResponse {
             success: true,
             error: None,
@@ -263,7 +449,8 @@ pub async fn update_execution(
 pub async fn get_filter_options(State(state): State<AppState>) -> ApiResult<FilterOptions> {
     state
         .storage
-        .get_filter_options()
+        .run(|s| s.get_filter_options())
+        .await
         .map(Json)
         .map_err(Into::into)
 }
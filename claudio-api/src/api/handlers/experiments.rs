@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use crate::analytics;
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::handlers::DeleteResponse;
+use crate::api::routes::AppState;
+use crate::storage::{CreateExperiment, Experiment, ExperimentVariant, UpdateExperiment};
+
+fn validate_variants(variants: &[ExperimentVariant]) -> Result<(), ApiError> {
+    if variants.len() < 2 {
+        return Err(ApiError::bad_request(
+            "an experiment needs at least two variants",
+        ));
+    }
+    let mut names = HashSet::new();
+    for variant in variants {
+        let name = variant.name.trim();
+        if name.is_empty() {
+            return Err(ApiError::bad_request("variant names must not be empty"));
+        }
+        if !names.insert(name) {
+            return Err(ApiError::bad_request(format!(
+                "duplicate variant name '{}'",
+                name
+            )));
+        }
+        if variant.weight == 0 {
+            return Err(ApiError::bad_request(format!(
+                "variant '{}' needs a weight above 0",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Only one experiment may run per agent, otherwise a request could be
+/// assigned to both.
+async fn ensure_no_running_experiment(
+    state: &AppState,
+    project_id: &str,
+    agent: &str,
+    except: Option<&str>,
+) -> Result<(), ApiError> {
+    let (pid, name) = (project_id.to_string(), agent.to_string());
+    let running = state
+        .storage
+        .run(move |s| s.running_experiment(&pid, &name))
+        .await?;
+    match running {
+        Some(other) if Some(other.id.as_str()) != except => Err(ApiError::bad_request(format!(
+            "experiment '{}' is already running on agent '{}'",
+            other.name, agent
+        ))),
+        _ => Ok(()),
+    }
+}
+
+pub async fn list_experiments(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> ApiResult<Vec<Experiment>> {
+    state
+        .storage
+        .run(move |s| s.list_experiments(&project_id))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn get_experiment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Experiment> {
+    let lookup = id.clone();
+    state
+        .storage
+        .run(move |s| s.get_experiment(&lookup))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Experiment", &id))
+}
+
+pub async fn create_experiment(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Json(mut input): Json<CreateExperiment>,
+) -> ApiResult<Experiment> {
+    input.name = input.name.trim().to_string();
+    if input.name.is_empty() {
+        return Err(ApiError::bad_request("name must not be empty"));
+    }
+    validate_variants(&input.variants)?;
+
+    let (pid, name) = (project_id.clone(), input.agent.trim().to_string());
+    let agent = state
+        .storage
+        .run(move |s| s.get_agent_by_name(&pid, &name))
+        .await?
+        .ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "agent '{}' does not exist in project '{}'",
+                input.agent.trim(),
+                project_id
+            ))
+        })?;
+    input.agent = agent.name;
+    ensure_no_running_experiment(&state, &project_id, &input.agent, None).await?;
+
+    state
+        .storage
+        .run(move |s| s.create_experiment(&project_id, &input))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn update_experiment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<UpdateExperiment>,
+) -> ApiResult<Experiment> {
+    let lookup = id.clone();
+    let experiment = state
+        .storage
+        .run(move |s| s.get_experiment(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Experiment", &id))?;
+
+    if let Some(ref variants) = input.variants {
+        validate_variants(variants)?;
+    }
+    match input.status.as_deref() {
+        None | Some("stopped") => {}
+        Some("running") => {
+            ensure_no_running_experiment(
+                &state,
+                &experiment.project_id,
+                &experiment.agent,
+                Some(&experiment.id),
+            )
+            .await?
+        }
+        Some(other) => {
+            return Err(ApiError::bad_request(format!(
+                "status must be 'running' or 'stopped', got '{}'",
+                other
+            )));
+        }
+    }
+
+    state
+        .storage
+        .run(move |s| s.update_experiment(&id, &input))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Experiment", &experiment.id))
+}
+
+pub async fn delete_experiment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state.storage.run(move |s| s.delete_experiment(&id)).await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+pub async fn get_experiment_results(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<analytics::ExperimentResultsResponse> {
+    let lookup = id.clone();
+    let experiment = state
+        .storage
+        .run(move |s| s.get_experiment(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Experiment", &id))?;
+
+    state
+        .storage
+        .with_connection(move |conn| analytics::get_experiment_results(conn, &experiment))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
@@ -0,0 +1,52 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use tracing::Instrument;
+
+use crate::api::routes::AppState;
+use crate::metrics;
+use crate::telemetry;
+
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(state.storage.pool_state()),
+    )
+}
+
+/// Counts requests and records their latency under the matched route
+/// pattern, so `/v1/agents/{id}` is one series rather than one per agent.
+/// Each request is traced in a server span that continues the caller's
+/// `traceparent`, if any.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let start = Instant::now();
+    let span = tracing::info_span!(
+        "http.request",
+        otel.name = %format!("{} {}", method, route),
+        otel.kind = "server",
+        http.request.method = %method,
+        http.route = %route,
+        http.response.status_code = tracing::field::Empty,
+    );
+    telemetry::set_remote_parent(&span, request.headers());
+
+    let response = next.run(request).instrument(span.clone()).await;
+
+    let status = response.status().as_u16().to_string();
+    span.record("http.response.status_code", status.as_str());
+    metrics::HTTP_REQUESTS.inc(&[&method, &route, &status]);
+    metrics::HTTP_DURATION.observe(&[&method, &route], start.elapsed().as_secs_f64());
+    response
+}
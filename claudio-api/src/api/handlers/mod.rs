@@ -1,15 +1,37 @@
 mod agents;
+mod alerts;
+mod budgets;
 mod chat;
+mod eval;
 mod executions;
+mod experiments;
+mod metrics;
 mod projects;
+mod reports;
+mod reruns;
+mod revisions;
 mod stats;
+mod suggestions;
+mod tags;
 mod users;
 mod utils;
+mod views;
 
 pub use agents::*;
+pub use alerts::*;
+pub use budgets::*;
 pub use chat::*;
+pub use eval::*;
 pub use executions::*;
+pub use experiments::*;
+pub use metrics::*;
 pub use projects::*;
+pub use reports::*;
+pub use reruns::*;
+pub use revisions::*;
 pub use stats::*;
+pub use suggestions::*;
+pub use tags::*;
 pub use users::*;
 pub use utils::*;
+pub use views::*;
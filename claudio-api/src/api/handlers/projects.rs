@@ -9,16 +9,23 @@ use crate::api::routes::AppState;
 use crate::storage::{CreateProject, Project, UpdateProject};
 
 pub async fn list_projects(State(state): State<AppState>) -> ApiResult<Vec<Project>> {
-    state.storage.list_projects().map(Json).map_err(Into::into)
+    state
+        .storage
+        .run(|s| s.list_projects())
+        .await
+        .map(Json)
+        .map_err(Into::into)
 }
 
 pub async fn get_project(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> ApiResult<Project> {
+    let lookup = id.clone();
     state
         .storage
-        .get_project(&id)?
+        .run(move |s| s.get_project(&lookup))
+        .await?
         .map(Json)
         .ok_or_else(|| ApiError::not_found("Project", &id))
 }
@@ -29,7 +36,8 @@ pub async fn create_project(
 ) -> ApiResult<Project> {
     state
         .storage
-        .create_project(input)
+        .run(move |s| s.create_project(input))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
@@ -39,9 +47,11 @@ pub async fn update_project(
     Path(id): Path<String>,
     Json(input): Json<UpdateProject>,
 ) -> ApiResult<Project> {
+    let lookup = id.clone();
     state
         .storage
-        .update_project(&id, &input)?
+        .run(move |s| s.update_project(&lookup, &input))
+        .await?
         .map(Json)
         .ok_or_else(|| ApiError::not_found("Project", &id))
 }
@@ -55,7 +65,11 @@ pub async fn delete_project(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> ApiResult<DeleteResponse> {
-    let deleted = state.storage.delete_project(&id)?;
+    let lookup = id.clone();
+    let deleted = state
+        .storage
+        .run(move |s| s.delete_project(&lookup))
+        .await?;
     if deleted {
         state.rate_limit.remove(&id);
     }
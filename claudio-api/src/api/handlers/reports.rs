@@ -0,0 +1,237 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::Deserialize;
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::handlers::DeleteResponse;
+use crate::api::reports;
+use crate::api::routes::AppState;
+use crate::config::Config;
+use crate::storage::{CreateReport, Report, ReportDestination, ReportRun, UpdateReport};
+use crate::utils::Schedule;
+
+#[derive(Debug, Deserialize)]
+pub struct RunReportQuery {
+    /// Render without delivering or recording the run
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportRunsQuery {
+    #[serde(default = "default_runs_limit")]
+    pub limit: i64,
+}
+
+fn default_runs_limit() -> i64 {
+    20
+}
+
+/// Empty strings clear optional text fields
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Checks a report's settings and returns its next scheduled run.
+fn validate(report: &Report) -> Result<Option<i64>, ApiError> {
+    if report.name.trim().is_empty() {
+        return Err(ApiError::bad_request("name must not be empty"));
+    }
+    if report.sections.is_empty() {
+        return Err(ApiError::bad_request("sections must not be empty"));
+    }
+    for destination in &report.destinations {
+        match destination {
+            ReportDestination::Slack { channel } => {
+                if channel.trim().is_empty() {
+                    return Err(ApiError::bad_request("slack destinations need a channel"));
+                }
+                if Config::global().reports.slack_bot_token.is_none() {
+                    return Err(ApiError::bad_request(
+                        "slack destinations need SLACK_BOT_TOKEN to be set",
+                    ));
+                }
+            }
+            ReportDestination::Webhook { url } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(ApiError::bad_request(format!(
+                        "webhook url '{}' must be http or https",
+                        url
+                    )));
+                }
+            }
+        }
+    }
+    let tz = reports::timezone(report.timezone.as_deref())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let schedule = Schedule::parse(&report.schedule)
+        .map_err(|e| ApiError::bad_request(format!("invalid schedule: {}", e)))?;
+    Ok(schedule
+        .next_after(chrono::Utc::now().timestamp(), tz)
+        .filter(|_| report.enabled))
+}
+
+async fn ensure_project(state: &AppState, project: Option<String>) -> Result<(), ApiError> {
+    let Some(project_id) = project else {
+        return Ok(());
+    };
+    let lookup = project_id.clone();
+    state
+        .storage
+        .run(move |s| s.get_project(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project", &project_id))?;
+    Ok(())
+}
+
+async fn load_report(state: &AppState, id: String) -> Result<Report, ApiError> {
+    let lookup = id.clone();
+    state
+        .storage
+        .run(move |s| s.get_report(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Report", &id))
+}
+
+pub async fn list_reports(State(state): State<AppState>) -> ApiResult<Vec<Report>> {
+    state
+        .storage
+        .run(|s| s.list_reports())
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn get_report(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Report> {
+    load_report(&state, id).await.map(Json)
+}
+
+pub async fn create_report(
+    State(state): State<AppState>,
+    Json(mut input): Json<CreateReport>,
+) -> ApiResult<Report> {
+    input.name = input.name.trim().to_string();
+    input.project = non_empty(input.project);
+    input.timezone = non_empty(input.timezone);
+    let next_run_at = validate(&Report {
+        id: String::new(),
+        name: input.name.clone(),
+        project: input.project.clone(),
+        schedule: input.schedule.clone(),
+        timezone: input.timezone.clone(),
+        period: input.period,
+        sections: input.sections.clone(),
+        format: input.format,
+        destinations: input.destinations.clone(),
+        enabled: input.enabled,
+        next_run_at: None,
+        last_run_at: None,
+        created_at: 0,
+        updated_at: 0,
+    })?;
+    ensure_project(&state, input.project.clone()).await?;
+
+    state
+        .storage
+        .write("create_report", move |s| {
+            s.create_report(&input, next_run_at)
+        })
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn update_report(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<UpdateReport>,
+) -> ApiResult<Report> {
+    let mut report = load_report(&state, id).await?;
+    if let Some(name) = input.name {
+        report.name = name.trim().to_string();
+    }
+    if input.project.is_some() {
+        report.project = non_empty(input.project);
+        ensure_project(&state, report.project.clone()).await?;
+    }
+    if let Some(schedule) = input.schedule {
+        report.schedule = schedule;
+    }
+    if input.timezone.is_some() {
+        report.timezone = non_empty(input.timezone);
+    }
+    if let Some(period) = input.period {
+        report.period = period;
+    }
+    if let Some(sections) = input.sections {
+        report.sections = sections;
+    }
+    if let Some(format) = input.format {
+        report.format = format;
+    }
+    if let Some(destinations) = input.destinations {
+        report.destinations = destinations;
+    }
+    if let Some(enabled) = input.enabled {
+        report.enabled = enabled;
+    }
+    report.next_run_at = validate(&report)?;
+    report.updated_at = chrono::Utc::now().timestamp();
+
+    let id = report.id.clone();
+    let updated = report.clone();
+    if !state
+        .storage
+        .write("update_report", move |s| s.update_report(&updated))
+        .await?
+    {
+        return Err(ApiError::not_found("Report", &id));
+    }
+    Ok(Json(report))
+}
+
+pub async fn delete_report(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state
+        .storage
+        .write("delete_report", move |s| s.delete_report(&id))
+        .await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+/// Renders and delivers a report now, outside its schedule.
+pub async fn run_report(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<RunReportQuery>,
+) -> ApiResult<ReportRun> {
+    let report = load_report(&state, id).await?;
+    reports::run(&state.storage, report, "manual", query.dry_run)
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn list_report_runs(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ReportRunsQuery>,
+) -> ApiResult<Vec<ReportRun>> {
+    let report = load_report(&state, id).await?;
+    let limit = query.limit.clamp(1, 100);
+    state
+        .storage
+        .run(move |s| s.list_report_runs(&report.id, limit))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
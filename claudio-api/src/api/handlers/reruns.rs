@@ -0,0 +1,213 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::handlers::chat::{
+    ExecutionRecord, execute_and_save, format_structured_message, merge_tools,
+};
+use crate::api::routes::AppState;
+use crate::api::types::{ChatCompletionRequest, ChatCompletionResponse, default_timeout};
+use crate::config::Config;
+use crate::storage::ExecutionDetail;
+
+#[derive(Deserialize, Default)]
+pub struct RerunRequest {
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Run with this agent's current configuration instead of the original's
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Replaces the stored instruction
+    #[serde(default)]
+    pub instruction: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    /// Execution to compare against; defaults to the latest rerun
+    #[serde(default)]
+    pub with: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ComparedExecution {
+    pub id: String,
+    pub agent: Option<String>,
+    pub model: Option<String>,
+    pub instruction: Option<String>,
+    pub response: String,
+    pub structured_output: Option<String>,
+    pub cost_usd: Option<f64>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Serialize)]
+pub struct ExecutionComparison {
+    pub original: ComparedExecution,
+    pub other: ComparedExecution,
+    /// `other` minus `original`
+    pub cost_delta_usd: Option<f64>,
+    pub duration_delta_ms: Option<i64>,
+}
+
+impl From<ExecutionDetail> for ComparedExecution {
+    fn from(e: ExecutionDetail) -> Self {
+        Self {
+            id: e.id,
+            agent: e.agent,
+            model: e.model,
+            instruction: e.instruction,
+            response: e.response,
+            structured_output: e.structured_output,
+            cost_usd: e.cost_usd,
+            input_tokens: e.input_tokens,
+            output_tokens: e.output_tokens,
+            duration_ms: e.duration_ms,
+            created_at: e.created_at,
+        }
+    }
+}
+
+pub async fn rerun_execution(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    body: Option<Json<RerunRequest>>,
+) -> ApiResult<ChatCompletionResponse> {
+    let overrides = body.map(|Json(b)| b).unwrap_or_default();
+    let original = load_execution(&state, &id).await?;
+
+    let project_id = original.project.clone();
+    let pid = project_id.clone();
+    let project = state
+        .storage
+        .run(move |s| s.get_project(&pid))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project", &project_id))?;
+
+    if state
+        .rate_limit
+        .check(&project_id, project.rate_limit_rpm)
+        .is_err()
+    {
+        return Err(ApiError::rate_limit(&project_id));
+    }
+
+    let agent_name = overrides.agent.clone().or_else(|| original.agent.clone());
+    let agent = match agent_name.clone() {
+        Some(name) => {
+            let pid = project_id.clone();
+            state
+                .storage
+                .run(move |s| s.get_agent_by_name(&pid, &name))
+                .await?
+        }
+        None => None,
+    };
+    if agent.is_none()
+        && let Some(ref name) = overrides.agent
+    {
+        return Err(ApiError::not_found("Agent", name));
+    }
+
+    let mut instruction = original.instruction.clone();
+    let mut model = original.model.clone();
+    let mut allowed_tools = original.allowed_tools.clone();
+    let mut agent_revision = original.agent_revision;
+    let mut timeout = default_timeout();
+    let mut output_schema = None;
+    let mut working_dir = None;
+    if let Some(agent) = agent {
+        // Switching agents takes the new agent's instruction, model and tools;
+        // otherwise the stored snapshot is replayed as-is.
+        if overrides.agent.is_some() {
+            instruction = agent.instruction.clone();
+            model = Some(agent.model.clone());
+            allowed_tools = merge_tools(agent.tools.clone(), project.allowed_tools.clone());
+            agent_revision = Some(agent.revision);
+        }
+        if agent.timeout > 0 {
+            timeout = Some(agent.timeout as u64);
+        }
+        output_schema = agent.output_schema;
+        working_dir = agent.working_dir;
+    }
+    if overrides.instruction.is_some() {
+        instruction = overrides.instruction;
+    }
+    if overrides.model.is_some() {
+        model = overrides.model;
+    }
+
+    let req = ChatCompletionRequest {
+        user_message: format_structured_message(
+            instruction.as_deref(),
+            original.user_context.as_deref(),
+            &original.user_message,
+        ),
+        project: Some(project_id),
+        requester: original.requester.clone(),
+        source: original.source.clone(),
+        agent: agent_name,
+        model,
+        allowed_tools,
+        disallowed_tools: original.disallowed_tools.clone(),
+        system_prompt: project.system_prompt.clone(),
+        working_dir: Some(
+            working_dir.unwrap_or_else(|| Config::global().defaults.isolated_dir.clone()),
+        ),
+        output_schema,
+        timeout,
+        ..Default::default()
+    };
+
+    let record = ExecutionRecord {
+        instruction,
+        user_message: original.user_message,
+        user_context: original.user_context,
+        agent_revision,
+        rerun_of: Some(original.id),
+        experiment: None,
+    };
+    execute_and_save(&state, req, record).await.map(Json)
+}
+
+pub async fn compare_executions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<CompareQuery>,
+) -> ApiResult<ExecutionComparison> {
+    let original = load_execution(&state, &id).await?;
+    let other_id = query
+        .with
+        .or_else(|| original.reruns.last().cloned())
+        .ok_or_else(|| ApiError::bad_request("execution has no reruns; pass ?with=<id>"))?;
+    let other = load_execution(&state, &other_id).await?;
+
+    let cost_delta_usd = other.cost_usd.zip(original.cost_usd).map(|(b, a)| b - a);
+    let duration_delta_ms = other
+        .duration_ms
+        .zip(original.duration_ms)
+        .map(|(b, a)| b - a);
+
+    Ok(Json(ExecutionComparison {
+        original: original.into(),
+        other: other.into(),
+        cost_delta_usd,
+        duration_delta_ms,
+    }))
+}
+
+async fn load_execution(state: &AppState, id: &str) -> Result<ExecutionDetail, ApiError> {
+    let lookup = id.to_string();
+    state
+        .storage
+        .run(move |s| s.get_execution_by_id(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Execution", id))
+}
@@ -0,0 +1,195 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::Deserialize;
+
+use crate::analytics;
+use crate::api::classify_cache;
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::routes::AppState;
+use crate::plugins::semantic;
+use crate::storage::{Agent, Project, Revision, RevisionDiff, RevisionKind, RevisionSummary};
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: i64,
+    #[serde(default)]
+    pub to: Option<i64>,
+}
+
+pub async fn list_agent_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<RevisionSummary>> {
+    list_revisions(&state, RevisionKind::Agent, id).await
+}
+
+pub async fn get_agent_revision(
+    State(state): State<AppState>,
+    Path((id, revision)): Path<(String, i64)>,
+) -> ApiResult<Revision> {
+    get_revision(&state, RevisionKind::Agent, id, revision).await
+}
+
+pub async fn diff_agent_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> ApiResult<RevisionDiff> {
+    let lookup = id.clone();
+    let current = state
+        .storage
+        .run(move |s| s.get_agent(&lookup))
+        .await?
+        .map(|a| a.revision);
+    diff_revisions(&state, RevisionKind::Agent, id, query, current).await
+}
+
+pub async fn rollback_agent(
+    State(state): State<AppState>,
+    Path((id, revision)): Path<(String, i64)>,
+) -> ApiResult<Agent> {
+    let lookup = id.clone();
+    let agent = state
+        .storage
+        .run(move |s| s.rollback_agent(&lookup, revision))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Agent revision", &format!("{}@{}", id, revision)))?;
+
+    semantic::enqueue_agent(&agent.project_id, &agent.id);
+    classify_cache::invalidate_project(&agent.project_id);
+
+    Ok(Json(agent))
+}
+
+pub async fn get_agent_revision_stats(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<analytics::AgentRevisionStatsResponse> {
+    let lookup = id.clone();
+    let agent = state
+        .storage
+        .run(move |s| s.get_agent(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Agent", &id))?;
+
+    state
+        .storage
+        .with_connection(move |conn| {
+            analytics::get_agent_revision_stats(conn, &agent.project_id, &agent.name)
+        })
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn list_project_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<RevisionSummary>> {
+    list_revisions(&state, RevisionKind::Project, id).await
+}
+
+pub async fn get_project_revision(
+    State(state): State<AppState>,
+    Path((id, revision)): Path<(String, i64)>,
+) -> ApiResult<Revision> {
+    get_revision(&state, RevisionKind::Project, id, revision).await
+}
+
+pub async fn diff_project_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> ApiResult<RevisionDiff> {
+    let lookup = id.clone();
+    let current = state
+        .storage
+        .run(move |s| s.get_project(&lookup))
+        .await?
+        .map(|p| p.revision);
+    diff_revisions(&state, RevisionKind::Project, id, query, current).await
+}
+
+pub async fn rollback_project(
+    State(state): State<AppState>,
+    Path((id, revision)): Path<(String, i64)>,
+) -> ApiResult<Project> {
+    let lookup = id.clone();
+    let project = state
+        .storage
+        .run(move |s| s.rollback_project(&lookup, revision))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project revision", &format!("{}@{}", id, revision)))?;
+
+    state.rate_limit.remove(&id);
+
+    Ok(Json(project))
+}
+
+async fn list_revisions(
+    state: &AppState,
+    kind: RevisionKind,
+    id: String,
+) -> ApiResult<Vec<RevisionSummary>> {
+    let lookup = id.clone();
+    let revisions = state
+        .storage
+        .run(move |s| s.list_revisions(kind, &lookup))
+        .await?;
+    if revisions.is_empty() {
+        return Err(ApiError::not_found(resource_name(kind), &id));
+    }
+    Ok(Json(revisions))
+}
+
+async fn get_revision(
+    state: &AppState,
+    kind: RevisionKind,
+    id: String,
+    revision: i64,
+) -> ApiResult<Revision> {
+    let lookup = id.clone();
+    state
+        .storage
+        .run(move |s| s.get_revision(kind, &lookup, revision))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Revision", &format!("{}@{}", id, revision)))
+}
+
+/// Diffs `from` against `to`, defaulting `to` to the entity's current revision.
+async fn diff_revisions(
+    state: &AppState,
+    kind: RevisionKind,
+    id: String,
+    query: DiffQuery,
+    current: Option<i64>,
+) -> ApiResult<RevisionDiff> {
+    let to = query
+        .to
+        .or(current)
+        .ok_or_else(|| ApiError::not_found(resource_name(kind), &id))?;
+
+    if query.from == to {
+        return Err(ApiError::bad_request(
+            "from and to must be different revisions",
+        ));
+    }
+
+    let (lookup, from) = (id.clone(), query.from);
+    state
+        .storage
+        .run(move |s| s.diff_revisions(kind, &lookup, from, to))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Revision", &format!("{}@{}..{}", id, query.from, to)))
+}
+
+fn resource_name(kind: RevisionKind) -> &'static str {
+    match kind {
+        RevisionKind::Agent => "Agent",
+        RevisionKind::Project => "Project",
+    }
+}
@@ -1,13 +1,18 @@
 use axum::{
     Json,
+    body::Body,
     extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::analytics::{self, Granularity, Period};
-use crate::api::error::ApiResult;
+use crate::analytics::{self, Granularity, Period, TimeRange};
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::export::{self, Encoder, ExportFormat};
 use crate::api::routes::AppState;
+use crate::config::Config;
 
 #[derive(Serialize)]
 pub struct StatsResponse {
@@ -29,7 +34,11 @@ pub struct FeedbackStats {
 }
 
 pub async fn get_stats(State(state): State<AppState>) -> Json<StatsResponse> {
-    let stats = state.storage.get_stats(None).unwrap_or_default();
+    let stats = state
+        .storage
+        .run(|s| s.get_stats(None))
+        .await
+        .unwrap_or_default();
     Json(build_stats_response(stats))
 }
 
@@ -37,7 +46,11 @@ pub async fn get_project_stats(
     State(state): State<AppState>,
     Path(project): Path<String>,
 ) -> Json<StatsResponse> {
-    let stats = state.storage.get_stats(Some(&project)).unwrap_or_default();
+    let stats = state
+        .storage
+        .run(move |s| s.get_stats(Some(&project)))
+        .await
+        .unwrap_or_default();
     Json(build_stats_response(stats))
 }
 
@@ -71,10 +84,35 @@ fn build_stats_response(stats: crate::storage::Stats) -> StatsResponse {
     }
 }
 
+/// Time window shared by the analytics endpoints: a `period`, or explicit
+/// `from`/`to` bounds, in `tz` (defaults to the configured timezone).
 #[derive(Deserialize)]
-pub struct OverviewQuery {
+pub struct RangeQuery {
+    #[serde(default)]
+    pub period: Option<Period>,
+    #[serde(default)]
+    pub from: Option<String>,
     #[serde(default)]
-    pub period: Period,
+    pub to: Option<String>,
+    #[serde(default)]
+    pub tz: Option<String>,
+}
+
+impl RangeQuery {
+    fn resolve(&self, default: Period) -> Result<TimeRange, ApiError> {
+        TimeRange::resolve(
+            self.period.unwrap_or(default),
+            self.from.as_deref(),
+            self.to.as_deref(),
+            self.tz.as_deref(),
+            &Config::global().defaults.timezone,
+        )
+        .map_err(|e| ApiError::bad_request(e.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OverviewQuery {
     #[serde(default)]
     pub project: Option<String>,
     #[serde(default)]
@@ -83,18 +121,21 @@ pub struct OverviewQuery {
 
 pub async fn get_stats_overview(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<OverviewQuery>,
 ) -> ApiResult<analytics::OverviewStats> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| {
+        .with_connection(move |conn| {
             analytics::get_overview_stats(
                 conn,
-                query.period,
+                &range,
                 query.project.as_deref(),
                 query.source.as_deref(),
             )
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
@@ -104,10 +145,6 @@ pub struct TimeseriesQuery {
     #[serde(default)]
     pub granularity: Granularity,
     #[serde(default)]
-    pub from: Option<String>,
-    #[serde(default)]
-    pub to: Option<String>,
-    #[serde(default)]
     pub project: Option<String>,
     #[serde(default)]
     pub source: Option<String>,
@@ -117,45 +154,30 @@ pub struct TimeseriesQuery {
 
 pub async fn get_stats_timeseries(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<TimeseriesQuery>,
 ) -> ApiResult<analytics::TimeSeriesResponse> {
-    let now = Utc::now().timestamp();
-
-    let to = query
-        .to
-        .as_ref()
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.timestamp())
-        .unwrap_or(now);
-
-    let from = query
-        .from
-        .as_ref()
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.timestamp())
-        .unwrap_or(to - 604800);
+    let range = range.resolve(Period::Days7)?;
 
     state
         .storage
-        .with_connection(|conn| {
+        .with_connection(move |conn| {
             analytics::get_timeseries(
                 conn,
                 query.granularity,
-                from,
-                to,
+                &range,
                 query.project.as_deref(),
                 query.source.as_deref(),
                 query.model.as_deref(),
             )
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
 pub struct ModelsQuery {
-    #[serde(default)]
-    pub period: Period,
     #[serde(default)]
     pub project: Option<String>,
     #[serde(default)]
@@ -164,26 +186,27 @@ pub struct ModelsQuery {
 
 pub async fn get_stats_models(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<ModelsQuery>,
 ) -> ApiResult<analytics::ModelsResponse> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| {
+        .with_connection(move |conn| {
             analytics::get_model_stats(
                 conn,
-                query.period,
+                &range,
                 query.project.as_deref(),
                 query.source.as_deref(),
             )
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
 pub struct ErrorsQuery {
-    #[serde(default)]
-    pub period: Period,
     #[serde(default)]
     pub project: Option<String>,
     #[serde(default)]
@@ -192,47 +215,49 @@ pub struct ErrorsQuery {
 
 pub async fn get_stats_errors(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<ErrorsQuery>,
 ) -> ApiResult<analytics::ErrorsResponse> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| {
+        .with_connection(move |conn| {
             analytics::get_error_stats(
                 conn,
-                query.period,
+                &range,
                 query.project.as_deref(),
                 query.source.as_deref(),
             )
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
 pub struct SourcesQuery {
-    #[serde(default)]
-    pub period: Period,
     #[serde(default)]
     pub project: Option<String>,
 }
 
 pub async fn get_stats_sources(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<SourcesQuery>,
 ) -> ApiResult<analytics::SourcesResponse> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| {
-            analytics::get_source_stats(conn, query.period, query.project.as_deref())
+        .with_connection(move |conn| {
+            analytics::get_source_stats(conn, &range, query.project.as_deref())
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
 pub struct RequestersQuery {
-    #[serde(default)]
-    pub period: Period,
     #[serde(default)]
     pub project: Option<String>,
     #[serde(default)]
@@ -247,36 +272,64 @@ fn default_limit() -> i64 {
 
 pub async fn get_stats_requesters(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<RequestersQuery>,
 ) -> ApiResult<analytics::RequestersResponse> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| {
+        .with_connection(move |conn| {
             analytics::get_requester_stats(
                 conn,
-                query.period,
+                &range,
                 query.project.as_deref(),
                 query.source.as_deref(),
                 query.limit,
             )
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
-pub struct WorkflowStatsQuery {
+pub struct AgentsQuery {
+    #[serde(default)]
+    pub project: Option<String>,
     #[serde(default)]
-    pub period: Period,
+    pub source: Option<String>,
+}
+
+pub async fn get_stats_agents(
+    State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
+    Query(query): Query<AgentsQuery>,
+) -> ApiResult<analytics::AgentsResponse> {
+    let range = range.resolve(Period::default())?;
+    state
+        .storage
+        .with_connection(move |conn| {
+            analytics::get_agent_stats(
+                conn,
+                &range,
+                query.project.as_deref(),
+                query.source.as_deref(),
+            )
+        })
+        .await
+        .map(Json)
+        .map_err(Into::into)
 }
 
 pub async fn get_workflow_stats(
     State(state): State<AppState>,
-    Query(query): Query<WorkflowStatsQuery>,
+    Query(range): Query<RangeQuery>,
 ) -> ApiResult<analytics::WorkflowsResponse> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| analytics::get_workflow_stats(conn, query.period))
+        .with_connection(move |conn| analytics::get_workflow_stats(conn, &range))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
@@ -287,36 +340,64 @@ pub async fn record_workflow_stats(
 ) -> ApiResult<analytics::RecordWorkflowResponse> {
     state
         .storage
-        .with_connection(|conn| analytics::record_workflow_execution(conn, &req))
+        .with_connection(move |conn| analytics::record_workflow_execution(conn, &req))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
-pub struct ClassifyStatsQuery {
+pub struct WorkflowRunsQuery {
     #[serde(default)]
-    pub period: Period,
+    pub status: Option<analytics::WorkflowStatus>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+/// Latest runs of a workflow with their linked executions, classifications
+/// and webhook deliveries.
+pub async fn get_workflow_runs(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(range): Query<RangeQuery>,
+    Query(query): Query<WorkflowRunsQuery>,
+) -> ApiResult<analytics::WorkflowRunsResponse> {
+    let range = range.resolve(Period::default())?;
+    let limit = query.limit.clamp(1, 100);
+    state
+        .storage
+        .with_connection(move |conn| {
+            analytics::get_workflow_runs(conn, &name, &range, query.status, limit)
+        })
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+#[derive(Deserialize)]
+pub struct ClassifyStatsQuery {
     #[serde(default)]
     pub project: Option<String>,
 }
 
 pub async fn get_classify_stats(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<ClassifyStatsQuery>,
 ) -> ApiResult<analytics::ClassifyStatsResponse> {
+    let range = range.resolve(Period::default())?;
     state
         .storage
-        .with_connection(|conn| {
-            analytics::get_classify_stats(conn, query.period, query.project.as_deref())
+        .with_connection(move |conn| {
+            analytics::get_classify_stats(conn, &range, query.project.as_deref())
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
 
 #[derive(Deserialize)]
 pub struct ClassifyLogsQuery {
-    #[serde(default)]
-    pub period: Period,
     #[serde(default)]
     pub project: Option<String>,
     #[serde(default)]
@@ -339,17 +420,19 @@ fn default_list_limit() -> i64 {
 
 pub async fn get_classify_logs(
     State(state): State<AppState>,
+    Query(range): Query<RangeQuery>,
     Query(query): Query<ClassifyLogsQuery>,
 ) -> ApiResult<analytics::ClassifyLogsResponse> {
+    let range = range.resolve(Period::default())?;
     let page = query.page.max(1);
     let limit = query.limit.clamp(1, 100);
 
     state
         .storage
-        .with_connection(|conn| {
+        .with_connection(move |conn| {
             analytics::get_classify_logs(
                 conn,
-                query.period,
+                &range,
                 query.project.as_deref(),
                 query.agent.as_deref(),
                 query.method.as_deref(),
@@ -357,6 +440,117 @@ pub async fn get_classify_logs(
                 limit,
             )
         })
+        .await
         .map(Json)
         .map_err(Into::into)
 }
+
+#[derive(Deserialize)]
+pub struct StatsExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Comma-separated columns to export, in order
+    #[serde(default)]
+    pub columns: Option<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub granularity: Granularity,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+/// Exports the rows of one stats breakdown: `overview`, `timeseries`,
+/// `models`, `errors`, `sources`, `requesters`, `agents`, `workflows`,
+/// `classify_agents` or `classify_methods`. Nested fields become dotted
+/// columns.
+pub async fn export_stats(
+    State(state): State<AppState>,
+    Path(breakdown): Path<String>,
+    Query(range): Query<RangeQuery>,
+    Query(query): Query<StatsExportQuery>,
+) -> Result<Response, ApiError> {
+    let range = range.resolve(match breakdown.as_str() {
+        "timeseries" => Period::Days7,
+        _ => Period::default(),
+    })?;
+    let format = query.format;
+    let selection = query.columns.clone();
+    let name = breakdown.clone();
+    let rows = state
+        .storage
+        .with_connection(move |conn| {
+            let project = query.project.as_deref();
+            let source = query.source.as_deref();
+            let rows = match name.as_str() {
+                "overview" => Value::Array(vec![serde_json::to_value(
+                    analytics::get_overview_stats(conn, &range, project, source)?,
+                )?]),
+                "timeseries" => serde_json::to_value(
+                    analytics::get_timeseries(
+                        conn,
+                        query.granularity,
+                        &range,
+                        project,
+                        source,
+                        query.model.as_deref(),
+                    )?
+                    .data,
+                )?,
+                "models" => serde_json::to_value(
+                    analytics::get_model_stats(conn, &range, project, source)?.models,
+                )?,
+                "errors" => serde_json::to_value(
+                    analytics::get_error_stats(conn, &range, project, source)?.errors,
+                )?,
+                "sources" => serde_json::to_value(
+                    analytics::get_source_stats(conn, &range, project)?.sources,
+                )?,
+                "requesters" => serde_json::to_value(
+                    analytics::get_requester_stats(
+                        conn,
+                        &range,
+                        project,
+                        source,
+                        query.limit.clamp(1, 1000),
+                    )?
+                    .requesters,
+                )?,
+                "agents" => serde_json::to_value(
+                    analytics::get_agent_stats(conn, &range, project, source)?.agents,
+                )?,
+                "workflows" => {
+                    serde_json::to_value(analytics::get_workflow_stats(conn, &range)?.workflows)?
+                }
+                "classify_agents" => serde_json::to_value(
+                    analytics::get_classify_stats(conn, &range, project)?.agents,
+                )?,
+                "classify_methods" => serde_json::to_value(
+                    analytics::get_classify_stats(conn, &range, project)?.methods,
+                )?,
+                _ => return Ok(None),
+            };
+            Ok(Some(rows))
+        })
+        .await?
+        .ok_or_else(|| ApiError::not_found("Stats breakdown", &breakdown))?;
+
+    let rows: Vec<_> = rows
+        .as_array()
+        .map(|rows| rows.iter().map(export::flatten).collect())
+        .unwrap_or_default();
+    // Columns come from the rows, so an empty breakdown has nothing to encode
+    if rows.is_empty() {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    let columns = export::select_columns(export::infer_columns(&rows), selection.as_deref())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let mut encoder = Encoder::new(format, columns)?;
+    let mut body = encoder.encode(&rows)?;
+    body.extend(encoder.finish()?);
+    Ok(export::attachment(format, &breakdown, Body::from(body)))
+}
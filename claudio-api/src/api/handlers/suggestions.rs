@@ -0,0 +1,144 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::classify_cache;
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::routes::AppState;
+use crate::plugins::semantic;
+use crate::storage::{Agent, ExampleSuggestion, RoutingCorrection};
+
+const DEFAULT_SUGGESTION_LIMIT: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct CorrectionRequest {
+    /// Agent the execution should have been routed to
+    pub agent: String,
+    #[serde(default)]
+    pub corrected_by: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GenerateQuery {
+    /// Executions considered from each source
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct SuggestionsQuery {
+    #[serde(default = "default_status")]
+    pub status: String,
+}
+
+fn default_status() -> String {
+    "pending".into()
+}
+
+#[derive(Serialize)]
+pub struct GenerateResponse {
+    pub added: usize,
+}
+
+#[derive(Serialize)]
+pub struct RejectResponse {
+    pub rejected: bool,
+}
+
+pub async fn set_routing_correction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<CorrectionRequest>,
+) -> ApiResult<RoutingCorrection> {
+    let lookup = id.clone();
+    let execution = state
+        .storage
+        .run(move |s| s.get_execution_by_id(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Execution", &id))?;
+
+    let project = execution.project.clone();
+    let name = input.agent.trim().to_string();
+    let agent = state
+        .storage
+        .run(move |s| s.get_agent_by_name(&project, &name))
+        .await?
+        .ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "agent '{}' does not exist in project '{}'",
+                input.agent.trim(),
+                execution.project
+            ))
+        })?;
+
+    state
+        .storage
+        .run(move |s| s.set_routing_correction(&id, &agent.name, input.corrected_by.as_deref()))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn generate_suggestions(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<GenerateQuery>,
+) -> ApiResult<GenerateResponse> {
+    let lookup = project_id.clone();
+    state
+        .storage
+        .run(move |s| s.get_project(&lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project", &project_id))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_SUGGESTION_LIMIT);
+    let added = state
+        .storage
+        .run(move |s| s.generate_suggestions(&project_id, limit))
+        .await?;
+    Ok(Json(GenerateResponse { added }))
+}
+
+pub async fn list_suggestions(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<SuggestionsQuery>,
+) -> ApiResult<Vec<ExampleSuggestion>> {
+    state
+        .storage
+        .run(move |s| s.list_suggestions(&project_id, &query.status))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn approve_suggestion(
+    State(state): State<AppState>,
+    Path((project_id, id)): Path<(String, String)>,
+) -> ApiResult<Agent> {
+    let pid = project_id.clone();
+    let lookup = id.clone();
+    let agent = state
+        .storage
+        .run(move |s| s.approve_suggestion(&pid, &lookup))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Pending suggestion", &id))?;
+
+    semantic::enqueue_agent(&agent.project_id, &agent.id);
+    classify_cache::invalidate_project(&agent.project_id);
+
+    Ok(Json(agent))
+}
+
+pub async fn reject_suggestion(
+    State(state): State<AppState>,
+    Path((project_id, id)): Path<(String, String)>,
+) -> ApiResult<RejectResponse> {
+    let rejected = state
+        .storage
+        .run(move |s| s.reject_suggestion(&project_id, &id))
+        .await?;
+    Ok(Json(RejectResponse { rejected }))
+}
@@ -0,0 +1,124 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use serde::Deserialize;
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::handlers::{DeleteResponse, UpdateResponse};
+use crate::api::routes::AppState;
+use crate::storage::{Annotation, ExecutionTag, normalize_tag};
+
+#[derive(Deserialize)]
+pub struct AddTagRequest {
+    pub tag: String,
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AddAnnotationRequest {
+    pub author: String,
+    pub body: String,
+}
+
+pub async fn list_execution_tags(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<ExecutionTag>> {
+    ensure_execution(&state, &id).await?;
+    state
+        .storage
+        .run(move |s| s.list_tags(&id))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn add_execution_tag(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<AddTagRequest>,
+) -> ApiResult<UpdateResponse> {
+    if normalize_tag(&req.tag).is_empty() {
+        return Err(ApiError::bad_request("tag must not be empty"));
+    }
+    ensure_execution(&state, &id).await?;
+
+    let added = state
+        .storage
+        .run(move |s| s.add_tag(&id, &req.tag, req.user_id.as_deref()))
+        .await?;
+
+    Ok(Json(UpdateResponse {
+        success: added,
+        error: if added {
+            None
+        } else {
+            Some("tag already exists".into())
+        },
+    }))
+}
+
+pub async fn remove_execution_tag(
+    State(state): State<AppState>,
+    Path((id, tag)): Path<(String, String)>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state.storage.run(move |s| s.remove_tag(&id, &tag)).await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+pub async fn list_annotations(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<Annotation>> {
+    ensure_execution(&state, &id).await?;
+    state
+        .storage
+        .run(move |s| s.list_annotations(&id))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn add_annotation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<AddAnnotationRequest>,
+) -> ApiResult<Annotation> {
+    if req.author.trim().is_empty() || req.body.trim().is_empty() {
+        return Err(ApiError::bad_request("author and body must not be empty"));
+    }
+    ensure_execution(&state, &id).await?;
+
+    state
+        .storage
+        .run(move |s| s.add_annotation(&id, req.author.trim(), &req.body))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn delete_annotation(
+    State(state): State<AppState>,
+    Path((id, annotation_id)): Path<(String, i64)>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state
+        .storage
+        .run(move |s| s.delete_annotation(&id, annotation_id))
+        .await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+async fn ensure_execution(state: &AppState, id: &str) -> Result<(), ApiError> {
+    let lookup = id.to_string();
+    if state
+        .storage
+        .run(move |s| s.execution_exists(&lookup))
+        .await?
+    {
+        Ok(())
+    } else {
+        Err(ApiError::not_found("Execution", id))
+    }
+}
@@ -13,7 +13,8 @@ use crate::storage::{UserContext, UserListItem};
 pub async fn list_users(State(state): State<AppState>) -> ApiResult<Vec<UserListItem>> {
     state
         .storage
-        .list_users_with_context()
+        .run(|s| s.list_users_with_context())
+        .await
         .map(Json)
         .map_err(Into::into)
 }
@@ -51,9 +52,11 @@ pub async fn get_user_context(
     Path(user_id): Path<String>,
     Query(query): Query<UserContextQuery>,
 ) -> ApiResult<UserContextResponse> {
+    let lookup = user_id.clone();
     let ctx = state
         .storage
-        .get_user_context(&user_id)
+        .run(move |s| s.get_user_context(&lookup))
+        .await
         .map_err(ApiError::from)?;
 
     if query.format.as_deref() == Some("markdown") {
@@ -65,9 +68,11 @@ pub async fn get_user_context(
             && !ctx.summary_locked
             && let Some(ref lid) = query.lock_id
         {
+            let lock_id = lid.clone();
             lock_acquired = state
                 .storage
-                .acquire_summary_lock(&user_id, lid)
+                .run(move |s| s.acquire_summary_lock(&user_id, &lock_id))
+                .await
                 .unwrap_or(false);
             if lock_acquired {
                 effective_lock_id = Some(lid.clone());
@@ -103,7 +108,8 @@ pub async fn save_user_context(
 ) -> ApiResult<UpdateResponse> {
     state
         .storage
-        .save_user_context(&user_id, &req.summary, &req.rules)
+        .run(move |s| s.save_user_context(&user_id, &req.summary, &req.rules))
+        .await
         .map(|_| {
             Json(UpdateResponse {
                 success: true,
@@ -119,7 +125,8 @@ pub async fn get_user_rules(
 ) -> ApiResult<Vec<String>> {
     state
         .storage
-        .get_user_rules(&user_id)
+        .run(move |s| s.get_user_rules(&user_id))
+        .await
         .map(Json)
         .map_err(Into::into)
 }
@@ -136,7 +143,8 @@ pub async fn add_user_rule(
 ) -> ApiResult<UpdateResponse> {
     state
         .storage
-        .add_user_rule(&user_id, &req.rule)
+        .run(move |s| s.add_user_rule(&user_id, &req.rule))
+        .await
         .map(|added| {
             Json(UpdateResponse {
                 success: added,
@@ -157,7 +165,8 @@ pub async fn delete_user_rule(
 ) -> ApiResult<UpdateResponse> {
     state
         .storage
-        .delete_user_rule(&user_id, &req.rule)
+        .run(move |s| s.delete_user_rule(&user_id, &req.rule))
+        .await
         .map(|deleted| {
             Json(UpdateResponse {
                 success: deleted,
@@ -183,7 +192,8 @@ pub async fn release_summary_lock(
 ) -> ApiResult<UpdateResponse> {
     state
         .storage
-        .release_summary_lock(&user_id, &req.lock_id)
+        .run(move |s| s.release_summary_lock(&user_id, &req.lock_id))
+        .await
         .map(|released| {
             Json(UpdateResponse {
                 success: released,
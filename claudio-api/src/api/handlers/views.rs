@@ -0,0 +1,63 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::handlers::DeleteResponse;
+use crate::api::routes::AppState;
+use crate::storage::{SaveView, SavedView};
+
+pub async fn list_views(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> ApiResult<Vec<SavedView>> {
+    state
+        .storage
+        .run(move |s| s.list_views(&user_id))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn save_view(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    Json(input): Json<SaveView>,
+) -> ApiResult<SavedView> {
+    if input.name.trim().is_empty() {
+        return Err(ApiError::bad_request("name must not be empty"));
+    }
+
+    state
+        .storage
+        .run(move |s| s.save_view(&user_id, &input))
+        .await
+        .map(Json)
+        .map_err(Into::into)
+}
+
+pub async fn get_view(
+    State(state): State<AppState>,
+    Path((user_id, view_id)): Path<(String, String)>,
+) -> ApiResult<SavedView> {
+    let lookup = view_id.clone();
+    state
+        .storage
+        .run(move |s| s.get_view(&lookup))
+        .await?
+        .filter(|view| view.user_id == user_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("View", &view_id))
+}
+
+pub async fn delete_view(
+    State(state): State<AppState>,
+    Path((user_id, view_id)): Path<(String, String)>,
+) -> ApiResult<DeleteResponse> {
+    let deleted = state
+        .storage
+        .run(move |s| s.delete_view(&user_id, &view_id))
+        .await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
@@ -0,0 +1,208 @@
+//! Cheap requests keep being served, at a measured throughput, while slow
+//! analytics queries hold storage workers.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::api::routes::{create_router, into_service};
+use crate::config::StorageConfig;
+use crate::storage::{
+    AsyncStorage, Backend, Connection, Dialect, PoolState, Row, SqliteBackend, Storage, Value,
+};
+
+/// Longest a slow query waits for the gate, so a failing test can't hang
+const SLOW_QUERY_LIMIT: Duration = Duration::from_secs(60);
+/// Longest the fast requests may take while the slow queries are held
+const FAST_BATCH_LIMIT: Duration = Duration::from_secs(30);
+/// Throughput the fast requests must keep up with the slow queries held;
+/// well under what a debug build manages, to stay clear of noisy machines
+const MIN_REQUESTS_PER_SEC: f64 = 100.0;
+const SLOW_REQUESTS: usize = 6;
+const FAST_REQUESTS: usize = 300;
+const CONCURRENCY: usize = 16;
+
+/// Held shut until the test has finished its fast requests
+#[derive(Default)]
+struct Gate {
+    open: Mutex<bool>,
+    opened: Condvar,
+}
+
+impl Gate {
+    fn wait(&self) {
+        let open = self.open.lock().unwrap();
+        let _ = self
+            .opened
+            .wait_timeout_while(open, SLOW_QUERY_LIMIT, |open| !*open)
+            .unwrap();
+    }
+
+    fn open(&self) {
+        *self.open.lock().unwrap() = true;
+        self.opened.notify_all();
+    }
+}
+
+/// Holds the first `executions` query on each connection at the gate,
+/// standing in for a heavy stats scan.
+struct SlowStatsBackend {
+    inner: SqliteBackend,
+    in_flight: Arc<AtomicUsize>,
+    gate: Arc<Gate>,
+}
+
+struct SlowStatsConnection {
+    inner: Box<dyn Connection>,
+    in_flight: Arc<AtomicUsize>,
+    gate: Arc<Gate>,
+    delayed: Cell<bool>,
+}
+
+impl Backend for SlowStatsBackend {
+    fn dialect(&self) -> Dialect {
+        self.inner.dialect()
+    }
+
+    fn connect(&self) -> Result<Box<dyn Connection>> {
+        Ok(Box::new(SlowStatsConnection {
+            inner: self.inner.connect()?,
+            in_flight: Arc::clone(&self.in_flight),
+            gate: Arc::clone(&self.gate),
+            delayed: Cell::new(false),
+        }))
+    }
+
+    fn pool_state(&self) -> PoolState {
+        self.inner.pool_state()
+    }
+}
+
+impl Connection for SlowStatsConnection {
+    fn dialect(&self) -> Dialect {
+        self.inner.dialect()
+    }
+
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<usize> {
+        self.inner.execute(sql, params)
+    }
+
+    fn query(&self, sql: &str, params: &[Value]) -> Result<Vec<Row>> {
+        if sql.contains("FROM executions") && !self.delayed.replace(true) {
+            self.in_flight.fetch_add(1, Ordering::SeqCst);
+            self.gate.wait();
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.inner.query(sql, params)
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<()> {
+        self.inner.execute_batch(sql)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_throughput_with_slow_stats_in_flight() {
+    let dir = tempfile::tempdir().unwrap();
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let gate = Arc::new(Gate::default());
+    let storage = Storage::with_backend(SlowStatsBackend {
+        inner: SqliteBackend::open(dir.path().join("load.db")).unwrap(),
+        in_flight: Arc::clone(&in_flight),
+        gate: Arc::clone(&gate),
+    });
+    let config = StorageConfig {
+        path: String::new(),
+        database_url: None,
+        workers: SLOW_REQUESTS + 2,
+        queue_size: 256,
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app = create_router(AsyncStorage::new(storage, &config));
+    tokio::spawn(async move { axum::serve(listener, into_service(app)).await });
+
+    let client = reqwest::Client::new();
+
+    // More slow requests than tokio workers: blocking handlers would stall
+    // the whole runtime here.
+    let slow: Vec<_> = (0..SLOW_REQUESTS)
+        .map(|_| {
+            let client = client.clone();
+            let url = format!("{}/v1/stats/overview", base);
+            tokio::spawn(async move { client.get(url).send().await.map(|r| r.status()) })
+        })
+        .collect();
+
+    while in_flight.load(Ordering::SeqCst) < SLOW_REQUESTS {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let next = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+    let clients: Vec<_> = (0..CONCURRENCY)
+        .map(|_| {
+            let client = client.clone();
+            let base = base.clone();
+            let next = Arc::clone(&next);
+            tokio::spawn(async move {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= FAST_REQUESTS {
+                        break;
+                    }
+                    let path = if i.is_multiple_of(2) {
+                        "/health"
+                    } else {
+                        "/v1/projects"
+                    };
+                    let status = client
+                        .get(format!("{}{}", base, path))
+                        .send()
+                        .await
+                        .unwrap()
+                        .status();
+                    assert!(status.is_success(), "{} returned {}", path, status);
+                }
+            })
+        })
+        .collect();
+    let fast = async {
+        for handle in clients {
+            handle.await.unwrap();
+        }
+    };
+    let finished = tokio::time::timeout(FAST_BATCH_LIMIT, fast).await;
+    let elapsed = start.elapsed();
+    let still_slow = in_flight.load(Ordering::SeqCst);
+    gate.open();
+    assert!(
+        finished.is_ok(),
+        "{} fast requests didn't finish within {:?} while slow stats queries were held",
+        FAST_REQUESTS,
+        FAST_BATCH_LIMIT
+    );
+    assert_eq!(
+        still_slow, SLOW_REQUESTS,
+        "slow stats queries should still hold their workers"
+    );
+    let throughput = FAST_REQUESTS as f64 / elapsed.as_secs_f64();
+    println!(
+        "{} requests in {:?} with {} slow stats queries held: {:.0} req/s",
+        FAST_REQUESTS, elapsed, SLOW_REQUESTS, throughput
+    );
+    assert!(
+        throughput >= MIN_REQUESTS_PER_SEC,
+        "{:.0} req/s is under the {:.0} req/s floor",
+        throughput,
+        MIN_REQUESTS_PER_SEC
+    );
+
+    for handle in slow {
+        assert!(handle.await.unwrap().unwrap().is_success());
+    }
+}
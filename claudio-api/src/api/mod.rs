@@ -1,6 +1,16 @@
+pub mod alerts;
+pub mod budget;
 pub mod classify;
+pub mod classify_cache;
 pub mod error;
+pub mod eval;
+pub mod export;
 mod handlers;
 pub mod rate_limit;
+pub mod reports;
 pub mod routes;
 pub mod types;
+pub mod webhook;
+
+#[cfg(test)]
+mod load_test;
@@ -8,6 +8,8 @@ use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Instant;
 
+use crate::metrics;
+
 type Limiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
 
 const MAX_CACHE_SIZE: usize = 1000;
@@ -36,7 +38,10 @@ impl RateLimitState {
         }
 
         let limiter = self.get_or_create(project_id, rate_limit_rpm);
-        limiter.check().map_err(|_| RateLimitError::TooManyRequests)
+        limiter.check().map_err(|_| {
+            metrics::RATE_LIMITED.inc(&[project_id]);
+            RateLimitError::TooManyRequests
+        })
     }
 
     pub fn remove(&self, project_id: &str) {
@@ -0,0 +1,495 @@
+//! Scheduled stats reports: the configured sections of the stats API
+//! rendered for a period and delivered to Slack channels and webhooks,
+//! either on the report's cron schedule or by hand.
+
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde::Serialize;
+use tracing::Instrument;
+
+use crate::analytics::{self, TimeRange};
+use crate::api::webhook;
+use crate::config::Config;
+use crate::storage::{
+    AsyncStorage, Connection, Report, ReportDelivery, ReportDestination, ReportFormat, ReportRun,
+    ReportSection,
+};
+use crate::utils::Schedule;
+
+const SLACK_POST_MESSAGE: &str = "https://slack.com/api/chat.postMessage";
+
+/// How often the scheduler looks for due reports
+const TICK: Duration = Duration::from_secs(30);
+
+/// Rows listed in the models, errors and agents sections
+const TOP_ROWS: usize = 5;
+
+/// A titled list of lines, rendered as a heading and bullets
+#[derive(Debug, PartialEq)]
+struct Block {
+    heading: &'static str,
+    lines: Vec<String>,
+}
+
+/// Webhook payload of a delivered report
+#[derive(Debug, Serialize)]
+struct ReportPayload<'a> {
+    event: &'static str,
+    report_id: &'a str,
+    name: &'a str,
+    period: &'a str,
+    period_start: i64,
+    period_end: i64,
+    format: &'static str,
+    text: &'a str,
+}
+
+/// Timezone of a report's schedule and stats.
+pub fn timezone(report_tz: Option<&str>) -> Result<Tz> {
+    let name = report_tz.unwrap_or(&Config::global().defaults.timezone);
+    name.parse()
+        .map_err(|_| anyhow!("unknown timezone '{}'", name))
+}
+
+/// First scheduled run after `after`.
+pub fn next_run(schedule: &str, report_tz: Option<&str>, after: i64) -> Result<Option<i64>> {
+    Ok(Schedule::parse(schedule)?.next_after(after, timezone(report_tz)?))
+}
+
+fn pct(value: f64) -> String {
+    format!("{:.1}%", value)
+}
+
+fn change(value: Option<f64>) -> String {
+    value.map_or_else(String::new, |v| format!(" ({:+.1}% vs previous)", v))
+}
+
+fn gather(conn: &dyn Connection, report: &Report, range: &TimeRange) -> Result<Vec<Block>> {
+    let project = report.project.as_deref();
+    let needs_overview = report
+        .sections
+        .iter()
+        .any(|s| matches!(s, ReportSection::Overview | ReportSection::Feedback));
+    let overview = needs_overview
+        .then(|| analytics::get_overview_stats(conn, range, project, None))
+        .transpose()?;
+
+    let mut blocks = Vec::with_capacity(report.sections.len());
+    for section in &report.sections {
+        let block = match section {
+            ReportSection::Overview => {
+                let o = overview.as_ref().expect("overview is loaded");
+                let s = &o.summary;
+                Block {
+                    heading: "Overview",
+                    lines: vec![
+                        format!(
+                            "Requests: {}{}",
+                            s.total_requests,
+                            change(o.comparison.requests_change_pct)
+                        ),
+                        format!(
+                            "Success rate: {} ({} failed)",
+                            pct(s.success_rate),
+                            s.failed_requests
+                        ),
+                        format!(
+                            "Cost: ${:.2}{}",
+                            s.total_cost_usd,
+                            change(o.comparison.cost_change_pct)
+                        ),
+                        format!(
+                            "Avg duration: {:.0} ms{}",
+                            s.avg_duration_ms,
+                            change(o.comparison.duration_change_pct)
+                        ),
+                        format!(
+                            "Tokens: {} in, {} out",
+                            s.total_input_tokens, s.total_output_tokens
+                        ),
+                    ],
+                }
+            }
+            ReportSection::Models => {
+                let models = analytics::get_model_stats(conn, range, project, None)?.models;
+                Block {
+                    heading: "Models",
+                    lines: models
+                        .iter()
+                        .take(TOP_ROWS)
+                        .map(|m| {
+                            format!(
+                                "{}: {} requests ({}), ${:.2}, {} success",
+                                m.display_name,
+                                m.requests,
+                                pct(m.percentage),
+                                m.cost_usd,
+                                pct(m.success_rate)
+                            )
+                        })
+                        .collect(),
+                }
+            }
+            ReportSection::Errors => {
+                let errors = analytics::get_error_stats(conn, range, project, None)?;
+                let mut lines = vec![format!(
+                    "{} errors, {} error rate",
+                    errors.total_errors,
+                    pct(errors.error_rate)
+                )];
+                lines.extend(
+                    errors
+                        .errors
+                        .iter()
+                        .take(TOP_ROWS)
+                        .map(|e| format!("{}: {} ({})", e.error_type, e.count, pct(e.percentage))),
+                );
+                Block {
+                    heading: "Errors",
+                    lines,
+                }
+            }
+            ReportSection::Agents => {
+                let agents = analytics::get_agent_stats(conn, range, project, None)?.agents;
+                Block {
+                    heading: "Agents",
+                    lines: agents
+                        .iter()
+                        .take(TOP_ROWS)
+                        .map(|a| {
+                            let satisfaction = a
+                                .satisfaction_rate
+                                .map_or_else(String::new, |r| format!(", {} satisfied", pct(r)));
+                            format!(
+                                "{}: {} requests, ${:.2}, {} success{}",
+                                a.agent,
+                                a.requests,
+                                a.cost_usd,
+                                pct(a.success_rate),
+                                satisfaction
+                            )
+                        })
+                        .collect(),
+                }
+            }
+            ReportSection::Feedback => {
+                let o = overview.as_ref().expect("overview is loaded");
+                let f = &o.feedback;
+                let satisfaction = f.satisfaction_rate.map_or_else(|| "n/a".to_string(), pct);
+                Block {
+                    heading: "Feedback",
+                    lines: vec![
+                        format!("{} positive, {} negative", f.positive, f.negative),
+                        format!(
+                            "Satisfaction: {}{}",
+                            satisfaction,
+                            change(o.comparison.satisfaction_change_pct)
+                        ),
+                        format!("{} responses awaiting feedback", f.pending_feedback),
+                    ],
+                }
+            }
+        };
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Slack only needs `&`, `<` and `>` escaped in mrkdwn
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn render(format: ReportFormat, title: &str, subtitle: &str, blocks: &[Block]) -> String {
+    let mut out = String::new();
+    match format {
+        ReportFormat::Mrkdwn => {
+            out.push_str(&format!(
+                "*{}*\n_{}_\n",
+                escape_mrkdwn(title),
+                escape_mrkdwn(subtitle)
+            ));
+            for block in blocks {
+                out.push_str(&format!("\n*{}*\n", block.heading));
+                if block.lines.is_empty() {
+                    out.push_str("No data\n");
+                }
+                for line in &block.lines {
+                    out.push_str(&format!("• {}\n", escape_mrkdwn(line)));
+                }
+            }
+        }
+        ReportFormat::Markdown => {
+            out.push_str(&format!("# {}\n\n_{}_\n", title, subtitle));
+            for block in blocks {
+                out.push_str(&format!("\n## {}\n\n", block.heading));
+                if block.lines.is_empty() {
+                    out.push_str("No data\n");
+                }
+                for line in &block.lines {
+                    out.push_str(&format!("- {}\n", line));
+                }
+            }
+        }
+        ReportFormat::Html => {
+            out.push_str(&format!(
+                "<h1>{}</h1>\n<p><em>{}</em></p>\n",
+                escape_html(title),
+                escape_html(subtitle)
+            ));
+            for block in blocks {
+                out.push_str(&format!("<h2>{}</h2>\n", block.heading));
+                if block.lines.is_empty() {
+                    out.push_str("<p>No data</p>\n");
+                    continue;
+                }
+                out.push_str("<ul>\n");
+                for line in &block.lines {
+                    out.push_str(&format!("<li>{}</li>\n", escape_html(line)));
+                }
+                out.push_str("</ul>\n");
+            }
+        }
+    }
+    out
+}
+
+fn subtitle(report: &Report, start: i64, end: i64, tz: Tz) -> String {
+    let local = |ts: i64| {
+        chrono::DateTime::from_timestamp(ts, 0)
+            .map(|dt| dt.with_timezone(&tz).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let scope = report
+        .project
+        .as_deref()
+        .map_or_else(|| "all projects".to_string(), |p| format!("project {}", p));
+    format!(
+        "{}, {} to {} ({}), {}",
+        report.period.as_str(),
+        local(start),
+        local(end),
+        tz.name(),
+        scope
+    )
+}
+
+async fn post_slack(channel: &str, text: &str) -> Result<()> {
+    let token = Config::global()
+        .reports
+        .slack_bot_token
+        .as_deref()
+        .ok_or_else(|| anyhow!("SLACK_BOT_TOKEN is not set"))?;
+    let response: serde_json::Value = webhook::client()
+        .post(SLACK_POST_MESSAGE)
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "channel": channel, "text": text, "mrkdwn": true }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if response["ok"].as_bool() != Some(true) {
+        bail!(
+            "Slack rejected the message: {}",
+            response["error"].as_str().unwrap_or("unknown error")
+        );
+    }
+    Ok(())
+}
+
+/// Renders a report for its period as of now and delivers it. Dry runs
+/// render without delivering or recording the run.
+pub async fn run(
+    storage: &AsyncStorage,
+    report: Report,
+    trigger: &str,
+    dry_run: bool,
+) -> Result<ReportRun> {
+    let started_at = Utc::now().timestamp();
+    let tz = timezone(report.timezone.as_deref())?;
+    let range = TimeRange::period(report.period, started_at, tz);
+    let period_end = range.end.unwrap_or(started_at);
+
+    let gathered = {
+        let (report, range) = (report.clone(), range.clone());
+        storage
+            .with_connection(move |conn| gather(conn, &report, &range))
+            .await
+    };
+    let mut run = ReportRun {
+        id: 0,
+        report_id: report.id.clone(),
+        trigger: trigger.to_string(),
+        status: "success".to_string(),
+        period_start: range.start,
+        period_end,
+        content: String::new(),
+        deliveries: Vec::new(),
+        error: None,
+        started_at,
+        finished_at: started_at,
+    };
+
+    match gathered {
+        Ok(blocks) => {
+            let subtitle = subtitle(&report, range.start, period_end, tz);
+            run.content = render(report.format, &report.name, &subtitle, &blocks);
+            if !dry_run {
+                let mrkdwn = render(ReportFormat::Mrkdwn, &report.name, &subtitle, &blocks);
+                let payload = ReportPayload {
+                    event: "report",
+                    report_id: &report.id,
+                    name: &report.name,
+                    period: report.period.as_str(),
+                    period_start: range.start,
+                    period_end,
+                    format: report.format.as_str(),
+                    text: &run.content,
+                };
+                for destination in &report.destinations {
+                    let sent = match destination {
+                        ReportDestination::Slack { channel } => post_slack(channel, &mrkdwn).await,
+                        ReportDestination::Webhook { url } => {
+                            webhook::post_json(url, &payload).await
+                        }
+                    };
+                    if let Err(ref e) = sent {
+                        tracing::warn!(report = %report.id, destination = %destination.describe(), error = %e, "Failed to deliver report");
+                    }
+                    run.deliveries.push(ReportDelivery {
+                        destination: destination.describe(),
+                        ok: sent.is_ok(),
+                        error: sent.err().map(|e| e.to_string()),
+                    });
+                }
+            }
+            let failed = run.deliveries.iter().filter(|d| !d.ok).count();
+            if failed > 0 {
+                run.status = if failed == run.deliveries.len() {
+                    "failed"
+                } else {
+                    "partial"
+                }
+                .to_string();
+            }
+        }
+        Err(e) => {
+            tracing::warn!(report = %report.id, error = %e, "Failed to gather report stats");
+            run.status = "failed".to_string();
+            run.error = Some(e.to_string());
+        }
+    }
+    run.finished_at = Utc::now().timestamp();
+
+    if !dry_run {
+        let record = run.clone();
+        run.id = storage
+            .write("record_report_run", move |s| s.record_report_run(&record))
+            .await?;
+    }
+    Ok(run)
+}
+
+/// Claims the reports due now, moving each on to its next scheduled run.
+/// Runs missed while the server was down collapse into one.
+async fn run_due(storage: &AsyncStorage) -> Result<()> {
+    let now = Utc::now().timestamp();
+    let claimed = storage
+        .write("claim_reports", move |s| {
+            let mut claimed = Vec::new();
+            for report in s.due_reports(now)? {
+                let Some(scheduled) = report.next_run_at else {
+                    continue;
+                };
+                let next = next_run(&report.schedule, report.timezone.as_deref(), now)
+                    .unwrap_or_else(|e| {
+                        tracing::warn!(report = %report.id, error = %e, "Report schedule is invalid");
+                        None
+                    });
+                if s.claim_report(&report.id, scheduled, next, now)? {
+                    claimed.push(report);
+                }
+            }
+            Ok(claimed)
+        })
+        .await?;
+
+    for report in claimed {
+        let storage = storage.clone();
+        let span = tracing::info_span!("report.run", report.id = %report.id);
+        tokio::spawn(
+            async move {
+                if let Err(e) = run(&storage, report, "schedule", false).await {
+                    tracing::warn!(error = %e, "Failed to run report");
+                }
+            }
+            .instrument(span),
+        );
+    }
+    Ok(())
+}
+
+/// Starts the background task that runs reports on their schedules.
+pub fn spawn_scheduler(storage: AsyncStorage) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due(&storage).await {
+                tracing::warn!(error = %e, "Failed to run due reports");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_formats() {
+        let blocks = vec![
+            Block {
+                heading: "Models",
+                lines: vec!["Sonnet: 3 requests (75.0%), $0.12, 100.0% success".into()],
+            },
+            Block {
+                heading: "Errors",
+                lines: vec!["timeout: 1 (<a & b>)".into()],
+            },
+            Block {
+                heading: "Agents",
+                lines: Vec::new(),
+            },
+        ];
+        let title = "Daily";
+        let subtitle = "24h";
+
+        assert_eq!(
+            render(ReportFormat::Mrkdwn, title, subtitle, &blocks),
+            "*Daily*\n_24h_\n\n*Models*\n• Sonnet: 3 requests (75.0%), $0.12, 100.0% success\n\n*Errors*\n• timeout: 1 (&lt;a &amp; b&gt;)\n\n*Agents*\nNo data\n"
+        );
+        assert_eq!(
+            render(ReportFormat::Markdown, title, subtitle, &blocks),
+            "# Daily\n\n_24h_\n\n## Models\n\n- Sonnet: 3 requests (75.0%), $0.12, 100.0% success\n\n## Errors\n\n- timeout: 1 (<a & b>)\n\n## Agents\n\nNo data\n"
+        );
+        assert_eq!(
+            render(ReportFormat::Html, title, subtitle, &blocks),
+            "<h1>Daily</h1>\n<p><em>24h</em></p>\n<h2>Models</h2>\n<ul>\n<li>Sonnet: 3 requests (75.0%), $0.12, 100.0% success</li>\n</ul>\n<h2>Errors</h2>\n<ul>\n<li>timeout: 1 (&lt;a &amp; b&gt;)</li>\n</ul>\n<h2>Agents</h2>\n<p>No data</p>\n"
+        );
+    }
+}
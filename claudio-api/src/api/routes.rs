@@ -2,23 +2,24 @@ use axum::{
     Router,
     extract::DefaultBodyLimit,
     http::{Method, header},
-    routing::{delete, get, post},
+    middleware,
+    routing::{delete, get, post, put},
 };
 use std::net::SocketAddr;
 
 use crate::api::handlers;
 use crate::api::rate_limit::RateLimitState;
-use crate::storage::Storage;
+use crate::storage::AsyncStorage;
 
 const MAX_REQUEST_SIZE: usize = 10 * 1024 * 1024; // 10MB
 
 #[derive(Clone)]
 pub struct AppState {
-    pub storage: Storage,
+    pub storage: AsyncStorage,
     pub rate_limit: RateLimitState,
 }
 
-pub fn create_router(storage: Storage) -> Router {
+pub fn create_router(storage: AsyncStorage) -> Router {
     let state = AppState {
         storage,
         rate_limit: RateLimitState::new(),
@@ -39,6 +40,7 @@ pub fn create_router(storage: Storage) -> Router {
     Router::new()
         // Health & Config
         .route("/health", get(handlers::health))
+        .route("/metrics", get(handlers::get_metrics))
         .route("/v1/config", get(handlers::get_config))
         // Projects
         .route(
@@ -51,28 +53,155 @@ pub fn create_router(storage: Storage) -> Router {
                 .put(handlers::update_project)
                 .delete(handlers::delete_project),
         )
+        .route(
+            "/v1/projects/{id}/revisions",
+            get(handlers::list_project_revisions),
+        )
+        .route(
+            "/v1/projects/{id}/revisions/diff",
+            get(handlers::diff_project_revisions),
+        )
+        .route(
+            "/v1/projects/{id}/revisions/{revision}",
+            get(handlers::get_project_revision),
+        )
+        .route(
+            "/v1/projects/{id}/revisions/{revision}/rollback",
+            post(handlers::rollback_project),
+        )
         // Agents
         .route(
             "/v1/projects/{project_id}/agents",
             get(handlers::list_agents).post(handlers::create_agent),
         )
+        .route(
+            "/v1/projects/{project_id}/agents/sync",
+            post(handlers::sync_agents),
+        )
+        .route(
+            "/v1/projects/{project_id}/agents/index-status",
+            get(handlers::get_index_status),
+        )
         .route(
             "/v1/agents/{id}",
             get(handlers::get_agent)
                 .put(handlers::update_agent)
                 .delete(handlers::delete_agent),
         )
+        .route(
+            "/v1/agents/{id}/revisions",
+            get(handlers::list_agent_revisions),
+        )
+        .route(
+            "/v1/agents/{id}/revisions/diff",
+            get(handlers::diff_agent_revisions),
+        )
+        .route(
+            "/v1/agents/{id}/revisions/stats",
+            get(handlers::get_agent_revision_stats),
+        )
+        .route(
+            "/v1/agents/{id}/revisions/{revision}",
+            get(handlers::get_agent_revision),
+        )
+        .route(
+            "/v1/agents/{id}/revisions/{revision}/rollback",
+            post(handlers::rollback_agent),
+        )
         // Chat & Classify
         .route(
             "/v1/projects/{project_id}/classify",
             post(handlers::classify_project),
         )
+        .route(
+            "/v1/projects/{project_id}/classify/explain",
+            post(handlers::explain_classify_project),
+        )
         .route(
             "/v1/projects/{project_id}/chat",
             post(handlers::chat_project),
         )
+        // Routing evaluation
+        .route(
+            "/v1/projects/{project_id}/eval/cases",
+            get(handlers::list_eval_cases).post(handlers::add_eval_case),
+        )
+        .route(
+            "/v1/projects/{project_id}/eval/cases/{case_id}",
+            delete(handlers::delete_eval_case),
+        )
+        .route(
+            "/v1/projects/{project_id}/eval/seed",
+            post(handlers::seed_eval_cases),
+        )
+        .route(
+            "/v1/projects/{project_id}/eval/runs",
+            get(handlers::list_eval_runs).post(handlers::run_eval),
+        )
+        // Example suggestions from feedback
+        .route(
+            "/v1/projects/{project_id}/suggestions",
+            get(handlers::list_suggestions),
+        )
+        .route(
+            "/v1/projects/{project_id}/suggestions/generate",
+            post(handlers::generate_suggestions),
+        )
+        .route(
+            "/v1/projects/{project_id}/suggestions/{id}/approve",
+            post(handlers::approve_suggestion),
+        )
+        .route(
+            "/v1/projects/{project_id}/suggestions/{id}/reject",
+            post(handlers::reject_suggestion),
+        )
+        // Cost budgets
+        .route(
+            "/v1/projects/{project_id}/budgets",
+            get(handlers::list_budgets).post(handlers::create_budget),
+        )
+        .route(
+            "/v1/projects/{project_id}/budgets/status",
+            get(handlers::get_budget_status),
+        )
+        .route(
+            "/v1/budgets/{id}",
+            put(handlers::update_budget).delete(handlers::delete_budget),
+        )
+        // Scheduled stats reports
+        .route(
+            "/v1/reports",
+            get(handlers::list_reports).post(handlers::create_report),
+        )
+        .route(
+            "/v1/reports/{id}",
+            get(handlers::get_report)
+                .put(handlers::update_report)
+                .delete(handlers::delete_report),
+        )
+        .route("/v1/reports/{id}/run", post(handlers::run_report))
+        .route("/v1/reports/{id}/runs", get(handlers::list_report_runs))
+        // Anomaly alerts
+        .route("/v1/alerts", get(handlers::list_alerts))
+        .route("/v1/alerts/{id}", get(handlers::get_alert))
+        // A/B experiments between agent variants
+        .route(
+            "/v1/projects/{project_id}/experiments",
+            get(handlers::list_experiments).post(handlers::create_experiment),
+        )
+        .route(
+            "/v1/experiments/{id}",
+            get(handlers::get_experiment)
+                .put(handlers::update_experiment)
+                .delete(handlers::delete_experiment),
+        )
+        .route(
+            "/v1/experiments/{id}/results",
+            get(handlers::get_experiment_results),
+        )
         // Executions
         .route("/v1/executions", get(handlers::list_executions))
+        .route("/v1/executions/export", get(handlers::export_executions))
         .route("/v1/executions/filters", get(handlers::get_filter_options))
         .route("/v1/executions/lookup", get(handlers::lookup_execution))
         .route(
@@ -89,6 +218,31 @@ pub fn create_router(storage: Storage) -> Router {
                 .post(handlers::add_reaction)
                 .delete(handlers::remove_reaction),
         )
+        .route(
+            "/v1/executions/{id}/correction",
+            put(handlers::set_routing_correction),
+        )
+        .route("/v1/executions/{id}/rerun", post(handlers::rerun_execution))
+        .route(
+            "/v1/executions/{id}/compare",
+            get(handlers::compare_executions),
+        )
+        .route(
+            "/v1/executions/{id}/tags",
+            get(handlers::list_execution_tags).post(handlers::add_execution_tag),
+        )
+        .route(
+            "/v1/executions/{id}/tags/{tag}",
+            delete(handlers::remove_execution_tag),
+        )
+        .route(
+            "/v1/executions/{id}/annotations",
+            get(handlers::list_annotations).post(handlers::add_annotation),
+        )
+        .route(
+            "/v1/executions/{id}/annotations/{annotation_id}",
+            delete(handlers::delete_annotation),
+        )
         // Stats
         .route("/v1/stats", get(handlers::get_stats))
         .route("/v1/stats/{project}", get(handlers::get_project_stats))
@@ -98,11 +252,17 @@ pub fn create_router(storage: Storage) -> Router {
         .route("/v1/stats/errors", get(handlers::get_stats_errors))
         .route("/v1/stats/sources", get(handlers::get_stats_sources))
         .route("/v1/stats/requesters", get(handlers::get_stats_requesters))
+        .route("/v1/stats/agents", get(handlers::get_stats_agents))
+        .route("/v1/stats/{breakdown}/export", get(handlers::export_stats))
         // Workflows
         .route(
             "/v1/workflows/stats",
             get(handlers::get_workflow_stats).post(handlers::record_workflow_stats),
         )
+        .route(
+            "/v1/workflows/{name}/runs",
+            get(handlers::get_workflow_runs),
+        )
         // Classify
         .route("/v1/classify/stats", get(handlers::get_classify_stats))
         .route("/v1/classify/logs", get(handlers::get_classify_logs))
@@ -122,8 +282,17 @@ pub fn create_router(storage: Storage) -> Router {
             "/v1/users/{user_id}/context/lock",
             delete(handlers::release_summary_lock),
         )
+        .route(
+            "/v1/users/{user_id}/views",
+            get(handlers::list_views).post(handlers::save_view),
+        )
+        .route(
+            "/v1/users/{user_id}/views/{view_id}",
+            get(handlers::get_view).delete(handlers::delete_view),
+        )
         // Utils
         .route("/v1/format/mrkdwn", post(handlers::convert_to_mrkdwn))
+        .layer(middleware::from_fn(handlers::track_requests))
         .layer(DefaultBodyLimit::max(MAX_REQUEST_SIZE))
         .layer(cors)
         .with_state(state)
@@ -70,9 +70,12 @@ pub struct ChatCompletionRequest {
     pub output_schema: Option<serde_json::Value>,
     #[serde(default = "default_timeout")]
     pub timeout: Option<u64>,
+    /// Workflow run making the request, linking the execution to it
+    #[serde(default)]
+    pub run_id: Option<String>,
 }
 
-fn default_timeout() -> Option<u64> {
+pub(crate) fn default_timeout() -> Option<u64> {
     Some(300)
 }
 
@@ -0,0 +1,34 @@
+//! Outgoing HTTP for alerts, budget alerts and reports: one client, and
+//! JSON POSTs that carry the current trace.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::telemetry;
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+pub fn client() -> &'static reqwest::Client {
+    &HTTP
+}
+
+/// POSTs `payload` as JSON with the trace headers; a non-2xx status is an
+/// error.
+pub async fn post_json<T: Serialize + ?Sized>(url: &str, payload: &T) -> Result<()> {
+    HTTP.post(url)
+        .headers(telemetry::trace_headers())
+        .json(payload)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// [`post_json`] for notifications nobody waits on: failures are logged as
+/// `what` and dropped.
+pub async fn notify<T: Serialize + ?Sized>(url: &str, payload: &T, what: &str) {
+    if let Err(e) = post_json(url, payload).await {
+        tracing::warn!(error = %e, "Failed to send {}", what);
+    }
+}
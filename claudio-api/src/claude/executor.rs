@@ -7,6 +7,7 @@ use crate::Config;
 use crate::api::types::{
     ChatCompletionRequest, ChatCompletionResponse, ClaudeCliOutput, ErrorInfo, ExecutionStatus,
 };
+use crate::metrics;
 
 pub struct ClaudeExecutor;
 
@@ -19,6 +20,11 @@ struct ResolvedProject {
 }
 
 impl ClaudeExecutor {
+    #[tracing::instrument(
+        name = "claude.execute",
+        skip_all,
+        fields(project = req.project.as_deref(), model = req.model.as_deref())
+    )]
     pub async fn execute(req: ChatCompletionRequest) -> ChatCompletionResponse {
         let request_id = uuid::Uuid::new_v4().to_string();
         let created = chrono::Utc::now().timestamp();
@@ -165,6 +171,7 @@ impl ClaudeExecutor {
                 let stdout = String::from_utf8_lossy(&output.stdout);
 
                 tracing::error!("Claude execution failed: {}", stderr);
+                metrics::CLI_FAILURES.inc(&["exit"]);
 
                 let error_msg =
                     if let Ok(claude_output) = serde_json::from_str::<ClaudeCliOutput>(&stdout) {
@@ -194,6 +201,7 @@ impl ClaudeExecutor {
             }
             Ok(Err(e)) => {
                 tracing::error!("Failed to spawn Claude CLI: {}", e);
+                metrics::CLI_FAILURES.inc(&["spawn"]);
 
                 ChatCompletionResponse {
                     id: request_id,
@@ -212,6 +220,7 @@ impl ClaudeExecutor {
             }
             Err(_) => {
                 tracing::error!("Claude execution timed out after {}s", timeout);
+                metrics::CLI_FAILURES.inc(&["timeout"]);
 
                 ChatCompletionResponse {
                     id: request_id,
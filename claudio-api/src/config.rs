@@ -12,6 +12,11 @@ pub struct Config {
     pub defaults: DefaultsConfig,
     pub slack: Option<SlackConfig>,
     pub semantic_search: SemanticSearchConfig,
+    pub classify_cache: ClassifyCacheConfig,
+    pub budgets: BudgetConfig,
+    pub reports: ReportConfig,
+    pub alerts: AlertConfig,
+    pub telemetry: TelemetryConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +34,9 @@ pub struct LoggingConfig {
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
     pub path: String,
+    pub database_url: Option<String>,
+    pub workers: usize,
+    pub queue_size: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +69,67 @@ pub struct SemanticSearchConfig {
     pub enabled: bool,
     pub min_score: f64,
     pub top_k: u32,
+    /// OpenAI-compatible embeddings endpoint; the built-in hashing embedder
+    /// is used when unset
+    pub embedding_url: Option<String>,
+    pub embedding_model: String,
+    pub embedding_api_key: Option<String>,
+    /// Vector size of the hashing embedder
+    pub hash_dimensions: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassifyCacheConfig {
+    /// Seconds a cached LLM decision stays valid; 0 disables the cache
+    pub ttl_secs: u64,
+    /// Cached texts per project, oldest evicted first
+    pub max_entries: usize,
+    /// Minimum embedding similarity for a near-duplicate hit; 0 only reuses
+    /// decisions for identical normalized text
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BudgetConfig {
+    /// Receives a JSON POST whenever a budget threshold is crossed
+    pub alert_webhook: Option<String>,
+    /// Usage percentages that trigger an alert, ascending
+    pub alert_thresholds: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportConfig {
+    /// Bot token reports are posted to Slack channels with; Slack
+    /// destinations are rejected when unset
+    pub slack_bot_token: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    /// Receives a JSON POST whenever an anomaly alert opens or resolves
+    pub webhook: Option<String>,
+    /// Seconds between anomaly checks; 0 disables detection
+    pub check_interval_secs: u64,
+    /// Length of the window compared against the baseline
+    pub window_secs: i64,
+    /// Days of history before the window the baseline is drawn from
+    pub baseline_days: i64,
+    /// Fewest executions in both the window and the baseline to judge a
+    /// project, agent or model
+    pub min_requests: i64,
+    /// How far off a window must be: standard deviations of the baseline
+    /// windows' cost, or z-score of the error and satisfaction rates
+    pub z_threshold: f64,
+    /// Smallest relative increase of cost and p95 latency, in percent
+    pub min_change_pct: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL; spans are exported to `{url}/v1/traces`
+    /// only when set
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
 }
 
 impl Config {
@@ -83,6 +152,17 @@ impl Config {
 
         let storage = StorageConfig {
             path: env::var("CLAUDIO_DB_PATH").unwrap_or_else(|_| "data/claudio.db".into()),
+            database_url: env::var("CLAUDIO_DATABASE_URL")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            workers: env::var("CLAUDIO_STORAGE_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(8),
+            queue_size: env::var("CLAUDIO_STORAGE_QUEUE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(256),
         };
 
         let defaults = DefaultsConfig {
@@ -120,6 +200,92 @@ impl Config {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(5),
+            embedding_url: env::var("SEMANTIC_EMBEDDING_URL")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            embedding_model: env::var("SEMANTIC_EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "text-embedding-3-small".into()),
+            embedding_api_key: env::var("SEMANTIC_EMBEDDING_API_KEY")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            hash_dimensions: env::var("SEMANTIC_HASH_DIMENSIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&d| d > 0)
+                .unwrap_or(1024),
+        };
+
+        let classify_cache = ClassifyCacheConfig {
+            ttl_secs: env::var("CLASSIFY_CACHE_TTL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+            max_entries: env::var("CLASSIFY_CACHE_MAX_ENTRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            similarity: env::var("CLASSIFY_CACHE_SIMILARITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.0),
+        };
+
+        let mut alert_thresholds: Vec<u32> = env::var("BUDGET_ALERT_THRESHOLDS")
+            .ok()
+            .map(|v| v.split(',').filter_map(|s| s.trim().parse().ok()).collect())
+            .unwrap_or_else(|| vec![50, 80, 100]);
+        alert_thresholds.retain(|&t| t > 0);
+        alert_thresholds.sort_unstable();
+        alert_thresholds.dedup();
+        let budgets = BudgetConfig {
+            alert_webhook: env::var("BUDGET_ALERT_WEBHOOK")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            alert_thresholds,
+        };
+
+        let reports = ReportConfig {
+            slack_bot_token: env::var("SLACK_BOT_TOKEN").ok().filter(|s| !s.is_empty()),
+        };
+
+        let alerts = AlertConfig {
+            webhook: env::var("ALERT_WEBHOOK").ok().filter(|s| !s.is_empty()),
+            check_interval_secs: env::var("ALERT_CHECK_INTERVAL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300),
+            window_secs: env::var("ALERT_WINDOW")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v| v > 0)
+                .unwrap_or(3600),
+            baseline_days: env::var("ALERT_BASELINE_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v| v > 0)
+                .unwrap_or(7),
+            min_requests: env::var("ALERT_MIN_REQUESTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
+            z_threshold: env::var("ALERT_Z_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3.0),
+            min_change_pct: env::var("ALERT_MIN_CHANGE_PCT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(50.0),
+        };
+
+        let telemetry = TelemetryConfig {
+            otlp_endpoint: env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            service_name: env::var("OTEL_SERVICE_NAME")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "claudio-api".into()),
         };
 
         Self {
@@ -129,6 +295,11 @@ impl Config {
             defaults,
             slack,
             semantic_search,
+            classify_cache,
+            budgets,
+            reports,
+            alerts,
+            telemetry,
         }
     }
 
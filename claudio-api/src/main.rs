@@ -5,8 +5,10 @@ mod analytics;
 mod api;
 mod claude;
 mod config;
+mod metrics;
 mod plugins;
 mod storage;
+mod telemetry;
 mod utils;
 
 pub use config::Config;
@@ -19,6 +21,7 @@ async fn main() -> Result<()> {
         .expect("Failed to install rustls crypto provider");
 
     let config = Config::load();
+    let tracer_provider = telemetry::init(&config.telemetry)?;
 
     tracing_subscriber::registry()
         .with(
@@ -26,19 +29,38 @@ async fn main() -> Result<()> {
                 .unwrap_or_else(|_| format!("claudio_api={}", config.logging.level).into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(telemetry::layer(&tracer_provider))
         .init();
 
     tracing::info!("Starting Claudio v{}", env!("CARGO_PKG_VERSION"));
 
-    let storage = Storage::new(&config.storage.path)?;
-    tracing::info!("Storage initialized at {}", config.storage.path);
+    let storage = Storage::open(&config.storage)?;
+    match &config.storage.database_url {
+        Some(_) => tracing::info!("Storage initialized on Postgres"),
+        None => tracing::info!("Storage initialized at {}", config.storage.path),
+    }
+
+    let storage = storage::AsyncStorage::new(storage, &config.storage);
 
     if config.semantic_search.enabled {
-        sync_agents_on_startup(&storage);
+        plugins::semantic::init(storage.clone()).await?;
+        sync_agents_on_startup(&storage).await;
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("eval") {
+        return api::eval::run_cli(&storage, &args[1..]).await;
+    }
+    if args.first().map(String::as_str) == Some("rollups") {
+        return rollups_cli(&storage, &args[1..]).await;
     }
 
+    api::reports::spawn_scheduler(storage.clone());
+    api::alerts::spawn_detector(storage.clone());
+
     let addr = config.socket_addr();
     let slack_config = config.slack.clone();
+    let slack_storage = storage.clone();
 
     let http_handle = tokio::spawn(async move {
         tracing::info!("HTTP API listening on http://{}", addr);
@@ -56,7 +78,7 @@ async fn main() -> Result<()> {
     let slack_handle = slack_config.map(|cfg| {
         tracing::info!("Starting Slack Socket Mode bridge...");
         tokio::spawn(async move {
-            let bridge = plugins::SlackBridge::new(cfg);
+            let bridge = plugins::SlackBridge::new(cfg, slack_storage);
             if let Err(e) = bridge.run().await {
                 tracing::error!("Slack bridge error: {}", e);
             }
@@ -71,11 +93,16 @@ async fn main() -> Result<()> {
         handle.abort();
     }
 
+    // Flushes spans still waiting in the batch exporter
+    if let Err(e) = tokio::task::spawn_blocking(move || tracer_provider.shutdown()).await? {
+        tracing::warn!("Failed to flush traces: {}", e);
+    }
+
     Ok(())
 }
 
-fn sync_agents_on_startup(storage: &Storage) {
-    let projects = match storage.list_projects() {
+async fn sync_agents_on_startup(storage: &storage::AsyncStorage) {
+    let projects = match storage.run(|s| s.list_projects()).await {
         Ok(p) => p,
         Err(e) => {
             tracing::warn!("Failed to list projects for agent sync: {}", e);
@@ -84,7 +111,8 @@ fn sync_agents_on_startup(storage: &Storage) {
     };
 
     for project in projects {
-        let agents = match storage.list_agents(&project.id) {
+        let pid = project.id.clone();
+        let agents = match storage.run(move |s| s.list_agents(&pid)).await {
             Ok(a) => a,
             Err(e) => {
                 tracing::warn!("Failed to list agents for {}: {}", project.id, e);
@@ -92,12 +120,29 @@ fn sync_agents_on_startup(storage: &Storage) {
             }
         };
 
-        if let Err(e) = plugins::semantic::sync_agents(&project.id, &agents) {
+        if let Err(e) = plugins::semantic::sync_agents(&project.id, &agents).await {
             tracing::warn!("Failed to sync agents for {}: {}", project.id, e);
         }
     }
 }
 
+/// `claudio-api rollups rebuild`: recomputes the hourly execution rollups
+/// analytics read long ranges from.
+async fn rollups_cli(storage: &storage::AsyncStorage, args: &[String]) -> Result<()> {
+    if args.iter().map(String::as_str).ne(["rebuild"]) {
+        anyhow::bail!(
+            "usage: claudio-api rollups rebuild\n\n\
+             Recomputes every rollup from executions in one transaction. New \
+             executions can't be saved until it finishes, so run it off-peak."
+        );
+    }
+    let executions = storage
+        .write("rebuild_rollups", |s| s.rebuild_rollups())
+        .await?;
+    eprintln!("Rolled up {} executions", executions);
+    Ok(())
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
@@ -0,0 +1,316 @@
+//! Process-wide Prometheus metrics, rendered in the text exposition format
+//! by `GET /metrics`. Counters live for the life of the process; database
+//! pool gauges are read at scrape time.
+
+use std::fmt::Write;
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+
+use crate::storage::PoolState;
+
+const HTTP_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+const CLASSIFY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+pub static HTTP_REQUESTS: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_http_requests_total",
+        "HTTP requests by route and status",
+        &["method", "route", "status"],
+    )
+});
+pub static HTTP_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        "claudio_http_request_duration_seconds",
+        "HTTP request latency by route",
+        &["method", "route"],
+        HTTP_BUCKETS,
+    )
+});
+pub static EXECUTIONS: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_executions_total",
+        "Claude executions by project, agent, model and status",
+        &["project", "agent", "model", "status"],
+    )
+});
+pub static CLI_FAILURES: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_cli_failures_total",
+        "Claude CLI runs that failed to spawn, timed out or exited with an error",
+        &["reason"],
+    )
+});
+pub static CLASSIFICATIONS: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_classifications_total",
+        "Logged classifications by deciding method",
+        &["method"],
+    )
+});
+pub static CLASSIFY_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        "claudio_classification_duration_seconds",
+        "Classification latency by deciding method",
+        &["method"],
+        CLASSIFY_BUCKETS,
+    )
+});
+pub static RATE_LIMITED: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_rate_limit_rejections_total",
+        "Requests rejected by the per-project rate limit",
+        &["project"],
+    )
+});
+pub static WEBHOOK_FAILURES: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_webhook_failures_total",
+        "Slack events the bridge failed to forward to n8n",
+        &["endpoint"],
+    )
+});
+pub static TOKENS: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_tokens_total",
+        "Tokens used by executions",
+        &["project", "model", "type"],
+    )
+});
+pub static COST: Lazy<CounterVec> = Lazy::new(|| {
+    CounterVec::new(
+        "claudio_cost_usd_total",
+        "Execution cost in USD",
+        &["project", "model"],
+    )
+});
+
+/// Monotonic counters partitioned by label values
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: DashMap<Vec<String>, f64>,
+}
+
+impl CounterVec {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: DashMap::new(),
+        }
+    }
+
+    pub fn inc(&self, labels: &[&str]) {
+        self.inc_by(labels, 1.0);
+    }
+
+    pub fn inc_by(&self, labels: &[&str], value: f64) {
+        debug_assert_eq!(labels.len(), self.labels.len(), "{}", self.name);
+        let key: Vec<String> = labels.iter().map(|l| l.to_string()).collect();
+        *self.values.entry(key).or_insert(0.0) += value;
+    }
+
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "counter");
+        for (key, value) in sorted(&self.values) {
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                self.name,
+                label_set(self.labels, &key, None),
+                value
+            );
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Histogram {
+    /// Per-bucket (non-cumulative) observation counts, plus one for +Inf
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+/// Histograms with fixed buckets partitioned by label values
+pub struct HistogramVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    buckets: &'static [f64],
+    values: DashMap<Vec<String>, Histogram>,
+}
+
+impl HistogramVec {
+    fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            buckets,
+            values: DashMap::new(),
+        }
+    }
+
+    pub fn observe(&self, labels: &[&str], value: f64) {
+        debug_assert_eq!(labels.len(), self.labels.len(), "{}", self.name);
+        let key: Vec<String> = labels.iter().map(|l| l.to_string()).collect();
+        let bucket = self
+            .buckets
+            .iter()
+            .position(|&b| value <= b)
+            .unwrap_or(self.buckets.len());
+        let mut histogram = self.values.entry(key).or_insert_with(|| Histogram {
+            counts: vec![0; self.buckets.len() + 1],
+            sum: 0.0,
+        });
+        histogram.counts[bucket] += 1;
+        histogram.sum += value;
+    }
+
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "histogram");
+        for (key, histogram) in sorted(&self.values) {
+            let mut cumulative = 0;
+            for (i, count) in histogram.counts.iter().enumerate() {
+                cumulative += count;
+                let le = match self.buckets.get(i) {
+                    Some(bound) => bound.to_string(),
+                    None => "+Inf".to_string(),
+                };
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    self.name,
+                    label_set(self.labels, &key, Some(&le)),
+                    cumulative
+                );
+            }
+            let labels = label_set(self.labels, &key, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels, histogram.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels, cumulative);
+        }
+    }
+}
+
+fn sorted<T: Clone>(values: &DashMap<Vec<String>, T>) -> Vec<(Vec<String>, T)> {
+    let mut entries: Vec<_> = values
+        .iter()
+        .map(|e| (e.key().clone(), e.value().clone()))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn label_set(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Every metric in the text exposition format.
+pub fn render(pool: PoolState) -> String {
+    let mut out = String::new();
+    HTTP_REQUESTS.render(&mut out);
+    HTTP_DURATION.render(&mut out);
+    EXECUTIONS.render(&mut out);
+    CLI_FAILURES.render(&mut out);
+    CLASSIFICATIONS.render(&mut out);
+    CLASSIFY_DURATION.render(&mut out);
+    RATE_LIMITED.render(&mut out);
+    WEBHOOK_FAILURES.render(&mut out);
+    TOKENS.render(&mut out);
+    COST.render(&mut out);
+
+    header(
+        &mut out,
+        "claudio_db_pool_connections",
+        "Database pool connections by state",
+        "gauge",
+    );
+    let _ = writeln!(
+        out,
+        "claudio_db_pool_connections{{state=\"active\"}} {}",
+        pool.connections.saturating_sub(pool.idle)
+    );
+    let _ = writeln!(
+        out,
+        "claudio_db_pool_connections{{state=\"idle\"}} {}",
+        pool.idle
+    );
+    header(
+        &mut out,
+        "claudio_db_pool_max_connections",
+        "Database pool size limit",
+        "gauge",
+    );
+    let _ = writeln!(out, "claudio_db_pool_max_connections {}", pool.max_size);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_text_format() {
+        let counter = CounterVec::new("test_total", "Test counter", &["route"]);
+        counter.inc(&["/v1/a\"b"]);
+        counter.inc_by(&["/v1/a\"b"], 2.0);
+        let histogram =
+            HistogramVec::new("test_seconds", "Test histogram", &["route"], &[0.1, 1.0]);
+        histogram.observe(&["/x"], 0.05);
+        histogram.observe(&["/x"], 0.5);
+        histogram.observe(&["/x"], 5.0);
+
+        let mut out = String::new();
+        counter.render(&mut out);
+        histogram.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP test_total Test counter\n\
+             # TYPE test_total counter\n\
+             test_total{route=\"/v1/a\\\"b\"} 3\n\
+             # HELP test_seconds Test histogram\n\
+             # TYPE test_seconds histogram\n\
+             test_seconds_bucket{route=\"/x\",le=\"0.1\"} 1\n\
+             test_seconds_bucket{route=\"/x\",le=\"1\"} 2\n\
+             test_seconds_bucket{route=\"/x\",le=\"+Inf\"} 3\n\
+             test_seconds_sum{route=\"/x\"} 5.55\n\
+             test_seconds_count{route=\"/x\"} 3\n"
+        );
+    }
+}
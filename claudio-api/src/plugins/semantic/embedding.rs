@@ -0,0 +1,192 @@
+use anyhow::{Context, Result, bail};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::config::SemanticSearchConfig;
+
+/// Turns text into vectors. Returned vectors are L2-normalized so cosine
+/// similarity is a plain dot product.
+pub trait EmbeddingProvider: Send + Sync {
+    /// Identifies the vector space; vectors from different models are never
+    /// compared, and persisted ones are re-embedded when it changes.
+    fn model(&self) -> &str;
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>>;
+}
+
+pub fn provider_from_config(config: &SemanticSearchConfig) -> Box<dyn EmbeddingProvider> {
+    match &config.embedding_url {
+        Some(url) => Box::new(HttpEmbedder::new(
+            url.clone(),
+            config.embedding_model.clone(),
+            config.embedding_api_key.clone(),
+        )),
+        None => Box::new(HashingEmbedder::new(config.hash_dimensions)),
+    }
+}
+
+/// Deterministic bag-of-features embedder: words, word bigrams and character
+/// trigrams hashed into a fixed number of buckets. Needs no model or network,
+/// which makes it the default for offline use and tests.
+pub struct HashingEmbedder {
+    dimensions: usize,
+    model: String,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions,
+            model: format!("hashing-v1-{}", dimensions),
+        }
+    }
+
+    pub fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        let lower = text.to_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        for word in &words {
+            self.add(&mut vector, word.as_bytes(), 1.0);
+
+            // Trigrams let inflected or compound forms ("deploy" / "deploying",
+            // Korean particles) still overlap
+            let chars: Vec<char> = format!("#{}#", word).chars().collect();
+            for gram in chars.windows(3) {
+                let gram: String = gram.iter().collect();
+                self.add(&mut vector, gram.as_bytes(), 0.5);
+            }
+        }
+        for pair in words.windows(2) {
+            self.add(
+                &mut vector,
+                format!("{} {}", pair[0], pair[1]).as_bytes(),
+                1.0,
+            );
+        }
+
+        normalize(&mut vector);
+        vector
+    }
+
+    fn add(&self, vector: &mut [f32], feature: &[u8], weight: f32) {
+        let hash = fnv1a(feature);
+        let bucket = (hash % self.dimensions as u64) as usize;
+        // The sign bit keeps colliding features from only ever adding up
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
+
+impl EmbeddingProvider for HashingEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move { Ok(texts.iter().map(|t| self.embed_one(t)).collect()) })
+    }
+}
+
+/// Client for OpenAI-compatible `/embeddings` endpoints, which covers hosted
+/// APIs as well as local model servers such as Ollama or llama.cpp.
+pub struct HttpEmbedder {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl HttpEmbedder {
+    pub fn new(url: String, model: String, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            model,
+            api_key,
+        }
+    }
+}
+
+impl EmbeddingProvider for HttpEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move {
+            if texts.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let mut request = self.client.post(&self.url).json(&EmbeddingRequest {
+                model: &self.model,
+                input: texts,
+            });
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+
+            let response: EmbeddingResponse = request
+                .send()
+                .await
+                .context("embedding request failed")?
+                .error_for_status()?
+                .json()
+                .await
+                .context("invalid embedding response")?;
+
+            if response.data.len() != texts.len() {
+                bail!(
+                    "embedding endpoint returned {} vectors for {} inputs",
+                    response.data.len(),
+                    texts.len()
+                );
+            }
+            let mut data = response.data;
+            data.sort_by_key(|d| d.index);
+            Ok(data
+                .into_iter()
+                .map(|d| {
+                    let mut vector = d.embedding;
+                    normalize(&mut vector);
+                    vector
+                })
+                .collect())
+        })
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// FNV-1a, so bucket assignment is stable across builds and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
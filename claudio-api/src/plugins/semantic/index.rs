@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::SemanticMatch;
+
+/// One embedded agent example.
+#[derive(Debug, Clone)]
+pub struct IndexedExample {
+    pub example: String,
+    pub vector: Vec<f32>,
+    /// Counterexamples never match; they veto their agent's examples for
+    /// queries closer to them
+    pub negative: bool,
+}
+
+/// In-memory vectors of every indexed agent example, grouped by project and
+/// agent. Searches are a linear scan, which is plenty for routing examples.
+#[derive(Default)]
+pub struct VectorIndex {
+    projects: RwLock<HashMap<String, HashMap<String, Vec<IndexedExample>>>>,
+}
+
+impl VectorIndex {
+    /// Replaces everything indexed for `agent_id`.
+    pub fn set_agent(&self, project_id: &str, agent_id: &str, examples: Vec<IndexedExample>) {
+        let mut projects = self.projects.write().unwrap();
+        // An agent only ever belongs to one project
+        for agents in projects.values_mut() {
+            agents.remove(agent_id);
+        }
+        if !examples.is_empty() {
+            projects
+                .entry(project_id.to_string())
+                .or_default()
+                .insert(agent_id.to_string(), examples);
+        }
+    }
+
+    pub fn remove_agent(&self, agent_id: &str) {
+        self.set_agent("", agent_id, Vec::new());
+    }
+
+    pub fn agent_examples(&self, agent_id: &str) -> Vec<IndexedExample> {
+        let projects = self.projects.read().unwrap();
+        projects
+            .values()
+            .find_map(|agents| agents.get(agent_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn agent_ids(&self, project_id: &str) -> Vec<String> {
+        let projects = self.projects.read().unwrap();
+        projects
+            .get(project_id)
+            .map(|agents| agents.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Best `top_k` examples scoring at least `min_score`, across all projects
+    /// when `project_id` is `None`. An agent's examples only count when they
+    /// score above its closest counterexample.
+    pub fn search(
+        &self,
+        project_id: Option<&str>,
+        query: &[f32],
+        top_k: usize,
+        min_score: f64,
+    ) -> Vec<SemanticMatch> {
+        let projects = self.projects.read().unwrap();
+        let mut matches: Vec<SemanticMatch> = projects
+            .iter()
+            .filter(|(pid, _)| project_id.is_none_or(|p| p == pid.as_str()))
+            .flat_map(|(_, agents)| agents.iter())
+            .flat_map(|(agent_id, examples)| {
+                let veto = examples
+                    .iter()
+                    .filter(|e| e.negative)
+                    .map(|e| dot(query, &e.vector))
+                    .fold(f64::NEG_INFINITY, f64::max);
+                examples
+                    .iter()
+                    .filter(|e| !e.negative)
+                    .map(move |e| SemanticMatch {
+                        agent: agent_id.clone(),
+                        score: dot(query, &e.vector),
+                        matched_example: e.example.clone(),
+                    })
+                    .filter(move |m| m.score > veto)
+            })
+            .filter(|m| m.score >= min_score)
+            .collect();
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(top_k);
+        matches
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| f64::from(*x) * f64::from(*y))
+        .sum()
+}
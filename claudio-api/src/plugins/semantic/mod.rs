@@ -1,14 +1,25 @@
-use serde::{Deserialize, Serialize};
-use std::io::Write;
-use std::process::{Command, Stdio};
-use tempfile::NamedTempFile;
-use tracing::{debug, info, warn};
+//! Built-in semantic routing: agent examples are embedded once, kept in an
+//! in-memory [`VectorIndex`] and persisted in the `semantic_embeddings` table
+//! so restarts don't re-embed anything.
+
+mod embedding;
+mod index;
+mod sync;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use tracing::{debug, info};
 
 use crate::config::Config;
-use crate::storage::Agent;
+use crate::storage::{Agent, AsyncStorage};
+
+pub use embedding::{EmbeddingProvider, provider_from_config};
+pub use index::{IndexedExample, VectorIndex};
+pub use sync::ProjectIndexStatus;
 
-const ROUTING_TAG: &str = "type:agent-routing";
-const INDEX_SOURCE: &str = "claudio";
+use sync::IndexQueue;
 
 #[derive(Debug, Clone)]
 pub struct SemanticMatch {
@@ -17,191 +28,508 @@ pub struct SemanticMatch {
     pub matched_example: String,
 }
 
-pub fn classify_for_project(text: &str, project_id: Option<&str>) -> Vec<SemanticMatch> {
+pub struct SemanticRouter {
+    provider: Box<dyn EmbeddingProvider>,
+    index: VectorIndex,
+    storage: AsyncStorage,
+    queue: IndexQueue,
+}
+
+static ROUTER: OnceLock<SemanticRouter> = OnceLock::new();
+
+/// Sets up the global router and loads persisted embeddings. Until this runs
+/// (or when semantic search is disabled) classification gets no semantic
+/// matches and syncs are no-ops.
+pub async fn init(storage: AsyncStorage) -> Result<()> {
+    let config = Config::global();
+    let router =
+        SemanticRouter::load(provider_from_config(&config.semantic_search), storage).await?;
+    if ROUTER.set(router).is_err() {
+        debug!("Semantic router already initialized");
+    }
+    if let Some(router) = ROUTER.get() {
+        router.start_worker();
+    }
+    Ok(())
+}
+
+pub async fn classify_for_project(text: &str, project_id: Option<&str>) -> Vec<SemanticMatch> {
     let config = Config::global();
     let semantic = &config.semantic_search;
 
     if !semantic.enabled {
         return vec![];
     }
-
-    let tags = match project_id {
-        Some(pid) => format!("{},project:{}", ROUTING_TAG, pid),
-        None => ROUTING_TAG.to_string(),
+    let Some(router) = ROUTER.get() else {
+        return vec![];
     };
 
-    let output = Command::new("ssearch")
-        .args([
-            "search",
+    match router
+        .search(
             text,
-            "--tags",
-            &tags,
-            "--format",
-            "json",
-            "--limit",
-            &semantic.top_k.to_string(),
-        ])
-        .output();
-
-    let output = match output {
-        Ok(out) if out.status.success() => out,
-        Ok(out) => {
-            let stderr = String::from_utf8_lossy(&out.stderr);
-            debug!("ssearch failed: {}", stderr);
-            return vec![];
-        }
+            project_id,
+            semantic.top_k as usize,
+            semantic.min_score,
+        )
+        .await
+    {
+        Ok(matches) => matches,
         Err(e) => {
-            debug!("Failed to run ssearch: {}", e);
-            return vec![];
+            debug!("Semantic search failed: {}", e);
+            vec![]
         }
-    };
+    }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let result: SearchResult = match serde_json::from_str(&stdout) {
-        Ok(r) => r,
-        Err(e) => {
-            debug!("Failed to parse ssearch output: {}", e);
-            return vec![];
-        }
+/// Reconciles the index of `project_id` with `agents`: changed agents are
+/// re-embedded, agents no longer listed are dropped. Returns the number of
+/// indexed examples.
+pub async fn sync_agents(project_id: &str, agents: &[Agent]) -> Result<usize> {
+    let Some(router) = enabled_router() else {
+        debug!("Semantic search disabled, skipping agent sync");
+        return Ok(0);
     };
 
-    result
-        .results
-        .into_iter()
-        .filter_map(|hit| {
-            let agent = hit.tags.iter().find(|t| t.key == "agent")?.value.clone();
+    let mut total_indexed = 0;
+    for agent in agents {
+        total_indexed += router.index_agent(agent).await?;
+        router.mark_synced(&agent.id);
+    }
 
-            if hit.score < semantic.min_score {
-                return None;
-            }
+    let listed: HashSet<&str> = agents.iter().map(|a| a.id.as_str()).collect();
+    for agent_id in router.index.agent_ids(project_id) {
+        if !listed.contains(agent_id.as_str()) {
+            router.remove_agent(project_id, &agent_id).await?;
+        }
+    }
 
-            Some(SemanticMatch {
-                agent,
-                score: hit.score,
-                matched_example: hit.content,
-            })
-        })
-        .collect()
+    info!(
+        "Agent sync complete: {} examples indexed for project {}",
+        total_indexed, project_id
+    );
+    Ok(total_indexed)
 }
 
-#[derive(Debug, Deserialize)]
-struct SearchResult {
-    results: Vec<SearchHit>,
+/// Queues a re-index of an agent after it was created, changed or deleted.
+/// The background worker picks up its current state and retries failures.
+pub fn enqueue_agent(project_id: &str, agent_id: &str) {
+    if let Some(router) = enabled_router() {
+        router.enqueue(project_id, agent_id);
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct SearchHit {
-    content: String,
-    score: f64,
-    tags: Vec<Tag>,
+pub fn index_status(project_id: &str, agents: &[Agent]) -> ProjectIndexStatus {
+    match enabled_router() {
+        Some(router) => router.project_status(project_id, agents),
+        None => ProjectIndexStatus {
+            project_id: project_id.to_string(),
+            enabled: false,
+            model: None,
+            indexed_examples: 0,
+            pending: 0,
+            agents: Vec::new(),
+        },
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Tag {
-    key: String,
-    value: String,
+/// Embeds `text` with the routing model. `None` when semantic search is off
+/// or the provider failed.
+pub async fn embed_text(text: &str) -> Option<Vec<f32>> {
+    let router = enabled_router()?;
+    match router.provider.embed(&[text.to_string()]).await {
+        Ok(vectors) => vectors.into_iter().next(),
+        Err(e) => {
+            debug!("Embedding failed: {}", e);
+            None
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
-struct ImportDoc {
-    content: String,
-    url: String,
+fn enabled_router() -> Option<&'static SemanticRouter> {
+    if Config::global().semantic_search.enabled {
+        ROUTER.get()
+    } else {
+        None
+    }
 }
 
-pub fn sync_agents(project_id: &str, agents: &[Agent]) -> anyhow::Result<usize> {
-    let config = Config::global();
-    if !config.semantic_search.enabled {
-        debug!("Semantic search disabled, skipping agent sync");
-        return Ok(0);
+impl SemanticRouter {
+    pub async fn load(provider: Box<dyn EmbeddingProvider>, storage: AsyncStorage) -> Result<Self> {
+        let model = provider.model().to_string();
+        let stored = storage.run(move |s| s.list_embeddings(&model)).await?;
+
+        let index = VectorIndex::default();
+        let loaded = stored.len();
+        let mut by_agent: HashMap<String, (String, Vec<IndexedExample>)> = HashMap::new();
+        for row in stored {
+            by_agent
+                .entry(row.agent_id)
+                .or_insert_with(|| (row.project_id, Vec::new()))
+                .1
+                .push(IndexedExample {
+                    example: row.example,
+                    vector: row.vector,
+                    negative: row.negative,
+                });
+        }
+        for (agent_id, (project_id, examples)) in by_agent {
+            index.set_agent(&project_id, &agent_id, examples);
+        }
+        info!(
+            "Loaded {} agent example embeddings ({})",
+            loaded,
+            provider.model()
+        );
+
+        Ok(Self {
+            provider,
+            index,
+            storage,
+            queue: IndexQueue::with_default_delay(),
+        })
     }
 
-    info!("Syncing agents for project: {}", project_id);
+    pub async fn search(
+        &self,
+        text: &str,
+        project_id: Option<&str>,
+        top_k: usize,
+        min_score: f64,
+    ) -> Result<Vec<SemanticMatch>> {
+        let query = self.provider.embed(&[text.to_string()]).await?;
+        let Some(query) = query.first() else {
+            return Ok(vec![]);
+        };
+        Ok(self.index.search(project_id, query, top_k, min_score))
+    }
 
-    let project_tag = format!("project:{}", project_id);
-    let delete_result = Command::new("ssearch")
-        .args(["tags", "delete", &project_tag, "-y"])
-        .output();
+    /// Embeds only examples and counterexamples that aren't indexed yet;
+    /// agents with a negative priority or no examples are removed from the
+    /// index. Returns the number of indexed examples.
+    pub async fn index_agent(&self, agent: &Agent) -> Result<usize> {
+        if agent.priority < 0 || agent.examples.is_empty() {
+            self.remove_agent(&agent.project_id, &agent.id).await?;
+            return Ok(0);
+        }
 
-    match delete_result {
-        Ok(out) if out.status.success() => {
-            info!("Deleted existing index for project: {}", project_id);
+        // (text, is counterexample); a text listed as both stays an example
+        let mut wanted: Vec<(String, bool)> = Vec::new();
+        let texts = agent
+            .examples
+            .iter()
+            .map(|e| (e, false))
+            .chain(agent.counterexamples.iter().map(|e| (e, true)));
+        for (text, negative) in texts {
+            let text = text.trim();
+            if !text.is_empty() && !wanted.iter().any(|(w, _)| w == text) {
+                wanted.push((text.to_string(), negative));
+            }
         }
-        Ok(out) => {
-            let stderr = String::from_utf8_lossy(&out.stderr);
-            debug!("ssearch delete (may be empty): {}", stderr);
+
+        let existing = self.index.agent_examples(&agent.id);
+        let kept: Vec<IndexedExample> = existing
+            .into_iter()
+            .filter(|e| wanted.contains(&(e.example.clone(), e.negative)))
+            .collect();
+        let missing: Vec<(String, bool)> = wanted
+            .into_iter()
+            .filter(|(w, _)| !kept.iter().any(|e| &e.example == w))
+            .collect();
+
+        let texts: Vec<String> = missing.iter().map(|(text, _)| text.clone()).collect();
+        let vectors = self.provider.embed(&texts).await?;
+        let added: Vec<(String, Vec<f32>, bool)> = missing
+            .into_iter()
+            .zip(vectors)
+            .map(|((text, negative), vector)| (text, vector, negative))
+            .collect();
+
+        let (project_id, agent_id, model) = (
+            agent.project_id.clone(),
+            agent.id.clone(),
+            self.provider.model().to_string(),
+        );
+        let keep: Vec<String> = kept.iter().map(|e| e.example.clone()).collect();
+        let persisted = added.clone();
+        self.storage
+            .run(move |s| {
+                s.save_agent_embeddings(&project_id, &agent_id, &model, &keep, &persisted)
+            })
+            .await?;
+
+        let mut examples = kept;
+        examples.extend(
+            added
+                .into_iter()
+                .map(|(example, vector, negative)| IndexedExample {
+                    example,
+                    vector,
+                    negative,
+                }),
+        );
+        let count = examples.iter().filter(|e| !e.negative).count();
+        self.index.set_agent(&agent.project_id, &agent.id, examples);
+        debug!("Indexed {} examples for agent: {}", count, agent.name);
+        Ok(count)
+    }
+
+    pub async fn remove_agent(&self, project_id: &str, agent_id: &str) -> Result<()> {
+        let (pid, aid, model) = (
+            project_id.to_string(),
+            agent_id.to_string(),
+            self.provider.model().to_string(),
+        );
+        self.storage
+            .run(move |s| s.save_agent_embeddings(&pid, &aid, &model, &[], &[]))
+            .await?;
+        self.index.remove_agent(agent_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future::BoxFuture;
+
+    use crate::config::StorageConfig;
+    use crate::storage::Storage;
+    use embedding::HashingEmbedder;
+
+    /// Hashing embedder that counts how many texts it embedded.
+    struct CountingEmbedder {
+        inner: HashingEmbedder,
+        embedded: Arc<AtomicUsize>,
+    }
+
+    impl EmbeddingProvider for CountingEmbedder {
+        fn model(&self) -> &str {
+            self.inner.model()
         }
-        Err(e) => {
-            warn!("Failed to delete existing index: {}", e);
+
+        fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            self.inner.embed(texts)
         }
     }
 
-    let mut total_indexed = 0;
-
-    for agent in agents {
-        if agent.priority < 0 || agent.examples.is_empty() {
-            continue;
+    fn agent(id: &str, examples: &[&str]) -> Agent {
+        Agent {
+            id: id.to_string(),
+            project_id: "demo".to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            model: "haiku".to_string(),
+            priority: 0,
+            keywords: vec![],
+            negative_keywords: vec![],
+            examples: examples.iter().map(|e| e.to_string()).collect(),
+            counterexamples: vec![],
+            instruction: None,
+            tools: None,
+            output_schema: None,
+            timeout: 0,
+            static_response: false,
+            working_dir: None,
+            revision: 1,
+            created_at: 0,
+            updated_at: 0,
         }
+    }
 
-        let examples = &agent.examples;
+    async fn load_router(storage: &AsyncStorage, embedded: &Arc<AtomicUsize>) -> SemanticRouter {
+        let provider = CountingEmbedder {
+            inner: HashingEmbedder::new(512),
+            embedded: Arc::clone(embedded),
+        };
+        SemanticRouter::load(Box::new(provider), storage.clone())
+            .await
+            .unwrap()
+    }
 
-        let mut temp_file = match NamedTempFile::new() {
-            Ok(f) => f,
-            Err(e) => {
-                warn!("Failed to create temp file: {}", e);
-                continue;
-            }
+    #[tokio::test]
+    async fn test_router_indexes_incrementally_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StorageConfig {
+            path: String::new(),
+            database_url: None,
+            workers: 2,
+            queue_size: 16,
         };
+        let storage = AsyncStorage::new(
+            Storage::new(dir.path().join("semantic.db")).unwrap(),
+            &config,
+        );
+        let embedded = Arc::new(AtomicUsize::new(0));
+        let router = load_router(&storage, &embedded).await;
 
-        for (i, example) in examples.iter().enumerate() {
-            let doc = ImportDoc {
-                content: example.clone(),
-                url: format!("agent://{}/{}", agent.id, i),
-            };
-            if let Ok(json) = serde_json::to_string(&doc) {
-                let _ = writeln!(temp_file, "{}", json);
-            }
+        let reviewer = agent("reviewer", &["review this merge request", "check my MR"]);
+        let ops = agent("ops", &["restart the production server", "deploy the api"]);
+        router.index_agent(&reviewer).await.unwrap();
+        router.index_agent(&ops).await.unwrap();
+        assert_eq!(embedded.load(Ordering::SeqCst), 4);
+
+        let matches = router
+            .search("please review my merge request", Some("demo"), 3, 0.1)
+            .await
+            .unwrap();
+        assert_eq!(matches[0].agent, "reviewer");
+        assert_eq!(matches[0].matched_example, "review this merge request");
+        assert!(
+            router
+                .search("please review my merge request", Some("other"), 3, 0.1)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // Only the new example is embedded; the dropped one leaves the index
+        embedded.store(0, Ordering::SeqCst);
+        let reviewer = agent("reviewer", &["check my MR", "look at this pull request"]);
+        assert_eq!(router.index_agent(&reviewer).await.unwrap(), 2);
+        assert_eq!(embedded.load(Ordering::SeqCst), 1);
+
+        router.remove_agent("demo", "ops").await.unwrap();
+        assert_eq!(router.index.agent_ids("demo"), vec!["reviewer".to_string()]);
+
+        // A fresh router restores the index without embedding anything
+        let reloaded = load_router(&storage, &embedded).await;
+        assert_eq!(embedded.load(Ordering::SeqCst), 1);
+        let mut examples: Vec<String> = reloaded
+            .index
+            .agent_examples("reviewer")
+            .into_iter()
+            .map(|e| e.example)
+            .collect();
+        examples.sort();
+        assert_eq!(examples, vec!["check my MR", "look at this pull request"]);
+        assert!(reloaded.index.agent_examples("ops").is_empty());
+
+        // A counterexample closer to the query than any example vetoes the agent
+        let mut reviewer = agent("reviewer", &["check my MR", "look at this pull request"]);
+        reviewer.counterexamples = vec!["check my MR pipeline".to_string()];
+        assert_eq!(reloaded.index_agent(&reviewer).await.unwrap(), 2);
+        let query = "check my MR pipeline status";
+        assert!(
+            reloaded
+                .search(query, Some("demo"), 3, 0.1)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            !router
+                .search(query, Some("demo"), 3, 0.1)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    /// Fails the first `failures` calls, then embeds normally.
+    struct FlakyEmbedder {
+        inner: HashingEmbedder,
+        failures: AtomicUsize,
+    }
+
+    impl EmbeddingProvider for FlakyEmbedder {
+        fn model(&self) -> &str {
+            self.inner.model()
         }
 
-        let _ = temp_file.flush();
-        let temp_path = temp_file.path();
-
-        let tags = format!("{},agent:{},project:{}", ROUTING_TAG, agent.id, project_id);
-
-        let import_result = Command::new("ssearch")
-            .args([
-                "import",
-                temp_path.to_str().unwrap_or(""),
-                "--tags",
-                &tags,
-                "--source",
-                INDEX_SOURCE,
-            ])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status();
-
-        match import_result {
-            Ok(status) if status.success() => {
-                debug!(
-                    "Indexed {} examples for agent: {}",
-                    examples.len(),
-                    agent.name
-                );
-                total_indexed += examples.len();
+        fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                return Box::pin(async { Err(anyhow::anyhow!("embedding endpoint unavailable")) });
             }
-            Ok(_) => {
-                warn!("Failed to index agent: {}", agent.name);
+            self.inner.embed(texts)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_retries_failed_index_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StorageConfig {
+            path: String::new(),
+            database_url: None,
+            workers: 2,
+            queue_size: 16,
+        };
+        let storage =
+            AsyncStorage::new(Storage::new(dir.path().join("queue.db")).unwrap(), &config);
+        let agent = storage
+            .run(|s| {
+                s.create_project(serde_json::from_value(serde_json::json!({"name": "demo"}))?)?;
+                s.create_agent(
+                    "demo",
+                    serde_json::from_value(serde_json::json!({
+                        "name": "reviewer",
+                        "description": "Reviews code",
+                        "examples": ["review this merge request", "check my MR"]
+                    }))?,
+                )
+            })
+            .await
+            .unwrap();
+
+        let provider = FlakyEmbedder {
+            inner: HashingEmbedder::new(256),
+            failures: AtomicUsize::new(1),
+        };
+        let mut router = SemanticRouter::load(Box::new(provider), storage.clone())
+            .await
+            .unwrap();
+        router.queue = IndexQueue::new(std::time::Duration::from_millis(10));
+        let router: &'static SemanticRouter = Box::leak(Box::new(router));
+        router.start_worker();
+
+        router.enqueue("demo", &agent.id);
+        let mut status = router.project_status("demo", std::slice::from_ref(&agent));
+        for _ in 0..100 {
+            if status.indexed_examples == 2 {
+                break;
             }
-            Err(e) => {
-                warn!("ssearch import error for {}: {}", agent.name, e);
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            status = router.project_status("demo", std::slice::from_ref(&agent));
+        }
+
+        let agent_status = &status.agents[0];
+        assert_eq!(status.indexed_examples, 2);
+        assert_eq!(status.pending, 0);
+        assert_eq!(agent_status.attempts, 0);
+        assert!(agent_status.last_synced_at.is_some());
+        assert!(agent_status.last_error.is_none());
+        // The failure stays visible after the retry succeeded
+        assert!(agent_status.last_error_at.is_some());
+
+        // Deleting the agent and enqueueing it again drops it from the index
+        storage
+            .run(move |s| s.delete_agent(&agent.id))
+            .await
+            .unwrap();
+        router.enqueue("demo", "demo-reviewer");
+        for _ in 0..100 {
+            if router.index.agent_ids("demo").is_empty() {
+                break;
             }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         }
+        assert!(router.index.agent_ids("demo").is_empty());
     }
 
-    info!(
-        "Agent sync complete: {} examples indexed for project {}",
-        total_indexed, project_id
-    );
-
-    Ok(total_indexed)
+    #[test]
+    fn test_hashing_embedder_is_deterministic() {
+        let embedder = HashingEmbedder::new(256);
+        let a = embedder.embed_one("Deploy the API");
+        assert_eq!(a, embedder.embed_one("deploy the api"));
+        let norm: f32 = a.iter().map(|v| v * v).sum();
+        assert!((norm - 1.0).abs() < 1e-5);
+        assert!(embedder.embed_one("").iter().all(|v| *v == 0.0));
+    }
 }
@@ -0,0 +1,223 @@
+//! Background application of per-agent index updates. Handlers only enqueue
+//! the agent id; the worker loads the agent's current state, so several edits
+//! in a row collapse into one re-index.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tracing::{debug, warn};
+
+use super::SemanticRouter;
+use crate::storage::Agent;
+
+/// Attempts per job before it is given up until the next edit or sync.
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub(super) struct IndexJob {
+    project_id: String,
+    agent_id: String,
+    attempt: u32,
+}
+
+pub(super) struct IndexQueue {
+    sender: UnboundedSender<IndexJob>,
+    receiver: Mutex<Option<UnboundedReceiver<IndexJob>>>,
+    status: DashMap<String, SyncState>,
+    retry_delay: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SyncState {
+    pending: bool,
+    attempts: u32,
+    last_synced_at: Option<i64>,
+    last_error: Option<String>,
+    last_error_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentIndexStatus {
+    pub agent_id: String,
+    pub name: String,
+    /// Examples configured on the agent
+    pub examples: usize,
+    /// Examples currently in the index
+    pub indexed: usize,
+    pub pending: bool,
+    /// Failed attempts since the last successful sync
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectIndexStatus {
+    pub project_id: String,
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub indexed_examples: usize,
+    pub pending: usize,
+    pub agents: Vec<AgentIndexStatus>,
+}
+
+impl IndexQueue {
+    pub(super) fn new(retry_delay: Duration) -> Self {
+        let (sender, receiver) = unbounded_channel();
+        Self {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            status: DashMap::new(),
+            retry_delay,
+        }
+    }
+
+    pub(super) fn with_default_delay() -> Self {
+        Self::new(RETRY_BASE_DELAY)
+    }
+}
+
+impl SemanticRouter {
+    /// Spawns the worker that drains the queue. Only the first call starts
+    /// one.
+    pub fn start_worker(&'static self) {
+        let Some(mut receiver) = self.queue.receiver.lock().unwrap().take() else {
+            return;
+        };
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                self.apply(job).await;
+            }
+        });
+    }
+
+    /// Queues a re-index of `agent_id`; a no-op when one is already waiting.
+    pub fn enqueue(&self, project_id: &str, agent_id: &str) {
+        let mut state = self.queue.status.entry(agent_id.to_string()).or_default();
+        if state.pending {
+            return;
+        }
+        state.pending = true;
+        drop(state);
+        self.send(IndexJob {
+            project_id: project_id.to_string(),
+            agent_id: agent_id.to_string(),
+            attempt: 0,
+        });
+    }
+
+    fn send(&self, job: IndexJob) {
+        if let Err(e) = self.queue.sender.send(job) {
+            warn!("Semantic index queue closed, dropping {:?}", e.0);
+        }
+    }
+
+    async fn apply(&'static self, job: IndexJob) {
+        if let Some(mut state) = self.queue.status.get_mut(&job.agent_id) {
+            state.pending = false;
+        }
+
+        let lookup = job.agent_id.clone();
+        let result = match self.storage.run(move |s| s.get_agent(&lookup)).await {
+            Ok(Some(agent)) if agent.project_id == job.project_id => {
+                self.index_agent(&agent).await.map(|_| ())
+            }
+            Ok(_) => self.remove_agent(&job.project_id, &job.agent_id).await,
+            Err(e) => Err(e),
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let mut state = self.queue.status.entry(job.agent_id.clone()).or_default();
+        match result {
+            Ok(()) => {
+                state.attempts = 0;
+                state.last_synced_at = Some(now);
+                state.last_error = None;
+                debug!("Semantic index updated for agent {}", job.agent_id);
+            }
+            Err(e) => {
+                state.attempts = job.attempt + 1;
+                state.last_error = Some(e.to_string());
+                state.last_error_at = Some(now);
+                warn!(
+                    agent_id = %job.agent_id,
+                    attempt = job.attempt + 1,
+                    error = %e,
+                    "Semantic index update failed"
+                );
+
+                // A newer edit already queued a fresh job
+                if state.pending || job.attempt + 1 >= MAX_ATTEMPTS {
+                    return;
+                }
+                state.pending = true;
+                drop(state);
+
+                let delay = self.queue.retry_delay * 2u32.pow(job.attempt);
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    self.send(IndexJob {
+                        attempt: job.attempt + 1,
+                        ..job
+                    });
+                });
+            }
+        }
+    }
+
+    /// Records a successful sync outside the queue, e.g. a full project sync.
+    pub(super) fn mark_synced(&self, agent_id: &str) {
+        let mut state = self.queue.status.entry(agent_id.to_string()).or_default();
+        state.attempts = 0;
+        state.last_synced_at = Some(chrono::Utc::now().timestamp());
+        state.last_error = None;
+    }
+
+    pub fn project_status(&self, project_id: &str, agents: &[Agent]) -> ProjectIndexStatus {
+        let agents: Vec<AgentIndexStatus> = agents
+            .iter()
+            .map(|agent| {
+                let state = self
+                    .queue
+                    .status
+                    .get(&agent.id)
+                    .map(|s| s.clone())
+                    .unwrap_or_default();
+                AgentIndexStatus {
+                    agent_id: agent.id.clone(),
+                    name: agent.name.clone(),
+                    examples: agent.examples.len(),
+                    indexed: self
+                        .index
+                        .agent_examples(&agent.id)
+                        .iter()
+                        .filter(|e| !e.negative)
+                        .count(),
+                    pending: state.pending,
+                    attempts: state.attempts,
+                    last_synced_at: state.last_synced_at,
+                    last_error: state.last_error,
+                    last_error_at: state.last_error_at,
+                }
+            })
+            .collect();
+
+        ProjectIndexStatus {
+            project_id: project_id.to_string(),
+            enabled: true,
+            model: Some(self.provider.model().to_string()),
+            indexed_examples: agents.iter().map(|a| a.indexed).sum(),
+            pending: agents.iter().filter(|a| a.pending).count(),
+            agents,
+        }
+    }
+}
@@ -5,9 +5,13 @@ use reqwest::{Client, StatusCode};
 use serde::Serialize;
 use slack_morphism::prelude::*;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing::{error, info, warn};
 
 use crate::config::SlackConfig;
+use crate::metrics;
+use crate::storage::{AsyncStorage, WebhookDelivery};
+use crate::telemetry;
 
 static MENTION_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"<@[A-Z0-9]+>").expect("Invalid mention regex pattern"));
@@ -40,9 +44,18 @@ pub struct SlackEvent {
     pub message_ts: Option<String>,
 }
 
+/// What n8n receives: the event and the run id it passes on to the API
+#[derive(Serialize)]
+struct ForwardedEvent<'a> {
+    #[serde(flatten)]
+    event: &'a SlackEvent,
+    run_id: &'a str,
+}
+
 #[derive(Clone)]
 struct BridgeState {
     config: Arc<SlackConfig>,
+    storage: AsyncStorage,
     http: Client,
     slack_client: Arc<SlackHyperClient>,
     bot_token: SlackApiToken,
@@ -50,12 +63,14 @@ struct BridgeState {
 
 pub struct SlackBridge {
     config: Arc<SlackConfig>,
+    storage: AsyncStorage,
 }
 
 impl SlackBridge {
-    pub fn new(config: SlackConfig) -> Self {
+    pub fn new(config: SlackConfig, storage: AsyncStorage) -> Self {
         Self {
             config: Arc::new(config),
+            storage,
         }
     }
 
@@ -68,6 +83,7 @@ impl SlackBridge {
 
         let state = BridgeState {
             config: self.config.clone(),
+            storage: self.storage.clone(),
             http: Client::new(),
             slack_client: client.clone(),
             bot_token,
@@ -297,6 +313,14 @@ fn forward_reaction(
     tokio::spawn(forward_to_webhook(state.clone(), event, endpoint));
 }
 
+/// Each forwarded event starts a trace and a workflow run; n8n continues
+/// them by passing the `traceparent` header and `run_id` on to the API calls
+/// it makes. Every attempt is recorded as a webhook delivery of the run.
+#[tracing::instrument(
+    name = "webhook.forward",
+    skip_all,
+    fields(otel.kind = "client", event = %event.event_type, channel = %event.channel, endpoint = ?endpoint)
+)]
 async fn forward_to_webhook(state: BridgeState, event: SlackEvent, endpoint: WebhookEndpoint) {
     let webhooks = &state.config.webhooks;
 
@@ -315,21 +339,61 @@ async fn forward_to_webhook(state: BridgeState, event: SlackEvent, endpoint: Web
         }
     };
 
+    let run_id = uuid::Uuid::new_v4().to_string();
     info!(
-        "Forwarding {} to {}: channel={}",
-        event.event_type, endpoint_name, event.channel
+        "Forwarding {} to {}: channel={} run_id={}",
+        event.event_type, endpoint_name, event.channel, run_id
     );
 
-    match state.http.post(webhook_url).json(&event).send().await {
+    let started = Instant::now();
+    let (status_code, error) = match state
+        .http
+        .post(webhook_url)
+        .headers(telemetry::trace_headers())
+        .json(&ForwardedEvent {
+            event: &event,
+            run_id: &run_id,
+        })
+        .send()
+        .await
+    {
         Ok(res) if res.status().is_success() => {
             info!("Forwarded to {} successfully", endpoint_name);
+            (Some(res.status().as_u16() as i64), None)
         }
         Ok(res) => {
             error!("{} returned error: {}", endpoint_name, res.status());
+            metrics::WEBHOOK_FAILURES.inc(&[endpoint_name]);
+            (
+                Some(res.status().as_u16() as i64),
+                Some(res.status().to_string()),
+            )
         }
         Err(e) => {
             error!("Failed to forward to {}: {}", endpoint_name, e);
+            metrics::WEBHOOK_FAILURES.inc(&[endpoint_name]);
+            (None, Some(e.to_string()))
         }
+    };
+
+    let delivery = WebhookDelivery {
+        run_id: Some(run_id),
+        endpoint: endpoint_name.to_string(),
+        event_type: event.event_type.clone(),
+        channel: Some(event.channel.clone()),
+        status_code,
+        error,
+        duration_ms: started.elapsed().as_millis() as i64,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    if let Err(e) = state
+        .storage
+        .write("record_webhook_delivery", move |s| {
+            s.record_webhook_delivery(&delivery)
+        })
+        .await
+    {
+        warn!("Failed to record webhook delivery: {}", e);
     }
 }
 
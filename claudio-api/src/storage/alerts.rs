@@ -0,0 +1,134 @@
+use anyhow::{Result, anyhow};
+
+use super::backend::{Row, ToValue, Value, params};
+use super::core::Storage;
+use super::types::{Alert, AlertFilter, AlertMetric, AlertScope, AlertStatus};
+use crate::analytics::Anomaly;
+
+const ALERT_COLUMNS: &str =
+    "id, project, scope, target, metric, status, baseline, value, started_at, ended_at, updated_at";
+
+fn map_alert_row(row: &Row) -> Result<Alert> {
+    let scope: String = row.get(2)?;
+    let target: String = row.get(3)?;
+    let metric: String = row.get(4)?;
+    let status: String = row.get(5)?;
+    Ok(Alert {
+        id: row.get(0)?,
+        project: row.get(1)?,
+        scope: AlertScope::parse(&scope).ok_or_else(|| anyhow!("unknown scope '{}'", scope))?,
+        // Project alerts store an empty target so the open-alert index can
+        // tell them apart
+        target: (!target.is_empty()).then_some(target),
+        metric: AlertMetric::parse(&metric)
+            .ok_or_else(|| anyhow!("unknown metric '{}'", metric))?,
+        status: AlertStatus::parse(&status)
+            .ok_or_else(|| anyhow!("unknown status '{}'", status))?,
+        baseline: row.get(6)?,
+        value: row.get(7)?,
+        started_at: row.get(8)?,
+        ended_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+impl Storage {
+    pub fn list_alerts(&self, filter: &AlertFilter, limit: i64) -> Result<Vec<Alert>> {
+        let conn = self.conn()?;
+        let mut conditions = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+        let fields = [
+            ("status", filter.status.map(|s| s.as_str())),
+            ("project", filter.project.as_deref()),
+            ("scope", filter.scope.map(|s| s.as_str())),
+            ("metric", filter.metric.map(|m| m.as_str())),
+        ];
+        for (column, value) in fields {
+            if let Some(value) = value {
+                params.push(value.to_value());
+                conditions.push(format!("{} = ?{}", column, params.len()));
+            }
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        params.push(Value::Integer(limit));
+        let sql = format!(
+            "SELECT {} FROM alerts {} ORDER BY started_at DESC, id DESC LIMIT ?{}",
+            ALERT_COLUMNS,
+            where_clause,
+            params.len()
+        );
+        let alerts = conn
+            .query_map(&sql, &params, map_alert_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(alerts)
+    }
+
+    pub fn get_alert(&self, id: i64) -> Result<Option<Alert>> {
+        let conn = self.conn()?;
+        let alert = conn
+            .query_map(
+                &format!("SELECT {} FROM alerts WHERE id = ?1", ALERT_COLUMNS),
+                params![id],
+                map_alert_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(alert)
+    }
+
+    /// Opens an alert for a metric out of its baseline. Returns `None` when
+    /// one is already open for it.
+    pub fn open_alert(&self, anomaly: &Anomaly, now: i64) -> Result<Option<Alert>> {
+        let conn = self.conn()?;
+        let alert = conn
+            .query_map(
+                &format!(
+                    "INSERT INTO alerts (project, scope, target, metric, status, baseline, value, started_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, 'open', ?5, ?6, ?7, ?7)
+                     ON CONFLICT (project, scope, target, metric) WHERE status = 'open' DO NOTHING
+                     RETURNING {}",
+                    ALERT_COLUMNS
+                ),
+                params![
+                    anomaly.project,
+                    anomaly.scope.as_str(),
+                    anomaly.target.as_deref().unwrap_or_default(),
+                    anomaly.metric.as_str(),
+                    anomaly.baseline,
+                    anomaly.value,
+                    now
+                ],
+                map_alert_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(alert)
+    }
+
+    /// Records the latest baseline and value of an open alert.
+    pub fn update_alert(&self, id: i64, baseline: f64, value: f64, now: i64) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE alerts SET baseline = ?2, value = ?3, updated_at = ?4
+             WHERE id = ?1 AND status = 'open'",
+            params![id, baseline, value, now],
+        )?;
+        Ok(())
+    }
+
+    /// Closes an open alert. Returns `false` when it already was closed.
+    pub fn resolve_alert(&self, id: i64, now: i64) -> Result<bool> {
+        let conn = self.conn()?;
+        let resolved = conn.execute(
+            "UPDATE alerts SET status = 'resolved', ended_at = ?2, updated_at = ?2
+             WHERE id = ?1 AND status = 'open'",
+            params![id, now],
+        )?;
+        Ok(resolved > 0)
+    }
+}
@@ -0,0 +1,162 @@
+mod postgres;
+mod sqlite;
+mod value;
+
+use anyhow::{Result, anyhow};
+
+pub use self::postgres::PostgresBackend;
+pub use sqlite::SqliteBackend;
+pub(crate) use value::params;
+pub use value::{Row, ToValue, Value};
+
+/// Execution metadata keys filtered on often enough to carry an expression
+/// index (Slack channel and the message refs used by `/v1/executions/lookup`).
+pub(crate) const INDEXED_METADATA_KEYS: &[&str] = &["channel", "reply_ts", "reaction_target_ts"];
+
+/// Whether `key` is safe to inline into a JSON path expression.
+pub(crate) fn is_metadata_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+            Self::Postgres => "postgres",
+        }
+    }
+
+    /// Native positional placeholder for the 1-based parameter `idx`.
+    pub fn placeholder(&self, idx: usize) -> String {
+        match self {
+            Self::Sqlite => format!("?{}", idx),
+            Self::Postgres => format!("${}", idx),
+        }
+    }
+
+    /// Two-argument scalar maximum (`MAX(a, b)` is an aggregate in Postgres).
+    pub fn greatest(&self, a: &str, b: &str) -> String {
+        match self {
+            Self::Sqlite => format!("MAX({}, {})", a, b),
+            Self::Postgres => format!("GREATEST({}, {})", a, b),
+        }
+    }
+
+    /// Extracts a top-level string field from a JSON document stored as text.
+    pub fn json_text(&self, column: &str, key: &str) -> String {
+        match self {
+            Self::Sqlite => format!("json_extract({}, '$.{}')", column, key),
+            Self::Postgres => format!("({}::json ->> '{}')", column, key),
+        }
+    }
+
+    /// Expression indexes over [`INDEXED_METADATA_KEYS`]. Queries must use
+    /// [`Dialect::json_text`] on the same key for the planner to pick them up.
+    fn metadata_indexes(&self) -> String {
+        INDEXED_METADATA_KEYS
+            .iter()
+            .map(|key| {
+                format!(
+                    "CREATE INDEX IF NOT EXISTS idx_executions_meta_{} ON executions(({}));",
+                    key,
+                    self.json_text("metadata", key)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn begin(&self) -> &'static str {
+        match self {
+            Self::Sqlite => "BEGIN IMMEDIATE",
+            Self::Postgres => "BEGIN",
+        }
+    }
+}
+
+/// A database the storage layer can run on. Implementations own pooling and
+/// schema setup; all queries go through [`Connection`].
+pub trait Backend: Send + Sync {
+    fn dialect(&self) -> Dialect;
+    fn connect(&self) -> Result<Box<dyn Connection>>;
+    fn pool_state(&self) -> PoolState;
+}
+
+/// Connection pool usage
+#[derive(Debug, Clone, Copy)]
+pub struct PoolState {
+    /// Open connections, idle or checked out
+    pub connections: u32,
+    pub idle: u32,
+    pub max_size: u32,
+}
+
+impl PoolState {
+    fn of<M: r2d2::ManageConnection>(pool: &r2d2::Pool<M>) -> Self {
+        let state = pool.state();
+        Self {
+            connections: state.connections,
+            idle: state.idle_connections,
+            max_size: pool.max_size(),
+        }
+    }
+}
+
+/// A pooled connection. SQL is written with SQLite-style `?N` placeholders,
+/// which non-SQLite backends translate before execution.
+pub trait Connection {
+    fn dialect(&self) -> Dialect;
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<usize>;
+    fn query(&self, sql: &str, params: &[Value]) -> Result<Vec<Row>>;
+    fn execute_batch(&self, sql: &str) -> Result<()>;
+}
+
+impl dyn Connection + '_ {
+    /// Returns the first row mapped through `f`, or an error if there is none.
+    pub fn query_row<T, F>(&self, sql: &str, params: &[Value], f: F) -> Result<T>
+    where
+        F: FnOnce(&Row) -> Result<T>,
+    {
+        let rows = self.query(sql, params)?;
+        let row = rows
+            .first()
+            .ok_or_else(|| anyhow!("Query returned no rows"))?;
+        f(row)
+    }
+
+    pub fn query_map<T, F>(
+        &self,
+        sql: &str,
+        params: &[Value],
+        f: F,
+    ) -> Result<std::vec::IntoIter<Result<T>>>
+    where
+        F: FnMut(&Row) -> Result<T>,
+    {
+        let rows = self.query(sql, params)?;
+        Ok(rows.iter().map(f).collect::<Vec<_>>().into_iter())
+    }
+}
+
+/// Runs `f` inside a write transaction, rolling back on error.
+pub(crate) fn transaction<T>(conn: &dyn Connection, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    conn.execute_batch(conn.dialect().begin())?;
+    match f() {
+        Ok(value) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback_err) = conn.execute_batch("ROLLBACK") {
+                tracing::error!(error = %rollback_err, "Failed to rollback transaction");
+            }
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,716 @@
+use anyhow::Result;
+use bytes::BytesMut;
+use postgres::types::{FromSql, IsNull, ToSql, Type, to_sql_checked};
+use postgres::{Config, NoTls};
+use r2d2::{Pool, PooledConnection};
+use r2d2_postgres::PostgresConnectionManager;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::error::Error;
+
+use super::{Backend, Connection, Dialect, PoolState, Row, Value};
+
+type Manager = PostgresConnectionManager<NoTls>;
+type BoxError = Box<dyn Error + Sync + Send>;
+
+/// Arbitrary key serializing schema setup across instances starting together.
+const SCHEMA_LOCK_KEY: i64 = 0x0063_6c61_7564_696f;
+
+pub struct PostgresBackend {
+    pool: Pool<Manager>,
+}
+
+impl PostgresBackend {
+    pub fn connect(url: &str) -> Result<Self> {
+        Self::new(url.parse()?)
+    }
+
+    pub fn new(config: Config) -> Result<Self> {
+        let manager = PostgresConnectionManager::new(config, NoTls);
+        let pool = blocking(|| Pool::builder().max_size(10).build(manager))?;
+
+        let backend = Self { pool };
+        backend.connect()?.execute_batch(&format!(
+            "BEGIN; SELECT pg_advisory_xact_lock({}); {} {} {} COMMIT;",
+            SCHEMA_LOCK_KEY,
+            SCHEMA,
+            MIGRATIONS,
+            Dialect::Postgres.metadata_indexes()
+        ))?;
+        Ok(backend)
+    }
+}
+
+impl Backend for PostgresBackend {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    fn connect(&self) -> Result<Box<dyn Connection>> {
+        let conn = blocking(|| self.pool.get())
+            .map_err(|e| anyhow::anyhow!("Failed to get DB connection: {}", e))?;
+        Ok(Box::new(PostgresConnection(RefCell::new(conn))))
+    }
+
+    fn pool_state(&self) -> PoolState {
+        PoolState::of(&self.pool)
+    }
+}
+
+struct PostgresConnection(RefCell<PooledConnection<Manager>>);
+
+impl Connection for PostgresConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<usize> {
+        let sql = translate_placeholders(sql);
+        let params = as_params(params);
+        let affected = blocking(|| self.0.borrow_mut().execute(sql.as_ref(), &params))?;
+        Ok(affected as usize)
+    }
+
+    fn query(&self, sql: &str, params: &[Value]) -> Result<Vec<Row>> {
+        let sql = translate_placeholders(sql);
+        let params = as_params(params);
+        let rows = blocking(|| self.0.borrow_mut().query(sql.as_ref(), &params))?;
+        rows.iter().map(convert_row).collect()
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<()> {
+        Ok(blocking(|| self.0.borrow_mut().batch_execute(sql))?)
+    }
+}
+
+/// The sync `postgres` client drives its own runtime, which panics when
+/// entered from a tokio worker; hand the thread over to blocking work first.
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+fn as_params(params: &[Value]) -> Vec<&(dyn ToSql + Sync)> {
+    params.iter().map(|p| p as &(dyn ToSql + Sync)).collect()
+}
+
+/// Rewrites SQLite `?N` / `?` placeholders to Postgres `$N`, leaving quoted
+/// literals and identifiers untouched.
+fn translate_placeholders(sql: &str) -> Cow<'_, str> {
+    if !sql.contains('?') {
+        return Cow::Borrowed(sql);
+    }
+
+    let mut out = String::with_capacity(sql.len() + 8);
+    let mut chars = sql.chars().peekable();
+    let mut quote: Option<char> = None;
+    let mut highest = 0usize;
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                out.push(c);
+            }
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                out.push(c);
+            }
+            None if c == '?' => {
+                let mut digits = String::new();
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(*d);
+                    chars.next();
+                }
+                let idx = match digits.parse::<usize>() {
+                    Ok(n) => n,
+                    Err(_) => highest + 1,
+                };
+                highest = highest.max(idx);
+                out.push('$');
+                out.push_str(&idx.to_string());
+            }
+            None => out.push(c),
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+impl ToSql for Value {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        match self {
+            Value::Null => Ok(IsNull::Yes),
+            Value::Integer(i) => {
+                if *ty == Type::INT8 {
+                    i.to_sql(ty, out)
+                } else if *ty == Type::INT4 {
+                    i32::try_from(*i)?.to_sql(ty, out)
+                } else if *ty == Type::INT2 {
+                    i16::try_from(*i)?.to_sql(ty, out)
+                } else if *ty == Type::FLOAT8 {
+                    (*i as f64).to_sql(ty, out)
+                } else if *ty == Type::FLOAT4 {
+                    (*i as f32).to_sql(ty, out)
+                } else if *ty == Type::BOOL {
+                    (*i != 0).to_sql(ty, out)
+                } else {
+                    i.to_string().as_str().to_sql(ty, out)
+                }
+            }
+            Value::Real(f) => {
+                if *ty == Type::FLOAT8 {
+                    f.to_sql(ty, out)
+                } else if *ty == Type::FLOAT4 {
+                    (*f as f32).to_sql(ty, out)
+                } else if *ty == Type::INT8 {
+                    (*f as i64).to_sql(ty, out)
+                } else {
+                    f.to_string().as_str().to_sql(ty, out)
+                }
+            }
+            Value::Text(s) => s.as_str().to_sql(ty, out),
+        }
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+fn convert_row(row: &postgres::Row) -> Result<Row> {
+    let values = row
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| column_value(row, idx, column.type_()))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Row::new(values))
+}
+
+fn column_value(row: &postgres::Row, idx: usize, ty: &Type) -> Result<Value> {
+    let value = if *ty == Type::INT8 {
+        row.try_get::<_, Option<i64>>(idx)?.map(Value::Integer)
+    } else if *ty == Type::INT4 {
+        row.try_get::<_, Option<i32>>(idx)?
+            .map(|v| Value::Integer(v.into()))
+    } else if *ty == Type::INT2 {
+        row.try_get::<_, Option<i16>>(idx)?
+            .map(|v| Value::Integer(v.into()))
+    } else if *ty == Type::BOOL {
+        row.try_get::<_, Option<bool>>(idx)?
+            .map(|v| Value::Integer(v.into()))
+    } else if *ty == Type::FLOAT8 {
+        row.try_get::<_, Option<f64>>(idx)?.map(Value::Real)
+    } else if *ty == Type::FLOAT4 {
+        row.try_get::<_, Option<f32>>(idx)?
+            .map(|v| Value::Real(v.into()))
+    } else if *ty == Type::NUMERIC {
+        row.try_get::<_, Option<Numeric>>(idx)?.map(|n| n.0)
+    } else {
+        row.try_get::<_, Option<String>>(idx)?.map(Value::Text)
+    };
+    Ok(value.unwrap_or(Value::Null))
+}
+
+/// Decodes the binary `NUMERIC` wire format that `SUM`/`AVG` over integer
+/// columns produce. Whole numbers stay integers; anything else becomes a float.
+struct Numeric(Value);
+
+impl<'a> FromSql<'a> for Numeric {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        let word = |i: usize| -> Result<u16, BoxError> {
+            raw.get(i..i + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .ok_or_else(|| "truncated numeric".into())
+        };
+
+        let ndigits = word(0)? as usize;
+        let weight = word(2)? as i16 as i32;
+        let sign = word(4)?;
+        let scale = word(6)?;
+
+        if sign == 0xC000 {
+            return Ok(Self(Value::Real(f64::NAN)));
+        }
+        let negative = sign == 0x4000;
+
+        let digits = (0..ndigits)
+            .map(|i| word(8 + i * 2))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if scale == 0 {
+            let mut whole: i128 = 0;
+            for (i, d) in digits.iter().enumerate() {
+                let exp = weight - i as i32;
+                if exp >= 0 {
+                    whole += i128::from(*d) * 10_000i128.pow(exp as u32);
+                }
+            }
+            let whole = if negative { -whole } else { whole };
+            if let Ok(i) = i64::try_from(whole) {
+                return Ok(Self(Value::Integer(i)));
+            }
+        }
+
+        let magnitude: f64 = digits
+            .iter()
+            .enumerate()
+            .map(|(i, d)| f64::from(*d) * 10_000f64.powi(weight - i as i32))
+            .sum();
+        Ok(Self(Value::Real(if negative {
+            -magnitude
+        } else {
+            magnitude
+        })))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS executions (
+        id TEXT PRIMARY KEY,
+        project TEXT NOT NULL,
+        source TEXT,
+        requester TEXT,
+        agent TEXT,
+        instruction TEXT,
+        user_message TEXT NOT NULL,
+        user_context TEXT,
+        response TEXT NOT NULL,
+        structured_output TEXT,
+        model TEXT,
+        cost_usd DOUBLE PRECISION,
+        input_tokens BIGINT,
+        output_tokens BIGINT,
+        cache_read_tokens BIGINT,
+        cache_creation_tokens BIGINT,
+        duration_ms BIGINT,
+        duration_api_ms BIGINT,
+        session_id TEXT,
+        metadata TEXT,
+        agent_revision BIGINT,
+        allowed_tools TEXT,
+        disallowed_tools TEXT,
+        rerun_of TEXT,
+        experiment_id TEXT,
+        variant TEXT,
+        run_id TEXT,
+        created_at BIGINT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_executions_project ON executions(project);
+    CREATE INDEX IF NOT EXISTS idx_executions_source ON executions(source);
+    CREATE INDEX IF NOT EXISTS idx_executions_requester ON executions(requester);
+    CREATE INDEX IF NOT EXISTS idx_executions_created ON executions(created_at);
+    CREATE INDEX IF NOT EXISTS idx_executions_model ON executions(model);
+    CREATE INDEX IF NOT EXISTS idx_executions_project_created ON executions(project, created_at);
+    CREATE INDEX IF NOT EXISTS idx_executions_source_created ON executions(source, created_at);
+    CREATE INDEX IF NOT EXISTS idx_executions_agent ON executions(agent);
+    CREATE INDEX IF NOT EXISTS idx_executions_agent_revision ON executions(agent, agent_revision);
+
+    CREATE TABLE IF NOT EXISTS reactions (
+        id BIGSERIAL PRIMARY KEY,
+        execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+        user_id TEXT NOT NULL,
+        reaction TEXT NOT NULL,
+        category TEXT NOT NULL CHECK(category IN ('feedback', 'trigger', 'action')),
+        created_at BIGINT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_reactions_execution ON reactions(execution_id);
+    CREATE INDEX IF NOT EXISTS idx_reactions_category ON reactions(category);
+    CREATE INDEX IF NOT EXISTS idx_reactions_created ON reactions(created_at);
+
+    CREATE TABLE IF NOT EXISTS classification_logs (
+        id BIGSERIAL PRIMARY KEY,
+        text TEXT NOT NULL,
+        agent TEXT NOT NULL,
+        model TEXT,
+        confidence DOUBLE PRECISION NOT NULL,
+        method TEXT NOT NULL,
+        matched_keyword TEXT,
+        reasoning TEXT,
+        duration_ms BIGINT NOT NULL,
+        project TEXT,
+        source TEXT,
+        requester TEXT,
+        agent_revision BIGINT,
+        llm_error TEXT,
+        run_id TEXT,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_classification_agent ON classification_logs(agent, created_at DESC);
+    CREATE INDEX IF NOT EXISTS idx_classification_method ON classification_logs(method, created_at DESC);
+    CREATE INDEX IF NOT EXISTS idx_classification_created ON classification_logs(created_at DESC);
+
+    CREATE TABLE IF NOT EXISTS workflow_executions (
+        id BIGSERIAL PRIMARY KEY,
+        workflow_name TEXT NOT NULL,
+        execution_id TEXT,
+        status TEXT NOT NULL CHECK(status IN ('success', 'error', 'timeout')),
+        duration_ms BIGINT,
+        metadata TEXT,
+        run_id TEXT,
+        error TEXT,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_workflow_name ON workflow_executions(workflow_name, created_at DESC);
+    CREATE INDEX IF NOT EXISTS idx_workflow_created ON workflow_executions(created_at DESC);
+
+    CREATE TABLE IF NOT EXISTS webhook_deliveries (
+        id BIGSERIAL PRIMARY KEY,
+        run_id TEXT,
+        endpoint TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        channel TEXT,
+        status_code BIGINT,
+        error TEXT,
+        duration_ms BIGINT NOT NULL,
+        created_at BIGINT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_run ON webhook_deliveries(run_id);
+
+    CREATE TABLE IF NOT EXISTS user_contexts (
+        user_id TEXT PRIMARY KEY,
+        summary TEXT,
+        last_summarized_at BIGINT,
+        summary_locked_at BIGINT,
+        summary_lock_id TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS user_rules (
+        user_id TEXT NOT NULL,
+        rule TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        PRIMARY KEY (user_id, rule)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_user_rules_user ON user_rules(user_id);
+
+    CREATE TABLE IF NOT EXISTS projects (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        system_prompt TEXT,
+        allowed_tools TEXT,
+        disallowed_tools TEXT,
+        is_default BIGINT NOT NULL DEFAULT 0,
+        enable_user_context BIGINT NOT NULL DEFAULT 1,
+        fallback_agent TEXT NOT NULL DEFAULT 'general',
+        classify_model TEXT NOT NULL DEFAULT 'haiku',
+        classify_timeout BIGINT NOT NULL DEFAULT 30,
+        rate_limit_rpm BIGINT NOT NULL DEFAULT 0,
+        clarify_threshold DOUBLE PRECISION NOT NULL DEFAULT 0,
+        revision BIGINT NOT NULL DEFAULT 1,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE TABLE IF NOT EXISTS agents (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        model TEXT NOT NULL DEFAULT 'haiku',
+        priority BIGINT NOT NULL DEFAULT 50,
+        keywords TEXT NOT NULL DEFAULT '[]',
+        negative_keywords TEXT NOT NULL DEFAULT '[]',
+        examples TEXT NOT NULL DEFAULT '[]',
+        counterexamples TEXT NOT NULL DEFAULT '[]',
+        instruction TEXT,
+        tools TEXT,
+        output_schema TEXT,
+        timeout BIGINT NOT NULL DEFAULT 300,
+        static_response BIGINT NOT NULL DEFAULT 0,
+        working_dir TEXT,
+        revision BIGINT NOT NULL DEFAULT 1,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        UNIQUE(project_id, name)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_agents_project ON agents(project_id);
+    CREATE INDEX IF NOT EXISTS idx_agents_priority ON agents(project_id, priority DESC);
+
+    CREATE TABLE IF NOT EXISTS revisions (
+        id BIGSERIAL PRIMARY KEY,
+        entity_type TEXT NOT NULL CHECK(entity_type IN ('agent', 'project')),
+        entity_id TEXT NOT NULL,
+        revision BIGINT NOT NULL,
+        action TEXT NOT NULL,
+        snapshot TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        UNIQUE(entity_type, entity_id, revision)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_revisions_entity ON revisions(entity_type, entity_id, revision DESC);
+
+    CREATE TABLE IF NOT EXISTS execution_tags (
+        execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        created_by TEXT,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        PRIMARY KEY (execution_id, tag)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_execution_tags_tag ON execution_tags(tag);
+
+    CREATE TABLE IF NOT EXISTS execution_annotations (
+        id BIGSERIAL PRIMARY KEY,
+        execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+        author TEXT NOT NULL,
+        body TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_execution_annotations_execution ON execution_annotations(execution_id, created_at);
+
+    CREATE TABLE IF NOT EXISTS saved_views (
+        id TEXT PRIMARY KEY,
+        user_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        filter TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        UNIQUE(user_id, name)
+    );
+
+    CREATE TABLE IF NOT EXISTS eval_cases (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        text TEXT NOT NULL,
+        expected_agent TEXT NOT NULL,
+        source TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        UNIQUE(project_id, text)
+    );
+
+    CREATE TABLE IF NOT EXISTS eval_runs (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        stages TEXT NOT NULL,
+        total BIGINT NOT NULL,
+        correct BIGINT NOT NULL,
+        accuracy DOUBLE PRECISION NOT NULL,
+        results TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_eval_runs_project ON eval_runs(project_id, stages, created_at DESC);
+
+    CREATE TABLE IF NOT EXISTS routing_corrections (
+        execution_id TEXT PRIMARY KEY,
+        agent TEXT NOT NULL,
+        corrected_by TEXT,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE TABLE IF NOT EXISTS example_suggestions (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        agent TEXT NOT NULL,
+        text TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        execution_id TEXT,
+        status TEXT NOT NULL DEFAULT 'pending',
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        reviewed_at BIGINT,
+        UNIQUE(project_id, agent, text, kind)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_example_suggestions_status ON example_suggestions(project_id, status, created_at DESC);
+
+    CREATE TABLE IF NOT EXISTS experiments (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        agent TEXT NOT NULL,
+        name TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'running',
+        variants TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_experiments_agent ON experiments(project_id, agent, status);
+
+    CREATE TABLE IF NOT EXISTS experiment_assignments (
+        experiment_id TEXT NOT NULL REFERENCES experiments(id) ON DELETE CASCADE,
+        requester TEXT NOT NULL,
+        variant TEXT NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        PRIMARY KEY (experiment_id, requester)
+    );
+
+    CREATE TABLE IF NOT EXISTS budgets (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        scope TEXT NOT NULL,
+        target TEXT,
+        period TEXT NOT NULL,
+        limit_usd DOUBLE PRECISION NOT NULL,
+        action TEXT NOT NULL DEFAULT 'reject',
+        downgrade_model TEXT,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_budgets_project ON budgets(project_id);
+
+    CREATE TABLE IF NOT EXISTS budget_alerts (
+        budget_id TEXT NOT NULL REFERENCES budgets(id) ON DELETE CASCADE,
+        period_start BIGINT NOT NULL,
+        threshold BIGINT NOT NULL,
+        spent_usd DOUBLE PRECISION NOT NULL,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        PRIMARY KEY (budget_id, period_start, threshold)
+    );
+
+    CREATE TABLE IF NOT EXISTS reports (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        project TEXT,
+        schedule TEXT NOT NULL,
+        timezone TEXT,
+        period TEXT NOT NULL,
+        sections TEXT NOT NULL,
+        format TEXT NOT NULL DEFAULT 'mrkdwn',
+        destinations TEXT NOT NULL,
+        enabled BIGINT NOT NULL DEFAULT 1,
+        next_run_at BIGINT,
+        last_run_at BIGINT,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_reports_next_run ON reports(enabled, next_run_at);
+
+    CREATE TABLE IF NOT EXISTS report_runs (
+        id BIGSERIAL PRIMARY KEY,
+        report_id TEXT NOT NULL,
+        triggered_by TEXT NOT NULL,
+        status TEXT NOT NULL,
+        period_start BIGINT NOT NULL,
+        period_end BIGINT NOT NULL,
+        content TEXT NOT NULL,
+        deliveries TEXT NOT NULL,
+        error TEXT,
+        started_at BIGINT NOT NULL,
+        finished_at BIGINT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_report_runs_report ON report_runs(report_id, started_at DESC);
+
+    CREATE TABLE IF NOT EXISTS alerts (
+        id BIGSERIAL PRIMARY KEY,
+        project TEXT NOT NULL,
+        scope TEXT NOT NULL,
+        target TEXT NOT NULL DEFAULT '',
+        metric TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'open',
+        baseline DOUBLE PRECISION NOT NULL,
+        value DOUBLE PRECISION NOT NULL,
+        started_at BIGINT NOT NULL,
+        ended_at BIGINT,
+        updated_at BIGINT NOT NULL
+    );
+
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_alerts_open ON alerts(project, scope, target, metric) WHERE status = 'open';
+    CREATE INDEX IF NOT EXISTS idx_alerts_started ON alerts(started_at DESC);
+
+    CREATE TABLE IF NOT EXISTS execution_rollups (
+        bucket_start BIGINT NOT NULL,
+        project TEXT NOT NULL,
+        source TEXT NOT NULL DEFAULT '',
+        model TEXT NOT NULL DEFAULT '',
+        agent TEXT NOT NULL DEFAULT '',
+        requests BIGINT NOT NULL DEFAULT 0,
+        successful BIGINT NOT NULL DEFAULT 0,
+        cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+        input_tokens BIGINT NOT NULL DEFAULT 0,
+        output_tokens BIGINT NOT NULL DEFAULT 0,
+        cache_read_tokens BIGINT NOT NULL DEFAULT 0,
+        cache_creation_tokens BIGINT NOT NULL DEFAULT 0,
+        duration_sum BIGINT NOT NULL DEFAULT 0,
+        duration_count BIGINT NOT NULL DEFAULT 0,
+        PRIMARY KEY (bucket_start, project, source, model, agent)
+    );
+
+    CREATE TABLE IF NOT EXISTS execution_rollup_latency (
+        bucket_start BIGINT NOT NULL,
+        project TEXT NOT NULL,
+        source TEXT NOT NULL DEFAULT '',
+        model TEXT NOT NULL DEFAULT '',
+        agent TEXT NOT NULL DEFAULT '',
+        bin BIGINT NOT NULL,
+        hits BIGINT NOT NULL DEFAULT 0,
+        PRIMARY KEY (bucket_start, project, source, model, agent, bin)
+    );
+
+    CREATE TABLE IF NOT EXISTS semantic_embeddings (
+        agent_id TEXT NOT NULL,
+        example TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        model TEXT NOT NULL,
+        vector TEXT NOT NULL,
+        negative BIGINT NOT NULL DEFAULT 0,
+        created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+        PRIMARY KEY (agent_id, example)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_semantic_embeddings_project ON semantic_embeddings(project_id);
+";
+
+/// Columns added after the Postgres schema was introduced; `SCHEMA` already
+/// has them for fresh databases.
+const MIGRATIONS: &str = "
+    ALTER TABLE executions ADD COLUMN IF NOT EXISTS allowed_tools TEXT;
+    ALTER TABLE executions ADD COLUMN IF NOT EXISTS disallowed_tools TEXT;
+    ALTER TABLE executions ADD COLUMN IF NOT EXISTS rerun_of TEXT;
+    CREATE INDEX IF NOT EXISTS idx_executions_rerun_of ON executions(rerun_of);
+    ALTER TABLE agents ADD COLUMN IF NOT EXISTS negative_keywords TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE projects ADD COLUMN IF NOT EXISTS clarify_threshold DOUBLE PRECISION NOT NULL DEFAULT 0;
+    ALTER TABLE agents ADD COLUMN IF NOT EXISTS counterexamples TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE semantic_embeddings ADD COLUMN IF NOT EXISTS negative BIGINT NOT NULL DEFAULT 0;
+    ALTER TABLE classification_logs ADD COLUMN IF NOT EXISTS llm_error TEXT;
+    ALTER TABLE executions ADD COLUMN IF NOT EXISTS experiment_id TEXT;
+    ALTER TABLE executions ADD COLUMN IF NOT EXISTS variant TEXT;
+    CREATE INDEX IF NOT EXISTS idx_executions_experiment ON executions(experiment_id, variant);
+    ALTER TABLE executions ADD COLUMN IF NOT EXISTS run_id TEXT;
+    ALTER TABLE classification_logs ADD COLUMN IF NOT EXISTS run_id TEXT;
+    ALTER TABLE workflow_executions ADD COLUMN IF NOT EXISTS run_id TEXT;
+    ALTER TABLE workflow_executions ADD COLUMN IF NOT EXISTS error TEXT;
+    CREATE INDEX IF NOT EXISTS idx_executions_run ON executions(run_id);
+    CREATE INDEX IF NOT EXISTS idx_classification_run ON classification_logs(run_id);
+    CREATE INDEX IF NOT EXISTS idx_workflow_run ON workflow_executions(run_id);
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_placeholders() {
+        assert_eq!(
+            translate_placeholders("SELECT * FROM t WHERE a = ?1 AND b = ?12"),
+            "SELECT * FROM t WHERE a = $1 AND b = $12"
+        );
+        assert_eq!(
+            translate_placeholders("SELECT '?1' FROM t WHERE a IN (?, ?)"),
+            "SELECT '?1' FROM t WHERE a IN ($1, $2)"
+        );
+    }
+}
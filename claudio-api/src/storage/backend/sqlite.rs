@@ -0,0 +1,584 @@
+use anyhow::Result;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::{ToSqlOutput, ValueRef};
+use std::path::Path;
+
+use super::{Backend, Connection, Dialect, PoolState, Row, Value};
+
+pub struct SqliteBackend {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteBackend {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let manager = SqliteConnectionManager::file(path);
+        let pool = Pool::builder().max_size(10).build(manager)?;
+
+        {
+            let conn = pool.get()?;
+            conn.execute_batch(
+                "PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL; PRAGMA foreign_keys=ON;",
+            )?;
+        }
+
+        let conn = pool.get()?;
+        init_schema(&conn)?;
+        migrate(&conn)?;
+
+        Ok(Self { pool })
+    }
+}
+
+impl Backend for SqliteBackend {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    fn connect(&self) -> Result<Box<dyn Connection>> {
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| anyhow::anyhow!("Failed to get DB connection: {}", e))?;
+        Ok(Box::new(SqliteConnection(conn)))
+    }
+
+    fn pool_state(&self) -> PoolState {
+        PoolState::of(&self.pool)
+    }
+}
+
+struct SqliteConnection(PooledConnection<SqliteConnectionManager>);
+
+impl Connection for SqliteConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<usize> {
+        let mut stmt = self.0.prepare_cached(sql)?;
+        Ok(stmt.execute(rusqlite::params_from_iter(params))?)
+    }
+
+    fn query(&self, sql: &str, params: &[Value]) -> Result<Vec<Row>> {
+        let mut stmt = self.0.prepare_cached(sql)?;
+        let columns = stmt.column_count();
+        let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let values = (0..columns)
+                .map(|i| row.get_ref(i).map(from_value_ref))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            result.push(Row::new(values));
+        }
+        Ok(result)
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<()> {
+        Ok(self.0.execute_batch(sql)?)
+    }
+}
+
+impl rusqlite::ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            Value::Null => ValueRef::Null,
+            Value::Integer(i) => ValueRef::Integer(*i),
+            Value::Real(f) => ValueRef::Real(*f),
+            Value::Text(s) => ValueRef::Text(s.as_bytes()),
+        }))
+    }
+}
+
+fn from_value_ref(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::Integer(i),
+        ValueRef::Real(f) => Value::Real(f),
+        ValueRef::Text(t) | ValueRef::Blob(t) => Value::Text(String::from_utf8_lossy(t).into()),
+    }
+}
+
+fn init_schema(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS executions (
+            id TEXT PRIMARY KEY,
+            project TEXT NOT NULL,
+            source TEXT,
+            requester TEXT,
+            agent TEXT,
+            instruction TEXT,
+            user_message TEXT NOT NULL,
+            user_context TEXT,
+            response TEXT NOT NULL,
+            structured_output TEXT,
+            model TEXT,
+            cost_usd REAL,
+            input_tokens INTEGER,
+            output_tokens INTEGER,
+            cache_read_tokens INTEGER,
+            cache_creation_tokens INTEGER,
+            duration_ms INTEGER,
+            duration_api_ms INTEGER,
+            session_id TEXT,
+            metadata TEXT,
+            agent_revision INTEGER,
+            allowed_tools TEXT,
+            disallowed_tools TEXT,
+            rerun_of TEXT,
+            experiment_id TEXT,
+            variant TEXT,
+            run_id TEXT,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_executions_project ON executions(project);
+        CREATE INDEX IF NOT EXISTS idx_executions_source ON executions(source);
+        CREATE INDEX IF NOT EXISTS idx_executions_requester ON executions(requester);
+        CREATE INDEX IF NOT EXISTS idx_executions_created ON executions(created_at);
+        CREATE INDEX IF NOT EXISTS idx_executions_model ON executions(model);
+        CREATE INDEX IF NOT EXISTS idx_executions_project_created ON executions(project, created_at);
+        CREATE INDEX IF NOT EXISTS idx_executions_source_created ON executions(source, created_at);
+        CREATE INDEX IF NOT EXISTS idx_executions_agent ON executions(agent);
+
+        CREATE TABLE IF NOT EXISTS reactions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            execution_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            reaction TEXT NOT NULL,
+            category TEXT NOT NULL CHECK(category IN ('feedback', 'trigger', 'action')),
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (execution_id) REFERENCES executions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_reactions_execution ON reactions(execution_id);
+        CREATE INDEX IF NOT EXISTS idx_reactions_category ON reactions(category);
+        CREATE INDEX IF NOT EXISTS idx_reactions_created ON reactions(created_at);
+
+        CREATE TABLE IF NOT EXISTS classification_logs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            text TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            model TEXT,
+            confidence REAL NOT NULL,
+            method TEXT NOT NULL,
+            matched_keyword TEXT,
+            reasoning TEXT,
+            duration_ms INTEGER NOT NULL,
+            project TEXT,
+            source TEXT,
+            requester TEXT,
+            agent_revision INTEGER,
+            llm_error TEXT,
+            run_id TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_classification_agent ON classification_logs(agent, created_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_classification_method ON classification_logs(method, created_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_classification_created ON classification_logs(created_at DESC);
+
+        CREATE TABLE IF NOT EXISTS workflow_executions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            workflow_name TEXT NOT NULL,
+            execution_id TEXT,
+            status TEXT NOT NULL CHECK(status IN ('success', 'error', 'timeout')),
+            duration_ms INTEGER,
+            metadata TEXT,
+            run_id TEXT,
+            error TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_workflow_name ON workflow_executions(workflow_name, created_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_workflow_created ON workflow_executions(created_at DESC);
+
+        CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id TEXT,
+            endpoint TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            channel TEXT,
+            status_code INTEGER,
+            error TEXT,
+            duration_ms INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_run ON webhook_deliveries(run_id);
+
+        CREATE TABLE IF NOT EXISTS user_contexts (
+            user_id TEXT PRIMARY KEY,
+            summary TEXT,
+            last_summarized_at INTEGER,
+            summary_locked_at INTEGER,
+            summary_lock_id TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS user_rules (
+            user_id TEXT NOT NULL,
+            rule TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (user_id, rule)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_user_rules_user ON user_rules(user_id);
+
+        CREATE TABLE IF NOT EXISTS projects (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            system_prompt TEXT,
+            allowed_tools TEXT,
+            disallowed_tools TEXT,
+            is_default INTEGER NOT NULL DEFAULT 0,
+            enable_user_context INTEGER NOT NULL DEFAULT 1,
+            fallback_agent TEXT NOT NULL DEFAULT 'general',
+            classify_model TEXT NOT NULL DEFAULT 'haiku',
+            classify_timeout INTEGER NOT NULL DEFAULT 30,
+            rate_limit_rpm INTEGER NOT NULL DEFAULT 0,
+            clarify_threshold REAL NOT NULL DEFAULT 0,
+            revision INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS agents (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            model TEXT NOT NULL DEFAULT 'haiku',
+            priority INTEGER NOT NULL DEFAULT 50,
+            keywords TEXT NOT NULL DEFAULT '[]',
+            negative_keywords TEXT NOT NULL DEFAULT '[]',
+            examples TEXT NOT NULL DEFAULT '[]',
+            counterexamples TEXT NOT NULL DEFAULT '[]',
+            instruction TEXT,
+            tools TEXT,
+            output_schema TEXT,
+            timeout INTEGER NOT NULL DEFAULT 300,
+            static_response INTEGER NOT NULL DEFAULT 0,
+            working_dir TEXT,
+            revision INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            UNIQUE(project_id, name)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_agents_project ON agents(project_id);
+        CREATE INDEX IF NOT EXISTS idx_agents_priority ON agents(project_id, priority DESC);
+
+        CREATE TABLE IF NOT EXISTS revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_type TEXT NOT NULL CHECK(entity_type IN ('agent', 'project')),
+            entity_id TEXT NOT NULL,
+            revision INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            snapshot TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            UNIQUE(entity_type, entity_id, revision)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_revisions_entity ON revisions(entity_type, entity_id, revision DESC);
+
+        CREATE TABLE IF NOT EXISTS execution_tags (
+            execution_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            created_by TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (execution_id, tag),
+            FOREIGN KEY (execution_id) REFERENCES executions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_execution_tags_tag ON execution_tags(tag);
+
+        CREATE TABLE IF NOT EXISTS execution_annotations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            execution_id TEXT NOT NULL,
+            author TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            FOREIGN KEY (execution_id) REFERENCES executions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_execution_annotations_execution ON execution_annotations(execution_id, created_at);
+
+        CREATE TABLE IF NOT EXISTS saved_views (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            filter TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            UNIQUE(user_id, name)
+        );
+
+        CREATE TABLE IF NOT EXISTS eval_cases (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            text TEXT NOT NULL,
+            expected_agent TEXT NOT NULL,
+            source TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            UNIQUE(project_id, text)
+        );
+
+        CREATE TABLE IF NOT EXISTS eval_runs (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            stages TEXT NOT NULL,
+            total INTEGER NOT NULL,
+            correct INTEGER NOT NULL,
+            accuracy REAL NOT NULL,
+            results TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_eval_runs_project ON eval_runs(project_id, stages, created_at DESC);
+
+        CREATE TABLE IF NOT EXISTS routing_corrections (
+            execution_id TEXT PRIMARY KEY,
+            agent TEXT NOT NULL,
+            corrected_by TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS example_suggestions (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            agent TEXT NOT NULL,
+            text TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            execution_id TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            reviewed_at INTEGER,
+            UNIQUE(project_id, agent, text, kind)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_example_suggestions_status ON example_suggestions(project_id, status, created_at DESC);
+
+        CREATE TABLE IF NOT EXISTS experiments (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+            agent TEXT NOT NULL,
+            name TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'running',
+            variants TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_experiments_agent ON experiments(project_id, agent, status);
+
+        CREATE TABLE IF NOT EXISTS experiment_assignments (
+            experiment_id TEXT NOT NULL REFERENCES experiments(id) ON DELETE CASCADE,
+            requester TEXT NOT NULL,
+            variant TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (experiment_id, requester)
+        );
+
+        CREATE TABLE IF NOT EXISTS budgets (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+            scope TEXT NOT NULL,
+            target TEXT,
+            period TEXT NOT NULL,
+            limit_usd REAL NOT NULL,
+            action TEXT NOT NULL DEFAULT 'reject',
+            downgrade_model TEXT,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_budgets_project ON budgets(project_id);
+
+        CREATE TABLE IF NOT EXISTS budget_alerts (
+            budget_id TEXT NOT NULL REFERENCES budgets(id) ON DELETE CASCADE,
+            period_start INTEGER NOT NULL,
+            threshold INTEGER NOT NULL,
+            spent_usd REAL NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (budget_id, period_start, threshold)
+        );
+
+        CREATE TABLE IF NOT EXISTS reports (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            project TEXT,
+            schedule TEXT NOT NULL,
+            timezone TEXT,
+            period TEXT NOT NULL,
+            sections TEXT NOT NULL,
+            format TEXT NOT NULL DEFAULT 'mrkdwn',
+            destinations TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            next_run_at INTEGER,
+            last_run_at INTEGER,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_reports_next_run ON reports(enabled, next_run_at);
+
+        CREATE TABLE IF NOT EXISTS report_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            report_id TEXT NOT NULL,
+            triggered_by TEXT NOT NULL,
+            status TEXT NOT NULL,
+            period_start INTEGER NOT NULL,
+            period_end INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            deliveries TEXT NOT NULL,
+            error TEXT,
+            started_at INTEGER NOT NULL,
+            finished_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_report_runs_report ON report_runs(report_id, started_at DESC);
+
+        CREATE TABLE IF NOT EXISTS alerts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            target TEXT NOT NULL DEFAULT '',
+            metric TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'open',
+            baseline REAL NOT NULL,
+            value REAL NOT NULL,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_alerts_open ON alerts(project, scope, target, metric) WHERE status = 'open';
+        CREATE INDEX IF NOT EXISTS idx_alerts_started ON alerts(started_at DESC);
+
+        CREATE TABLE IF NOT EXISTS execution_rollups (
+            bucket_start INTEGER NOT NULL,
+            project TEXT NOT NULL,
+            source TEXT NOT NULL DEFAULT '',
+            model TEXT NOT NULL DEFAULT '',
+            agent TEXT NOT NULL DEFAULT '',
+            requests INTEGER NOT NULL DEFAULT 0,
+            successful INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL NOT NULL DEFAULT 0,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            duration_sum INTEGER NOT NULL DEFAULT 0,
+            duration_count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (bucket_start, project, source, model, agent)
+        );
+
+        CREATE TABLE IF NOT EXISTS execution_rollup_latency (
+            bucket_start INTEGER NOT NULL,
+            project TEXT NOT NULL,
+            source TEXT NOT NULL DEFAULT '',
+            model TEXT NOT NULL DEFAULT '',
+            agent TEXT NOT NULL DEFAULT '',
+            bin INTEGER NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (bucket_start, project, source, model, agent, bin)
+        );
+
+        CREATE TABLE IF NOT EXISTS semantic_embeddings (
+            agent_id TEXT NOT NULL,
+            example TEXT NOT NULL,
+            project_id TEXT NOT NULL,
+            model TEXT NOT NULL,
+            vector TEXT NOT NULL,
+            negative INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (agent_id, example)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_semantic_embeddings_project ON semantic_embeddings(project_id);
+        ",
+    )?;
+    Ok(())
+}
+
+/// Adds columns introduced after the initial schema to existing databases.
+fn migrate(conn: &rusqlite::Connection) -> Result<()> {
+    add_column_if_missing(conn, "executions", "agent_revision", "INTEGER")?;
+    add_column_if_missing(conn, "classification_logs", "agent_revision", "INTEGER")?;
+    add_column_if_missing(conn, "projects", "revision", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "agents", "revision", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "executions", "allowed_tools", "TEXT")?;
+    add_column_if_missing(conn, "executions", "disallowed_tools", "TEXT")?;
+    add_column_if_missing(conn, "executions", "rerun_of", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "agents",
+        "negative_keywords",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    add_column_if_missing(
+        conn,
+        "projects",
+        "clarify_threshold",
+        "REAL NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        conn,
+        "agents",
+        "counterexamples",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    add_column_if_missing(
+        conn,
+        "semantic_embeddings",
+        "negative",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(conn, "classification_logs", "llm_error", "TEXT")?;
+    add_column_if_missing(conn, "executions", "experiment_id", "TEXT")?;
+    add_column_if_missing(conn, "executions", "variant", "TEXT")?;
+    add_column_if_missing(conn, "executions", "run_id", "TEXT")?;
+    add_column_if_missing(conn, "classification_logs", "run_id", "TEXT")?;
+    add_column_if_missing(conn, "workflow_executions", "run_id", "TEXT")?;
+    add_column_if_missing(conn, "workflow_executions", "error", "TEXT")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_executions_agent_revision ON executions(agent, agent_revision);
+         CREATE INDEX IF NOT EXISTS idx_executions_rerun_of ON executions(rerun_of);
+         CREATE INDEX IF NOT EXISTS idx_executions_experiment ON executions(experiment_id, variant);
+         CREATE INDEX IF NOT EXISTS idx_executions_run ON executions(run_id);
+         CREATE INDEX IF NOT EXISTS idx_classification_run ON classification_logs(run_id);
+         CREATE INDEX IF NOT EXISTS idx_workflow_run ON workflow_executions(run_id);",
+    )?;
+    conn.execute_batch(&Dialect::Sqlite.metadata_indexes())?;
+    Ok(())
+}
+
+fn add_column_if_missing(
+    conn: &rusqlite::Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists: bool = conn.query_row(
+        &format!(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1)",
+            table
+        ),
+        [column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))?;
+    }
+    Ok(())
+}
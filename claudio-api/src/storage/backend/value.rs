@@ -0,0 +1,192 @@
+use anyhow::{Result, anyhow};
+
+/// A dialect-neutral SQL value used for both bound parameters and result columns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "NULL",
+            Self::Integer(_) => "INTEGER",
+            Self::Real(_) => "REAL",
+            Self::Text(_) => "TEXT",
+        }
+    }
+}
+
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+impl ToValue for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        Value::Text(self.to_string())
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::Text(self.clone())
+    }
+}
+
+impl ToValue for i64 {
+    fn to_value(&self) -> Value {
+        Value::Integer(*self)
+    }
+}
+
+impl ToValue for i32 {
+    fn to_value(&self) -> Value {
+        Value::Integer(i64::from(*self))
+    }
+}
+
+impl ToValue for u32 {
+    fn to_value(&self) -> Value {
+        Value::Integer(i64::from(*self))
+    }
+}
+
+impl ToValue for f64 {
+    fn to_value(&self) -> Value {
+        Value::Real(*self)
+    }
+}
+
+impl ToValue for bool {
+    fn to_value(&self) -> Value {
+        Value::Integer(i64::from(*self))
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        self.as_ref().map_or(Value::Null, ToValue::to_value)
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+/// Integer columns also accept whole-number reals, since Postgres widens
+/// integer aggregates (`SUM`, division) to `NUMERIC`.
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Integer(i) => Ok(*i),
+            Value::Real(f) => Ok(*f as i64),
+            other => Err(anyhow!("expected INTEGER, found {}", other.type_name())),
+        }
+    }
+}
+
+impl FromValue for i32 {
+    fn from_value(value: &Value) -> Result<Self> {
+        let wide = i64::from_value(value)?;
+        i32::try_from(wide).map_err(|_| anyhow!("integer {} out of range for i32", wide))
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Real(f) => Ok(*f),
+            Value::Integer(i) => Ok(*i as f64),
+            other => Err(anyhow!("expected REAL, found {}", other.type_name())),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(i64::from_value(value)? != 0)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Text(s) => Ok(s.clone()),
+            other => Err(anyhow!("expected TEXT, found {}", other.type_name())),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+/// A materialized result row.
+#[derive(Debug, Clone)]
+pub struct Row(Vec<Value>);
+
+impl Row {
+    pub fn new(values: Vec<Value>) -> Self {
+        Self(values)
+    }
+
+    pub fn get<T: FromValue>(&self, idx: usize) -> Result<T> {
+        let value = self
+            .0
+            .get(idx)
+            .ok_or_else(|| anyhow!("column index {} out of range", idx))?;
+        T::from_value(value).map_err(|e| anyhow!("column {}: {}", idx, e))
+    }
+}
+
+/// Builds a `&[Value]` parameter slice, mirroring `rusqlite::params!`.
+macro_rules! params {
+    () => {
+        &[] as &[$crate::storage::Value]
+    };
+    ($($param:expr),+ $(,)?) => {
+        &[$($crate::storage::ToValue::to_value(&$param)),+] as &[$crate::storage::Value]
+    };
+}
+
+pub(crate) use params;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_value_widens_and_narrows() {
+        assert_eq!(f64::from_value(&Value::Integer(3)).unwrap(), 3.0);
+        assert_eq!(i64::from_value(&Value::Real(42.0)).unwrap(), 42);
+        assert!(i32::from_value(&Value::Integer(i64::MAX)).is_err());
+        assert_eq!(Option::<String>::from_value(&Value::Null).unwrap(), None);
+        assert!(bool::from_value(&Value::Integer(1)).unwrap());
+    }
+}
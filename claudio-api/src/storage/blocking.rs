@@ -0,0 +1,183 @@
+use anyhow::{Result, anyhow};
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::backend::{Connection, PoolState};
+use super::core::Storage;
+use crate::config::StorageConfig;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Returned when every worker is busy and the queue is full.
+#[derive(Debug)]
+pub struct Overloaded;
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Storage queue is full")
+    }
+}
+
+impl std::error::Error for Overloaded {}
+
+/// Fixed set of OS threads fed by a bounded queue. Jobs beyond `queue_size`
+/// waiting for a worker are rejected with [`Overloaded`] instead of piling up.
+#[derive(Clone)]
+pub struct BlockingPool {
+    sender: SyncSender<Job>,
+}
+
+impl BlockingPool {
+    pub fn new(name: &str, workers: usize, queue_size: usize) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..workers.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("{}-{}", name, i))
+                .spawn(move || worker_loop(&receiver))
+                .expect("Failed to spawn blocking pool worker");
+        }
+
+        Self { sender }
+    }
+
+    /// Runs `f` on a pool thread and waits for its result without blocking
+    /// the async runtime.
+    pub async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(f());
+        });
+
+        self.sender.try_send(job).map_err(|e| match e {
+            TrySendError::Full(_) => anyhow::Error::new(Overloaded),
+            TrySendError::Disconnected(_) => anyhow!("Blocking pool has shut down"),
+        })?;
+
+        rx.await.map_err(|_| anyhow!("Blocking pool job panicked"))
+    }
+}
+
+fn worker_loop(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else { return };
+
+        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+            tracing::error!("Blocking pool job panicked");
+        }
+    }
+}
+
+/// Async facade over [`Storage`]: every call runs on a dedicated
+/// [`BlockingPool`] so handlers never block tokio worker threads.
+#[derive(Clone)]
+pub struct AsyncStorage {
+    storage: Storage,
+    pool: BlockingPool,
+}
+
+impl AsyncStorage {
+    pub fn new(storage: Storage, config: &StorageConfig) -> Self {
+        Self {
+            storage,
+            pool: BlockingPool::new("claudio-storage", config.workers, config.queue_size),
+        }
+    }
+
+    pub async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Storage) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let storage = self.storage.clone();
+        // Spans opened on the worker belong to the caller's trace
+        let span = tracing::Span::current();
+        self.pool.run(move || span.in_scope(|| f(&storage))).await?
+    }
+
+    /// [`run`](Self::run) for a write, traced as its own span named after
+    /// `operation`.
+    pub async fn write<F, T>(&self, operation: &'static str, f: F) -> Result<T>
+    where
+        F: FnOnce(&Storage) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let span = tracing::info_span!(
+            "storage.write",
+            otel.name = operation,
+            db.operation.name = operation
+        );
+        self.run(move |s| span.in_scope(|| f(s))).await
+    }
+
+    pub fn pool_state(&self) -> PoolState {
+        self.storage.pool_state()
+    }
+
+    pub async fn with_connection<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&dyn Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.run(move |s| s.with_connection(f)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pool_rejects_when_queue_is_full() {
+        let pool = BlockingPool::new("test-pool", 1, 1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+
+        let busy = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(move || {
+                    let _ = started_tx.send(());
+                    release_rx.recv().ok();
+                })
+                .await
+            }
+        });
+        started_rx.await.unwrap();
+
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 42).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let rejected = pool.run(|| 0).await.unwrap_err();
+        assert!(rejected.downcast_ref::<Overloaded>().is_some());
+
+        release_tx.send(()).unwrap();
+        busy.await.unwrap().unwrap();
+        assert_eq!(queued.await.unwrap().unwrap(), 42);
+        assert_eq!(pool.run(|| 7).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_pool_survives_panicking_job() {
+        let pool = BlockingPool::new("test-pool", 1, 4);
+        assert!(pool.run(|| panic!("boom")).await.is_err());
+        assert_eq!(pool.run(|| 1).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{Result, anyhow};
+
+use super::backend::{Row, ToValue, Value, params};
+use super::core::Storage;
+use super::types::{Budget, BudgetAction, BudgetPeriod, BudgetScope, CreateBudget, UpdateBudget};
+
+const BUDGET_COLUMNS: &str = "id, project_id, scope, target, period, limit_usd, action, downgrade_model, created_at, updated_at";
+
+fn map_budget_row(row: &Row) -> Result<Budget> {
+    let scope: String = row.get(2)?;
+    let period: String = row.get(4)?;
+    let action: String = row.get(6)?;
+    Ok(Budget {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        scope: BudgetScope::parse(&scope).ok_or_else(|| anyhow!("unknown scope '{}'", scope))?,
+        target: row.get(3)?,
+        period: BudgetPeriod::parse(&period)
+            .ok_or_else(|| anyhow!("unknown period '{}'", period))?,
+        limit_usd: row.get(5)?,
+        action: BudgetAction::parse(&action)
+            .ok_or_else(|| anyhow!("unknown action '{}'", action))?,
+        downgrade_model: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+impl Storage {
+    pub fn list_budgets(&self, project_id: &str) -> Result<Vec<Budget>> {
+        let conn = self.conn()?;
+        let budgets = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM budgets WHERE project_id = ?1 ORDER BY scope, target, period",
+                    BUDGET_COLUMNS
+                ),
+                params![project_id],
+                map_budget_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(budgets)
+    }
+
+    pub fn get_budget(&self, id: &str) -> Result<Option<Budget>> {
+        let conn = self.conn()?;
+        let budget = conn
+            .query_map(
+                &format!("SELECT {} FROM budgets WHERE id = ?1", BUDGET_COLUMNS),
+                params![id],
+                map_budget_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(budget)
+    }
+
+    /// Budgets of a project that apply to a request from `requester` routed
+    /// to `agent`.
+    pub fn applicable_budgets(
+        &self,
+        project_id: &str,
+        agent: Option<&str>,
+        requester: Option<&str>,
+    ) -> Result<Vec<Budget>> {
+        Ok(self
+            .list_budgets(project_id)?
+            .into_iter()
+            .filter(|b| match b.scope {
+                BudgetScope::Project => true,
+                BudgetScope::Agent => b.target.is_some() && b.target.as_deref() == agent,
+                BudgetScope::Requester => b.target.is_some() && b.target.as_deref() == requester,
+            })
+            .collect())
+    }
+
+    pub fn create_budget(&self, project_id: &str, input: &CreateBudget) -> Result<Budget> {
+        let conn = self.conn()?;
+        let now = chrono::Utc::now().timestamp();
+        let budget = Budget {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_id: project_id.to_string(),
+            scope: input.scope,
+            target: match input.scope {
+                BudgetScope::Project => None,
+                _ => input.target.clone(),
+            },
+            period: input.period,
+            limit_usd: input.limit_usd,
+            action: input.action,
+            downgrade_model: input.downgrade_model.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        conn.execute(
+            "INSERT INTO budgets (id, project_id, scope, target, period, limit_usd, action, downgrade_model, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                budget.id,
+                budget.project_id,
+                budget.scope.as_str(),
+                budget.target,
+                budget.period.as_str(),
+                budget.limit_usd,
+                budget.action.as_str(),
+                budget.downgrade_model,
+                budget.created_at,
+                budget.updated_at
+            ],
+        )?;
+        Ok(budget)
+    }
+
+    pub fn update_budget(&self, id: &str, input: &UpdateBudget) -> Result<Option<Budget>> {
+        let conn = self.conn()?;
+        let mut sets = vec!["updated_at = ?1".to_string()];
+        let mut params: Vec<Value> = vec![Value::Integer(chrono::Utc::now().timestamp())];
+        if let Some(period) = input.period {
+            params.push(period.as_str().to_value());
+            sets.push(format!("period = ?{}", params.len()));
+        }
+        if let Some(limit_usd) = input.limit_usd {
+            params.push(Value::Real(limit_usd));
+            sets.push(format!("limit_usd = ?{}", params.len()));
+        }
+        if let Some(action) = input.action {
+            params.push(action.as_str().to_value());
+            sets.push(format!("action = ?{}", params.len()));
+        }
+        if let Some(ref model) = input.downgrade_model {
+            params.push(model.to_value());
+            sets.push(format!("downgrade_model = ?{}", params.len()));
+        }
+        params.push(id.to_value());
+        let sql = format!(
+            "UPDATE budgets SET {} WHERE id = ?{}",
+            sets.join(", "),
+            params.len()
+        );
+        if conn.execute(&sql, &params)? == 0 {
+            return Ok(None);
+        }
+        self.get_budget(id)
+    }
+
+    pub fn delete_budget(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        conn.execute(
+            "DELETE FROM budget_alerts WHERE budget_id = ?1",
+            params![id],
+        )?;
+        let deleted = conn.execute("DELETE FROM budgets WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Cost of the executions a budget covers since `since`.
+    pub fn budget_spend(&self, budget: &Budget, since: i64) -> Result<f64> {
+        let conn = self.conn()?;
+        let filter = match budget.scope {
+            BudgetScope::Project => "",
+            BudgetScope::Agent => " AND agent = ?3",
+            BudgetScope::Requester => " AND requester = ?3",
+        };
+        let sql = format!(
+            "SELECT COALESCE(SUM(cost_usd), 0) FROM executions
+             WHERE project = ?1 AND created_at >= ?2{}",
+            filter
+        );
+        let spent = match budget.target {
+            Some(ref target) if budget.scope != BudgetScope::Project => {
+                conn.query_row(&sql, params![budget.project_id, since, target], |row| {
+                    row.get(0)
+                })?
+            }
+            _ => conn.query_row(&sql, params![budget.project_id, since], |row| row.get(0))?,
+        };
+        Ok(spent)
+    }
+
+    /// Remembers that `threshold` percent of a budget was crossed in the
+    /// period starting at `period_start`. Returns `false` when it already
+    /// was, so each alert goes out once per period.
+    pub fn record_budget_alert(
+        &self,
+        budget_id: &str,
+        period_start: i64,
+        threshold: u32,
+        spent_usd: f64,
+    ) -> Result<bool> {
+        let conn = self.conn()?;
+        let inserted = conn.execute(
+            "INSERT INTO budget_alerts (budget_id, period_start, threshold, spent_usd, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(budget_id, period_start, threshold) DO NOTHING",
+            params![
+                budget_id,
+                period_start,
+                i64::from(threshold),
+                spent_usd,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+}
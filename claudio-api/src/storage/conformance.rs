@@ -0,0 +1,1444 @@
+//! Behaviour every storage backend must share. The SQLite run always executes;
+//! the Postgres run needs `CLAUDIO_TEST_POSTGRES_URL` (for example the
+//! `postgres` service in docker-compose) and is skipped otherwise.
+
+use serde_json::json;
+
+use super::backend::PostgresBackend;
+use super::*;
+use crate::analytics::{
+    self, Granularity, Period, RecordWorkflowRequest, TimeRange, WorkflowStatus,
+};
+
+const POSTGRES_URL_ENV: &str = "CLAUDIO_TEST_POSTGRES_URL";
+
+#[test]
+fn test_sqlite_conformance() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = Storage::new(dir.path().join("conformance.db")).unwrap();
+    run_suite(&storage);
+}
+
+#[test]
+fn test_postgres_conformance() {
+    let Ok(url) = std::env::var(POSTGRES_URL_ENV) else {
+        eprintln!(
+            "{} not set, skipping Postgres conformance run",
+            POSTGRES_URL_ENV
+        );
+        return;
+    };
+
+    // Each run gets a throwaway schema so concurrent runs don't collide.
+    let schema = format!("claudio_conformance_{}", uuid::Uuid::new_v4().simple());
+    let mut admin = postgres::Client::connect(&url, postgres::NoTls).unwrap();
+    admin
+        .batch_execute(&format!("CREATE SCHEMA {}", schema))
+        .unwrap();
+
+    let mut config: postgres::Config = url.parse().unwrap();
+    config.options(&format!("-c search_path={}", schema));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let storage = Storage::with_backend(PostgresBackend::new(config).unwrap());
+        run_suite(&storage);
+    }));
+
+    admin
+        .batch_execute(&format!("DROP SCHEMA {} CASCADE", schema))
+        .unwrap();
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+fn run_suite(storage: &Storage) {
+    projects_and_agents(storage);
+    executions_and_reactions(storage);
+    tags_annotations_and_views(storage);
+    eval_cases_and_runs(storage);
+    semantic_embeddings(storage);
+    user_context(storage);
+    analytics_queries(storage);
+    example_suggestions(storage);
+    experiments(storage);
+    budgets(storage);
+    reports(storage);
+    alerts(storage);
+    rollups(storage);
+    workflow_runs(storage);
+}
+
+fn execution(id: &str, requester: &str, model: &str, response: &str, created_at: i64) -> Execution {
+    Execution {
+        id: id.to_string(),
+        project: "demo".to_string(),
+        source: Some("slack".to_string()),
+        requester: Some(requester.to_string()),
+        agent: Some("ops".to_string()),
+        instruction: None,
+        user_message: format!("question {}", id),
+        user_context: None,
+        response: response.to_string(),
+        structured_output: None,
+        model: Some(model.to_string()),
+        cost_usd: Some(0.5),
+        input_tokens: Some(100),
+        output_tokens: Some(51),
+        cache_read_tokens: Some(10),
+        cache_creation_tokens: Some(30),
+        duration_ms: Some(1000),
+        duration_api_ms: Some(900),
+        session_id: None,
+        metadata: Some(json!({"channel": "C1", "thread_ts": id}).to_string()),
+        agent_revision: Some(1),
+        allowed_tools: Some(vec!["Read".to_string()]),
+        disallowed_tools: None,
+        rerun_of: None,
+        experiment_id: None,
+        variant: None,
+        run_id: None,
+        created_at,
+    }
+}
+
+fn projects_and_agents(storage: &Storage) {
+    let project: CreateProject =
+        serde_json::from_value(json!({"name": "Demo", "is_default": true})).unwrap();
+    let project = storage.create_project(project).unwrap();
+    assert_eq!(project.id, "demo");
+    assert!(
+        storage
+            .create_project(serde_json::from_value(json!({"name": "Demo"})).unwrap())
+            .is_err()
+    );
+    assert_eq!(storage.get_default_project().unwrap().unwrap().id, "demo");
+
+    let update: UpdateProject =
+        serde_json::from_value(json!({"rate_limit_rpm": 30, "allowed_tools": ["Read"]})).unwrap();
+    let updated = storage.update_project("demo", &update).unwrap().unwrap();
+    assert_eq!(updated.rate_limit_rpm, 30);
+    assert_eq!(updated.revision, 2);
+    assert!(
+        storage
+            .update_project("missing", &update)
+            .unwrap()
+            .is_none()
+    );
+
+    let agent: CreateAgent = serde_json::from_value(json!({
+        "name": "ops",
+        "description": "Operations",
+        "keywords": ["deploy"],
+        "negative_keywords": [{"pattern": "docs", "whole_word": true}],
+        "priority": 80,
+        "static_response": true
+    }))
+    .unwrap();
+    let agent = storage.create_agent("demo", agent).unwrap();
+    assert_eq!(agent.id, "demo-ops");
+
+    let update: UpdateAgent =
+        serde_json::from_value(json!({"keywords": ["deploy", "rollout"]})).unwrap();
+    storage.update_agent(&agent.id, &update).unwrap().unwrap();
+    let restored = storage.rollback_agent(&agent.id, 1).unwrap().unwrap();
+    assert_eq!(restored.revision, 3);
+    assert_eq!(restored.keywords, vec!["deploy".to_string()]);
+    assert!(restored.static_response);
+
+    let fetched = storage.get_agent_by_name("demo", "ops").unwrap().unwrap();
+    assert_eq!(fetched.priority, 80);
+    assert!(fetched.negative_keywords[0].whole_word);
+    assert_eq!(storage.list_agents("demo").unwrap().len(), 1);
+
+    let revisions = storage
+        .list_revisions(RevisionKind::Agent, &agent.id)
+        .unwrap();
+    assert_eq!(revisions.len(), 3);
+    assert_eq!(revisions[0].changed_fields, vec!["keywords".to_string()]);
+    let diff = storage
+        .diff_revisions(RevisionKind::Project, "demo", 1, 2)
+        .unwrap()
+        .unwrap();
+    assert_eq!(diff.changes.len(), 2);
+
+    let scratch = storage
+        .create_project(serde_json::from_value(json!({"name": "Scratch"})).unwrap())
+        .unwrap();
+    assert!(storage.delete_project("demo").is_err());
+    assert!(storage.delete_project(&scratch.id).unwrap());
+    assert_eq!(storage.list_projects().unwrap().len(), 1);
+}
+
+fn executions_and_reactions(storage: &Storage) {
+    let now = chrono::Utc::now().timestamp();
+    storage
+        .save(&execution(
+            "e1",
+            "alice",
+            "claude-sonnet-4-5",
+            "ok",
+            now - 30,
+        ))
+        .unwrap();
+    storage
+        .save(&execution("e2", "alice", "claude-haiku", "ok", now - 20))
+        .unwrap();
+    let mut rerun = execution("e3", "bob", "claude-haiku", "", now - 10);
+    rerun.rerun_of = Some("e2".to_string());
+    storage.save(&rerun).unwrap();
+
+    let original = storage.get_execution_by_id("e2").unwrap().unwrap();
+    assert_eq!(original.reruns, vec!["e3".to_string()]);
+    assert_eq!(original.allowed_tools, Some(vec!["Read".to_string()]));
+    let rerun = storage.get_execution_by_id("e3").unwrap().unwrap();
+    assert_eq!(rerun.rerun_of.as_deref(), Some("e2"));
+
+    assert_eq!(
+        storage.upsert_reaction("e1", "alice", "thumbsup").unwrap(),
+        ReactionResult::Created
+    );
+    assert_eq!(
+        storage.upsert_reaction("e1", "alice", "-1").unwrap(),
+        ReactionResult::Updated
+    );
+    assert_eq!(
+        storage.upsert_reaction("e1", "alice", "+1").unwrap(),
+        ReactionResult::Updated
+    );
+    assert_eq!(
+        storage.upsert_reaction("e3", "bob", "thumbsdown").unwrap(),
+        ReactionResult::Created
+    );
+    assert_eq!(
+        storage.upsert_reaction("e2", "bob", "thumbsdown").unwrap(),
+        ReactionResult::Created
+    );
+    assert_eq!(
+        storage.upsert_reaction("e2", "carol", "one").unwrap(),
+        ReactionResult::Created
+    );
+    assert_eq!(
+        storage.upsert_reaction("e2", "dave", "one").unwrap(),
+        ReactionResult::AlreadyTriggered
+    );
+
+    let summary = storage.get_reaction_summary("e1").unwrap();
+    assert_eq!((summary.positive, summary.negative), (1, 0));
+    assert_eq!(
+        storage.get_reaction_summary("e2").unwrap().triggers.len(),
+        1
+    );
+    assert_eq!(storage.get_feedback_stats(None).unwrap(), (1, 1));
+    assert_eq!(
+        storage.get_feedback_stats(Some(&["e1", "e2"])).unwrap(),
+        (1, 0)
+    );
+
+    let stats = storage.get_stats(Some("demo")).unwrap();
+    assert_eq!(stats.total_requests, 3);
+    assert_eq!(stats.successful_requests, 2);
+    assert_eq!(stats.total_input_tokens, 300);
+    assert!((stats.total_cost_usd - 1.5).abs() < 1e-9);
+    assert!((stats.avg_duration_ms - 1000.0).abs() < 1e-9);
+    assert_eq!((stats.positive_feedback, stats.negative_feedback), (1, 1));
+
+    let recent = storage.get_recent_executions(2).unwrap();
+    assert_eq!(recent.len(), 2);
+    assert_eq!(recent[0].id, "e3");
+    assert_eq!(recent[0].status, "error");
+    assert_eq!(recent[0].feedback, Some(-1));
+
+    let positive = ExecutionFilter {
+        feedback: Some(1),
+        ..Default::default()
+    };
+    let list = storage.get_executions(&positive, 1, 10).unwrap();
+    assert_eq!(list.total, 1);
+    assert_eq!(list.executions[0].id, "e1");
+    assert_eq!(list.executions[0].channel.as_deref(), Some("C1"));
+
+    let filtered = ExecutionFilter {
+        requester: Some("alice".to_string()),
+        search: Some("question e2".to_string()),
+        channel: Some("C1".to_string()),
+        from: Some(now - 25),
+        ..Default::default()
+    };
+    let list = storage.get_executions(&filtered, 1, 10).unwrap();
+    assert_eq!(list.total, 1);
+    assert_eq!(list.executions[0].id, "e2");
+
+    let no_feedback = ExecutionFilter {
+        feedback: Some(0),
+        ..Default::default()
+    };
+    assert_eq!(
+        storage.get_executions(&no_feedback, 1, 10).unwrap().total,
+        1
+    );
+
+    // Exports page through executions oldest first by (created_at, id)
+    let demo = ExecutionFilter {
+        project: Some("demo".to_string()),
+        ..Default::default()
+    };
+    let first = storage.export_executions(&demo, None, 2).unwrap();
+    let last = first.last().map(|e| (e.created_at, e.id.clone()));
+    let rest = storage.export_executions(&demo, last, 2).unwrap();
+    let ids: Vec<&str> = first.iter().chain(&rest).map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, ["e1", "e2", "e3"]);
+    assert_eq!(first[0].feedback, Some(1));
+    assert_eq!(first[0].channel.as_deref(), Some("C1"));
+    assert_eq!(rest[0].user_message, "question e3");
+    assert!(
+        storage
+            .export_executions(&positive, None, 10)
+            .unwrap()
+            .iter()
+            .all(|e| e.id == "e1")
+    );
+
+    let detail = storage.get_execution_by_id("e1").unwrap().unwrap();
+    assert_eq!(detail.feedback, Some(1));
+    assert_eq!(detail.agent_revision, Some(1));
+    assert!(storage.get_execution_by_id("missing").unwrap().is_none());
+
+    assert!(
+        storage
+            .update_metadata("e3", &json!({"retried": true}))
+            .unwrap()
+    );
+    let found = storage
+        .find_by_metadata_ref("slack", "thread_ts", "e3")
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, "e3");
+    assert!(found.metadata.unwrap().contains("retried"));
+    assert!(
+        storage
+            .find_by_metadata_ref("slack", "thread_ts", "e")
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        storage
+            .find_by_metadata_ref("slack", "thread_ts') OR 1=1 --", "e3")
+            .is_err()
+    );
+
+    let options = storage.get_filter_options().unwrap();
+    assert_eq!(options.models, vec!["claude-haiku", "claude-sonnet-4-5"]);
+    assert_eq!(options.channels, vec!["C1"]);
+
+    assert!(storage.delete_reaction("e2", "carol", "one").unwrap());
+    assert!(!storage.delete_reaction("e2", "carol", "one").unwrap());
+
+    let log = ClassificationLog {
+        text: "deploy please".to_string(),
+        agent: "ops".to_string(),
+        model: None,
+        confidence: 0.9,
+        method: "keyword".to_string(),
+        matched_keyword: Some("deploy".to_string()),
+        reasoning: None,
+        duration_ms: 3,
+        project: Some("demo".to_string()),
+        source: Some("slack".to_string()),
+        requester: Some("alice".to_string()),
+        agent_revision: Some(1),
+        llm_error: None,
+        run_id: None,
+    };
+    let first = storage.save_classification(&log).unwrap();
+    let second = storage.save_classification(&log).unwrap();
+    assert!(second > first);
+}
+
+fn tags_annotations_and_views(storage: &Storage) {
+    assert!(storage.add_tag("e1", " Incident ", Some("alice")).unwrap());
+    assert!(!storage.add_tag("e1", "incident", None).unwrap());
+    assert!(storage.add_tag("e1", "billing", None).unwrap());
+    assert!(storage.add_tag("e2", "incident", None).unwrap());
+    assert!(storage.add_tag("missing", "incident", None).is_err());
+
+    let tags = storage.list_tags("e1").unwrap();
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags[1].tag, "incident");
+    assert_eq!(tags[1].created_by.as_deref(), Some("alice"));
+
+    let by_tag = |tags: &[&str]| {
+        let filter = ExecutionFilter {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        storage.get_executions(&filter, 1, 10).unwrap()
+    };
+    assert_eq!(by_tag(&["incident"]).total, 2);
+    let both = by_tag(&["INCIDENT", "billing"]);
+    assert_eq!(both.total, 1);
+    assert_eq!(both.executions[0].tags, vec!["billing", "incident"]);
+    assert_eq!(by_tag(&["nope"]).total, 0);
+
+    let note = storage
+        .add_annotation("e1", "alice", "root cause: DNS")
+        .unwrap();
+    storage.add_annotation("e1", "bob", "confirmed").unwrap();
+    let detail = storage.get_execution_by_id("e1").unwrap().unwrap();
+    assert_eq!(detail.tags, vec!["billing", "incident"]);
+    assert_eq!(detail.annotations.len(), 2);
+    assert_eq!(detail.annotations[0].body, "root cause: DNS");
+    assert!(storage.delete_annotation("e1", note.id).unwrap());
+    assert!(!storage.delete_annotation("e2", note.id).unwrap());
+    assert_eq!(storage.list_annotations("e1").unwrap().len(), 1);
+
+    assert!(storage.remove_tag("e1", "Billing").unwrap());
+    assert!(!storage.remove_tag("e1", "billing").unwrap());
+    assert_eq!(storage.get_filter_options().unwrap().tags, vec!["incident"]);
+
+    let input = SaveView {
+        name: "incidents".to_string(),
+        filter: ExecutionFilter {
+            tags: vec!["incident".to_string()],
+            ..Default::default()
+        },
+    };
+    let view = storage.save_view("alice", &input).unwrap();
+    assert_eq!(view.filter, input.filter);
+    let replaced = storage
+        .save_view(
+            "alice",
+            &SaveView {
+                name: "incidents".to_string(),
+                filter: ExecutionFilter {
+                    requester: Some("alice".to_string()),
+                    ..input.filter.clone()
+                },
+            },
+        )
+        .unwrap();
+    assert_eq!(replaced.id, view.id);
+    storage.save_view("bob", &input).unwrap();
+
+    assert_eq!(storage.list_views("alice").unwrap().len(), 1);
+    let stored = storage.get_view(&view.id).unwrap().unwrap();
+    assert_eq!(stored.filter.requester.as_deref(), Some("alice"));
+    assert!(!storage.delete_view("bob", &view.id).unwrap());
+    assert!(storage.delete_view("alice", &view.id).unwrap());
+    assert!(storage.get_view(&view.id).unwrap().is_none());
+}
+
+fn eval_cases_and_runs(storage: &Storage) {
+    // Cached and unsure decisions aren't worth replaying
+    for (text, method, confidence) in [
+        ("deploy again", "cache", 0.9),
+        ("maybe ops", "llm", 0.4),
+        ("surely ops", "llm", 0.95),
+    ] {
+        storage
+            .save_classification(&ClassificationLog {
+                text: text.to_string(),
+                agent: "ops".to_string(),
+                model: None,
+                confidence,
+                method: method.to_string(),
+                matched_keyword: None,
+                reasoning: None,
+                duration_ms: 3,
+                project: Some("eval".to_string()),
+                source: None,
+                requester: None,
+                agent_revision: None,
+                llm_error: None,
+                run_id: None,
+            })
+            .unwrap();
+    }
+    assert_eq!(storage.seed_eval_cases("eval", 10).unwrap(), 1);
+    let seeded = storage.list_eval_cases("eval").unwrap();
+    assert_eq!(
+        (seeded[0].text.as_str(), seeded[0].verified),
+        ("surely ops", false)
+    );
+
+    // e1 has the requester's thumbs up; the logged "deploy please" is a
+    // keyword match. Re-seeding adds nothing.
+    assert_eq!(storage.seed_eval_cases("demo", 10).unwrap(), 2);
+    assert_eq!(storage.seed_eval_cases("demo", 10).unwrap(), 0);
+
+    let input = CreateEvalCase {
+        text: " restart the db ".to_string(),
+        expected_agent: "ops".to_string(),
+    };
+    let case = storage
+        .add_eval_case("demo", &input, "manual")
+        .unwrap()
+        .unwrap();
+    assert_eq!(case.text, "restart the db");
+    assert!(
+        storage
+            .add_eval_case("demo", &input, "manual")
+            .unwrap()
+            .is_none()
+    );
+
+    let cases = storage.list_eval_cases("demo").unwrap();
+    let sources: Vec<_> = cases.iter().map(|c| c.source.as_str()).collect();
+    assert_eq!(sources.len(), 3);
+    assert!(sources.contains(&"feedback") && sources.contains(&"classification_log"));
+    for case in &cases {
+        assert_eq!(case.verified, case.source != "classification_log");
+    }
+
+    let stages = vec!["keyword".to_string()];
+    assert!(storage.last_eval_run("demo", &stages).unwrap().is_none());
+    let run = EvalRun {
+        id: "run1".to_string(),
+        project_id: "demo".to_string(),
+        stages: stages.clone(),
+        total: 1,
+        correct: 1,
+        accuracy: 1.0,
+        results: vec![EvalCaseResult {
+            case_id: case.id.clone(),
+            text: case.text.clone(),
+            expected: "ops".to_string(),
+            predicted: "ops".to_string(),
+            method: "keyword".to_string(),
+            correct: true,
+            verified: true,
+        }],
+        created_at: 100,
+    };
+    storage.save_eval_run(&run).unwrap();
+    let last = storage.last_eval_run("demo", &stages).unwrap().unwrap();
+    assert_eq!((last.id.as_str(), last.results.len()), ("run1", 1));
+    assert!(
+        storage
+            .last_eval_run("demo", &["llm".to_string()])
+            .unwrap()
+            .is_none()
+    );
+    let runs = storage.list_eval_runs("demo", 10).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(runs[0].results.is_empty());
+
+    assert!(storage.delete_eval_case("demo", &case.id).unwrap());
+    assert!(!storage.delete_eval_case("other", &cases[0].id).unwrap());
+}
+
+fn semantic_embeddings(storage: &Storage) {
+    let added = vec![
+        ("deploy it".to_string(), vec![0.6, 0.8], false),
+        ("roll back".to_string(), vec![1.0, 0.0], false),
+    ];
+    storage
+        .save_agent_embeddings("demo", "demo-ops", "hashing-v1-2", &[], &added)
+        .unwrap();
+    // Keeps "deploy it", drops "roll back", re-embeds "restart" in place
+    let added = vec![("restart".to_string(), vec![0.0, 1.0], true)];
+    storage
+        .save_agent_embeddings(
+            "demo",
+            "demo-ops",
+            "hashing-v1-2",
+            &["deploy it".to_string()],
+            &added,
+        )
+        .unwrap();
+
+    let mut stored = storage.list_embeddings("hashing-v1-2").unwrap();
+    stored.sort_by(|a, b| a.example.cmp(&b.example));
+    let examples: Vec<_> = stored.iter().map(|e| e.example.as_str()).collect();
+    assert_eq!(examples, vec!["deploy it", "restart"]);
+    assert_eq!(stored[0].vector, vec![0.6, 0.8]);
+    assert_eq!((stored[0].negative, stored[1].negative), (false, true));
+    assert!(storage.list_embeddings("other-model").unwrap().is_empty());
+
+    storage
+        .save_agent_embeddings("demo", "demo-ops", "hashing-v1-2", &[], &[])
+        .unwrap();
+    assert!(storage.list_embeddings("hashing-v1-2").unwrap().is_empty());
+}
+
+fn user_context(storage: &Storage) {
+    assert!(storage.add_user_rule("alice", "answer in English").unwrap());
+    assert!(!storage.add_user_rule("alice", "answer in English").unwrap());
+    storage
+        .sync_user_rules("bob", &["be brief".to_string(), "use tables".to_string()])
+        .unwrap();
+    assert_eq!(storage.get_user_rules("bob").unwrap().len(), 2);
+    assert!(storage.delete_user_rule("bob", "use tables").unwrap());
+
+    let context = storage.get_user_context("alice").unwrap();
+    assert_eq!(context.rules, vec!["answer in English".to_string()]);
+    assert_eq!(context.conversation_count, 2);
+    assert_eq!(context.recent_conversations[0].id, "e2");
+    assert!(!context.recent_conversations[0].has_negative_feedback);
+
+    let context = storage.get_user_context("bob").unwrap();
+    assert!(context.recent_conversations[0].has_negative_feedback);
+    assert_eq!(
+        context.recent_conversations[0].response.as_deref(),
+        Some("")
+    );
+
+    assert!(storage.acquire_summary_lock("alice", "lock-1").unwrap());
+    assert!(!storage.acquire_summary_lock("alice", "lock-2").unwrap());
+    assert!(storage.release_summary_lock("alice", "lock-1").unwrap());
+    storage
+        .save_user_context(
+            "alice",
+            "Prefers English",
+            &["answer in English".to_string()],
+        )
+        .unwrap();
+    let context = storage.get_user_context("alice").unwrap();
+    assert_eq!(context.summary.as_deref(), Some("Prefers English"));
+    assert_eq!(context.conversation_count, 0);
+
+    let users = storage.list_users_with_context().unwrap();
+    let alice = users.iter().find(|u| u.user_id == "alice").unwrap();
+    assert!(alice.has_summary);
+    assert_eq!(alice.request_count, 2);
+    assert_eq!(alice.primary_source.as_deref(), Some("slack"));
+    assert!((alice.total_cost_usd - 1.0).abs() < 1e-9);
+}
+
+fn analytics_queries(storage: &Storage) {
+    let now = chrono::Utc::now().timestamp();
+    let day = TimeRange::period(Period::Hours24, now, chrono_tz::UTC);
+    storage
+        .with_connection(|conn| {
+            let overview = analytics::get_overview_stats(conn, &day, Some("demo"), None)?;
+            assert_eq!(overview.summary.total_requests, 3);
+            assert_eq!(overview.summary.failed_requests, 1);
+            assert_eq!(overview.summary.p50_duration_ms, Some(1000));
+            assert_eq!(overview.feedback.positive, 1);
+            assert_eq!(overview.feedback.pending_feedback, 0);
+
+            let series = analytics::get_timeseries(
+                conn,
+                Granularity::Day,
+                &TimeRange::period(Period::Hours24, now, chrono_tz::Asia::Seoul),
+                Some("demo"),
+                None,
+                None,
+            )?;
+            assert_eq!(series.timezone, "Asia/Seoul");
+            assert!(series.data.iter().all(|p| p.timestamp.ends_with("+09:00")));
+            let requests: i64 = series.data.iter().map(|p| p.requests).sum();
+            let tokens: i64 = series.data.iter().map(|p| p.output_tokens).sum();
+            assert_eq!(requests, 3);
+            assert_eq!(tokens, 153);
+
+            let models = analytics::get_model_stats(conn, &day, None, None)?;
+            assert_eq!(models.models[0].model, "claude-haiku");
+            assert_eq!(models.models[0].requests, 2);
+            assert!((models.models[0].cost_usd - 1.0).abs() < 1e-9);
+
+            let errors = analytics::get_error_stats(conn, &day, None, None)?;
+            assert_eq!(errors.total_errors, 1);
+
+            let sources = analytics::get_source_stats(conn, &day, None)?;
+            assert_eq!(sources.sources[0].unique_requesters, 2);
+
+            let requesters = analytics::get_requester_stats(conn, &day, None, None, 10)?;
+            assert_eq!(requesters.requesters[0].requester, "alice");
+            assert_eq!(requesters.requesters[0].source.as_deref(), Some("slack"));
+
+            let agents = analytics::get_agent_stats(conn, &day, Some("demo"), None)?;
+            let ops = &agents.agents[0];
+            assert_eq!((agents.agents.len(), ops.agent.as_str()), (1, "ops"));
+            assert_eq!(ops.requests, 3);
+            assert!((ops.cost_per_request - 0.5).abs() < 1e-9);
+            assert!((ops.failure_rate - 100.0 / 3.0).abs() < 1e-9);
+            assert_eq!(ops.satisfaction_rate, Some(50.0));
+            assert_eq!(
+                (ops.p50_duration_ms, ops.p95_duration_ms),
+                (Some(1000), Some(1000))
+            );
+            assert_eq!(ops.routing.len(), 1);
+            assert_eq!(
+                (ops.routing[0].method.as_str(), ops.routing[0].count),
+                ("keyword", 2)
+            );
+            assert_eq!(ops.comparison.requests_change_pct, Some(100.0));
+
+            let recorded = analytics::record_workflow_execution(
+                conn,
+                &RecordWorkflowRequest {
+                    workflow: "slack-mention-handler".to_string(),
+                    execution_id: Some("e1".to_string()),
+                    run_id: None,
+                    status: WorkflowStatus::Success,
+                    duration_ms: Some(1200),
+                    error: None,
+                    metadata: Some(json!({"run": 1})),
+                },
+            )?;
+            assert!(recorded.id > 0);
+            let workflows = analytics::get_workflow_stats(conn, &day)?;
+            assert_eq!(workflows.workflows[0].executions, 1);
+            assert_eq!(
+                workflows.workflows[0].last_status.as_deref(),
+                Some("success")
+            );
+
+            let classify = analytics::get_classify_stats(conn, &day, Some("demo"))?;
+            assert_eq!(classify.total_classifications, 2);
+            let logs = analytics::get_classify_logs(
+                conn,
+                &day,
+                Some("demo"),
+                None,
+                Some("keyword"),
+                1,
+                1,
+            )?;
+            assert_eq!((logs.total, logs.logs.len()), (2, 1));
+
+            let revisions = analytics::get_agent_revision_stats(conn, "demo", "ops")?;
+            assert_eq!(revisions.revisions.len(), 1);
+            assert_eq!(revisions.revisions[0].requests, 3);
+            assert_eq!(revisions.revisions[0].classifications, 2);
+            Ok(())
+        })
+        .unwrap();
+}
+
+fn example_suggestions(storage: &Storage) {
+    // e1's thumbs up only counts once the classifier logged its routing
+    assert_eq!(storage.generate_suggestions("demo", 10).unwrap(), 0);
+    let log = ClassificationLog {
+        text: "question e1".to_string(),
+        agent: "ops".to_string(),
+        model: None,
+        confidence: 0.8,
+        method: "semantic".to_string(),
+        matched_keyword: None,
+        reasoning: None,
+        duration_ms: 5,
+        project: Some("demo".to_string()),
+        source: Some("slack".to_string()),
+        requester: Some("alice".to_string()),
+        agent_revision: Some(1),
+        llm_error: None,
+        run_id: None,
+    };
+    storage.save_classification(&log).unwrap();
+    assert_eq!(storage.generate_suggestions("demo", 10).unwrap(), 1);
+
+    // bob's thumbs down on e3 plus a correction yields a counterexample for
+    // ops and an example for support
+    let support: CreateAgent =
+        serde_json::from_value(json!({"name": "support", "description": "Support"})).unwrap();
+    storage.create_agent("demo", support).unwrap();
+    let correction = storage
+        .set_routing_correction("e3", "support", Some("bob"))
+        .unwrap();
+    assert_eq!(correction.agent, "support");
+    assert_eq!(storage.generate_suggestions("demo", 10).unwrap(), 2);
+    assert_eq!(storage.generate_suggestions("demo", 10).unwrap(), 0);
+
+    let pending = storage.list_suggestions("demo", "pending").unwrap();
+    assert_eq!(pending.len(), 3);
+    let counter = pending
+        .iter()
+        .find(|s| s.kind == SuggestionKind::Counterexample)
+        .unwrap();
+    assert_eq!(
+        (counter.agent.as_str(), counter.text.as_str()),
+        ("ops", "question e3")
+    );
+
+    let ops = storage
+        .approve_suggestion("demo", &counter.id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(ops.counterexamples, vec!["question e3".to_string()]);
+    assert!(
+        storage
+            .approve_suggestion("demo", &counter.id)
+            .unwrap()
+            .is_none()
+    );
+
+    let example = pending.iter().find(|s| s.agent == "support").unwrap();
+    assert!(storage.reject_suggestion("demo", &example.id).unwrap());
+    assert!(!storage.reject_suggestion("demo", &example.id).unwrap());
+    assert_eq!(
+        storage.list_suggestions("demo", "rejected").unwrap().len(),
+        1
+    );
+    assert_eq!(
+        storage.list_suggestions("demo", "pending").unwrap().len(),
+        1
+    );
+
+    // Approving the same suggestion twice at once applies it once
+    let last = storage.list_suggestions("demo", "pending").unwrap()[0]
+        .id
+        .clone();
+    let approved = std::thread::scope(|scope| {
+        let approvals: Vec<_> = (0..2)
+            .map(|_| scope.spawn(|| storage.approve_suggestion("demo", &last).unwrap()))
+            .collect();
+        approvals
+            .into_iter()
+            .filter_map(|t| t.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(approved.len(), 1);
+    assert!(
+        storage
+            .list_suggestions("demo", "pending")
+            .unwrap()
+            .is_empty()
+    );
+}
+
+fn experiments(storage: &Storage) {
+    let input: CreateExperiment = serde_json::from_value(json!({
+        "name": "haiku vs sonnet",
+        "agent": "ops",
+        "variants": [
+            {"name": "control"},
+            {"name": "haiku", "weight": 3, "model": "haiku"}
+        ]
+    }))
+    .unwrap();
+    let experiment = storage.create_experiment("demo", &input).unwrap();
+    assert_eq!(experiment.variants[0].weight, 1);
+    assert_eq!(
+        storage
+            .running_experiment("demo", "ops")
+            .unwrap()
+            .map(|e| e.id),
+        Some(experiment.id.clone())
+    );
+
+    // Assignments stick even after the weights change
+    let first = storage
+        .assign_variant(&experiment, Some("alice"))
+        .unwrap()
+        .unwrap();
+    let mut reweighted = experiment.clone();
+    reweighted.variants[0].weight = 100;
+    reweighted.variants[1].weight = 100;
+    for _ in 0..3 {
+        let again = storage
+            .assign_variant(&reweighted, Some("alice"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.name, first.name);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    for (id, variant, response) in [
+        ("x1", "control", "ok"),
+        ("x2", "control", "ok"),
+        ("x3", "haiku", "ok"),
+        ("x4", "haiku", ""),
+    ] {
+        let mut exec = execution(id, "carol", "claude-haiku", response, now);
+        exec.experiment_id = Some(experiment.id.clone());
+        exec.variant = Some(variant.to_string());
+        storage.save(&exec).unwrap();
+    }
+    storage.upsert_reaction("x3", "carol", "+1").unwrap();
+    let saved = storage.get_execution_by_id("x4").unwrap().unwrap();
+    assert_eq!(saved.variant.as_deref(), Some("haiku"));
+
+    let results = storage
+        .with_connection(|conn| analytics::get_experiment_results(conn, &experiment))
+        .unwrap();
+    assert_eq!(results.control, "control");
+    let control = &results.variants[0];
+    assert_eq!((control.requests, control.failures), (2, 0));
+    assert!(control.vs_control.is_none());
+    let haiku = &results.variants[1];
+    assert_eq!((haiku.requests, haiku.failures), (2, 1));
+    assert_eq!(haiku.positive_feedback, 1);
+    assert_eq!(haiku.satisfaction_rate, Some(100.0));
+    let vs_control = haiku.vs_control.as_ref().unwrap();
+    assert!((vs_control.failure_rate.as_ref().unwrap().difference - 50.0).abs() < 1e-9);
+    // Identical costs leave nothing to test
+    assert!(vs_control.cost.is_none());
+
+    let stopped = storage
+        .update_experiment(
+            &experiment.id,
+            &UpdateExperiment {
+                name: None,
+                status: Some("stopped".to_string()),
+                variants: None,
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(stopped.status, "stopped");
+    assert!(storage.running_experiment("demo", "ops").unwrap().is_none());
+
+    // Renaming the agent carries its experiments along
+    let rename = |name: &str| {
+        let update: UpdateAgent = serde_json::from_value(json!({"name": name})).unwrap();
+        storage.update_agent("demo-ops", &update).unwrap().unwrap();
+    };
+    rename("operations");
+    let renamed = storage.list_experiments("demo").unwrap();
+    assert_eq!(renamed[0].agent, "operations");
+    rename("ops");
+
+    // Deleting a project takes its experiments and assignments with it
+    let scratch = storage
+        .create_project(serde_json::from_value(json!({"name": "Experiments"})).unwrap())
+        .unwrap();
+    let doomed = storage.create_experiment(&scratch.id, &input).unwrap();
+    storage.assign_variant(&doomed, Some("alice")).unwrap();
+    assert!(storage.delete_project(&scratch.id).unwrap());
+    let assignments: i64 = storage
+        .with_connection(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM experiment_assignments WHERE experiment_id = ?1",
+                params![doomed.id],
+                |row| row.get(0),
+            )
+        })
+        .unwrap();
+    assert_eq!(assignments, 0);
+    assert!(storage.list_experiments(&scratch.id).unwrap().is_empty());
+
+    assert!(storage.delete_experiment(&experiment.id).unwrap());
+    assert!(storage.list_experiments("demo").unwrap().is_empty());
+}
+
+fn budgets(storage: &Storage) {
+    let project: CreateBudget = serde_json::from_value(json!({
+        "scope": "project", "period": "daily", "limit_usd": 10.0
+    }))
+    .unwrap();
+    let project = storage.create_budget("demo", &project).unwrap();
+    assert_eq!(project.action, BudgetAction::Reject);
+    let alice: CreateBudget = serde_json::from_value(json!({
+        "scope": "requester", "target": "alice", "period": "monthly",
+        "limit_usd": 1.0, "action": "downgrade", "downgrade_model": "haiku"
+    }))
+    .unwrap();
+    let alice = storage.create_budget("demo", &alice).unwrap();
+
+    let applicable = storage
+        .applicable_budgets("demo", Some("ops"), Some("bob"))
+        .unwrap();
+    assert_eq!(applicable.len(), 1);
+    let applicable = storage
+        .applicable_budgets("demo", Some("ops"), Some("alice"))
+        .unwrap();
+    assert_eq!(applicable.len(), 2);
+
+    // alice ran e1 and e2 at $0.5 each
+    let spent = storage.budget_spend(&alice, 0).unwrap();
+    assert!((spent - 1.0).abs() < 1e-9);
+    let future = chrono::Utc::now().timestamp() + 3600;
+    assert_eq!(storage.budget_spend(&project, future).unwrap(), 0.0);
+
+    assert!(
+        storage
+            .record_budget_alert(&alice.id, 0, 80, spent)
+            .unwrap()
+    );
+    assert!(
+        !storage
+            .record_budget_alert(&alice.id, 0, 80, spent)
+            .unwrap()
+    );
+    assert!(
+        storage
+            .record_budget_alert(&alice.id, 86400, 80, spent)
+            .unwrap()
+    );
+
+    let updated = storage
+        .update_budget(
+            &project.id,
+            &UpdateBudget {
+                limit_usd: Some(20.0),
+                action: Some(BudgetAction::Warn),
+                ..UpdateBudget::default()
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (updated.limit_usd, updated.action),
+        (20.0, BudgetAction::Warn)
+    );
+    assert_eq!(updated.period, BudgetPeriod::Daily);
+
+    // Agent budgets follow a rename
+    let ops: CreateBudget = serde_json::from_value(json!({
+        "scope": "agent", "target": "ops", "period": "daily", "limit_usd": 5.0
+    }))
+    .unwrap();
+    let ops = storage.create_budget("demo", &ops).unwrap();
+    let rename = |name: &str| {
+        let update: UpdateAgent = serde_json::from_value(json!({"name": name})).unwrap();
+        storage.update_agent("demo-ops", &update).unwrap().unwrap();
+    };
+    rename("operations");
+    let applicable = storage
+        .applicable_budgets("demo", Some("operations"), Some("bob"))
+        .unwrap();
+    assert!(applicable.iter().any(|b| b.id == ops.id));
+    rename("ops");
+
+    assert!(storage.delete_budget(&ops.id).unwrap());
+    assert!(storage.delete_budget(&alice.id).unwrap());
+    assert!(storage.delete_budget(&project.id).unwrap());
+    assert!(storage.list_budgets("demo").unwrap().is_empty());
+
+    // Deleting a project takes its budgets and alerts with it
+    let scratch = storage
+        .create_project(serde_json::from_value(json!({"name": "Budgets"})).unwrap())
+        .unwrap();
+    let input: CreateBudget = serde_json::from_value(json!({
+        "scope": "project", "period": "daily", "limit_usd": 1.0
+    }))
+    .unwrap();
+    let doomed = storage.create_budget(&scratch.id, &input).unwrap();
+    storage.record_budget_alert(&doomed.id, 0, 80, 0.9).unwrap();
+    assert!(storage.delete_project(&scratch.id).unwrap());
+    assert!(storage.list_budgets(&scratch.id).unwrap().is_empty());
+    let alerts: i64 = storage
+        .with_connection(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM budget_alerts WHERE budget_id = ?1",
+                params![doomed.id],
+                |row| row.get(0),
+            )
+        })
+        .unwrap();
+    assert_eq!(alerts, 0);
+}
+
+fn reports(storage: &Storage) {
+    let input: CreateReport = serde_json::from_value(json!({
+        "name": "Daily", "schedule": "0 9 * * *", "period": "yesterday",
+        "sections": ["overview", "errors"],
+        "destinations": [{ "type": "webhook", "url": "http://hooks.local/report" }]
+    }))
+    .unwrap();
+    let report = storage.create_report(&input, Some(1_000)).unwrap();
+    assert_eq!(
+        storage.get_report(&report.id).unwrap().unwrap().period,
+        Period::Yesterday
+    );
+    let mut paused = storage
+        .create_report(
+            &CreateReport {
+                enabled: false,
+                ..input
+            },
+            Some(500),
+        )
+        .unwrap();
+    assert_eq!(storage.list_reports().unwrap().len(), 2);
+
+    // Disabled and not yet due reports are skipped
+    assert!(storage.due_reports(999).unwrap().is_empty());
+    let due = storage.due_reports(1_000).unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].destinations, report.destinations);
+    assert_eq!(
+        due[0].sections,
+        vec![ReportSection::Overview, ReportSection::Errors]
+    );
+
+    // Only one claim of a scheduled run wins
+    assert!(
+        storage
+            .claim_report(&report.id, 1_000, Some(2_000), 1_001)
+            .unwrap()
+    );
+    assert!(
+        !storage
+            .claim_report(&report.id, 1_000, Some(2_000), 1_001)
+            .unwrap()
+    );
+    let claimed = storage.get_report(&report.id).unwrap().unwrap();
+    assert_eq!(
+        (claimed.next_run_at, claimed.last_run_at),
+        (Some(2_000), Some(1_001))
+    );
+
+    paused.enabled = true;
+    paused.format = ReportFormat::Html;
+    assert!(storage.update_report(&paused).unwrap());
+    assert_eq!(
+        storage.due_reports(1_000).unwrap()[0].format,
+        ReportFormat::Html
+    );
+
+    for (started_at, status) in [(1_001, "success"), (1_002, "partial")] {
+        let id = storage
+            .record_report_run(&ReportRun {
+                id: 0,
+                report_id: report.id.clone(),
+                trigger: "schedule".into(),
+                status: status.into(),
+                period_start: 0,
+                period_end: 1_000,
+                content: "*Daily*".into(),
+                deliveries: vec![ReportDelivery {
+                    destination: "webhook:http://hooks.local/report".into(),
+                    ok: status == "success",
+                    error: None,
+                }],
+                error: None,
+                started_at,
+                finished_at: started_at,
+            })
+            .unwrap();
+        assert!(id > 0);
+    }
+    let runs = storage.list_report_runs(&report.id, 10).unwrap();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(
+        (runs[0].status.as_str(), runs[0].deliveries[0].ok),
+        ("partial", false)
+    );
+    assert_eq!(storage.list_report_runs(&report.id, 1).unwrap().len(), 1);
+
+    assert!(storage.delete_report(&report.id).unwrap());
+    assert!(storage.list_report_runs(&report.id, 10).unwrap().is_empty());
+    assert!(storage.delete_report(&paused.id).unwrap());
+    assert!(storage.list_reports().unwrap().is_empty());
+}
+
+fn alerts(storage: &Storage) {
+    // Only the requester's own feedback counts: alice liked e1 and bob
+    // disliked his failed e3, while bob's thumbs down on alice's e2 is ignored
+    let now = chrono::Utc::now().timestamp();
+    let samples = storage
+        .with_connection(|conn| analytics::query_execution_samples(conn, now - 60, now + 1))
+        .unwrap();
+    let demo: Vec<_> = samples.iter().filter(|s| s.project == "demo").collect();
+    // Seconds after e1, the earliest, so a tick of the clock can't shift them
+    let first = demo.iter().map(|s| s.created_at).min().unwrap();
+    let feedback: Vec<(i64, bool, Option<bool>)> = demo
+        .iter()
+        .map(|s| (s.created_at - first, s.success, s.satisfied))
+        .collect();
+    for expected in [
+        (0, true, Some(true)),
+        (10, true, None),
+        (20, false, Some(false)),
+    ] {
+        assert!(feedback.contains(&expected), "{:?}", expected);
+    }
+
+    let anomaly = analytics::Anomaly {
+        project: "demo".into(),
+        scope: AlertScope::Agent,
+        target: Some("ops".into()),
+        metric: AlertMetric::Cost,
+        baseline: 0.5,
+        value: 12.0,
+    };
+    let opened = storage.open_alert(&anomaly, 1_000).unwrap().unwrap();
+    assert_eq!(
+        (opened.status, opened.target.as_deref()),
+        (AlertStatus::Open, Some("ops"))
+    );
+    // One open alert per subject and metric
+    assert!(storage.open_alert(&anomaly, 1_100).unwrap().is_none());
+    let project = storage
+        .open_alert(
+            &analytics::Anomaly {
+                scope: AlertScope::Project,
+                target: None,
+                ..anomaly.clone()
+            },
+            1_100,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(project.target, None);
+
+    storage.update_alert(opened.id, 0.6, 15.0, 1_200).unwrap();
+    assert!(storage.resolve_alert(opened.id, 1_300).unwrap());
+    assert!(!storage.resolve_alert(opened.id, 1_400).unwrap());
+    let resolved = storage.get_alert(opened.id).unwrap().unwrap();
+    assert_eq!(resolved.status, AlertStatus::Resolved);
+    assert_eq!((resolved.value, resolved.ended_at), (15.0, Some(1_300)));
+
+    // Resolving frees the subject for a new incident
+    let reopened = storage.open_alert(&anomaly, 1_500).unwrap().unwrap();
+    let open = storage
+        .list_alerts(
+            &AlertFilter {
+                status: Some(AlertStatus::Open),
+                ..AlertFilter::default()
+            },
+            10,
+        )
+        .unwrap();
+    assert_eq!(
+        open.iter().map(|a| a.id).collect::<Vec<_>>(),
+        vec![reopened.id, project.id]
+    );
+    let agent = AlertFilter {
+        project: Some("demo".into()),
+        scope: Some(AlertScope::Agent),
+        metric: Some(AlertMetric::Cost),
+        ..AlertFilter::default()
+    };
+    assert_eq!(storage.list_alerts(&agent, 10).unwrap().len(), 2);
+    assert_eq!(storage.list_alerts(&agent, 1).unwrap()[0].id, reopened.id);
+}
+
+fn rollups(storage: &Storage) {
+    // Long ranges read whole hours from the rollups each save adds to
+    let now = chrono::Utc::now().timestamp();
+    for (id, age, response, duration) in [
+        ("r1", 3 * 86400, "ok", 100),
+        ("r2", 2 * 86400, "", 200),
+        ("r3", 3600, "ok", 300),
+    ] {
+        storage
+            .save(&Execution {
+                project: "rollups".to_string(),
+                duration_ms: Some(duration),
+                ..execution(id, "alice", "claude-sonnet", response, now - age)
+            })
+            .unwrap();
+    }
+    let week = TimeRange::period(Period::Days7, now, chrono_tz::UTC);
+    let check = || {
+        storage
+            .with_connection(|conn| {
+                let overview = analytics::get_overview_stats(conn, &week, Some("rollups"), None)?;
+                assert_eq!(overview.summary.total_requests, 3);
+                assert_eq!(overview.summary.failed_requests, 1);
+                assert_eq!(overview.summary.total_output_tokens, 153);
+                assert!((overview.summary.total_cost_usd - 1.5).abs() < 1e-9);
+                assert!((overview.summary.avg_duration_ms - 200.0).abs() < 1e-9);
+                let p50 = overview.summary.p50_duration_ms.unwrap();
+                assert!((198..=202).contains(&p50), "{}", p50);
+
+                let agents = analytics::get_agent_stats(conn, &week, Some("rollups"), None)?;
+                let p95 = agents.agents[0].p95_duration_ms.unwrap();
+                assert!((297..=303).contains(&p95), "{}", p95);
+                Ok(())
+            })
+            .unwrap()
+    };
+    check();
+    assert!(storage.rebuild_rollups().unwrap() >= 3);
+    check();
+
+    // Alert baselines over days read the same rollups
+    let settings = analytics::AnomalySettings {
+        window_secs: 7200,
+        baseline_secs: 5 * 86400,
+        min_requests: 1,
+        z_threshold: 3.0,
+        min_change_pct: 50.0,
+    };
+    let anomalies = storage
+        .with_connection(|conn| analytics::detect_anomalies(conn, now, &settings))
+        .unwrap();
+    let project: Vec<_> = anomalies
+        .iter()
+        .filter(|a| a.project == "rollups" && a.scope == AlertScope::Project)
+        .collect();
+    assert_eq!(project.len(), 2);
+    // $1 over the two executions before the window, spread over 60 windows
+    assert_eq!(project[0].metric, AlertMetric::Cost);
+    assert!((project[0].baseline - 1.0 / 60.0).abs() < 1e-9);
+    assert!((project[0].value - 0.5).abs() < 1e-9);
+    assert_eq!(project[1].metric, AlertMetric::Latency);
+    assert!((198.0..=202.0).contains(&project[1].baseline));
+    assert_eq!(project[1].value, 300.0);
+}
+
+fn workflow_runs(storage: &Storage) {
+    // run-1 carries its run id through chat, classify and the bridge; run-2
+    // only reports the execution it made
+    let now = chrono::Utc::now().timestamp();
+    for (id, run_id) in [("w1", Some("run-1")), ("w2", None)] {
+        storage
+            .save(&Execution {
+                project: "workflows".to_string(),
+                run_id: run_id.map(str::to_string),
+                ..execution(id, "alice", "claude-sonnet", "ok", now)
+            })
+            .unwrap();
+    }
+    assert_eq!(
+        storage.get_execution_by_id("w1").unwrap().unwrap().run_id,
+        Some("run-1".to_string())
+    );
+    storage
+        .save_classification(&ClassificationLog {
+            text: "question w1".to_string(),
+            agent: "ops".to_string(),
+            model: None,
+            confidence: 0.9,
+            method: "keyword".to_string(),
+            matched_keyword: Some("deploy".to_string()),
+            reasoning: None,
+            duration_ms: 3,
+            project: Some("workflows".to_string()),
+            source: Some("slack".to_string()),
+            requester: Some("alice".to_string()),
+            agent_revision: None,
+            llm_error: None,
+            run_id: Some("run-1".to_string()),
+        })
+        .unwrap();
+    storage
+        .record_webhook_delivery(&WebhookDelivery {
+            run_id: Some("run-1".to_string()),
+            endpoint: "mention".to_string(),
+            event_type: "app_mention".to_string(),
+            channel: Some("C1".to_string()),
+            status_code: Some(200),
+            error: None,
+            duration_ms: 12,
+            created_at: now,
+        })
+        .unwrap();
+
+    let day = TimeRange::period(Period::Hours24, now, chrono_tz::UTC);
+    storage
+        .with_connection(|conn| {
+            for (run_id, execution_id, status, duration, error, metadata) in [
+                ("run-1", None, WorkflowStatus::Success, 100, None, None),
+                (
+                    "run-2",
+                    Some("w2"),
+                    WorkflowStatus::Error,
+                    300,
+                    Some("boom"),
+                    None,
+                ),
+                (
+                    "run-3",
+                    None,
+                    WorkflowStatus::Timeout,
+                    200,
+                    None,
+                    Some(json!({"error": "slow"})),
+                ),
+                ("run-4", None, WorkflowStatus::Error, 400, None, None),
+            ] {
+                analytics::record_workflow_execution(
+                    conn,
+                    &RecordWorkflowRequest {
+                        workflow: "incident-reaction-handler".to_string(),
+                        execution_id: execution_id.map(str::to_string),
+                        run_id: Some(run_id.to_string()),
+                        status,
+                        duration_ms: Some(duration),
+                        error: error.map(str::to_string),
+                        metadata,
+                    },
+                )?;
+            }
+
+            let listing =
+                analytics::get_workflow_runs(conn, "incident-reaction-handler", &day, None, 10)?;
+            let stats = listing.stats.unwrap();
+            assert_eq!((stats.executions, stats.successful), (4, 1));
+            assert_eq!(stats.status, analytics::HealthStatus::Critical);
+            assert_eq!(
+                (
+                    stats.p50_duration_ms,
+                    stats.p95_duration_ms,
+                    stats.p99_duration_ms
+                ),
+                (Some(300), Some(400), Some(400))
+            );
+            assert_eq!(
+                stats
+                    .failure_reasons
+                    .iter()
+                    .map(|f| (f.reason.as_str(), f.count))
+                    .collect::<Vec<_>>(),
+                vec![("boom", 1), ("error", 1), ("slow", 1)]
+            );
+
+            // Newest first
+            let runs: Vec<_> = listing
+                .runs
+                .iter()
+                .map(|r| r.run_id.as_deref().unwrap())
+                .collect();
+            assert_eq!(runs, vec!["run-4", "run-3", "run-2", "run-1"]);
+            let first = &listing.runs[3];
+            assert_eq!(first.executions.len(), 1);
+            assert_eq!(first.executions[0].id, "w1");
+            assert_eq!(first.classifications[0].agent, "ops");
+            assert_eq!(first.deliveries[0].status_code, Some(200));
+            let reported = &listing.runs[2];
+            assert_eq!(reported.error.as_deref(), Some("boom"));
+            assert_eq!(reported.executions[0].id, "w2");
+            assert!(reported.classifications.is_empty());
+
+            let failed = analytics::get_workflow_runs(
+                conn,
+                "incident-reaction-handler",
+                &day,
+                Some(WorkflowStatus::Error),
+                1,
+            )?;
+            assert_eq!(failed.runs.len(), 1);
+            assert_eq!(failed.runs[0].run_id.as_deref(), Some("run-4"));
+
+            // One run in four succeeding leaves the workflow critical
+            let settings = analytics::AnomalySettings {
+                window_secs: 3600,
+                baseline_secs: 86400,
+                min_requests: 4,
+                z_threshold: 3.0,
+                min_change_pct: 50.0,
+            };
+            let unhealthy: Vec<_> = analytics::detect_anomalies(conn, now + 1, &settings)?
+                .into_iter()
+                .filter(|a| a.scope == AlertScope::Workflow)
+                .collect();
+            assert_eq!(unhealthy.len(), 1);
+            assert_eq!(
+                unhealthy[0].target.as_deref(),
+                Some("incident-reaction-handler")
+            );
+            assert_eq!(unhealthy[0].metric, AlertMetric::Health);
+            assert_eq!((unhealthy[0].baseline, unhealthy[0].value), (100.0, 25.0));
+
+            // A run reported twice, failing and then retried, links to the
+            // same rows both times
+            for status in [WorkflowStatus::Error, WorkflowStatus::Success] {
+                analytics::record_workflow_execution(
+                    conn,
+                    &RecordWorkflowRequest {
+                        workflow: "feedback-handler".to_string(),
+                        execution_id: None,
+                        run_id: Some("run-1".to_string()),
+                        status,
+                        duration_ms: Some(100),
+                        error: None,
+                        metadata: None,
+                    },
+                )?;
+            }
+            let retried = analytics::get_workflow_runs(conn, "feedback-handler", &day, None, 10)?;
+            assert_eq!(retried.runs.len(), 2);
+            for run in &retried.runs {
+                assert_eq!(run.executions.len(), 1);
+                assert_eq!(run.classifications.len(), 1);
+                assert_eq!(run.deliveries.len(), 1);
+            }
+            Ok(())
+        })
+        .unwrap();
+}
@@ -0,0 +1,234 @@
+use anyhow::Result;
+
+use super::backend::{Row, params};
+use super::core::Storage;
+use super::feedback::sql::{FEEDBACK_JOIN_VERIFIED, NEGATIVE_DISTINCT, POSITIVE_DISTINCT};
+use super::types::{CreateEvalCase, EvalCase, EvalCaseResult, EvalRun};
+
+const CASE_COLUMNS: &str = "id, project_id, text, expected_agent, source, created_at";
+
+/// Source of cases labeled with whatever the classifier decided
+const LOGGED_SOURCE: &str = "classification_log";
+
+/// Lowest confidence a logged decision needs to be seeded as a case
+const SEED_MIN_CONFIDENCE: f64 = 0.8;
+
+fn map_case_row(row: &Row) -> Result<EvalCase> {
+    let source: String = row.get(4)?;
+    Ok(EvalCase {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        text: row.get(2)?,
+        expected_agent: row.get(3)?,
+        verified: source != LOGGED_SOURCE,
+        source,
+        created_at: row.get(5)?,
+    })
+}
+
+fn map_run_row(row: &Row) -> Result<EvalRun> {
+    let stages: String = row.get(2)?;
+    Ok(EvalRun {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        stages: stages.split(',').map(String::from).collect(),
+        total: row.get(3)?,
+        correct: row.get(4)?,
+        accuracy: row.get(5)?,
+        results: Vec::new(),
+        created_at: row.get(6)?,
+    })
+}
+
+impl Storage {
+    pub fn list_eval_cases(&self, project_id: &str) -> Result<Vec<EvalCase>> {
+        let conn = self.conn()?;
+        let cases = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM eval_cases WHERE project_id = ?1 ORDER BY created_at, id",
+                    CASE_COLUMNS
+                ),
+                params![project_id],
+                map_case_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(cases)
+    }
+
+    /// Returns `None` when the project already has a case with the same text.
+    pub fn add_eval_case(
+        &self,
+        project_id: &str,
+        input: &CreateEvalCase,
+        source: &str,
+    ) -> Result<Option<EvalCase>> {
+        let conn = self.conn()?;
+        let case = conn
+            .query_map(
+                &format!(
+                    "INSERT INTO eval_cases (id, project_id, text, expected_agent, source, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT(project_id, text) DO NOTHING
+                     RETURNING {}",
+                    CASE_COLUMNS
+                ),
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    project_id,
+                    input.text.trim(),
+                    input.expected_agent.trim(),
+                    source,
+                    chrono::Utc::now().timestamp()
+                ],
+                map_case_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(case)
+    }
+
+    pub fn delete_eval_case(&self, project_id: &str, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute(
+            "DELETE FROM eval_cases WHERE id = ?1 AND project_id = ?2",
+            params![id, project_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Adds cases from routing corrections, then from executions the
+    /// requester gave positive feedback, then from confident classification
+    /// logs. Logged labels are only the classifier's own decisions, so they
+    /// stay unverified, and cached, fallback and clarification decisions are
+    /// left out. Texts already in the dataset are skipped, so reviewed labels
+    /// win over logged ones. Returns how many cases were added.
+    pub fn seed_eval_cases(&self, project_id: &str, limit: i64) -> Result<usize> {
+        let conn = self.conn()?;
+
+        let corrected: Vec<(String, String)> = conn
+            .query_map(
+                "SELECT e.user_message, c.agent FROM routing_corrections c
+                 JOIN executions e ON e.id = c.execution_id
+                 WHERE e.project = ?1
+                 ORDER BY c.created_at DESC, c.execution_id LIMIT ?2",
+                params![project_id, limit],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let feedback: Vec<(String, String)> = conn
+            .query_map(
+                &format!(
+                    "SELECT e.user_message, e.agent FROM executions e {}
+                     WHERE e.project = ?1 AND e.agent IS NOT NULL
+                     GROUP BY e.id, e.user_message, e.agent
+                     HAVING {} > 0 AND {} = 0
+                     ORDER BY MAX(e.created_at) DESC LIMIT ?2",
+                    FEEDBACK_JOIN_VERIFIED, POSITIVE_DISTINCT, NEGATIVE_DISTINCT
+                ),
+                params![project_id, limit],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let logged: Vec<(String, String)> = conn
+            .query_map(
+                "SELECT text, agent FROM classification_logs
+                 WHERE project = ?1 AND method NOT IN ('fallback', 'clarification', 'cache')
+                   AND confidence >= ?3
+                 ORDER BY created_at DESC, id DESC LIMIT ?2",
+                params![project_id, limit, SEED_MIN_CONFIDENCE],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let now = chrono::Utc::now().timestamp();
+        let mut added = 0;
+        let sources = corrected
+            .into_iter()
+            .map(|case| (case, "correction"))
+            .chain(feedback.into_iter().map(|case| (case, "feedback")))
+            .chain(logged.into_iter().map(|case| (case, LOGGED_SOURCE)));
+        for ((text, agent), source) in sources {
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            added += conn.execute(
+                "INSERT INTO eval_cases (id, project_id, text, expected_agent, source, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(project_id, text) DO NOTHING",
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    project_id,
+                    text,
+                    agent,
+                    source,
+                    now
+                ],
+            )?;
+        }
+        Ok(added)
+    }
+
+    pub fn save_eval_run(&self, run: &EvalRun) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO eval_runs (id, project_id, stages, total, correct, accuracy, results, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                run.id,
+                run.project_id,
+                run.stages.join(","),
+                run.total,
+                run.correct,
+                run.accuracy,
+                serde_json::to_string(&run.results)?,
+                run.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_eval_runs(&self, project_id: &str, limit: i64) -> Result<Vec<EvalRun>> {
+        let conn = self.conn()?;
+        let runs = conn
+            .query_map(
+                "SELECT id, project_id, stages, total, correct, accuracy, created_at
+                 FROM eval_runs WHERE project_id = ?1
+                 ORDER BY created_at DESC, id DESC LIMIT ?2",
+                params![project_id, limit],
+                map_run_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(runs)
+    }
+
+    /// Most recent run of `project_id` over the same stages, with results.
+    pub fn last_eval_run(&self, project_id: &str, stages: &[String]) -> Result<Option<EvalRun>> {
+        let conn = self.conn()?;
+        let run = conn
+            .query_map(
+                "SELECT id, project_id, stages, total, correct, accuracy, created_at, results
+                 FROM eval_runs WHERE project_id = ?1 AND stages = ?2
+                 ORDER BY created_at DESC, id DESC LIMIT 1",
+                params![project_id, stages.join(",")],
+                |row| {
+                    let mut run = map_run_row(row)?;
+                    let results: String = row.get(7)?;
+                    run.results =
+                        serde_json::from_str::<Vec<EvalCaseResult>>(&results).unwrap_or_default();
+                    Ok(run)
+                },
+            )?
+            .next()
+            .transpose()?;
+        Ok(run)
+    }
+}
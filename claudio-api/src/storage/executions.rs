@@ -1,27 +1,140 @@
-use anyhow::Result;
-use rusqlite::params;
+use anyhow::{Result, bail};
 
+use super::backend::{Dialect, ToValue, Value, is_metadata_key, params, transaction};
 use super::core::Storage;
 use super::feedback::calculate_score;
+use super::rollups::{RollupSample, Rollups};
+use super::tags::{normalize_tag, select_annotations, tags_for};
 use super::types::{
-    ClassificationLog, Execution, ExecutionDetail, ExecutionFilter, ExecutionListItem,
-    ExecutionListResponse, FilterOptions, RecentExecution, Stats,
+    ClassificationLog, Execution, ExecutionDetail, ExecutionExport, ExecutionFilter,
+    ExecutionListItem, ExecutionListResponse, FilterOptions, RecentExecution, Stats,
 };
 
+const VERIFIED_POSITIVE: &str =
+    "SUM(CASE WHEN r.reaction IN ('thumbsup', '+1') AND r.user_id = e.requester THEN 1 ELSE 0 END)";
+
+const VERIFIED_NEGATIVE: &str = "SUM(CASE WHEN r.reaction IN ('thumbsdown', '-1') AND r.user_id = e.requester THEN 1 ELSE 0 END)";
+
 fn calculate_feedback(positive: i64, negative: i64) -> Option<i32> {
     calculate_score(positive, negative)
 }
 
+fn metadata_channel(metadata: Option<&str>) -> Option<String> {
+    metadata.and_then(|m| {
+        serde_json::from_str::<serde_json::Value>(m)
+            .ok()
+            .and_then(|v| v.get("channel").and_then(|c| c.as_str()).map(String::from))
+    })
+}
+
+fn tools_json(tools: &Option<Vec<String>>) -> Result<Option<String>> {
+    Ok(tools.as_ref().map(serde_json::to_string).transpose()?)
+}
+
+fn parse_tools(json: Option<String>) -> Option<Vec<String>> {
+    json.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+/// WHERE and HAVING clauses, with their parameters, selecting `filter`'s
+/// executions from `executions e` left-joined with feedback `reactions r`
+/// and grouped by `e.id`.
+fn filter_clauses(dialect: Dialect, filter: &ExecutionFilter) -> (String, String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+    let mut having_clause = String::new();
+
+    macro_rules! add_filter {
+        ($field:expr, $value:expr) => {
+            if let Some(ref v) = $value {
+                conditions.push(format!("{} = ?{}", $field, params.len() + 1));
+                params.push(v.to_value());
+            }
+        };
+    }
+
+    add_filter!("e.project", filter.project);
+    add_filter!("e.source", filter.source);
+    add_filter!("e.model", filter.model);
+    add_filter!("e.agent", filter.agent);
+    add_filter!("e.requester", filter.requester);
+
+    if let Some(ref ch) = filter.channel {
+        conditions.push(format!(
+            "{} = ?{}",
+            dialect.json_text("e.metadata", "channel"),
+            params.len() + 1
+        ));
+        params.push(ch.to_value());
+    }
+
+    for tag in &filter.tags {
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM execution_tags t WHERE t.execution_id = e.id AND t.tag = ?{})",
+            params.len() + 1
+        ));
+        params.push(Value::Text(normalize_tag(tag)));
+    }
+
+    if let Some(from) = filter.from {
+        conditions.push(format!("e.created_at >= ?{}", params.len() + 1));
+        params.push(Value::Integer(from));
+    }
+
+    if let Some(to) = filter.to {
+        conditions.push(format!("e.created_at <= ?{}", params.len() + 1));
+        params.push(Value::Integer(to));
+    }
+
+    if let Some(ref search) = filter.search {
+        conditions.push(format!(
+            "(e.user_message LIKE ?{} OR e.instruction LIKE ?{} OR e.response LIKE ?{})",
+            params.len() + 1,
+            params.len() + 2,
+            params.len() + 3
+        ));
+        let pattern = format!("%{}%", search);
+        params.extend(std::iter::repeat_n(Value::Text(pattern), 3));
+    }
+
+    if filter.failed_only == Some(true) {
+        conditions.push("e.response = ''".to_string());
+    }
+
+    // Postgres does not resolve output aliases in HAVING, so repeat the aggregates.
+    if let Some(fb) = filter.feedback {
+        having_clause = if fb == 0 {
+            format!(
+                "HAVING {} = 0 AND {} = 0",
+                VERIFIED_POSITIVE, VERIFIED_NEGATIVE
+            )
+        } else if fb == 1 {
+            format!("HAVING {} > {}", VERIFIED_POSITIVE, VERIFIED_NEGATIVE)
+        } else {
+            format!("HAVING {} > {}", VERIFIED_NEGATIVE, VERIFIED_POSITIVE)
+        };
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, having_clause, params)
+}
+
 impl Storage {
+    /// Stores an execution and adds it to its hourly rollup.
     pub fn save(&self, execution: &Execution) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
+        transaction(conn.as_ref(), || {
+            conn.execute(
             "INSERT INTO executions (
                 id, project, source, requester, agent, instruction, user_message, user_context,
                 response, structured_output, model, cost_usd, input_tokens, output_tokens,
                 cache_read_tokens, cache_creation_tokens, duration_ms, duration_api_ms,
-                session_id, metadata, created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+                session_id, metadata, agent_revision, allowed_tools, disallowed_tools, rerun_of,
+                experiment_id, variant, run_id, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28)",
             params![
                 execution.id,
                 execution.project,
@@ -43,19 +156,31 @@ impl Storage {
                 execution.duration_api_ms,
                 execution.session_id,
                 execution.metadata,
+                execution.agent_revision,
+                tools_json(&execution.allowed_tools)?,
+                tools_json(&execution.disallowed_tools)?,
+                execution.rerun_of,
+                execution.experiment_id,
+                execution.variant,
+                execution.run_id,
                 execution.created_at,
             ],
         )?;
-        Ok(())
+            let mut rollups = Rollups::default();
+            rollups.add(RollupSample::from(execution));
+            rollups.write(conn.as_ref())
+        })
     }
 
     pub fn save_classification(&self, log: &ClassificationLog) -> Result<i64> {
         let conn = self.conn()?;
-        conn.execute(
+        conn.query_row(
             "INSERT INTO classification_logs (
                 text, agent, model, confidence, method, matched_keyword,
-                reasoning, duration_ms, project, source, requester
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                reasoning, duration_ms, project, source, requester, agent_revision, llm_error,
+                run_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+            RETURNING id",
             params![
                 log.text,
                 log.agent,
@@ -68,9 +193,12 @@ impl Storage {
                 log.project,
                 log.source,
                 log.requester,
+                log.agent_revision,
+                log.llm_error,
+                log.run_id,
             ],
-        )?;
-        Ok(conn.last_insert_rowid())
+            |row| row.get(0),
+        )
     }
 
     pub fn update_metadata(&self, id: &str, updates: &serde_json::Value) -> Result<bool> {
@@ -109,19 +237,21 @@ impl Storage {
         ref_key: &str,
         ref_value: &str,
     ) -> Result<Option<Execution>> {
+        if !is_metadata_key(ref_key) {
+            bail!("Invalid metadata key: {}", ref_key);
+        }
+
         let conn = self.conn()?;
-        let pattern = format!("%\"{}\":\"{}%", ref_key, ref_value);
-        let mut stmt = conn.prepare(
-            "SELECT id, project, source, requester, agent, instruction, user_message, user_context,
+        let result = conn
+            .query_row(
+&format!("SELECT id, project, source, requester, agent, instruction, user_message, user_context,
                     response, structured_output, model, cost_usd, input_tokens, output_tokens,
                     cache_read_tokens, cache_creation_tokens, duration_ms, duration_api_ms,
-                    session_id, metadata, created_at
-             FROM executions WHERE source = ?1 AND metadata LIKE ?2
-             ORDER BY created_at DESC LIMIT 1",
-        )?;
-
-        let result = stmt
-            .query_row(params![source, pattern], |row| {
+                    session_id, metadata, agent_revision, allowed_tools, disallowed_tools, rerun_of,
+                    created_at, experiment_id, variant, run_id
+             FROM executions WHERE source = ?1 AND {} = ?2
+             ORDER BY created_at DESC LIMIT 1", conn.dialect().json_text("metadata", ref_key)),
+params![source, ref_value], |row| {
                 Ok(Execution {
                     id: row.get(0)?,
                     project: row.get(1)?,
@@ -143,7 +273,14 @@ impl Storage {
                     duration_api_ms: row.get(17)?,
                     session_id: row.get(18)?,
                     metadata: row.get(19)?,
-                    created_at: row.get(20)?,
+                    agent_revision: row.get(20)?,
+                    allowed_tools: parse_tools(row.get(21)?),
+                    disallowed_tools: parse_tools(row.get(22)?),
+                    rerun_of: row.get(23)?,
+                    experiment_id: row.get(25)?,
+                    variant: row.get(26)?,
+                    run_id: row.get(27)?,
+                    created_at: row.get(24)?,
                 })
             })
             .ok();
@@ -179,41 +316,25 @@ impl Storage {
             ),
         };
 
-        let (total, successful, cost, input, output, avg_dur): (i64, i64, f64, i64, i64, f64) = {
-            let mut stmt = conn.prepare(exec_query)?;
-            if let Some(p) = param {
-                stmt.query_row([p], |row| {
-                    Ok((
-                        row.get(0)?,
-                        row.get(1)?,
-                        row.get(2)?,
-                        row.get(3)?,
-                        row.get(4)?,
-                        row.get(5)?,
-                    ))
-                })?
-            } else {
-                stmt.query_row([], |row| {
-                    Ok((
-                        row.get(0)?,
-                        row.get(1)?,
-                        row.get(2)?,
-                        row.get(3)?,
-                        row.get(4)?,
-                        row.get(5)?,
-                    ))
-                })?
-            }
+        let params: &[Value] = match param {
+            Some(p) => params![p],
+            None => params![],
         };
 
-        let (positive, negative): (i64, i64) = {
-            let mut stmt = conn.prepare(fb_query)?;
-            if let Some(p) = param {
-                stmt.query_row([p], |row| Ok((row.get(0)?, row.get(1)?)))?
-            } else {
-                stmt.query_row([], |row| Ok((row.get(0)?, row.get(1)?)))?
-            }
-        };
+        let (total, successful, cost, input, output, avg_dur): (i64, i64, f64, i64, i64, f64) =
+            conn.query_row(exec_query, params, |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })?;
+
+        let (positive, negative): (i64, i64) =
+            conn.query_row(fb_query, params, |row| Ok((row.get(0)?, row.get(1)?)))?;
 
         Ok(Stats {
             total_requests: total,
@@ -229,8 +350,9 @@ impl Storage {
 
     pub fn get_recent_executions(&self, limit: i64) -> Result<Vec<RecentExecution>> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT e.id, e.project, e.agent, e.requester, substr(e.user_message, 1, 100),
+        let results = conn
+            .query_map(
+"SELECT e.id, e.project, e.agent, e.requester, substr(e.user_message, 1, 100),
                     CASE WHEN e.response != '' THEN 'completed' ELSE 'error' END,
                     e.cost_usd, e.duration_ms, e.created_at,
                     COALESCE(SUM(CASE WHEN r.reaction IN ('thumbsup', '+1') AND r.user_id = e.requester THEN 1 ELSE 0 END), 0),
@@ -238,10 +360,7 @@ impl Storage {
              FROM executions e
              LEFT JOIN reactions r ON e.id = r.execution_id AND r.category = 'feedback'
              GROUP BY e.id ORDER BY e.created_at DESC LIMIT ?1",
-        )?;
-
-        let results = stmt
-            .query_map([limit], |row| {
+params![limit], |row| {
                 let pos: i64 = row.get(9)?;
                 let neg: i64 = row.get(10)?;
                 Ok(RecentExecution {
@@ -272,87 +391,17 @@ impl Storage {
         let conn = self.conn()?;
         let offset = (page - 1) * limit;
 
-        let mut conditions = Vec::new();
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-        let mut having_clause = String::new();
-
-        macro_rules! add_filter {
-            ($field:expr, $value:expr) => {
-                if let Some(ref v) = $value {
-                    conditions.push(format!("{} = ?{}", $field, params.len() + 1));
-                    params.push(Box::new(v.clone()));
-                }
-            };
-        }
-
-        add_filter!("e.project", filter.project);
-        add_filter!("e.source", filter.source);
-        add_filter!("e.model", filter.model);
-        add_filter!("e.agent", filter.agent);
-        add_filter!("e.requester", filter.requester);
-
-        if let Some(ref ch) = filter.channel {
-            conditions.push(format!("e.metadata LIKE ?{}", params.len() + 1));
-            params.push(Box::new(format!("%\"channel\":\"{}%", ch)));
-        }
-
-        if let Some(from) = filter.from {
-            conditions.push(format!("e.created_at >= ?{}", params.len() + 1));
-            params.push(Box::new(from));
-        }
-
-        if let Some(to) = filter.to {
-            conditions.push(format!("e.created_at <= ?{}", params.len() + 1));
-            params.push(Box::new(to));
-        }
-
-        if let Some(ref search) = filter.search {
-            conditions.push(format!(
-                "(e.user_message LIKE ?{} OR e.instruction LIKE ?{} OR e.response LIKE ?{})",
-                params.len() + 1,
-                params.len() + 2,
-                params.len() + 3
-            ));
-            let pattern = format!("%{}%", search);
-            params.push(Box::new(pattern.clone()));
-            params.push(Box::new(pattern.clone()));
-            params.push(Box::new(pattern));
-        }
-
-        if filter.failed_only == Some(true) {
-            conditions.push("e.response = ''".to_string());
-        }
-
-        if let Some(fb) = filter.feedback {
-            having_clause = if fb == 0 {
-                "HAVING pos = 0 AND neg = 0".to_string()
-            } else if fb == 1 {
-                "HAVING pos > neg".to_string()
-            } else {
-                "HAVING neg > pos".to_string()
-            };
-        }
-
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", conditions.join(" AND "))
-        };
+        let (where_clause, having_clause, mut params) = filter_clauses(conn.dialect(), filter);
 
         let count_sql = format!(
             "SELECT COUNT(*) FROM (
-                SELECT e.id, SUM(CASE WHEN r.reaction IN ('thumbsup', '+1') AND r.user_id = e.requester THEN 1 ELSE 0 END) as pos,
-                       SUM(CASE WHEN r.reaction IN ('thumbsdown', '-1') AND r.user_id = e.requester THEN 1 ELSE 0 END) as neg
+                SELECT e.id
                 FROM executions e LEFT JOIN reactions r ON e.id = r.execution_id AND r.category = 'feedback'
                 {} GROUP BY e.id {}
-            )", where_clause, having_clause
+            ) filtered", where_clause, having_clause
         );
 
-        let total: i64 = {
-            let mut stmt = conn.prepare(&count_sql)?;
-            let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-            stmt.query_row(params_ref.as_slice(), |row| row.get(0))?
-        };
+        let total: i64 = conn.query_row(&count_sql, &params, |row| row.get(0))?;
 
         let query_sql = format!(
             "SELECT e.id, e.project, e.agent, e.source, e.requester, substr(e.user_message, 1, 150), substr(e.instruction, 1, 100),
@@ -364,20 +413,13 @@ impl Storage {
             where_clause, having_clause, params.len() + 1, params.len() + 2
         );
 
-        params.push(Box::new(limit));
-        params.push(Box::new(offset));
-
-        let mut stmt = conn.prepare(&query_sql)?;
-        let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        params.push(Value::Integer(limit));
+        params.push(Value::Integer(offset));
 
-        let executions = stmt
-            .query_map(params_ref.as_slice(), |row| {
+        let mut executions: Vec<ExecutionListItem> = conn
+            .query_map(&query_sql, &params, |row| {
                 let metadata: Option<String> = row.get(11)?;
-                let channel = metadata.as_ref().and_then(|m| {
-                    serde_json::from_str::<serde_json::Value>(m)
-                        .ok()
-                        .and_then(|v| v.get("channel").and_then(|c| c.as_str()).map(String::from))
-                });
+                let channel = metadata_channel(metadata.as_deref());
                 let pos: i64 = row.get(13)?;
                 let neg: i64 = row.get(14)?;
 
@@ -395,12 +437,19 @@ impl Storage {
                     duration_ms: row.get(10)?,
                     feedback: calculate_feedback(pos, neg),
                     channel,
+                    tags: Vec::new(),
                     created_at: row.get(12)?,
                 })
             })?
             .filter_map(|r| r.ok())
             .collect();
 
+        let ids: Vec<String> = executions.iter().map(|e| e.id.clone()).collect();
+        let mut tags = tags_for(conn.as_ref(), &ids)?;
+        for execution in &mut executions {
+            execution.tags = tags.remove(&execution.id).unwrap_or_default();
+        }
+
         let total_pages = (total + limit - 1) / limit;
 
         Ok(ExecutionListResponse {
@@ -412,21 +461,107 @@ impl Storage {
         })
     }
 
+    /// Executions matching `filter`, oldest first, `limit` at a time. Pass
+    /// the `created_at` and id of the last one returned to get the next batch.
+    pub fn export_executions(
+        &self,
+        filter: &ExecutionFilter,
+        after: Option<(i64, String)>,
+        limit: i64,
+    ) -> Result<Vec<ExecutionExport>> {
+        let conn = self.conn()?;
+        let (mut where_clause, having_clause, mut params) = filter_clauses(conn.dialect(), filter);
+        if let Some((created_at, id)) = after {
+            let cursor = format!(
+                "(e.created_at > ?{0} OR (e.created_at = ?{0} AND e.id > ?{1}))",
+                params.len() + 1,
+                params.len() + 2
+            );
+            where_clause = if where_clause.is_empty() {
+                format!("WHERE {}", cursor)
+            } else {
+                format!("{} AND {}", where_clause, cursor)
+            };
+            params.push(Value::Integer(created_at));
+            params.push(Value::Text(id));
+        }
+
+        let sql = format!(
+            "SELECT e.id, e.project, e.agent, e.source, e.requester, e.model, e.session_id,
+                    e.agent_revision, e.experiment_id, e.variant, e.rerun_of, e.cost_usd,
+                    e.input_tokens, e.output_tokens, e.cache_read_tokens, e.cache_creation_tokens,
+                    e.duration_ms, e.duration_api_ms, e.metadata, e.instruction, e.user_message,
+                    e.user_context, e.response, e.structured_output, e.created_at,
+                    COALESCE({}, 0), COALESCE({}, 0)
+             FROM executions e LEFT JOIN reactions r ON e.id = r.execution_id AND r.category = 'feedback'
+             {} GROUP BY e.id {} ORDER BY e.created_at, e.id LIMIT ?{}",
+            VERIFIED_POSITIVE,
+            VERIFIED_NEGATIVE,
+            where_clause,
+            having_clause,
+            params.len() + 1
+        );
+        params.push(Value::Integer(limit));
+
+        let mut executions: Vec<ExecutionExport> = conn
+            .query_map(&sql, &params, |row| {
+                let metadata: Option<String> = row.get(18)?;
+                Ok(ExecutionExport {
+                    id: row.get(0)?,
+                    project: row.get(1)?,
+                    agent: row.get(2)?,
+                    source: row.get(3)?,
+                    requester: row.get(4)?,
+                    channel: metadata_channel(metadata.as_deref()),
+                    model: row.get(5)?,
+                    session_id: row.get(6)?,
+                    agent_revision: row.get(7)?,
+                    experiment_id: row.get(8)?,
+                    variant: row.get(9)?,
+                    rerun_of: row.get(10)?,
+                    cost_usd: row.get(11)?,
+                    input_tokens: row.get(12)?,
+                    output_tokens: row.get(13)?,
+                    cache_read_tokens: row.get(14)?,
+                    cache_creation_tokens: row.get(15)?,
+                    duration_ms: row.get(16)?,
+                    duration_api_ms: row.get(17)?,
+                    feedback: calculate_feedback(row.get(25)?, row.get(26)?),
+                    tags: Vec::new(),
+                    metadata,
+                    instruction: row.get(19)?,
+                    user_message: row.get(20)?,
+                    user_context: row.get(21)?,
+                    response: row.get(22)?,
+                    structured_output: row.get(23)?,
+                    created_at: row.get(24)?,
+                })
+            })?
+            .collect::<Result<_>>()?;
+
+        let ids: Vec<String> = executions.iter().map(|e| e.id.clone()).collect();
+        let mut tags = tags_for(conn.as_ref(), &ids)?;
+        for execution in &mut executions {
+            execution.tags = tags.remove(&execution.id).unwrap_or_default();
+        }
+        Ok(executions)
+    }
+
     pub fn get_execution_by_id(&self, id: &str) -> Result<Option<ExecutionDetail>> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT e.id, e.project, e.agent, e.source, e.requester, e.session_id, e.instruction,
+        let result = conn
+            .query_row(
+"SELECT e.id, e.project, e.agent, e.source, e.requester, e.session_id, e.instruction,
                     e.user_message, e.user_context, e.response, e.structured_output, e.model,
                     e.cost_usd, e.input_tokens, e.output_tokens, e.cache_read_tokens,
                     e.cache_creation_tokens, e.duration_ms, e.duration_api_ms, e.metadata, e.created_at,
                     COALESCE(SUM(CASE WHEN r.reaction IN ('thumbsup', '+1') AND r.user_id = e.requester THEN 1 ELSE 0 END), 0),
-                    COALESCE(SUM(CASE WHEN r.reaction IN ('thumbsdown', '-1') AND r.user_id = e.requester THEN 1 ELSE 0 END), 0)
+                    COALESCE(SUM(CASE WHEN r.reaction IN ('thumbsdown', '-1') AND r.user_id = e.requester THEN 1 ELSE 0 END), 0),
+                    e.agent_revision, e.allowed_tools, e.disallowed_tools, e.rerun_of,
+                    e.experiment_id, e.variant, e.run_id
              FROM executions e LEFT JOIN reactions r ON e.id = r.execution_id AND r.category = 'feedback'
-             WHERE e.id = ?1 GROUP BY e.id"
-        )?;
-
-        let result = stmt
-            .query_row([id], |row| {
+             WHERE e.id = ?1 GROUP BY e.id",
+params![id], |row| {
                 let pos: i64 = row.get(21)?;
                 let neg: i64 = row.get(22)?;
                 Ok(ExecutionDetail {
@@ -451,12 +586,38 @@ impl Storage {
                     duration_api_ms: row.get(18)?,
                     feedback: calculate_feedback(pos, neg),
                     metadata: row.get(19)?,
+                    agent_revision: row.get(23)?,
+                    allowed_tools: parse_tools(row.get(24)?),
+                    disallowed_tools: parse_tools(row.get(25)?),
+                    rerun_of: row.get(26)?,
+                    experiment_id: row.get(27)?,
+                    variant: row.get(28)?,
+                    run_id: row.get(29)?,
+                    reruns: Vec::new(),
+                    tags: Vec::new(),
+                    annotations: Vec::new(),
                     created_at: row.get(20)?,
                 })
             })
             .ok();
 
-        Ok(result)
+        let Some(mut detail) = result else {
+            return Ok(None);
+        };
+        detail.tags = tags_for(conn.as_ref(), std::slice::from_ref(&detail.id))?
+            .remove(&detail.id)
+            .unwrap_or_default();
+        detail.annotations = select_annotations(conn.as_ref(), &detail.id)?;
+        detail.reruns = conn
+            .query_map(
+                "SELECT id FROM executions WHERE rerun_of = ?1 ORDER BY created_at, id",
+                params![detail.id],
+                |row| row.get(0),
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(Some(detail))
     }
 
     pub fn get_filter_options(&self) -> Result<FilterOptions> {
@@ -464,24 +625,35 @@ impl Storage {
 
         macro_rules! get_distinct {
             ($query:expr) => {{
-                let mut stmt = conn.prepare($query)?;
-                stmt.query_map([], |row| row.get(0))?
+                conn.query_map($query, params![], |row| row.get(0))?
                     .filter_map(|r| r.ok())
                     .collect()
             }};
         }
 
         Ok(FilterOptions {
-            projects: get_distinct!("SELECT DISTINCT project FROM executions WHERE project IS NOT NULL ORDER BY project"),
-            sources: get_distinct!("SELECT DISTINCT source FROM executions WHERE source IS NOT NULL AND source != '' ORDER BY source"),
-            models: get_distinct!("SELECT DISTINCT model FROM executions WHERE model IS NOT NULL ORDER BY model"),
-            agents: get_distinct!("SELECT DISTINCT agent FROM executions WHERE agent IS NOT NULL ORDER BY agent"),
-            requesters: get_distinct!("SELECT DISTINCT requester FROM executions WHERE requester IS NOT NULL ORDER BY requester"),
-            channels: get_distinct!(
-                "SELECT DISTINCT json_extract(metadata, '$.channel') as ch
-                 FROM executions WHERE metadata IS NOT NULL AND json_extract(metadata, '$.channel') IS NOT NULL
-                 ORDER BY ch"
+            projects: get_distinct!(
+                "SELECT DISTINCT project FROM executions WHERE project IS NOT NULL ORDER BY project"
+            ),
+            sources: get_distinct!(
+                "SELECT DISTINCT source FROM executions WHERE source IS NOT NULL AND source != '' ORDER BY source"
+            ),
+            models: get_distinct!(
+                "SELECT DISTINCT model FROM executions WHERE model IS NOT NULL ORDER BY model"
+            ),
+            agents: get_distinct!(
+                "SELECT DISTINCT agent FROM executions WHERE agent IS NOT NULL ORDER BY agent"
+            ),
+            requesters: get_distinct!(
+                "SELECT DISTINCT requester FROM executions WHERE requester IS NOT NULL ORDER BY requester"
             ),
+            channels: get_distinct!(&format!(
+                "SELECT DISTINCT {channel} as ch
+                 FROM executions WHERE metadata IS NOT NULL AND {channel} IS NOT NULL
+                 ORDER BY ch",
+                channel = conn.dialect().json_text("metadata", "channel")
+            )),
+            tags: get_distinct!("SELECT DISTINCT tag FROM execution_tags ORDER BY tag"),
         })
     }
 }
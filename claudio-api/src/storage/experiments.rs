@@ -0,0 +1,231 @@
+use anyhow::Result;
+
+use super::backend::{Row, ToValue, Value, params, transaction};
+use super::core::Storage;
+use super::types::{CreateExperiment, Experiment, ExperimentVariant, UpdateExperiment};
+
+const EXPERIMENT_COLUMNS: &str =
+    "id, project_id, agent, name, status, variants, created_at, updated_at";
+
+fn map_experiment_row(row: &Row) -> Result<Experiment> {
+    let variants: String = row.get(5)?;
+    Ok(Experiment {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        agent: row.get(2)?,
+        name: row.get(3)?,
+        status: row.get(4)?,
+        variants: serde_json::from_str(&variants)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+/// Picks the variant `roll` lands on when the variants' weights are laid
+/// end to end.
+fn pick_variant(variants: &[ExperimentVariant], roll: u64) -> Option<&ExperimentVariant> {
+    let total: u64 = variants.iter().map(|v| u64::from(v.weight)).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut point = roll % total;
+    variants.iter().find(|v| {
+        let weight = u64::from(v.weight);
+        if point < weight {
+            return true;
+        }
+        point -= weight;
+        false
+    })
+}
+
+/// FNV-1a, so a requester keeps the same roll across restarts.
+fn stable_roll(experiment_id: &str, requester: &str) -> u64 {
+    format!("{}:{}", experiment_id, requester)
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        })
+}
+
+impl Storage {
+    pub fn list_experiments(&self, project_id: &str) -> Result<Vec<Experiment>> {
+        let conn = self.conn()?;
+        let experiments = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM experiments WHERE project_id = ?1 ORDER BY created_at DESC, id",
+                    EXPERIMENT_COLUMNS
+                ),
+                params![project_id],
+                map_experiment_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(experiments)
+    }
+
+    pub fn get_experiment(&self, id: &str) -> Result<Option<Experiment>> {
+        let conn = self.conn()?;
+        let experiment = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM experiments WHERE id = ?1",
+                    EXPERIMENT_COLUMNS
+                ),
+                params![id],
+                map_experiment_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(experiment)
+    }
+
+    /// Running experiment on `agent`, if any.
+    pub fn running_experiment(&self, project_id: &str, agent: &str) -> Result<Option<Experiment>> {
+        let conn = self.conn()?;
+        let experiment = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM experiments
+                     WHERE project_id = ?1 AND agent = ?2 AND status = 'running'
+                     ORDER BY created_at DESC LIMIT 1",
+                    EXPERIMENT_COLUMNS
+                ),
+                params![project_id, agent],
+                map_experiment_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(experiment)
+    }
+
+    pub fn create_experiment(
+        &self,
+        project_id: &str,
+        input: &CreateExperiment,
+    ) -> Result<Experiment> {
+        let conn = self.conn()?;
+        let now = chrono::Utc::now().timestamp();
+        let experiment = Experiment {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_id: project_id.to_string(),
+            agent: input.agent.clone(),
+            name: input.name.clone(),
+            status: "running".to_string(),
+            variants: input.variants.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        conn.execute(
+            "INSERT INTO experiments (id, project_id, agent, name, status, variants, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                experiment.id,
+                experiment.project_id,
+                experiment.agent,
+                experiment.name,
+                experiment.status,
+                serde_json::to_string(&experiment.variants)?,
+                experiment.created_at,
+                experiment.updated_at
+            ],
+        )?;
+        Ok(experiment)
+    }
+
+    pub fn update_experiment(
+        &self,
+        id: &str,
+        input: &UpdateExperiment,
+    ) -> Result<Option<Experiment>> {
+        let conn = self.conn()?;
+        let mut sets = vec!["updated_at = ?1".to_string()];
+        let mut params: Vec<Value> = vec![Value::Integer(chrono::Utc::now().timestamp())];
+        if let Some(ref name) = input.name {
+            params.push(name.to_value());
+            sets.push(format!("name = ?{}", params.len()));
+        }
+        if let Some(ref status) = input.status {
+            params.push(status.to_value());
+            sets.push(format!("status = ?{}", params.len()));
+        }
+        if let Some(ref variants) = input.variants {
+            params.push(Value::Text(serde_json::to_string(variants)?));
+            sets.push(format!("variants = ?{}", params.len()));
+        }
+        params.push(id.to_value());
+        let sql = format!(
+            "UPDATE experiments SET {} WHERE id = ?{}",
+            sets.join(", "),
+            params.len()
+        );
+        if conn.execute(&sql, &params)? == 0 {
+            return Ok(None);
+        }
+        self.get_experiment(id)
+    }
+
+    pub fn delete_experiment(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        transaction(conn.as_ref(), || {
+            conn.execute(
+                "DELETE FROM experiment_assignments WHERE experiment_id = ?1",
+                params![id],
+            )?;
+            let deleted = conn.execute("DELETE FROM experiments WHERE id = ?1", params![id])?;
+            Ok(deleted > 0)
+        })
+    }
+
+    /// Variant `requester` is in. The first assignment is stored and kept
+    /// while its variant exists, so weight changes don't move requesters
+    /// around; requests without a requester get a fresh random variant.
+    pub fn assign_variant(
+        &self,
+        experiment: &Experiment,
+        requester: Option<&str>,
+    ) -> Result<Option<ExperimentVariant>> {
+        let Some(requester) = requester else {
+            let roll = uuid::Uuid::new_v4().as_u64_pair().0;
+            return Ok(pick_variant(&experiment.variants, roll).cloned());
+        };
+
+        let conn = self.conn()?;
+        // Only a missing row means unassigned; other errors must not re-roll
+        let assigned: Option<String> = conn
+            .query_map(
+                "SELECT variant FROM experiment_assignments WHERE experiment_id = ?1 AND requester = ?2",
+                params![experiment.id, requester],
+                |row| row.get(0),
+            )?
+            .next()
+            .transpose()?;
+        if let Some(variant) = assigned
+            .as_ref()
+            .and_then(|name| experiment.variants.iter().find(|v| &v.name == name))
+        {
+            return Ok(Some(variant.clone()));
+        }
+
+        let Some(variant) =
+            pick_variant(&experiment.variants, stable_roll(&experiment.id, requester))
+        else {
+            return Ok(None);
+        };
+        conn.execute(
+            "INSERT INTO experiment_assignments (experiment_id, requester, variant, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(experiment_id, requester) DO UPDATE SET
+                variant = excluded.variant,
+                created_at = excluded.created_at",
+            params![
+                experiment.id,
+                requester,
+                variant.name,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(Some(variant.clone()))
+    }
+}
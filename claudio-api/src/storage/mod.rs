@@ -1,11 +1,34 @@
+mod alerts;
+mod backend;
+mod blocking;
+mod budgets;
 mod core;
+mod eval;
 mod executions;
+mod experiments;
 mod feedback;
 mod projects;
 mod reactions;
+mod reports;
+mod revisions;
+mod rollups;
+mod semantic;
+mod suggestions;
+mod tags;
 mod types;
 mod users;
+mod views;
+mod webhooks;
 
+#[cfg(test)]
+mod conformance;
+
+pub(crate) use backend::params;
+#[cfg(test)]
+pub(crate) use backend::{Backend, SqliteBackend};
+pub use backend::{Connection, Dialect, PoolState, Row, ToValue, Value};
+pub use blocking::{AsyncStorage, Overloaded};
 pub use core::Storage;
 pub use feedback::{is_feedback_reaction, sql as feedback_sql};
+pub use tags::normalize_tag;
 pub use types::*;
@@ -1,10 +1,18 @@
 use anyhow::Result;
-use rusqlite::params;
 
+use super::backend::{Connection, Dialect, Row, ToValue, Value, params, transaction};
 use super::core::{Storage, slugify};
-use super::types::{Agent, CreateAgent, CreateProject, Project, UpdateAgent, UpdateProject};
+use super::revisions::{record_revision, select_revision};
+use super::types::{
+    Agent, CreateAgent, CreateProject, Project, RevisionKind, UpdateAgent, UpdateProject,
+};
 
-fn map_project_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+const PROJECT_COLUMNS: &str = "id, name, system_prompt, allowed_tools, disallowed_tools, is_default,
+    enable_user_context, fallback_agent, classify_model, classify_timeout, rate_limit_rpm, revision, created_at, updated_at, clarify_threshold";
+
+const AGENT_COLUMNS: &str = "id, project_id, name, description, model, priority, keywords, examples, instruction, tools, output_schema, timeout, static_response, working_dir, revision, created_at, updated_at, negative_keywords, counterexamples";
+
+fn map_project_row(row: &Row) -> Result<Project> {
     let allowed: Option<String> = row.get(3)?;
     let disallowed: Option<String> = row.get(4)?;
     Ok(Project {
@@ -13,22 +21,26 @@ fn map_project_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
         system_prompt: row.get(2)?,
         allowed_tools: allowed.and_then(|s| serde_json::from_str(&s).ok()),
         disallowed_tools: disallowed.and_then(|s| serde_json::from_str(&s).ok()),
-        is_default: row.get::<_, i32>(5)? == 1,
-        enable_user_context: row.get::<_, i32>(6)? == 1,
+        is_default: row.get::<i32>(5)? == 1,
+        enable_user_context: row.get::<i32>(6)? == 1,
         fallback_agent: row.get(7)?,
         classify_model: row.get(8)?,
         classify_timeout: row.get(9)?,
         rate_limit_rpm: row.get(10)?,
-        created_at: row.get(11)?,
-        updated_at: row.get(12)?,
+        revision: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+        clarify_threshold: row.get(14)?,
     })
 }
 
-fn map_agent_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+fn map_agent_row(row: &Row) -> Result<Agent> {
     let keywords: String = row.get(6)?;
     let examples: String = row.get(7)?;
     let tools: Option<String> = row.get(9)?;
     let output_schema: Option<String> = row.get(10)?;
+    let negative_keywords: String = row.get(17)?;
+    let counterexamples: String = row.get(18)?;
     Ok(Agent {
         id: row.get(0)?,
         project_id: row.get(1)?,
@@ -37,28 +49,169 @@ fn map_agent_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
         model: row.get(4)?,
         priority: row.get(5)?,
         keywords: serde_json::from_str(&keywords).unwrap_or_default(),
+        negative_keywords: serde_json::from_str(&negative_keywords).unwrap_or_default(),
         examples: serde_json::from_str(&examples).unwrap_or_default(),
+        counterexamples: serde_json::from_str(&counterexamples).unwrap_or_default(),
         instruction: row.get(8)?,
         tools: tools.and_then(|s| serde_json::from_str(&s).ok()),
         output_schema: output_schema.and_then(|s| serde_json::from_str(&s).ok()),
         timeout: row.get(11)?,
-        static_response: row.get::<_, i32>(12)? == 1,
+        static_response: row.get::<i32>(12)? == 1,
         working_dir: row.get(13)?,
-        created_at: row.get(14)?,
-        updated_at: row.get(15)?,
+        revision: row.get(14)?,
+        created_at: row.get(15)?,
+        updated_at: row.get(16)?,
     })
 }
 
+fn select_project(conn: &dyn Connection, id: &str) -> Option<Project> {
+    conn.query_row(
+        &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+        params![id],
+        map_project_row,
+    )
+    .ok()
+}
+
+fn select_agent(conn: &dyn Connection, id: &str) -> Option<Agent> {
+    conn.query_row(
+        &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+        params![id],
+        map_agent_row,
+    )
+    .ok()
+}
+
+/// Applies an agent update and records its revision; callers run it in a
+/// transaction.
+pub(super) fn update_agent_in(
+    conn: &dyn Connection,
+    id: &str,
+    input: &UpdateAgent,
+) -> Result<Option<Agent>> {
+    let now = chrono::Utc::now().timestamp();
+    let previous = input.name.as_ref().and_then(|_| select_agent(conn, id));
+
+    let mut sets = vec!["updated_at = ?1".to_string()];
+    let mut params: Vec<Value> = vec![Value::Integer(now)];
+    let mut idx = 2;
+
+    macro_rules! add_field {
+        ($field:expr, $value:expr) => {
+            if let Some(ref v) = $value {
+                sets.push(format!("{} = ?{}", $field, idx));
+                params.push(v.to_value());
+                idx += 1;
+            }
+        };
+        ($field:expr, $value:expr, json) => {
+            if let Some(ref v) = $value {
+                sets.push(format!("{} = ?{}", $field, idx));
+                params.push(Value::Text(serde_json::to_string(v)?));
+                idx += 1;
+            }
+        };
+        ($field:expr, $value:expr, bool) => {
+            if let Some(v) = $value {
+                sets.push(format!("{} = ?{}", $field, idx));
+                params.push(v.to_value());
+                idx += 1;
+            }
+        };
+    }
+
+    add_field!("name", input.name);
+    add_field!("description", input.description);
+    add_field!("model", input.model);
+    add_field!("priority", input.priority);
+    add_field!("instruction", input.instruction);
+    add_field!("timeout", input.timeout);
+    add_field!("keywords", input.keywords, json);
+    add_field!("negative_keywords", input.negative_keywords, json);
+    add_field!("examples", input.examples, json);
+    add_field!("counterexamples", input.counterexamples, json);
+    add_field!("tools", input.tools, json);
+    add_field!("output_schema", input.output_schema, json);
+    add_field!("static_response", input.static_response, bool);
+    add_field!("working_dir", input.working_dir);
+    sets.push("revision = revision + 1".to_string());
+
+    params.push(id.to_value());
+    let sql = format!("UPDATE agents SET {} WHERE id = ?{}", sets.join(", "), idx);
+
+    if conn.execute(&sql, &params)? == 0 {
+        return Ok(None);
+    }
+    let agent = select_agent(conn, id)
+        .ok_or_else(|| anyhow::anyhow!("Agent '{}' vanished during update", id))?;
+    if let Some(previous) = previous {
+        rename_agent_refs(conn, &agent.project_id, &previous.name, &agent.name)?;
+    }
+    record_revision(
+        conn,
+        RevisionKind::Agent,
+        id,
+        agent.revision,
+        "update",
+        &agent,
+    )?;
+    Ok(Some(agent))
+}
+
+/// Points rows that refer to an agent by name at its new name, so a rename
+/// doesn't detach them.
+fn rename_agent_refs(conn: &dyn Connection, project_id: &str, old: &str, new: &str) -> Result<()> {
+    if old == new {
+        return Ok(());
+    }
+    conn.execute(
+        "UPDATE experiments SET agent = ?1 WHERE project_id = ?2 AND agent = ?3",
+        params![new, project_id, old],
+    )?;
+    conn.execute(
+        "UPDATE budgets SET target = ?1 WHERE project_id = ?2 AND scope = 'agent' AND target = ?3",
+        params![new, project_id, old],
+    )?;
+    Ok(())
+}
+
+/// An agent by name, locked against concurrent updates until the
+/// transaction ends. SQLite transactions already hold the write lock.
+pub(super) fn lock_agent_by_name(
+    conn: &dyn Connection,
+    project_id: &str,
+    name: &str,
+) -> Result<Option<Agent>> {
+    let lock = match conn.dialect() {
+        Dialect::Sqlite => "",
+        Dialect::Postgres => " FOR UPDATE",
+    };
+    let agent = conn
+        .query_map(
+            &format!(
+                "SELECT {} FROM agents WHERE project_id = ?1 AND name = ?2{}",
+                AGENT_COLUMNS, lock
+            ),
+            params![project_id, name],
+            map_agent_row,
+        )?
+        .next()
+        .transpose()?;
+    Ok(agent)
+}
+
 impl Storage {
     pub fn list_projects(&self) -> Result<Vec<Project>> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, name, system_prompt, allowed_tools, disallowed_tools, is_default,
-                    enable_user_context, fallback_agent, classify_model, classify_timeout, rate_limit_rpm, created_at, updated_at
-             FROM projects ORDER BY is_default DESC, name",
-        )?;
-        let projects = stmt
-            .query_map([], map_project_row)?
+        let projects = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM projects ORDER BY is_default DESC, name",
+                    PROJECT_COLUMNS
+                ),
+                params![],
+                map_project_row,
+            )?
             .filter_map(|r| r.ok())
             .collect();
         Ok(projects)
@@ -66,26 +219,18 @@ impl Storage {
 
     pub fn get_project(&self, id: &str) -> Result<Option<Project>> {
         let conn = self.conn()?;
-        let result = conn
-            .query_row(
-                "SELECT id, name, system_prompt, allowed_tools, disallowed_tools, is_default,
-                        enable_user_context, fallback_agent, classify_model, classify_timeout, rate_limit_rpm, created_at, updated_at
-                 FROM projects WHERE id = ?1",
-                [id],
-                map_project_row,
-            )
-            .ok();
-        Ok(result)
+        Ok(select_project(conn.as_ref(), id))
     }
 
     pub fn get_default_project(&self) -> Result<Option<Project>> {
         let conn = self.conn()?;
         let result = conn
             .query_row(
-                "SELECT id, name, system_prompt, allowed_tools, disallowed_tools, is_default,
-                        enable_user_context, fallback_agent, classify_model, classify_timeout, rate_limit_rpm, created_at, updated_at
-                 FROM projects WHERE is_default = 1 LIMIT 1",
-                [],
+                &format!(
+                    "SELECT {} FROM projects WHERE is_default = 1 LIMIT 1",
+                    PROJECT_COLUMNS
+                ),
+                params![],
                 map_project_row,
             )
             .ok();
@@ -103,12 +248,10 @@ impl Storage {
             ));
         }
 
-        conn.execute("BEGIN IMMEDIATE", [])?;
-
-        let result = (|| -> Result<Project> {
+        transaction(conn.as_ref(), || {
             let exists: bool = conn.query_row(
                 "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
-                [&id],
+                params![id],
                 |row| row.get(0),
             )?;
             if exists {
@@ -118,7 +261,7 @@ impl Storage {
             if input.is_default {
                 conn.execute(
                     "UPDATE projects SET is_default = 0 WHERE is_default = 1",
-                    [],
+                    params![],
                 )?;
             }
 
@@ -135,14 +278,15 @@ impl Storage {
 
             conn.execute(
                 "INSERT INTO projects (id, name, system_prompt, allowed_tools, disallowed_tools, is_default,
-                                       enable_user_context, fallback_agent, classify_model, classify_timeout, rate_limit_rpm, created_at, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                                       enable_user_context, fallback_agent, classify_model, classify_timeout, rate_limit_rpm, created_at, updated_at,
+                                       clarify_threshold)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
                 params![id, input.name, input.system_prompt, allowed, disallowed,
                         input.is_default as i32, input.enable_user_context as i32, input.fallback_agent, input.classify_model, input.classify_timeout,
-                        input.rate_limit_rpm, now, now],
+                        input.rate_limit_rpm, now, now, input.clarify_threshold],
             )?;
 
-            Ok(Project {
+            let project = Project {
                 id,
                 name: input.name,
                 system_prompt: input.system_prompt,
@@ -154,59 +298,56 @@ impl Storage {
                 classify_model: input.classify_model,
                 classify_timeout: input.classify_timeout,
                 rate_limit_rpm: input.rate_limit_rpm,
+                clarify_threshold: input.clarify_threshold,
+                revision: 1,
                 created_at: now,
                 updated_at: now,
-            })
-        })();
+            };
+            record_revision(
+                conn.as_ref(),
+                RevisionKind::Project,
+                &project.id,
+                1,
+                "create",
+                &project,
+            )?;
 
-        match result {
-            Ok(project) => {
-                conn.execute("COMMIT", [])?;
-                Ok(project)
-            }
-            Err(e) => {
-                if let Err(rollback_err) = conn.execute("ROLLBACK", []) {
-                    tracing::error!(error = %rollback_err, "Failed to rollback transaction");
-                }
-                Err(e)
-            }
-        }
+            Ok(project)
+        })
     }
 
     pub fn update_project(&self, id: &str, input: &UpdateProject) -> Result<Option<Project>> {
         let conn = self.conn()?;
         let now = chrono::Utc::now().timestamp();
 
-        conn.execute("BEGIN IMMEDIATE", [])?;
-
-        let result = (|| -> Result<()> {
+        transaction(conn.as_ref(), || {
             if input.is_default == Some(true) {
                 conn.execute(
                     "UPDATE projects SET is_default = 0 WHERE is_default = 1 AND id != ?1",
-                    [id],
+                    params![id],
                 )?;
             }
 
             let mut updates = vec!["updated_at = ?1"];
-            let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
+            let mut params: Vec<Value> = vec![Value::Integer(now)];
 
             macro_rules! add_field {
                 ($field:expr, $value:expr) => {
                     if let Some(ref v) = $value {
                         updates.push($field);
-                        params.push(Box::new(v.clone()));
+                        params.push(v.to_value());
                     }
                 };
                 ($field:expr, $value:expr, json) => {
                     if let Some(ref v) = $value {
                         updates.push($field);
-                        params.push(Box::new(serde_json::to_string(v)?));
+                        params.push(Value::Text(serde_json::to_string(v)?));
                     }
                 };
                 ($field:expr, $value:expr, bool) => {
                     if let Some(v) = $value {
                         updates.push($field);
-                        params.push(Box::new(v as i32));
+                        params.push(v.to_value());
                     }
                 };
             }
@@ -221,8 +362,9 @@ impl Storage {
             add_field!("classify_model = ?", input.classify_model);
             add_field!("classify_timeout = ?", input.classify_timeout);
             add_field!("rate_limit_rpm = ?", input.rate_limit_rpm);
+            add_field!("clarify_threshold = ?", input.clarify_threshold);
 
-            let placeholders: Vec<String> = updates
+            let mut placeholders: Vec<String> = updates
                 .iter()
                 .enumerate()
                 .map(|(i, u)| {
@@ -233,33 +375,83 @@ impl Storage {
                     }
                 })
                 .collect();
+            placeholders.push("revision = revision + 1".to_string());
 
-            params.push(Box::new(id.to_string()));
+            params.push(id.to_value());
             let sql = format!(
                 "UPDATE projects SET {} WHERE id = ?{}",
                 placeholders.join(", "),
                 params.len()
             );
 
-            let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-            conn.execute(&sql, params_ref.as_slice())?;
-            Ok(())
-        })();
-
-        match result {
-            Ok(()) => {
-                conn.execute("COMMIT", [])?;
+            if conn.execute(&sql, &params)? == 0 {
+                return Ok(None);
             }
-            Err(e) => {
-                if let Err(rollback_err) = conn.execute("ROLLBACK", []) {
-                    tracing::error!(error = %rollback_err, "Failed to rollback transaction");
-                }
-                return Err(e);
+
+            let project = select_project(conn.as_ref(), id)
+                .ok_or_else(|| anyhow::anyhow!("Project '{}' vanished during update", id))?;
+            record_revision(
+                conn.as_ref(),
+                RevisionKind::Project,
+                id,
+                project.revision,
+                "update",
+                &project,
+            )?;
+            Ok(Some(project))
+        })
+    }
+
+    /// Restores a project's configuration from a past revision, recording the
+    /// rollback as a new revision. `is_default` is left untouched since it is
+    /// shared across projects.
+    pub fn rollback_project(&self, id: &str, revision: i64) -> Result<Option<Project>> {
+        let conn = self.conn()?;
+        let now = chrono::Utc::now().timestamp();
+
+        transaction(conn.as_ref(), || {
+            let Some(target) = select_revision(conn.as_ref(), RevisionKind::Project, id, revision)?
+            else {
+                return Ok(None);
+            };
+            let snapshot: Project = serde_json::from_value(target.snapshot)?;
+
+            let allowed = snapshot
+                .allowed_tools
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+            let disallowed = snapshot
+                .disallowed_tools
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+
+            let updated = conn.execute(
+                "UPDATE projects SET name = ?1, system_prompt = ?2, allowed_tools = ?3, disallowed_tools = ?4,
+                        enable_user_context = ?5, fallback_agent = ?6, classify_model = ?7, classify_timeout = ?8,
+                        rate_limit_rpm = ?9, clarify_threshold = ?10, revision = revision + 1, updated_at = ?11
+                 WHERE id = ?12",
+                params![snapshot.name, snapshot.system_prompt, allowed, disallowed,
+                        snapshot.enable_user_context as i32, snapshot.fallback_agent, snapshot.classify_model,
+                        snapshot.classify_timeout, snapshot.rate_limit_rpm, snapshot.clarify_threshold, now, id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
             }
-        }
 
-        drop(conn);
-        self.get_project(id)
+            let project = select_project(conn.as_ref(), id)
+                .ok_or_else(|| anyhow::anyhow!("Project '{}' vanished during rollback", id))?;
+            record_revision(
+                conn.as_ref(),
+                RevisionKind::Project,
+                id,
+                project.revision,
+                &format!("rollback:{}", revision),
+                &project,
+            )?;
+            Ok(Some(project))
+        })
     }
 
     pub fn delete_project(&self, id: &str) -> Result<bool> {
@@ -268,8 +460,8 @@ impl Storage {
         let is_default: bool = conn
             .query_row(
                 "SELECT is_default FROM projects WHERE id = ?1",
-                [id],
-                |row| Ok(row.get::<_, i32>(0)? == 1),
+                params![id],
+                |row| Ok(row.get::<i32>(0)? == 1),
             )
             .unwrap_or(false);
 
@@ -277,18 +469,36 @@ impl Storage {
             return Err(anyhow::anyhow!("Cannot delete the default project"));
         }
 
-        let deleted = conn.execute("DELETE FROM projects WHERE id = ?1", [id])?;
-        Ok(deleted > 0)
+        // Databases created before these tables had foreign keys don't cascade.
+        transaction(conn.as_ref(), || {
+            conn.execute(
+                "DELETE FROM experiment_assignments
+                 WHERE experiment_id IN (SELECT id FROM experiments WHERE project_id = ?1)",
+                params![id],
+            )?;
+            conn.execute("DELETE FROM experiments WHERE project_id = ?1", params![id])?;
+            conn.execute(
+                "DELETE FROM budget_alerts
+                 WHERE budget_id IN (SELECT id FROM budgets WHERE project_id = ?1)",
+                params![id],
+            )?;
+            conn.execute("DELETE FROM budgets WHERE project_id = ?1", params![id])?;
+            let deleted = conn.execute("DELETE FROM projects WHERE id = ?1", params![id])?;
+            Ok(deleted > 0)
+        })
     }
 
     pub fn list_agents(&self, project_id: &str) -> Result<Vec<Agent>> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, project_id, name, description, model, priority, keywords, examples, instruction, tools, output_schema, timeout, static_response, working_dir, created_at, updated_at
-             FROM agents WHERE project_id = ?1 ORDER BY priority DESC, name",
-        )?;
-        let agents = stmt
-            .query_map([project_id], map_agent_row)?
+        let agents = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM agents WHERE project_id = ?1 ORDER BY priority DESC, name",
+                    AGENT_COLUMNS
+                ),
+                params![project_id],
+                map_agent_row,
+            )?
             .filter_map(|r| r.ok())
             .collect();
         Ok(agents)
@@ -296,23 +506,17 @@ impl Storage {
 
     pub fn get_agent(&self, id: &str) -> Result<Option<Agent>> {
         let conn = self.conn()?;
-        let result = conn
-            .query_row(
-                "SELECT id, project_id, name, description, model, priority, keywords, examples, instruction, tools, output_schema, timeout, static_response, working_dir, created_at, updated_at
-                 FROM agents WHERE id = ?1",
-                [id],
-                map_agent_row,
-            )
-            .ok();
-        Ok(result)
+        Ok(select_agent(conn.as_ref(), id))
     }
 
     pub fn get_agent_by_name(&self, project_id: &str, name: &str) -> Result<Option<Agent>> {
         let conn = self.conn()?;
         let result = conn
             .query_row(
-                "SELECT id, project_id, name, description, model, priority, keywords, examples, instruction, tools, output_schema, timeout, static_response, working_dir, created_at, updated_at
-                 FROM agents WHERE project_id = ?1 AND name = ?2",
+                &format!(
+                    "SELECT {} FROM agents WHERE project_id = ?1 AND name = ?2",
+                    AGENT_COLUMNS
+                ),
                 params![project_id, name],
                 map_agent_row,
             )
@@ -334,7 +538,7 @@ impl Storage {
 
         let exists: bool = conn.query_row(
             "SELECT EXISTS(SELECT 1 FROM agents WHERE id = ?1)",
-            [&id],
+            params![id],
             |row| row.get(0),
         )?;
         if exists {
@@ -342,7 +546,9 @@ impl Storage {
         }
 
         let keywords = serde_json::to_string(&input.keywords)?;
+        let negative_keywords = serde_json::to_string(&input.negative_keywords)?;
         let examples = serde_json::to_string(&input.examples)?;
+        let counterexamples = serde_json::to_string(&input.counterexamples)?;
         let tools = input
             .tools
             .as_ref()
@@ -354,13 +560,7 @@ impl Storage {
             .map(serde_json::to_string)
             .transpose()?;
 
-        conn.execute(
-            "INSERT INTO agents (id, project_id, name, description, model, priority, keywords, examples, instruction, tools, output_schema, timeout, static_response, working_dir, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
-            params![id, project_id, input.name, input.description, input.model, input.priority, keywords, examples, input.instruction, tools, output_schema, input.timeout, input.static_response as i32, input.working_dir, now, now],
-        )?;
-
-        Ok(Agent {
+        let agent = Agent {
             id,
             project_id: project_id.to_string(),
             name: input.name,
@@ -368,76 +568,112 @@ impl Storage {
             model: input.model,
             priority: input.priority,
             keywords: input.keywords,
+            negative_keywords: input.negative_keywords,
             examples: input.examples,
+            counterexamples: input.counterexamples,
             instruction: input.instruction,
             tools: input.tools,
             output_schema: input.output_schema,
             timeout: input.timeout,
             static_response: input.static_response,
             working_dir: input.working_dir,
+            revision: 1,
             created_at: now,
             updated_at: now,
-        })
+        };
+
+        transaction(conn.as_ref(), || {
+            conn.execute(
+                "INSERT INTO agents (id, project_id, name, description, model, priority, keywords, examples, instruction, tools, output_schema, timeout, static_response, working_dir, created_at, updated_at, negative_keywords, counterexamples)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                params![agent.id, project_id, agent.name, agent.description, agent.model, agent.priority, keywords, examples, agent.instruction, tools, output_schema, agent.timeout, agent.static_response as i32, agent.working_dir, now, now, negative_keywords, counterexamples],
+            )?;
+            record_revision(
+                conn.as_ref(),
+                RevisionKind::Agent,
+                &agent.id,
+                1,
+                "create",
+                &agent,
+            )
+        })?;
+
+        Ok(agent)
     }
 
     pub fn update_agent(&self, id: &str, input: &UpdateAgent) -> Result<Option<Agent>> {
+        let conn = self.conn()?;
+        transaction(conn.as_ref(), || update_agent_in(conn.as_ref(), id, input))
+    }
+
+    /// Restores an agent's routing and execution settings from a past revision,
+    /// recording the rollback as a new revision.
+    pub fn rollback_agent(&self, id: &str, revision: i64) -> Result<Option<Agent>> {
         let conn = self.conn()?;
         let now = chrono::Utc::now().timestamp();
 
-        let mut sets = vec!["updated_at = ?1".to_string()];
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
-        let mut idx = 2;
-
-        macro_rules! add_field {
-            ($field:expr, $value:expr) => {
-                if let Some(ref v) = $value {
-                    sets.push(format!("{} = ?{}", $field, idx));
-                    params.push(Box::new(v.clone()));
-                    idx += 1;
-                }
-            };
-            ($field:expr, $value:expr, json) => {
-                if let Some(ref v) = $value {
-                    sets.push(format!("{} = ?{}", $field, idx));
-                    params.push(Box::new(serde_json::to_string(v)?));
-                    idx += 1;
-                }
+        transaction(conn.as_ref(), || {
+            let Some(target) = select_revision(conn.as_ref(), RevisionKind::Agent, id, revision)?
+            else {
+                return Ok(None);
             };
-            ($field:expr, $value:expr, bool) => {
-                if let Some(v) = $value {
-                    sets.push(format!("{} = ?{}", $field, idx));
-                    params.push(Box::new(v as i32));
-                    idx += 1;
-                }
+            let snapshot: Agent = serde_json::from_value(target.snapshot)?;
+            let Some(previous) = select_agent(conn.as_ref(), id) else {
+                return Ok(None);
             };
-        }
 
-        add_field!("name", input.name);
-        add_field!("description", input.description);
-        add_field!("model", input.model);
-        add_field!("priority", input.priority);
-        add_field!("instruction", input.instruction);
-        add_field!("timeout", input.timeout);
-        add_field!("keywords", input.keywords, json);
-        add_field!("examples", input.examples, json);
-        add_field!("tools", input.tools, json);
-        add_field!("output_schema", input.output_schema, json);
-        add_field!("static_response", input.static_response, bool);
-        add_field!("working_dir", input.working_dir);
-
-        params.push(Box::new(id.to_string()));
-        let sql = format!("UPDATE agents SET {} WHERE id = ?{}", sets.join(", "), idx);
-
-        let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        conn.execute(&sql, params_ref.as_slice())?;
-
-        drop(conn);
-        self.get_agent(id)
+            let keywords = serde_json::to_string(&snapshot.keywords)?;
+            let negative_keywords = serde_json::to_string(&snapshot.negative_keywords)?;
+            let examples = serde_json::to_string(&snapshot.examples)?;
+            let counterexamples = serde_json::to_string(&snapshot.counterexamples)?;
+            let tools = snapshot
+                .tools
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+            let output_schema = snapshot
+                .output_schema
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+
+            let updated = conn.execute(
+                "UPDATE agents SET name = ?1, description = ?2, model = ?3, priority = ?4, keywords = ?5, examples = ?6,
+                        instruction = ?7, tools = ?8, output_schema = ?9, timeout = ?10, static_response = ?11,
+                        working_dir = ?12, negative_keywords = ?13, counterexamples = ?14, revision = revision + 1,
+                        updated_at = ?15
+                 WHERE id = ?16",
+                params![snapshot.name, snapshot.description, snapshot.model, snapshot.priority, keywords, examples,
+                        snapshot.instruction, tools, output_schema, snapshot.timeout, snapshot.static_response as i32,
+                        snapshot.working_dir, negative_keywords, counterexamples, now, id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+
+            let agent = select_agent(conn.as_ref(), id)
+                .ok_or_else(|| anyhow::anyhow!("Agent '{}' vanished during rollback", id))?;
+            rename_agent_refs(
+                conn.as_ref(),
+                &agent.project_id,
+                &previous.name,
+                &agent.name,
+            )?;
+            record_revision(
+                conn.as_ref(),
+                RevisionKind::Agent,
+                id,
+                agent.revision,
+                &format!("rollback:{}", revision),
+                &agent,
+            )?;
+            Ok(Some(agent))
+        })
     }
 
     pub fn delete_agent(&self, id: &str) -> Result<bool> {
         let conn = self.conn()?;
-        let deleted = conn.execute("DELETE FROM agents WHERE id = ?1", [id])?;
+        let deleted = conn.execute("DELETE FROM agents WHERE id = ?1", params![id])?;
         Ok(deleted > 0)
     }
 }
@@ -1,6 +1,6 @@
 use anyhow::Result;
-use rusqlite::params;
 
+use super::backend::{ToValue, Value, params};
 use super::core::Storage;
 use super::types::{ReactionCategory, ReactionResult, ReactionSummary, TriggerInfo};
 
@@ -108,12 +108,10 @@ impl Storage {
             )
             .unwrap_or(0);
 
-        let mut stmt = conn.prepare(
-            "SELECT reaction, user_id, created_at FROM reactions WHERE execution_id = ?1 AND category = 'trigger' ORDER BY created_at"
-        )?;
-
-        let triggers = stmt
-            .query_map(params![execution_id], |row| {
+        let triggers = conn
+            .query_map(
+"SELECT reaction, user_id, created_at FROM reactions WHERE execution_id = ?1 AND category = 'trigger' ORDER BY created_at",
+params![execution_id], |row| {
                 Ok(TriggerInfo {
                     reaction: row.get(0)?,
                     user_id: row.get(1)?,
@@ -137,7 +135,10 @@ impl Storage {
             if ids.is_empty() {
                 return Ok((0, 0));
             }
-            let placeholders: String = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+            let placeholders: String = (1..=ids.len())
+                .map(|i| format!("?{}", i))
+                .collect::<Vec<_>>()
+                .join(",");
             let sql = format!(
                 "SELECT
                     COALESCE(SUM(CASE WHEN r.reaction IN ('thumbsup', '+1') THEN 1 ELSE 0 END), 0),
@@ -147,10 +148,8 @@ impl Storage {
                 WHERE r.category = 'feedback' AND r.user_id = e.requester AND r.execution_id IN ({})",
                 placeholders
             );
-            let mut stmt = conn.prepare(&sql)?;
-            let params: Vec<&dyn rusqlite::ToSql> =
-                ids.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
-            stmt.query_row(params.as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))?
+            let params: Vec<Value> = ids.iter().map(|s| s.to_value()).collect();
+            conn.query_row(&sql, &params, |row| Ok((row.get(0)?, row.get(1)?)))?
         } else {
             conn.query_row(
                 "SELECT
@@ -159,7 +158,7 @@ impl Storage {
                 FROM reactions r
                 JOIN executions e ON r.execution_id = e.id
                 WHERE r.category = 'feedback' AND r.user_id = e.requester",
-                [],
+                params![],
                 |row| Ok((row.get(0)?, row.get(1)?)),
             )?
         };
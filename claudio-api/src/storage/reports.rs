@@ -0,0 +1,227 @@
+use anyhow::{Result, anyhow};
+
+use super::backend::{Row, Value, params};
+use super::core::Storage;
+use super::types::{CreateReport, Report, ReportFormat, ReportRun};
+use crate::analytics::Period;
+
+const REPORT_COLUMNS: &str = "id, name, project, schedule, timezone, period, sections, format, destinations, enabled, next_run_at, last_run_at, created_at, updated_at";
+
+const RUN_COLUMNS: &str = "id, report_id, triggered_by, status, period_start, period_end, content, deliveries, error, started_at, finished_at";
+
+fn map_report_row(row: &Row) -> Result<Report> {
+    let period: String = row.get(5)?;
+    let sections: String = row.get(6)?;
+    let format: String = row.get(7)?;
+    let destinations: String = row.get(8)?;
+    Ok(Report {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        project: row.get(2)?,
+        schedule: row.get(3)?,
+        timezone: row.get(4)?,
+        period: Period::parse(&period).ok_or_else(|| anyhow!("unknown period '{}'", period))?,
+        sections: serde_json::from_str(&sections)?,
+        format: ReportFormat::parse(&format)
+            .ok_or_else(|| anyhow!("unknown format '{}'", format))?,
+        destinations: serde_json::from_str(&destinations)?,
+        enabled: row.get(9)?,
+        next_run_at: row.get(10)?,
+        last_run_at: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
+fn map_run_row(row: &Row) -> Result<ReportRun> {
+    let deliveries: String = row.get(7)?;
+    Ok(ReportRun {
+        id: row.get(0)?,
+        report_id: row.get(1)?,
+        trigger: row.get(2)?,
+        status: row.get(3)?,
+        period_start: row.get(4)?,
+        period_end: row.get(5)?,
+        content: row.get(6)?,
+        deliveries: serde_json::from_str(&deliveries)?,
+        error: row.get(8)?,
+        started_at: row.get(9)?,
+        finished_at: row.get(10)?,
+    })
+}
+
+fn report_params(report: &Report) -> Result<Vec<Value>> {
+    Ok(params![
+        report.id,
+        report.name,
+        report.project,
+        report.schedule,
+        report.timezone,
+        report.period.as_str(),
+        serde_json::to_string(&report.sections)?,
+        report.format.as_str(),
+        serde_json::to_string(&report.destinations)?,
+        report.enabled,
+        report.next_run_at,
+        report.last_run_at,
+        report.created_at,
+        report.updated_at
+    ]
+    .to_vec())
+}
+
+impl Storage {
+    pub fn list_reports(&self) -> Result<Vec<Report>> {
+        let conn = self.conn()?;
+        let reports = conn
+            .query_map(
+                &format!("SELECT {} FROM reports ORDER BY name, id", REPORT_COLUMNS),
+                &[],
+                map_report_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(reports)
+    }
+
+    pub fn get_report(&self, id: &str) -> Result<Option<Report>> {
+        let conn = self.conn()?;
+        let report = conn
+            .query_map(
+                &format!("SELECT {} FROM reports WHERE id = ?1", REPORT_COLUMNS),
+                params![id],
+                map_report_row,
+            )?
+            .next()
+            .transpose()?;
+        Ok(report)
+    }
+
+    pub fn create_report(&self, input: &CreateReport, next_run_at: Option<i64>) -> Result<Report> {
+        let conn = self.conn()?;
+        let now = chrono::Utc::now().timestamp();
+        let report = Report {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: input.name.clone(),
+            project: input.project.clone(),
+            schedule: input.schedule.clone(),
+            timezone: input.timezone.clone(),
+            period: input.period,
+            sections: input.sections.clone(),
+            format: input.format,
+            destinations: input.destinations.clone(),
+            enabled: input.enabled,
+            next_run_at,
+            last_run_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+        conn.execute(
+            &format!(
+                "INSERT INTO reports ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                REPORT_COLUMNS
+            ),
+            &report_params(&report)?,
+        )?;
+        Ok(report)
+    }
+
+    /// Overwrites every column of an existing report. Returns `false` when
+    /// there is none with its id.
+    pub fn update_report(&self, report: &Report) -> Result<bool> {
+        let conn = self.conn()?;
+        let updated = conn.execute(
+            "UPDATE reports SET name = ?2, project = ?3, schedule = ?4, timezone = ?5, period = ?6,
+                    sections = ?7, format = ?8, destinations = ?9, enabled = ?10, next_run_at = ?11,
+                    last_run_at = ?12, created_at = ?13, updated_at = ?14
+             WHERE id = ?1",
+            &report_params(report)?,
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_report(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM report_runs WHERE report_id = ?1", params![id])?;
+        let deleted = conn.execute("DELETE FROM reports WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Enabled reports whose next run is at or before `now`.
+    pub fn due_reports(&self, now: i64) -> Result<Vec<Report>> {
+        let conn = self.conn()?;
+        let reports = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM reports
+                     WHERE enabled = 1 AND next_run_at IS NOT NULL AND next_run_at <= ?1
+                     ORDER BY next_run_at",
+                    REPORT_COLUMNS
+                ),
+                params![now],
+                map_report_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(reports)
+    }
+
+    /// Moves a due report on to its `next` run, unless another instance
+    /// already did since it was read with `scheduled` as its next run.
+    /// Returns whether this caller should run it.
+    pub fn claim_report(
+        &self,
+        id: &str,
+        scheduled: i64,
+        next: Option<i64>,
+        now: i64,
+    ) -> Result<bool> {
+        let conn = self.conn()?;
+        let claimed = conn.execute(
+            "UPDATE reports SET next_run_at = ?3, last_run_at = ?4
+             WHERE id = ?1 AND next_run_at = ?2",
+            params![id, scheduled, next, now],
+        )?;
+        Ok(claimed > 0)
+    }
+
+    pub fn record_report_run(&self, run: &ReportRun) -> Result<i64> {
+        let conn = self.conn()?;
+        conn.query_row(
+            "INSERT INTO report_runs (report_id, triggered_by, status, period_start, period_end, content, deliveries, error, started_at, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             RETURNING id",
+            params![
+                run.report_id,
+                run.trigger,
+                run.status,
+                run.period_start,
+                run.period_end,
+                run.content,
+                serde_json::to_string(&run.deliveries)?,
+                run.error,
+                run.started_at,
+                run.finished_at
+            ],
+            |row| row.get(0),
+        )
+    }
+
+    /// Most recent runs of a report first.
+    pub fn list_report_runs(&self, report_id: &str, limit: i64) -> Result<Vec<ReportRun>> {
+        let conn = self.conn()?;
+        let runs = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM report_runs WHERE report_id = ?1
+                     ORDER BY started_at DESC, id DESC LIMIT ?2",
+                    RUN_COLUMNS
+                ),
+                params![report_id, limit],
+                map_run_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(runs)
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::backend::{Connection, params, transaction};
+use super::core::Storage;
+use super::types::{FieldChange, Revision, RevisionDiff, RevisionKind, RevisionSummary};
+
+/// Snapshot keys that change on every write and carry no configuration meaning.
+const IGNORED_FIELDS: &[&str] = &["id", "project_id", "revision", "created_at", "updated_at"];
+
+pub(super) fn record_revision<T: Serialize>(
+    conn: &dyn Connection,
+    kind: RevisionKind,
+    entity_id: &str,
+    revision: i64,
+    action: &str,
+    snapshot: &T,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO revisions (entity_type, entity_id, revision, action, snapshot, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            kind.as_str(),
+            entity_id,
+            revision,
+            action,
+            serde_json::to_string(snapshot)?,
+            chrono::Utc::now().timestamp(),
+        ],
+    )?;
+    Ok(())
+}
+
+pub(super) fn select_revision(
+    conn: &dyn Connection,
+    kind: RevisionKind,
+    entity_id: &str,
+    revision: i64,
+) -> Result<Option<Revision>> {
+    let result = conn
+        .query_row(
+            "SELECT revision, action, snapshot, created_at FROM revisions
+             WHERE entity_type = ?1 AND entity_id = ?2 AND revision = ?3",
+            params![kind.as_str(), entity_id, revision],
+            |row| {
+                let snapshot: String = row.get(2)?;
+                Ok(Revision {
+                    entity_type: kind,
+                    entity_id: entity_id.to_string(),
+                    revision: row.get(0)?,
+                    action: row.get(1)?,
+                    snapshot: serde_json::from_str(&snapshot).unwrap_or_default(),
+                    created_at: row.get(3)?,
+                })
+            },
+        )
+        .ok();
+    Ok(result)
+}
+
+pub fn diff_snapshots(from: &serde_json::Value, to: &serde_json::Value) -> Vec<FieldChange> {
+    let empty = serde_json::Map::new();
+    let from = from.as_object().unwrap_or(&empty);
+    let to = to.as_object().unwrap_or(&empty);
+
+    let mut fields: Vec<&String> = from.keys().chain(to.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter(|f| !IGNORED_FIELDS.contains(&f.as_str()))
+        .filter_map(|field| {
+            let before = from.get(field).cloned().unwrap_or(serde_json::Value::Null);
+            let after = to.get(field).cloned().unwrap_or(serde_json::Value::Null);
+            (before != after).then(|| FieldChange {
+                field: field.clone(),
+                from: before,
+                to: after,
+            })
+        })
+        .collect()
+}
+
+impl Storage {
+    pub fn list_revisions(
+        &self,
+        kind: RevisionKind,
+        entity_id: &str,
+    ) -> Result<Vec<RevisionSummary>> {
+        let conn = self.conn()?;
+        let rows: Vec<(i64, String, serde_json::Value, i64)> = conn
+            .query_map(
+                "SELECT revision, action, snapshot, created_at FROM revisions
+             WHERE entity_type = ?1 AND entity_id = ?2 ORDER BY revision",
+                params![kind.as_str(), entity_id],
+                |row| {
+                    let snapshot: String = row.get(2)?;
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        serde_json::from_str(&snapshot).unwrap_or_default(),
+                        row.get(3)?,
+                    ))
+                },
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut previous: Option<&serde_json::Value> = None;
+        let mut summaries = Vec::with_capacity(rows.len());
+        for (revision, action, snapshot, created_at) in &rows {
+            let changed_fields = match previous {
+                Some(prev) => diff_snapshots(prev, snapshot)
+                    .into_iter()
+                    .map(|c| c.field)
+                    .collect(),
+                None => Vec::new(),
+            };
+            summaries.push(RevisionSummary {
+                revision: *revision,
+                action: action.clone(),
+                changed_fields,
+                created_at: *created_at,
+            });
+            previous = Some(snapshot);
+        }
+
+        summaries.reverse();
+        Ok(summaries)
+    }
+
+    pub fn get_revision(
+        &self,
+        kind: RevisionKind,
+        entity_id: &str,
+        revision: i64,
+    ) -> Result<Option<Revision>> {
+        let conn = self.conn()?;
+        select_revision(conn.as_ref(), kind, entity_id, revision)
+    }
+
+    pub fn diff_revisions(
+        &self,
+        kind: RevisionKind,
+        entity_id: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Option<RevisionDiff>> {
+        let conn = self.conn()?;
+        let (Some(before), Some(after)) = (
+            select_revision(conn.as_ref(), kind, entity_id, from)?,
+            select_revision(conn.as_ref(), kind, entity_id, to)?,
+        ) else {
+            return Ok(None);
+        };
+
+        Ok(Some(RevisionDiff {
+            entity_type: kind,
+            entity_id: entity_id.to_string(),
+            from,
+            to,
+            changes: diff_snapshots(&before.snapshot, &after.snapshot),
+        }))
+    }
+
+    /// Records the starting configuration of projects and agents created
+    /// before revisions were kept, so their history lists and can be rolled
+    /// back to like any other.
+    pub(crate) fn backfill_revisions(&self) -> Result<()> {
+        let conn = self.conn()?;
+        let recorded: HashSet<(String, String)> = conn
+            .query_map(
+                "SELECT DISTINCT entity_type, entity_id FROM revisions",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .collect::<Result<_>>()?;
+        let missing = |kind: RevisionKind, id: &str| {
+            !recorded.contains(&(kind.as_str().to_string(), id.to_string()))
+        };
+
+        let projects = self.list_projects()?;
+        let mut agents = Vec::new();
+        for project in &projects {
+            agents.extend(self.list_agents(&project.id)?);
+        }
+        let projects: Vec<_> = projects
+            .into_iter()
+            .filter(|p| missing(RevisionKind::Project, &p.id))
+            .collect();
+        let agents: Vec<_> = agents
+            .into_iter()
+            .filter(|a| missing(RevisionKind::Agent, &a.id))
+            .collect();
+        if projects.is_empty() && agents.is_empty() {
+            return Ok(());
+        }
+
+        transaction(conn.as_ref(), || {
+            for project in &projects {
+                record_revision(
+                    conn.as_ref(),
+                    RevisionKind::Project,
+                    &project.id,
+                    project.revision,
+                    "create",
+                    project,
+                )?;
+            }
+            for agent in &agents {
+                record_revision(
+                    conn.as_ref(),
+                    RevisionKind::Agent,
+                    &agent.id,
+                    agent.revision,
+                    "create",
+                    agent,
+                )?;
+            }
+            Ok(())
+        })?;
+        tracing::info!(
+            projects = projects.len(),
+            agents = agents.len(),
+            "Backfilled starting revisions"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{CreateAgent, CreateProject, UpdateAgent};
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_snapshots_ignores_bookkeeping_fields() {
+        let from = json!({"name": "a", "keywords": ["x"], "revision": 1, "updated_at": 10});
+        let to = json!({"name": "a", "keywords": ["x", "y"], "revision": 2, "updated_at": 20});
+
+        let changes = diff_snapshots(&from, &to);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "keywords");
+        assert_eq!(changes[0].to, json!(["x", "y"]));
+    }
+
+    #[test]
+    fn test_agent_update_and_rollback_record_revisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("test.db")).unwrap();
+
+        let project: CreateProject = serde_json::from_value(json!({"name": "Demo"})).unwrap();
+        storage.create_project(project).unwrap();
+        let agent: CreateAgent = serde_json::from_value(json!({
+            "name": "ops",
+            "description": "Operations",
+            "keywords": ["deploy"],
+            "instruction": "original"
+        }))
+        .unwrap();
+        let agent = storage.create_agent("demo", agent).unwrap();
+
+        let update: UpdateAgent =
+            serde_json::from_value(json!({"keywords": ["deploy", "rollout"]})).unwrap();
+        let updated = storage.update_agent(&agent.id, &update).unwrap().unwrap();
+        assert_eq!(updated.revision, 2);
+
+        let restored = storage.rollback_agent(&agent.id, 1).unwrap().unwrap();
+        assert_eq!(restored.revision, 3);
+        assert_eq!(restored.keywords, vec!["deploy".to_string()]);
+
+        let revisions = storage
+            .list_revisions(RevisionKind::Agent, &agent.id)
+            .unwrap();
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(revisions[0].action, "rollback:1");
+        assert_eq!(revisions[0].changed_fields, vec!["keywords".to_string()]);
+    }
+
+    #[test]
+    fn test_backfill_records_starting_revisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("test.db")).unwrap();
+
+        let project: CreateProject = serde_json::from_value(json!({"name": "Demo"})).unwrap();
+        storage.create_project(project).unwrap();
+        let agent: CreateAgent = serde_json::from_value(json!({
+            "name": "ops",
+            "description": "Operations",
+            "instruction": "original"
+        }))
+        .unwrap();
+        let agent = storage.create_agent("demo", agent).unwrap();
+        // As a database from before revisions were kept
+        storage
+            .with_connection(|conn| conn.execute("DELETE FROM revisions", params![]))
+            .unwrap();
+        assert!(
+            storage
+                .list_revisions(RevisionKind::Agent, &agent.id)
+                .unwrap()
+                .is_empty()
+        );
+
+        storage.backfill_revisions().unwrap();
+        storage.backfill_revisions().unwrap();
+        for (kind, id) in [
+            (RevisionKind::Project, "demo"),
+            (RevisionKind::Agent, agent.id.as_str()),
+        ] {
+            let revisions = storage.list_revisions(kind, id).unwrap();
+            assert_eq!(revisions.len(), 1);
+            assert_eq!(
+                (revisions[0].revision, revisions[0].action.as_str()),
+                (1, "create")
+            );
+        }
+
+        let update: UpdateAgent =
+            serde_json::from_value(json!({"instruction": "changed"})).unwrap();
+        storage.update_agent(&agent.id, &update).unwrap();
+        let restored = storage.rollback_agent(&agent.id, 1).unwrap().unwrap();
+        assert_eq!(restored.instruction.as_deref(), Some("original"));
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+
+use super::backend::{Connection, Dialect, params, transaction};
+use super::core::Storage;
+use super::types::Execution;
+use crate::analytics::{latency_bin, rollup_bucket};
+
+/// Executions read at a time while rebuilding
+const REBUILD_BATCH: i64 = 5000;
+
+/// Bucket start, project, source, model and agent
+type RollupKey = (i64, String, String, String, String);
+
+/// What an execution adds to its rollup
+pub(super) struct RollupSample {
+    key: RollupKey,
+    success: bool,
+    cost_usd: Option<f64>,
+    input_tokens: Option<i64>,
+    output_tokens: Option<i64>,
+    cache_read_tokens: Option<i64>,
+    cache_creation_tokens: Option<i64>,
+    duration_ms: Option<i64>,
+}
+
+impl From<&Execution> for RollupSample {
+    fn from(e: &Execution) -> Self {
+        Self {
+            key: (
+                rollup_bucket(e.created_at),
+                e.project.clone(),
+                e.source.clone().unwrap_or_default(),
+                e.model.clone().unwrap_or_default(),
+                e.agent.clone().unwrap_or_default(),
+            ),
+            success: !e.response.is_empty(),
+            cost_usd: e.cost_usd,
+            input_tokens: e.input_tokens,
+            output_tokens: e.output_tokens,
+            cache_read_tokens: e.cache_read_tokens,
+            cache_creation_tokens: e.cache_creation_tokens,
+            duration_ms: e.duration_ms,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Rollup {
+    requests: i64,
+    successful: i64,
+    cost_usd: f64,
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_read_tokens: i64,
+    cache_creation_tokens: i64,
+    duration_sum: i64,
+    duration_count: i64,
+    latency: BTreeMap<i64, i64>,
+}
+
+/// Rollup increments, written with additive upserts so concurrent saves
+/// never lose each other's counts
+#[derive(Debug, Default)]
+pub(super) struct Rollups(HashMap<RollupKey, Rollup>);
+
+impl Rollups {
+    pub(super) fn add(&mut self, sample: RollupSample) {
+        let rollup = self.0.entry(sample.key).or_default();
+        rollup.requests += 1;
+        rollup.successful += sample.success as i64;
+        rollup.cost_usd += sample.cost_usd.unwrap_or_default();
+        rollup.input_tokens += sample.input_tokens.unwrap_or_default();
+        rollup.output_tokens += sample.output_tokens.unwrap_or_default();
+        rollup.cache_read_tokens += sample.cache_read_tokens.unwrap_or_default();
+        rollup.cache_creation_tokens += sample.cache_creation_tokens.unwrap_or_default();
+        if let Some(duration) = sample.duration_ms {
+            rollup.duration_sum += duration;
+            rollup.duration_count += 1;
+            *rollup.latency.entry(latency_bin(duration)).or_default() += 1;
+        }
+    }
+
+    pub(super) fn write(self, conn: &dyn Connection) -> Result<()> {
+        for ((bucket_start, project, source, model, agent), rollup) in self.0 {
+            conn.execute(
+                "INSERT INTO execution_rollups (
+                    bucket_start, project, source, model, agent, requests, successful, cost_usd,
+                    input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+                    duration_sum, duration_count
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                ON CONFLICT (bucket_start, project, source, model, agent) DO UPDATE SET
+                    requests = execution_rollups.requests + excluded.requests,
+                    successful = execution_rollups.successful + excluded.successful,
+                    cost_usd = execution_rollups.cost_usd + excluded.cost_usd,
+                    input_tokens = execution_rollups.input_tokens + excluded.input_tokens,
+                    output_tokens = execution_rollups.output_tokens + excluded.output_tokens,
+                    cache_read_tokens = execution_rollups.cache_read_tokens + excluded.cache_read_tokens,
+                    cache_creation_tokens = execution_rollups.cache_creation_tokens + excluded.cache_creation_tokens,
+                    duration_sum = execution_rollups.duration_sum + excluded.duration_sum,
+                    duration_count = execution_rollups.duration_count + excluded.duration_count",
+                params![
+                    bucket_start,
+                    project,
+                    source,
+                    model,
+                    agent,
+                    rollup.requests,
+                    rollup.successful,
+                    rollup.cost_usd,
+                    rollup.input_tokens,
+                    rollup.output_tokens,
+                    rollup.cache_read_tokens,
+                    rollup.cache_creation_tokens,
+                    rollup.duration_sum,
+                    rollup.duration_count,
+                ],
+            )?;
+            for (bin, hits) in rollup.latency {
+                conn.execute(
+                    "INSERT INTO execution_rollup_latency (bucket_start, project, source, model, agent, bin, hits)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT (bucket_start, project, source, model, agent, bin) DO UPDATE SET
+                        hits = execution_rollup_latency.hits + excluded.hits",
+                    params![bucket_start, project, source, model, agent, bin, hits],
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Storage {
+    /// Recomputes every rollup from `executions` in one transaction.
+    /// Returns the number of executions rolled up.
+    ///
+    /// Saves wait until the rebuild commits: on Postgres each batch would
+    /// otherwise see executions committed mid-rebuild that already added
+    /// themselves to a fresh rollup row, counting them twice.
+    pub fn rebuild_rollups(&self) -> Result<i64> {
+        let conn = self.conn()?;
+        transaction(conn.as_ref(), || {
+            if conn.dialect() == Dialect::Postgres {
+                conn.execute_batch("LOCK TABLE executions IN SHARE MODE")?;
+            }
+            conn.execute("DELETE FROM execution_rollup_latency", params![])?;
+            conn.execute("DELETE FROM execution_rollups", params![])?;
+
+            let mut after = (i64::MIN, String::new());
+            let mut total = 0;
+            loop {
+                let batch = conn
+                    .query_map(
+                        "SELECT id, project, source, model, agent, response != '', cost_usd,
+                                input_tokens, output_tokens, cache_read_tokens,
+                                cache_creation_tokens, duration_ms, created_at
+                         FROM executions
+                         WHERE created_at > ?1 OR (created_at = ?1 AND id > ?2)
+                         ORDER BY created_at, id LIMIT ?3",
+                        params![after.0, after.1, REBUILD_BATCH],
+                        |row| {
+                            let created_at: i64 = row.get(12)?;
+                            let sample = RollupSample {
+                                key: (
+                                    rollup_bucket(created_at),
+                                    row.get(1)?,
+                                    row.get::<Option<String>>(2)?.unwrap_or_default(),
+                                    row.get::<Option<String>>(3)?.unwrap_or_default(),
+                                    row.get::<Option<String>>(4)?.unwrap_or_default(),
+                                ),
+                                success: row.get(5)?,
+                                cost_usd: row.get(6)?,
+                                input_tokens: row.get(7)?,
+                                output_tokens: row.get(8)?,
+                                cache_read_tokens: row.get(9)?,
+                                cache_creation_tokens: row.get(10)?,
+                                duration_ms: row.get(11)?,
+                            };
+                            Ok(((created_at, row.get::<String>(0)?), sample))
+                        },
+                    )?
+                    .collect::<Result<Vec<_>>>()?;
+                let Some((last, _)) = batch.last() else {
+                    break;
+                };
+                after = last.clone();
+                total += batch.len() as i64;
+
+                let mut rollups = Rollups::default();
+                for (_, sample) in batch {
+                    rollups.add(sample);
+                }
+                rollups.write(conn.as_ref())?;
+            }
+            Ok(total)
+        })
+    }
+
+    /// Rolls up existing executions the first time a database runs with
+    /// rollups, so analytics over older periods stay complete.
+    pub(crate) fn backfill_rollups(&self) -> Result<()> {
+        let missing: bool = self.conn()?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM executions)
+                AND NOT EXISTS(SELECT 1 FROM execution_rollups)",
+            params![],
+            |row| row.get(0),
+        )?;
+        if missing {
+            let executions = self.rebuild_rollups()?;
+            tracing::info!(executions, "Backfilled execution rollups");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+
+use super::backend::{Value, params, transaction};
+use super::core::Storage;
+use super::types::StoredEmbedding;
+
+impl Storage {
+    /// Embeddings produced by `model`; rows from other models are stale.
+    pub fn list_embeddings(&self, model: &str) -> Result<Vec<StoredEmbedding>> {
+        let conn = self.conn()?;
+        let embeddings = conn
+            .query_map(
+                "SELECT project_id, agent_id, example, vector, negative FROM semantic_embeddings
+                 WHERE model = ?1",
+                params![model],
+                |row| {
+                    let vector: String = row.get(3)?;
+                    Ok(StoredEmbedding {
+                        project_id: row.get(0)?,
+                        agent_id: row.get(1)?,
+                        example: row.get(2)?,
+                        vector: serde_json::from_str(&vector)?,
+                        negative: row.get::<i32>(4)? == 1,
+                    })
+                },
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(embeddings)
+    }
+
+    /// Makes the stored examples of `agent_id` exactly `keep` plus `added`,
+    /// whose flag marks counterexamples.
+    pub fn save_agent_embeddings(
+        &self,
+        project_id: &str,
+        agent_id: &str,
+        model: &str,
+        keep: &[String],
+        added: &[(String, Vec<f32>, bool)],
+    ) -> Result<()> {
+        let conn = self.conn()?;
+        transaction(conn.as_ref(), || {
+            let mut params = vec![Value::Text(agent_id.to_string())];
+            params.extend(keep.iter().map(|e| Value::Text(e.clone())));
+            let keep_clause = if keep.is_empty() {
+                String::new()
+            } else {
+                let placeholders = (2..=keep.len() + 1)
+                    .map(|i| format!("?{}", i))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(" AND example NOT IN ({})", placeholders)
+            };
+            conn.execute(
+                &format!(
+                    "DELETE FROM semantic_embeddings WHERE agent_id = ?1{}",
+                    keep_clause
+                ),
+                &params,
+            )?;
+
+            let now = chrono::Utc::now().timestamp();
+            for (example, vector, negative) in added {
+                conn.execute(
+                    "INSERT INTO semantic_embeddings (agent_id, example, project_id, model, vector, negative, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(agent_id, example) DO UPDATE SET
+                        project_id = excluded.project_id,
+                        model = excluded.model,
+                        vector = excluded.vector,
+                        negative = excluded.negative,
+                        created_at = excluded.created_at",
+                    params![
+                        agent_id,
+                        example,
+                        project_id,
+                        model,
+                        serde_json::to_string(vector)?,
+                        *negative as i32,
+                        now
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::Result;
+
+use super::backend::{Row, params, transaction};
+use super::core::Storage;
+use super::feedback::sql::{FEEDBACK_JOIN_VERIFIED, NEGATIVE_DISTINCT, POSITIVE_DISTINCT};
+use super::projects::{lock_agent_by_name, update_agent_in};
+use super::types::{Agent, ExampleSuggestion, RoutingCorrection, SuggestionKind, UpdateAgent};
+
+const SUGGESTION_COLUMNS: &str =
+    "id, project_id, agent, text, kind, execution_id, status, created_at, reviewed_at";
+
+fn map_suggestion_row(row: &Row) -> Result<ExampleSuggestion> {
+    let kind: String = row.get(4)?;
+    Ok(ExampleSuggestion {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        agent: row.get(2)?,
+        text: row.get(3)?,
+        kind: if kind == "counterexample" {
+            SuggestionKind::Counterexample
+        } else {
+            SuggestionKind::Example
+        },
+        execution_id: row.get(5)?,
+        status: row.get(6)?,
+        created_at: row.get(7)?,
+        reviewed_at: row.get(8)?,
+    })
+}
+
+impl Storage {
+    /// Records which agent an execution should have been routed to,
+    /// replacing any earlier correction.
+    pub fn set_routing_correction(
+        &self,
+        execution_id: &str,
+        agent: &str,
+        corrected_by: Option<&str>,
+    ) -> Result<RoutingCorrection> {
+        let conn = self.conn()?;
+        let correction = RoutingCorrection {
+            execution_id: execution_id.to_string(),
+            agent: agent.to_string(),
+            corrected_by: corrected_by.map(String::from),
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        conn.execute(
+            "INSERT INTO routing_corrections (execution_id, agent, corrected_by, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(execution_id) DO UPDATE SET
+                agent = excluded.agent,
+                corrected_by = excluded.corrected_by,
+                created_at = excluded.created_at",
+            params![
+                correction.execution_id,
+                correction.agent,
+                correction.corrected_by,
+                correction.created_at
+            ],
+        )?;
+        Ok(correction)
+    }
+
+    /// Proposes examples from positively rated executions whose routing was
+    /// logged by the classifier, and counterexamples (plus an example for the
+    /// right agent) from negatively rated executions with a correction. Texts
+    /// the agent already has, and suggestions reviewed before, are skipped.
+    /// Returns how many suggestions were added.
+    pub fn generate_suggestions(&self, project_id: &str, limit: i64) -> Result<usize> {
+        let conn = self.conn()?;
+
+        let positive: Vec<(String, String, String)> = conn
+            .query_map(
+                &format!(
+                    "SELECT e.id, e.user_message, e.agent FROM executions e {}
+                     WHERE e.project = ?1 AND e.agent IS NOT NULL
+                       AND EXISTS (
+                         SELECT 1 FROM classification_logs l
+                         WHERE l.project = e.project AND l.text = e.user_message AND l.agent = e.agent
+                       )
+                     GROUP BY e.id, e.user_message, e.agent
+                     HAVING {} > 0 AND {} = 0
+                     ORDER BY MAX(e.created_at) DESC LIMIT ?2",
+                    FEEDBACK_JOIN_VERIFIED, POSITIVE_DISTINCT, NEGATIVE_DISTINCT
+                ),
+                params![project_id, limit],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let corrected: Vec<(String, String, String, String)> = conn
+            .query_map(
+                &format!(
+                    "SELECT e.id, e.user_message, e.agent, c.agent FROM executions e
+                     JOIN routing_corrections c ON c.execution_id = e.id {}
+                     WHERE e.project = ?1 AND e.agent IS NOT NULL
+                     GROUP BY e.id, e.user_message, e.agent, c.agent
+                     HAVING {} > 0
+                     ORDER BY MAX(e.created_at) DESC LIMIT ?2",
+                    FEEDBACK_JOIN_VERIFIED, NEGATIVE_DISTINCT
+                ),
+                params![project_id, limit],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut candidates = Vec::new();
+        for (execution_id, text, agent) in positive {
+            candidates.push((execution_id, text, agent, SuggestionKind::Example));
+        }
+        for (execution_id, text, routed, correct) in corrected {
+            if routed == correct {
+                continue;
+            }
+            candidates.push((
+                execution_id.clone(),
+                text.clone(),
+                routed,
+                SuggestionKind::Counterexample,
+            ));
+            candidates.push((execution_id, text, correct, SuggestionKind::Example));
+        }
+
+        let agents = self.list_agents(project_id)?;
+        let now = chrono::Utc::now().timestamp();
+        let mut added = 0;
+        for (execution_id, text, agent_name, kind) in candidates {
+            let text = text.trim();
+            let Some(agent) = agents.iter().find(|a| a.name == agent_name) else {
+                continue;
+            };
+            let existing = match kind {
+                SuggestionKind::Example => &agent.examples,
+                SuggestionKind::Counterexample => &agent.counterexamples,
+            };
+            if text.is_empty() || existing.iter().any(|e| e == text) {
+                continue;
+            }
+            added += conn.execute(
+                "INSERT INTO example_suggestions (id, project_id, agent, text, kind, execution_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(project_id, agent, text, kind) DO NOTHING",
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    project_id,
+                    agent.name,
+                    text,
+                    kind.as_str(),
+                    execution_id,
+                    now
+                ],
+            )?;
+        }
+        Ok(added)
+    }
+
+    pub fn list_suggestions(
+        &self,
+        project_id: &str,
+        status: &str,
+    ) -> Result<Vec<ExampleSuggestion>> {
+        let conn = self.conn()?;
+        let suggestions = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM example_suggestions WHERE project_id = ?1 AND status = ?2
+                     ORDER BY created_at DESC, id",
+                    SUGGESTION_COLUMNS
+                ),
+                params![project_id, status],
+                map_suggestion_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(suggestions)
+    }
+
+    /// Appends a pending suggestion to its agent's examples or
+    /// counterexamples, recording an agent revision. Returns the updated agent,
+    /// or `None` when the suggestion isn't pending or the agent is gone.
+    pub fn approve_suggestion(&self, project_id: &str, id: &str) -> Result<Option<Agent>> {
+        let conn = self.conn()?;
+        let conn = conn.as_ref();
+        transaction(conn, || {
+            // Claiming the suggestion first lets only one approval through
+            let claimed = conn.execute(
+                "UPDATE example_suggestions SET status = 'approved', reviewed_at = ?1
+                 WHERE id = ?2 AND project_id = ?3 AND status = 'pending'
+                   AND EXISTS (
+                     SELECT 1 FROM agents a
+                     WHERE a.project_id = example_suggestions.project_id
+                       AND a.name = example_suggestions.agent
+                   )",
+                params![chrono::Utc::now().timestamp(), id, project_id],
+            )?;
+            if claimed == 0 {
+                return Ok(None);
+            }
+            let suggestion = conn.query_row(
+                &format!(
+                    "SELECT {} FROM example_suggestions WHERE id = ?1",
+                    SUGGESTION_COLUMNS
+                ),
+                params![id],
+                map_suggestion_row,
+            )?;
+            // Re-read under lock so concurrent approvals for the agent each
+            // append to the other's list instead of overwriting it
+            let Some(agent) = lock_agent_by_name(conn, project_id, &suggestion.agent)? else {
+                return Ok(None);
+            };
+
+            let mut update = UpdateAgent::default();
+            let (mut list, field) = match suggestion.kind {
+                SuggestionKind::Example => (agent.examples.clone(), &mut update.examples),
+                SuggestionKind::Counterexample => {
+                    (agent.counterexamples.clone(), &mut update.counterexamples)
+                }
+            };
+            if list.contains(&suggestion.text) {
+                return Ok(Some(agent));
+            }
+            list.push(suggestion.text);
+            *field = Some(list);
+            update_agent_in(conn, &agent.id, &update)
+        })
+    }
+
+    /// Returns `false` when the suggestion isn't pending.
+    pub fn reject_suggestion(&self, project_id: &str, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let rejected = conn.execute(
+            "UPDATE example_suggestions SET status = 'rejected', reviewed_at = ?1
+             WHERE id = ?2 AND project_id = ?3 AND status = 'pending'",
+            params![chrono::Utc::now().timestamp(), id, project_id],
+        )?;
+        Ok(rejected > 0)
+    }
+}
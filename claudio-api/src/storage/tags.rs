@@ -0,0 +1,156 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use super::backend::{Connection, Value, params};
+use super::core::Storage;
+use super::types::{Annotation, ExecutionTag};
+
+/// Tags are matched case-insensitively and without surrounding whitespace.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Tags of each execution in `ids`, in tag order.
+pub(super) fn tags_for(
+    conn: &dyn Connection,
+    ids: &[String],
+) -> Result<HashMap<String, Vec<String>>> {
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    if ids.is_empty() {
+        return Ok(tags);
+    }
+
+    let placeholders = (1..=ids.len())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let params: Vec<Value> = ids.iter().map(|id| Value::Text(id.clone())).collect();
+
+    let rows = conn.query_map(
+        &format!(
+            "SELECT execution_id, tag FROM execution_tags
+             WHERE execution_id IN ({}) ORDER BY tag",
+            placeholders
+        ),
+        &params,
+        |row| Ok((row.get::<String>(0)?, row.get::<String>(1)?)),
+    )?;
+    for (id, tag) in rows.filter_map(|r| r.ok()) {
+        tags.entry(id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+pub(super) fn select_annotations(
+    conn: &dyn Connection,
+    execution_id: &str,
+) -> Result<Vec<Annotation>> {
+    let annotations = conn
+        .query_map(
+            "SELECT id, execution_id, author, body, created_at FROM execution_annotations
+             WHERE execution_id = ?1 ORDER BY created_at, id",
+            params![execution_id],
+            |row| {
+                Ok(Annotation {
+                    id: row.get(0)?,
+                    execution_id: row.get(1)?,
+                    author: row.get(2)?,
+                    body: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            },
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(annotations)
+}
+
+impl Storage {
+    pub fn execution_exists(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let rows = conn.query("SELECT 1 FROM executions WHERE id = ?1", params![id])?;
+        Ok(!rows.is_empty())
+    }
+
+    /// Returns `false` when the execution already has the tag.
+    pub fn add_tag(&self, execution_id: &str, tag: &str, created_by: Option<&str>) -> Result<bool> {
+        let conn = self.conn()?;
+        let inserted = conn.execute(
+            "INSERT INTO execution_tags (execution_id, tag, created_by, created_at)
+             VALUES (?1, ?2, ?3, ?4) ON CONFLICT DO NOTHING",
+            params![
+                execution_id,
+                normalize_tag(tag),
+                created_by,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    pub fn remove_tag(&self, execution_id: &str, tag: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute(
+            "DELETE FROM execution_tags WHERE execution_id = ?1 AND tag = ?2",
+            params![execution_id, normalize_tag(tag)],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    pub fn list_tags(&self, execution_id: &str) -> Result<Vec<ExecutionTag>> {
+        let conn = self.conn()?;
+        let tags = conn
+            .query_map(
+                "SELECT tag, created_by, created_at FROM execution_tags
+                 WHERE execution_id = ?1 ORDER BY tag",
+                params![execution_id],
+                |row| {
+                    Ok(ExecutionTag {
+                        tag: row.get(0)?,
+                        created_by: row.get(1)?,
+                        created_at: row.get(2)?,
+                    })
+                },
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(tags)
+    }
+
+    pub fn add_annotation(
+        &self,
+        execution_id: &str,
+        author: &str,
+        body: &str,
+    ) -> Result<Annotation> {
+        let conn = self.conn()?;
+        let now = chrono::Utc::now().timestamp();
+        let id: i64 = conn.query_row(
+            "INSERT INTO execution_annotations (execution_id, author, body, created_at)
+             VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            params![execution_id, author, body, now],
+            |row| row.get(0),
+        )?;
+        Ok(Annotation {
+            id,
+            execution_id: execution_id.to_string(),
+            author: author.to_string(),
+            body: body.to_string(),
+            created_at: now,
+        })
+    }
+
+    pub fn list_annotations(&self, execution_id: &str) -> Result<Vec<Annotation>> {
+        let conn = self.conn()?;
+        select_annotations(conn.as_ref(), execution_id)
+    }
+
+    pub fn delete_annotation(&self, execution_id: &str, id: i64) -> Result<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute(
+            "DELETE FROM execution_annotations WHERE execution_id = ?1 AND id = ?2",
+            params![execution_id, id],
+        )?;
+        Ok(deleted > 0)
+    }
+}
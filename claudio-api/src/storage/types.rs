@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::analytics::Period;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     pub id: String,
@@ -16,6 +18,10 @@ pub struct Project {
     pub classify_model: String,
     pub classify_timeout: i32,
     pub rate_limit_rpm: i32,
+    /// Classifications below this confidence ask for clarification; 0 disables
+    #[serde(default)]
+    pub clarify_threshold: f64,
+    pub revision: i64,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -38,6 +44,8 @@ pub struct CreateProject {
     pub classify_timeout: i32,
     #[serde(default)]
     pub rate_limit_rpm: i32,
+    #[serde(default)]
+    pub clarify_threshold: f64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -52,6 +60,7 @@ pub struct UpdateProject {
     pub classify_model: Option<String>,
     pub classify_timeout: Option<i32>,
     pub rate_limit_rpm: Option<i32>,
+    pub clarify_threshold: Option<f64>,
 }
 
 fn default_fallback_agent() -> String {
@@ -75,8 +84,14 @@ pub struct Agent {
     pub description: String,
     pub model: String,
     pub priority: i32,
-    pub keywords: Vec<String>,
+    pub keywords: Vec<Keyword>,
+    /// Any match excludes the agent from the keyword stage
+    #[serde(default)]
+    pub negative_keywords: Vec<Keyword>,
     pub examples: Vec<String>,
+    /// Requests that look like an example but belong elsewhere
+    #[serde(default)]
+    pub counterexamples: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instruction: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -87,10 +102,94 @@ pub struct Agent {
     pub static_response: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub working_dir: Option<String>,
+    pub revision: i64,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+/// Routing keyword. Written either as a plain string (weight 1, substring
+/// match, `/.../` for a regex) or as `{"pattern", "weight", "whole_word"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "KeywordSpec", into = "KeywordSpec")]
+pub struct Keyword {
+    pub pattern: String,
+    /// Added to the agent's score on a match
+    pub weight: f64,
+    /// Only match at word boundaries; ignored for regexes
+    pub whole_word: bool,
+}
+
+impl Keyword {
+    pub fn is_regex(&self) -> bool {
+        self.pattern.starts_with('/') && self.pattern.ends_with('/') && self.pattern.len() > 2
+    }
+}
+
+impl From<&str> for Keyword {
+    fn from(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            weight: default_keyword_weight(),
+            whole_word: false,
+        }
+    }
+}
+
+impl PartialEq<String> for Keyword {
+    fn eq(&self, other: &String) -> bool {
+        self.pattern == *other && self.weight == default_keyword_weight() && !self.whole_word
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeywordSpec {
+    Plain(String),
+    Detailed {
+        pattern: String,
+        #[serde(default = "default_keyword_weight")]
+        weight: f64,
+        #[serde(default)]
+        whole_word: bool,
+    },
+}
+
+impl From<KeywordSpec> for Keyword {
+    fn from(spec: KeywordSpec) -> Self {
+        match spec {
+            KeywordSpec::Plain(pattern) => Keyword::from(pattern.as_str()),
+            KeywordSpec::Detailed {
+                pattern,
+                weight,
+                whole_word,
+            } => Keyword {
+                pattern,
+                weight,
+                whole_word,
+            },
+        }
+    }
+}
+
+impl From<Keyword> for KeywordSpec {
+    fn from(keyword: Keyword) -> Self {
+        // Plain keywords keep their original string form in storage and APIs
+        if keyword.weight == default_keyword_weight() && !keyword.whole_word {
+            KeywordSpec::Plain(keyword.pattern)
+        } else {
+            KeywordSpec::Detailed {
+                pattern: keyword.pattern,
+                weight: keyword.weight,
+                whole_word: keyword.whole_word,
+            }
+        }
+    }
+}
+
+fn default_keyword_weight() -> f64 {
+    1.0
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateAgent {
     pub name: String,
@@ -100,9 +199,13 @@ pub struct CreateAgent {
     #[serde(default = "default_priority")]
     pub priority: i32,
     #[serde(default)]
-    pub keywords: Vec<String>,
+    pub keywords: Vec<Keyword>,
+    #[serde(default)]
+    pub negative_keywords: Vec<Keyword>,
     #[serde(default)]
     pub examples: Vec<String>,
+    #[serde(default)]
+    pub counterexamples: Vec<String>,
     pub instruction: Option<String>,
     pub tools: Option<Vec<String>>,
     pub output_schema: Option<serde_json::Value>,
@@ -113,14 +216,16 @@ pub struct CreateAgent {
     pub working_dir: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct UpdateAgent {
     pub name: Option<String>,
     pub description: Option<String>,
     pub model: Option<String>,
     pub priority: Option<i32>,
-    pub keywords: Option<Vec<String>>,
+    pub keywords: Option<Vec<Keyword>>,
+    pub negative_keywords: Option<Vec<Keyword>>,
     pub examples: Option<Vec<String>>,
+    pub counterexamples: Option<Vec<String>>,
     pub instruction: Option<String>,
     pub tools: Option<Vec<String>>,
     pub output_schema: Option<serde_json::Value>,
@@ -161,9 +266,74 @@ pub struct Execution {
     pub duration_api_ms: Option<i64>,
     pub session_id: Option<String>,
     pub metadata: Option<String>,
+    pub agent_revision: Option<i64>,
+    pub allowed_tools: Option<Vec<String>>,
+    pub disallowed_tools: Option<Vec<String>>,
+    /// Execution this one re-ran, if any
+    pub rerun_of: Option<String>,
+    /// Experiment that picked this execution's agent variant, if any
+    #[serde(default)]
+    pub experiment_id: Option<String>,
+    #[serde(default)]
+    pub variant: Option<String>,
+    /// Workflow run that made the request, if any
+    #[serde(default)]
+    pub run_id: Option<String>,
     pub created_at: i64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionKind {
+    Agent,
+    Project,
+}
+
+impl RevisionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Agent => "agent",
+            Self::Project => "project",
+        }
+    }
+}
+
+/// Immutable snapshot of an agent or project written on every change
+#[derive(Debug, Clone, Serialize)]
+pub struct Revision {
+    pub entity_type: RevisionKind,
+    pub entity_id: String,
+    pub revision: i64,
+    /// What produced this revision: `create`, `update` or `rollback:<revision>`
+    pub action: String,
+    pub snapshot: serde_json::Value,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionSummary {
+    pub revision: i64,
+    pub action: String,
+    pub changed_fields: Vec<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub entity_type: RevisionKind,
+    pub entity_id: String,
+    pub from: i64,
+    pub to: i64,
+    pub changes: Vec<FieldChange>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReactionCategory {
     Feedback,
@@ -224,6 +394,25 @@ pub struct ClassificationLog {
     pub project: Option<String>,
     pub source: Option<String>,
     pub requester: Option<String>,
+    pub agent_revision: Option<i64>,
+    /// Why the LLM stage fell back, if it did
+    pub llm_error: Option<String>,
+    /// Workflow run that asked for the classification, if any
+    pub run_id: Option<String>,
+}
+
+/// One event the Slack bridge forwarded to an n8n webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub run_id: Option<String>,
+    pub endpoint: String,
+    pub event_type: String,
+    pub channel: Option<String>,
+    /// HTTP status returned by n8n, if it answered
+    pub status_code: Option<i64>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: i64,
 }
 
 #[derive(Debug, Default)]
@@ -275,6 +464,23 @@ pub struct ExecutionDetail {
     pub duration_api_ms: Option<i64>,
     pub feedback: Option<i32>,
     pub metadata: Option<String>,
+    pub agent_revision: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disallowed_tools: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerun_of: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experiment_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Re-runs of this execution, oldest first
+    pub reruns: Vec<String>,
+    pub tags: Vec<String>,
+    pub annotations: Vec<Annotation>,
     pub created_at: i64,
 }
 
@@ -293,6 +499,40 @@ pub struct ExecutionListItem {
     pub duration_ms: Option<i64>,
     pub feedback: Option<i32>,
     pub channel: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Execution as exported, with every column flat
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionExport {
+    pub id: String,
+    pub project: String,
+    pub agent: Option<String>,
+    pub source: Option<String>,
+    pub requester: Option<String>,
+    pub channel: Option<String>,
+    pub model: Option<String>,
+    pub session_id: Option<String>,
+    pub agent_revision: Option<i64>,
+    pub experiment_id: Option<String>,
+    pub variant: Option<String>,
+    pub rerun_of: Option<String>,
+    pub cost_usd: Option<f64>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cache_read_tokens: Option<i64>,
+    pub cache_creation_tokens: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub duration_api_ms: Option<i64>,
+    pub feedback: Option<i32>,
+    pub tags: Vec<String>,
+    pub metadata: Option<String>,
+    pub instruction: Option<String>,
+    pub user_message: String,
+    pub user_context: Option<String>,
+    pub response: String,
+    pub structured_output: Option<String>,
     pub created_at: i64,
 }
 
@@ -305,19 +545,655 @@ pub struct ExecutionListResponse {
     pub total_pages: i64,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ExecutionFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub feedback: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub requester: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub from: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub to: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub search: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub failed_only: Option<bool>,
+    /// Executions must carry every one of these tags
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl ExecutionFilter {
+    /// Fills fields left unset here from `base` (e.g. a saved view).
+    pub fn or(self, base: ExecutionFilter) -> Self {
+        Self {
+            project: self.project.or(base.project),
+            source: self.source.or(base.source),
+            model: self.model.or(base.model),
+            agent: self.agent.or(base.agent),
+            feedback: self.feedback.or(base.feedback),
+            requester: self.requester.or(base.requester),
+            channel: self.channel.or(base.channel),
+            from: self.from.or(base.from),
+            to: self.to.or(base.to),
+            search: self.search.or(base.search),
+            failed_only: self.failed_only.or(base.failed_only),
+            tags: if self.tags.is_empty() {
+                base.tags
+            } else {
+                self.tags
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionTag {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    pub created_at: i64,
+}
+
+/// Free-text note left on an execution
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    pub id: i64,
+    pub execution_id: String,
+    pub author: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+/// Named execution filter saved by a user
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedView {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub filter: ExecutionFilter,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveView {
+    pub name: String,
+    #[serde(default)]
+    pub filter: ExecutionFilter,
+}
+
+/// Labeled routing example: `text` should classify to `expected_agent`
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalCase {
+    pub id: String,
+    pub project_id: String,
+    pub text: String,
+    pub expected_agent: String,
+    /// manual, correction, feedback or classification_log
+    pub source: String,
+    /// Labeled by a person rather than copied from a classifier decision
+    pub verified: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateEvalCase {
+    pub text: String,
+    pub expected_agent: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCaseResult {
+    pub case_id: String,
+    pub text: String,
+    pub expected: String,
+    pub predicted: String,
+    pub method: String,
+    pub correct: bool,
+    #[serde(default)]
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalRun {
+    pub id: String,
+    pub project_id: String,
+    pub stages: Vec<String>,
+    pub total: i64,
+    pub correct: i64,
+    pub accuracy: f64,
+    /// Omitted when listing runs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<EvalCaseResult>,
+    pub created_at: i64,
+}
+
+/// Agent configuration tried in an experiment; unset fields keep the
+/// agent's own
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentVariant {
+    pub name: String,
+    /// Relative share of traffic
+    #[serde(default = "default_variant_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub instruction: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+}
+
+fn default_variant_weight() -> u32 {
+    1
+}
+
+/// A/B test splitting an agent's traffic between variants. The first
+/// variant is the control the others are compared against.
+#[derive(Debug, Clone, Serialize)]
+pub struct Experiment {
+    pub id: String,
+    pub project_id: String,
+    pub agent: String,
+    pub name: String,
+    /// running or stopped
+    pub status: String,
+    pub variants: Vec<ExperimentVariant>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateExperiment {
+    pub name: String,
+    pub agent: String,
+    pub variants: Vec<ExperimentVariant>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateExperiment {
+    pub name: Option<String>,
+    pub status: Option<String>,
+    pub variants: Option<Vec<ExperimentVariant>>,
+}
+
+/// What a budget caps: a whole project, one agent or one requester
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetScope {
+    Project,
+    Agent,
+    Requester,
+}
+
+impl BudgetScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Project => "project",
+            Self::Agent => "agent",
+            Self::Requester => "requester",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "project" => Some(Self::Project),
+            "agent" => Some(Self::Agent),
+            "requester" => Some(Self::Requester),
+            _ => None,
+        }
+    }
+}
+
+/// Window a budget's spend is summed over, in the configured timezone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            "monthly" => Some(Self::Monthly),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to requests once a budget is used up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    #[default]
+    Reject,
+    /// Run on `downgrade_model` instead
+    Downgrade,
+    /// Run anyway; only alerts are sent
+    Warn,
+}
+
+impl BudgetAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::Downgrade => "downgrade",
+            Self::Warn => "warn",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reject" => Some(Self::Reject),
+            "downgrade" => Some(Self::Downgrade),
+            "warn" => Some(Self::Warn),
+            _ => None,
+        }
+    }
+}
+
+/// USD cap on execution cost over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct Budget {
+    pub id: String,
+    pub project_id: String,
+    pub scope: BudgetScope,
+    /// Agent name or requester id; `None` for project budgets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub period: BudgetPeriod,
+    pub limit_usd: f64,
+    pub action: BudgetAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downgrade_model: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateBudget {
+    pub scope: BudgetScope,
+    #[serde(default)]
+    pub target: Option<String>,
+    pub period: BudgetPeriod,
+    pub limit_usd: f64,
+    #[serde(default)]
+    pub action: BudgetAction,
+    #[serde(default)]
+    pub downgrade_model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateBudget {
+    pub period: Option<BudgetPeriod>,
+    pub limit_usd: Option<f64>,
+    pub action: Option<BudgetAction>,
+    pub downgrade_model: Option<String>,
+}
+
+/// Stats block of a report; a report renders its sections in the order listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSection {
+    Overview,
+    Models,
+    Errors,
+    Agents,
+    Feedback,
+}
+
+impl ReportSection {
+    pub const ALL: [ReportSection; 5] = [
+        Self::Overview,
+        Self::Models,
+        Self::Errors,
+        Self::Agents,
+        Self::Feedback,
+    ];
+}
+
+/// Markup a report is rendered in. Slack destinations always get mrkdwn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Mrkdwn,
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mrkdwn => "mrkdwn",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "mrkdwn" => Some(Self::Mrkdwn),
+            "markdown" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Where a rendered report is delivered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReportDestination {
+    /// Posted with the bot token (`SLACK_BOT_TOKEN`)
+    Slack { channel: String },
+    /// Receives a JSON POST with the rendered report in `text`
+    Webhook { url: String },
+}
+
+impl ReportDestination {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Slack { channel } => format!("slack:{}", channel),
+            Self::Webhook { url } => format!("webhook:{}", url),
+        }
+    }
+}
+
+/// Stats report rendered and delivered on a cron schedule
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub id: String,
+    pub name: String,
+    /// Project the stats are limited to; all projects when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Five-field cron expression
+    pub schedule: String,
+    /// IANA timezone of the schedule and stats; the configured one when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    pub period: Period,
+    pub sections: Vec<ReportSection>,
+    pub format: ReportFormat,
+    pub destinations: Vec<ReportDestination>,
+    pub enabled: bool,
+    /// Next scheduled run; unset while disabled
+    pub next_run_at: Option<i64>,
+    /// Last scheduled run; manual runs only show up in the run history
+    pub last_run_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateReport {
+    pub name: String,
+    #[serde(default)]
+    pub project: Option<String>,
+    pub schedule: String,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub period: Period,
+    #[serde(default = "all_sections")]
+    pub sections: Vec<ReportSection>,
+    #[serde(default)]
+    pub format: ReportFormat,
+    #[serde(default)]
+    pub destinations: Vec<ReportDestination>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn all_sections() -> Vec<ReportSection> {
+    ReportSection::ALL.to_vec()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateReport {
+    pub name: Option<String>,
+    pub project: Option<String>,
+    pub schedule: Option<String>,
+    pub timezone: Option<String>,
+    pub period: Option<Period>,
+    pub sections: Option<Vec<ReportSection>>,
+    pub format: Option<ReportFormat>,
+    pub destinations: Option<Vec<ReportDestination>>,
+    pub enabled: Option<bool>,
+}
+
+/// Outcome of sending a report to one destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDelivery {
+    pub destination: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One rendering of a report, scheduled or triggered by hand
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRun {
+    pub id: i64,
+    pub report_id: String,
+    /// schedule or manual
+    pub trigger: String,
+    /// success, partial (some deliveries failed) or failed
+    pub status: String,
+    pub period_start: i64,
+    pub period_end: i64,
+    pub content: String,
+    pub deliveries: Vec<ReportDelivery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+}
+
+/// What an anomaly alert watches: a whole project, one agent or model in it,
+/// or an n8n workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertScope {
+    Project,
+    Agent,
+    Model,
+    Workflow,
+}
+
+impl AlertScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Project => "project",
+            Self::Agent => "agent",
+            Self::Model => "model",
+            Self::Workflow => "workflow",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "project" => Some(Self::Project),
+            "agent" => Some(Self::Agent),
+            "model" => Some(Self::Model),
+            "workflow" => Some(Self::Workflow),
+            _ => None,
+        }
+    }
+}
+
+/// Metric an anomaly alert fired on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Spend in the window, against the baseline's windows of the same length
+    Cost,
+    /// p95 duration in milliseconds
+    Latency,
+    /// Failed executions, in percent
+    ErrorRate,
+    /// Positive share of rated executions, in percent
+    Satisfaction,
+    /// Successful workflow runs, in percent, once the workflow is no longer
+    /// healthy
+    Health,
+}
+
+impl AlertMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cost => "cost",
+            Self::Latency => "latency",
+            Self::ErrorRate => "error_rate",
+            Self::Satisfaction => "satisfaction",
+            Self::Health => "health",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "cost" => Some(Self::Cost),
+            "latency" => Some(Self::Latency),
+            "error_rate" => Some(Self::ErrorRate),
+            "satisfaction" => Some(Self::Satisfaction),
+            "health" => Some(Self::Health),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Open,
+    Resolved,
+}
+
+impl AlertStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Resolved => "resolved",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(Self::Open),
+            "resolved" => Some(Self::Resolved),
+            _ => None,
+        }
+    }
+}
+
+/// Incident opened when a metric leaves its baseline, resolved once it
+/// returns
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub id: i64,
+    pub project: String,
+    pub scope: AlertScope,
+    /// Agent or model name; unset for project alerts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub metric: AlertMetric,
+    pub status: AlertStatus,
+    /// Baseline and current value as of the latest check, in the metric's unit
+    pub baseline: f64,
+    pub value: f64,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlertFilter {
+    pub status: Option<AlertStatus>,
+    pub project: Option<String>,
+    pub scope: Option<AlertScope>,
+    pub metric: Option<AlertMetric>,
+}
+
+/// Whether an approved suggestion is added to an agent's examples or counterexamples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Example,
+    Counterexample,
+}
+
+impl SuggestionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionKind::Example => "example",
+            SuggestionKind::Counterexample => "counterexample",
+        }
+    }
+}
+
+/// Routing example proposed from execution feedback, waiting for review
+#[derive(Debug, Clone, Serialize)]
+pub struct ExampleSuggestion {
+    pub id: String,
+    pub project_id: String,
+    pub agent: String,
+    pub text: String,
+    pub kind: SuggestionKind,
+    pub execution_id: Option<String>,
+    /// pending, approved or rejected
+    pub status: String,
+    pub created_at: i64,
+    pub reviewed_at: Option<i64>,
+}
+
+/// Agent an execution should have been routed to
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingCorrection {
+    pub execution_id: String,
+    pub agent: String,
+    pub corrected_by: Option<String>,
+    pub created_at: i64,
+}
+
+/// Persisted embedding of one agent example for semantic routing
+#[derive(Debug, Clone)]
+pub struct StoredEmbedding {
+    pub project_id: String,
+    pub agent_id: String,
+    pub example: String,
+    pub vector: Vec<f32>,
+    /// Embedding of a counterexample
+    pub negative: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -328,6 +1204,7 @@ pub struct FilterOptions {
     pub agents: Vec<String>,
     pub requesters: Vec<String>,
     pub channels: Vec<String>,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
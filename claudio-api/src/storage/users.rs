@@ -1,6 +1,6 @@
 use anyhow::Result;
-use rusqlite::params;
 
+use super::backend::params;
 use super::core::Storage;
 use super::types::{ConversationItem, UserContext, UserListItem};
 
@@ -14,10 +14,12 @@ const RECENT_CONVERSATIONS_LIMIT: usize = 10;
 impl Storage {
     pub fn get_user_rules(&self, user_id: &str) -> Result<Vec<String>> {
         let conn = self.conn()?;
-        let mut stmt =
-            conn.prepare("SELECT rule FROM user_rules WHERE user_id = ?1 ORDER BY created_at")?;
-        let rules = stmt
-            .query_map([user_id], |row| row.get(0))?
+        let rules = conn
+            .query_map(
+                "SELECT rule FROM user_rules WHERE user_id = ?1 ORDER BY created_at",
+                params![user_id],
+                |row| row.get(0),
+            )?
             .filter_map(|r| r.ok())
             .collect();
         Ok(rules)
@@ -26,7 +28,7 @@ impl Storage {
     pub fn add_user_rule(&self, user_id: &str, rule: &str) -> Result<bool> {
         let conn = self.conn()?;
         let inserted = conn.execute(
-            "INSERT OR IGNORE INTO user_rules (user_id, rule) VALUES (?1, ?2)",
+            "INSERT INTO user_rules (user_id, rule) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
             params![user_id, rule],
         )?;
         Ok(inserted > 0)
@@ -62,24 +64,26 @@ impl Storage {
         let now = chrono::Utc::now().timestamp();
 
         let rules: Vec<String> = {
-            let mut stmt =
-                conn.prepare("SELECT rule FROM user_rules WHERE user_id = ?1 ORDER BY created_at")?;
-            stmt.query_map([user_id], |row| row.get(0))?
-                .filter_map(|r| r.ok())
-                .collect()
+            conn.query_map(
+                "SELECT rule FROM user_rules WHERE user_id = ?1 ORDER BY created_at",
+                params![user_id],
+                |row| row.get(0),
+            )?
+            .filter_map(|r| r.ok())
+            .collect()
         };
 
         let (summary, last_summarized_at, locked_at, lock_id) = conn
             .query_row(
                 "SELECT summary, last_summarized_at, summary_locked_at, summary_lock_id
                  FROM user_contexts WHERE user_id = ?1",
-                [user_id],
+                params![user_id],
                 |row| {
                     Ok((
-                        row.get::<_, Option<String>>(0)?,
-                        row.get::<_, Option<i64>>(1)?,
-                        row.get::<_, Option<i64>>(2)?,
-                        row.get::<_, Option<String>>(3)?,
+                        row.get::<Option<String>>(0)?,
+                        row.get::<Option<i64>>(1)?,
+                        row.get::<Option<i64>>(2)?,
+                        row.get::<Option<String>>(3)?,
                     ))
                 },
             )
@@ -88,14 +92,14 @@ impl Storage {
         let total_conversation_count: i64 = conn
             .query_row(
                 "SELECT COUNT(*) FROM executions
-                 WHERE requester = ?1 AND (?2 IS NULL OR created_at > ?2)",
+                 WHERE requester = ?1 AND (CAST(?2 AS BIGINT) IS NULL OR created_at > ?2)",
                 params![user_id, last_summarized_at],
                 |row| row.get(0),
             )
             .unwrap_or(0);
 
         let recent_conversations: Vec<ConversationItem> = {
-            let mut stmt = conn.prepare(
+            conn.query_map(
                 "SELECT e.id, e.user_message, e.response, e.created_at,
                         EXISTS(SELECT 1 FROM reactions r
                                WHERE r.execution_id = e.id
@@ -104,19 +108,16 @@ impl Storage {
                                AND r.reaction IN ('thumbsdown', '-1')) as has_neg
                  FROM executions e
                  WHERE e.requester = ?1
-                 AND (?2 IS NULL OR e.created_at > ?2)
+                 AND (CAST(?2 AS BIGINT) IS NULL OR e.created_at > ?2)
                  ORDER BY e.created_at DESC
                  LIMIT ?3",
-            )?;
-
-            stmt.query_map(
                 params![
                     user_id,
                     last_summarized_at,
                     RECENT_CONVERSATIONS_LIMIT as i64
                 ],
                 |row| {
-                    let has_neg: bool = row.get::<_, i64>(4)? == 1;
+                    let has_neg: bool = row.get::<i64>(4)? == 1;
                     let response: String = row.get(2)?;
                     Ok(ConversationItem {
                         id: row.get(0)?,
@@ -203,7 +204,8 @@ impl Storage {
              ON CONFLICT(user_id) DO UPDATE SET
                 summary_locked_at = ?2,
                 summary_lock_id = ?3
-             WHERE summary_locked_at IS NULL OR summary_locked_at < ?4",
+             WHERE user_contexts.summary_locked_at IS NULL
+                OR user_contexts.summary_locked_at < ?4",
             params![user_id, now, lock_id, stale_threshold],
         )?;
 
@@ -222,8 +224,9 @@ impl Storage {
 
     pub fn list_users_with_context(&self) -> Result<Vec<UserListItem>> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare(
-            "WITH all_users AS (
+        let users = conn
+            .query_map(
+                "WITH all_users AS (
                 SELECT DISTINCT requester as user_id FROM executions WHERE requester IS NOT NULL
                 UNION
                 SELECT user_id FROM user_rules
@@ -256,20 +259,19 @@ impl Storage {
             ) e ON u.user_id = e.requester
             LEFT JOIN user_sources s ON u.user_id = s.requester AND s.rn = 1
             ORDER BY e.last_activity DESC NULLS LAST",
-        )?;
-
-        let users = stmt
-            .query_map([], |row| {
-                Ok(UserListItem {
-                    user_id: row.get(0)?,
-                    rule_count: row.get(1)?,
-                    has_summary: row.get::<_, i64>(2)? == 1,
-                    last_activity: row.get(3)?,
-                    request_count: row.get(4)?,
-                    total_cost_usd: row.get(5)?,
-                    primary_source: row.get(6)?,
-                })
-            })?
+                params![],
+                |row| {
+                    Ok(UserListItem {
+                        user_id: row.get(0)?,
+                        rule_count: row.get(1)?,
+                        has_summary: row.get::<i64>(2)? == 1,
+                        last_activity: row.get(3)?,
+                        request_count: row.get(4)?,
+                        total_cost_usd: row.get(5)?,
+                        primary_source: row.get(6)?,
+                    })
+                },
+            )?
             .filter_map(|r| r.ok())
             .collect();
 
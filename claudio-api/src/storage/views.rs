@@ -0,0 +1,84 @@
+use anyhow::Result;
+
+use super::backend::{Row, params};
+use super::core::Storage;
+use super::types::{SaveView, SavedView};
+
+const VIEW_COLUMNS: &str = "id, user_id, name, filter, created_at, updated_at";
+
+fn map_view_row(row: &Row) -> Result<SavedView> {
+    let filter: String = row.get(3)?;
+    Ok(SavedView {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        filter: serde_json::from_str(&filter).unwrap_or_default(),
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+impl Storage {
+    pub fn list_views(&self, user_id: &str) -> Result<Vec<SavedView>> {
+        let conn = self.conn()?;
+        let views = conn
+            .query_map(
+                &format!(
+                    "SELECT {} FROM saved_views WHERE user_id = ?1 ORDER BY name",
+                    VIEW_COLUMNS
+                ),
+                params![user_id],
+                map_view_row,
+            )?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(views)
+    }
+
+    pub fn get_view(&self, id: &str) -> Result<Option<SavedView>> {
+        let conn = self.conn()?;
+        let view = conn
+            .query_row(
+                &format!("SELECT {} FROM saved_views WHERE id = ?1", VIEW_COLUMNS),
+                params![id],
+                map_view_row,
+            )
+            .ok();
+        Ok(view)
+    }
+
+    /// Saves a view under `input.name`, replacing the filter of an existing
+    /// view with the same name.
+    pub fn save_view(&self, user_id: &str, input: &SaveView) -> Result<SavedView> {
+        let conn = self.conn()?;
+        let now = chrono::Utc::now().timestamp();
+        conn.query_row(
+            &format!(
+                "INSERT INTO saved_views (id, user_id, name, filter, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                 ON CONFLICT(user_id, name) DO UPDATE SET
+                    filter = excluded.filter,
+                    updated_at = excluded.updated_at
+                 RETURNING {}",
+                VIEW_COLUMNS
+            ),
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                user_id,
+                input.name.trim(),
+                serde_json::to_string(&input.filter)?,
+                now
+            ],
+            map_view_row,
+        )
+    }
+
+    pub fn delete_view(&self, user_id: &str, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute(
+            "DELETE FROM saved_views WHERE id = ?1 AND user_id = ?2",
+            params![id, user_id],
+        )?;
+        Ok(deleted > 0)
+    }
+}
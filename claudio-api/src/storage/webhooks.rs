@@ -0,0 +1,25 @@
+use anyhow::Result;
+
+use super::backend::params;
+use super::core::Storage;
+use super::types::WebhookDelivery;
+
+impl Storage {
+    pub fn record_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO webhook_deliveries (run_id, endpoint, event_type, channel, status_code, error, duration_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                delivery.run_id,
+                delivery.endpoint,
+                delivery.event_type,
+                delivery.channel,
+                delivery.status_code,
+                delivery.error,
+                delivery.duration_ms,
+                delivery.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+}
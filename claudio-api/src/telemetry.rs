@@ -0,0 +1,184 @@
+//! Distributed tracing. `tracing` spans are bridged to OpenTelemetry and,
+//! when an OTLP endpoint is configured, exported over OTLP/HTTP. W3C
+//! `traceparent` headers carry a trace across the HTTP API, the n8n webhooks
+//! the Slack bridge calls and budget alerts.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::TelemetryConfig;
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builds the tracer provider. Spans always get trace ids, so propagation
+/// and the trace id in execution metadata work without a collector, but
+/// they only leave the process when an OTLP endpoint is configured.
+pub fn init(config: &TelemetryConfig) -> Result<SdkTracerProvider> {
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+    let mut builder = SdkTracerProvider::builder().with_resource(resource);
+    if let Some(ref endpoint) = config.otlp_endpoint {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .with_timeout(EXPORT_TIMEOUT)
+            .build()?;
+        builder = builder.with_batch_exporter(exporter);
+    }
+    Ok(builder.build())
+}
+
+/// Subscriber layer recording `tracing` spans with `provider`.
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Continues the trace of an incoming request's `traceparent` header in
+/// `span`. Requests without one start a new trace.
+pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    if context.span().span_context().is_valid() {
+        let _ = span.set_parent(context);
+    }
+}
+
+/// `traceparent` header of the current span, for outgoing requests.
+pub fn trace_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    TraceContextPropagator::new().inject_context(
+        &tracing::Span::current().context(),
+        &mut HeaderInjector(&mut headers),
+    );
+    headers
+}
+
+/// Trace id of the current span, when there is one.
+pub fn current_trace_id() -> Option<String> {
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Bytes, routing::post};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exports_continued_trace_to_collector() {
+        // Collector stub accepting OTLP/HTTP protobuf
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Bytes>();
+        let app = Router::new().route(
+            "/v1/traces",
+            post(move |body: Bytes| {
+                let _ = tx.send(body);
+                async {}
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let provider = init(&TelemetryConfig {
+            otlp_endpoint: Some(format!("http://{}/", addr)),
+            service_name: "claudio-test".into(),
+        })
+        .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+
+        let mut incoming = HeaderMap::new();
+        incoming.insert(
+            "traceparent",
+            format!("00-{}-00f067aa0ba902b7-01", TRACE_ID)
+                .parse()
+                .unwrap(),
+        );
+        let (trace_id, outgoing) = tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("test.request");
+            set_remote_parent(&span, &incoming);
+            let _entered = span.enter();
+            let _child = tracing::info_span!("test.webhook").entered();
+            (current_trace_id(), trace_headers())
+        });
+
+        assert_eq!(trace_id.as_deref(), Some(TRACE_ID));
+        let traceparent = outgoing.get("traceparent").unwrap().to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{}-", TRACE_ID)));
+        assert!(!traceparent.contains("00f067aa0ba902b7"));
+
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+        let body = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let contains = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"test.request"));
+        assert!(contains(b"test.webhook"));
+        assert!(contains(b"claudio-test"));
+    }
+
+    #[test]
+    fn test_trace_ids_without_exporter() {
+        let provider = init(&TelemetryConfig {
+            otlp_endpoint: None,
+            service_name: "claudio-test".into(),
+        })
+        .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(current_trace_id().is_none());
+            let _span = tracing::info_span!("test.request").entered();
+            assert!(current_trace_id().is_some());
+            assert!(trace_headers().contains_key("traceparent"));
+        });
+    }
+}
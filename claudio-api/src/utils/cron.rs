@@ -0,0 +1,229 @@
+//! Five-field cron expressions (`minute hour day-of-month month
+//! day-of-week`) evaluated in an IANA timezone.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+/// Days searched for the next run: eight years, so February 29 is always found
+const SEARCH_DAYS: u64 = 366 * 8;
+
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Parsed cron schedule. Fields accept `*`, values, `a-b` ranges, `/step`
+/// and comma-separated lists; months and weekdays also take names (`jan`,
+/// `mon`) and Sunday is 0 or 7. As in Vixie cron, when both day fields are
+/// restricted a day matching either runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32> {
+    let lower = value.to_ascii_lowercase();
+    if let Some(i) = names.iter().position(|n| *n == lower) {
+        return Ok(min + i as u32);
+    }
+    let n: u32 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid value '{}'", value))?;
+    if n < min || n > max {
+        bail!("{} is outside {}-{}", n, min, max);
+    }
+    Ok(n)
+}
+
+/// Bitset of the values a field matches.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .ok_or_else(|| anyhow!("invalid step '{}'", step))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (
+                    parse_value(a, min, max, names)?,
+                    parse_value(b, min, max, names)?,
+                ),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (parse_value(range, min, max, names)?, max),
+                None => {
+                    let n = parse_value(range, min, max, names)?;
+                    (n, n)
+                }
+            },
+        };
+        if start > end {
+            bail!("invalid range '{}'", range);
+        }
+        for n in (start..=end).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("expected 5 fields, got {}", fields.len());
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, WEEKDAYS)?;
+        // Sunday is both 0 and 7
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[])?,
+            hours: parse_field(hour, 0, 23, &[])?,
+            days: parse_field(day, 1, 31, &[])?,
+            months: parse_field(month, 1, 12, MONTHS)?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// First run strictly after `after`, with wall-clock times in `tz`.
+    /// Times skipped by a DST change do not run; repeated ones run once.
+    pub fn next_after(&self, after: i64, tz: Tz) -> Option<i64> {
+        let start = DateTime::from_timestamp(after, 0)?
+            .with_timezone(&tz)
+            .date_naive();
+        (0..SEARCH_DAYS)
+            .filter_map(|offset| start.checked_add_days(Days::new(offset)))
+            .filter(|&date| self.matches_date(date))
+            .find_map(|date| {
+                (0..24u32)
+                    .filter(|h| self.hours & (1 << h) != 0)
+                    .flat_map(|h| {
+                        (0..60u32)
+                            .filter(|m| self.minutes & (1 << m) != 0)
+                            .map(move |m| (h, m))
+                    })
+                    .filter_map(|(h, m)| {
+                        let time = NaiveTime::from_hms_opt(h, m, 0)?;
+                        tz.from_local_datetime(&date.and_time(time)).earliest()
+                    })
+                    .map(|dt| dt.timestamp())
+                    .find(|&ts| ts > after)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_run_in_timezone() {
+        let seoul: Tz = "Asia/Seoul".parse().unwrap();
+        let local = |y, m, d, h, min| {
+            seoul
+                .with_ymd_and_hms(y, m, d, h, min, 0)
+                .unwrap()
+                .timestamp()
+        };
+        // Weekdays at 10:00, asked on Friday 2025-01-17 at 10:00 sharp
+        let weekdays = Schedule::parse("0 10 * * mon-fri").unwrap();
+        let friday = local(2025, 1, 17, 10, 0);
+        assert_eq!(weekdays.next_after(friday - 1, seoul), Some(friday));
+        assert_eq!(
+            weekdays.next_after(friday, seoul),
+            Some(local(2025, 1, 20, 10, 0))
+        );
+
+        let quarter = Schedule::parse("*/15 9-10 * * *").unwrap();
+        assert_eq!(
+            quarter.next_after(local(2025, 1, 17, 10, 50), seoul),
+            Some(local(2025, 1, 18, 9, 0))
+        );
+        // Either day field matches when both are restricted
+        let either = Schedule::parse("0 0 1 * 0").unwrap();
+        assert_eq!(
+            either.next_after(local(2025, 1, 2, 0, 0), seoul),
+            Some(local(2025, 1, 5, 0, 0))
+        );
+        let leap = Schedule::parse("0 0 29 feb *").unwrap();
+        assert_eq!(
+            leap.next_after(local(2025, 1, 1, 0, 0), seoul),
+            Some(local(2028, 2, 29, 0, 0))
+        );
+        assert_eq!(
+            Schedule::parse("@weekly").unwrap(),
+            Schedule::parse("0 0 * * 7").unwrap()
+        );
+
+        // 02:30 does not exist when New York springs forward
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        let nightly = Schedule::parse("30 2 * * *").unwrap();
+        let before = new_york
+            .with_ymd_and_hms(2025, 3, 8, 12, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            nightly.next_after(before, new_york),
+            Some(
+                new_york
+                    .with_ymd_and_hms(2025, 3, 10, 2, 30, 0)
+                    .unwrap()
+                    .timestamp()
+            )
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_expressions() {
+        for expression in [
+            "",
+            "0 10 * *",
+            "60 * * * *",
+            "0 0 0 * *",
+            "*/0 * * * *",
+            "0 0 * * fri-mon",
+            "0 0 * xyz *",
+        ] {
+            assert!(Schedule::parse(expression).is_err(), "{}", expression);
+        }
+    }
+}
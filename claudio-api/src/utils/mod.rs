@@ -1,3 +1,5 @@
+mod cron;
 mod mrkdwn;
 
+pub use cron::Schedule;
 pub use mrkdwn::to_mrkdwn;
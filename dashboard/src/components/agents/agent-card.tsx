@@ -6,6 +6,7 @@ import { Bot, Braces, Clock, Copy, History, Pencil, Trash2 } from 'lucide-react'
 import { Badge } from '@/components/ui/badge';
 import { Button } from '@/components/ui/button';
 import type { Agent } from '@/types/api';
+import { keywordLabel, keywordPattern } from '@/lib/utils';
 
 interface AgentCardProps {
   agent: Agent;
@@ -111,8 +112,8 @@ export function AgentCard({ agent, onEdit, onDelete, onDuplicate }: AgentCardPro
           {agent.keywords.length > 0 && (
             <div className="flex flex-wrap gap-1 max-h-[52px] overflow-hidden">
               {visibleKeywords.map((keyword) => (
-                <span key={keyword} className="text-xs px-1.5 py-0.5 bg-muted rounded truncate max-w-[120px]">
-                  {keyword}
+                <span key={keywordPattern(keyword)} className="text-xs px-1.5 py-0.5 bg-muted rounded truncate max-w-[120px]">
+                  {keywordLabel(keyword)}
                 </span>
               ))}
               {remainingCount > 0 && (
@@ -26,7 +26,8 @@ import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import { Checkbox } from '@/components/ui/checkbox';
 import { MarkdownEditor } from '@/components/ui/markdown-editor';
 import { SchemaEditor } from '@/components/agents/schema-editor';
-import type { Agent, CreateAgent, UpdateAgent, JsonSchema } from '@/types/api';
+import type { Agent, CreateAgent, UpdateAgent, JsonSchema, Keyword } from '@/types/api';
+import { keywordLabel, keywordPattern } from '@/lib/utils';
 
 interface AgentEditorProps {
   agent: Agent | null;
@@ -107,7 +108,7 @@ function AgentEditorContent({
   const [staticResponse, setStaticResponse] = useState(initialState.staticResponse);
   const [workingDir, setWorkingDir] = useState(initialState.workingDir);
   const [instruction, setInstruction] = useState(initialState.instruction);
-  const [keywords, setKeywords] = useState<string[]>(initialState.keywords);
+  const [keywords, setKeywords] = useState<Keyword[]>(initialState.keywords);
   const [examples, setExamples] = useState<string[]>(initialState.examples);
   const [tools, setTools] = useState<string[]>(initialState.tools);
   const [outputSchema, setOutputSchema] = useState<JsonSchema | null>(initialState.outputSchema);
@@ -163,6 +164,15 @@ function AgentEditorContent({
     }
   };
 
+  // Weighted keywords set through the API are kept as-is; new ones are plain
+  const addKeyword = () => {
+    const trimmed = keywordInput.trim();
+    if (trimmed && !keywords.some((k) => keywordPattern(k) === trimmed)) {
+      setKeywords([...keywords, trimmed]);
+      setKeywordInput('');
+    }
+  };
+
   const removeItem = (list: string[], setList: (v: string[]) => void, item: string) => {
     setList(list.filter((i) => i !== item));
   };
@@ -342,7 +352,7 @@ function AgentEditorContent({
                   onKeyDown={(e) =>
                     e.key === 'Enter' &&
                     (e.preventDefault(),
-                    addItem(keywords, setKeywords, keywordInput, setKeywordInput))
+                    addKeyword())
                   }
                   placeholder="review, /review, code review..."
                   className="flex-1"
@@ -352,7 +362,7 @@ function AgentEditorContent({
                   type="button"
                   variant="outline"
                   size="sm"
-                  onClick={() => addItem(keywords, setKeywords, keywordInput, setKeywordInput)}
+                  onClick={addKeyword}
                   aria-label="Add keyword"
                 >
                   <Plus className="h-4 w-4" />
@@ -362,8 +372,11 @@ function AgentEditorContent({
               {keywords.length > 0 && (
                 <div className="flex flex-wrap gap-1.5 p-3 bg-muted/50 rounded-lg max-h-28 overflow-y-auto">
                   {keywords.map((kw) => (
-                    <RemovableBadge key={kw} onRemove={() => removeItem(keywords, setKeywords, kw)}>
-                      {kw}
+                    <RemovableBadge
+                      key={keywordPattern(kw)}
+                      onRemove={() => setKeywords(keywords.filter((k) => k !== kw))}
+                    >
+                      {keywordLabel(kw)}
                     </RemovableBadge>
                   ))}
                 </div>
@@ -10,6 +10,7 @@ import type {
   WorkflowsResponse,
   SourcesResponse,
   RequestersResponse,
+  AgentsResponse,
   RecentExecutionsResponse,
   ExecutionListResponse,
   ExecutionDetailResponse,
@@ -129,6 +130,20 @@ export function useRequesterStats(
   });
 }
 
+export function useAgentStats(period: Period = '30d', project?: string, source?: string) {
+  const params = new URLSearchParams();
+  params.set('period', period);
+  if (project) params.set('project', project);
+  if (source) params.set('source', source);
+
+  return useQuery<AgentsResponse>({
+    queryKey: ['stats', 'agents', period, project, source],
+    queryFn: () => api.get(`/v1/stats/agents?${params}`),
+    refetchInterval: 60 * 1000,
+    staleTime: 30 * 1000,
+  });
+}
+
 export function useRecentExecutions(limit: number = 10) {
   const params = new URLSearchParams();
   params.set('limit', limit.toString());
@@ -1,5 +1,6 @@
 import { clsx, type ClassValue } from 'clsx';
 import { twMerge } from 'tailwind-merge';
+import type { Keyword } from '@/types/api';
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
@@ -88,3 +89,13 @@ export function formatModelName(model: string | null | undefined): string {
   if (!model) return '';
   return model.replace('claude-', '').replace(/-\d{8}$/, '');
 }
+
+export function keywordPattern(keyword: Keyword): string {
+  return typeof keyword === 'string' ? keyword : keyword.pattern;
+}
+
+export function keywordLabel(keyword: Keyword): string {
+  if (typeof keyword === 'string') return keyword;
+  const weight = keyword.weight !== undefined && keyword.weight !== 1 ? ` ×${keyword.weight}` : '';
+  return `${keyword.pattern}${keyword.whole_word ? ' (word)' : ''}${weight}`;
+}
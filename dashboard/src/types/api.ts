@@ -58,6 +58,7 @@ export interface TimeSeriesResponse {
   granularity: string;
   from: string;
   to: string;
+  timezone: string;
   data: TimeSeriesPoint[];
 }
 
@@ -147,6 +148,35 @@ export interface RequestersResponse {
   requesters: RequesterStats[];
 }
 
+export interface RoutingMethodStats {
+  method: string;
+  count: number;
+  percentage: number;
+}
+
+export interface AgentStats {
+  agent: string;
+  requests: number;
+  percentage: number;
+  cost_usd: number;
+  cost_per_request: number;
+  success_rate: number;
+  failure_rate: number;
+  satisfaction_rate: number | null;
+  avg_duration_ms: number;
+  p50_duration_ms: number | null;
+  p95_duration_ms: number | null;
+  routing: RoutingMethodStats[];
+  comparison: ComparisonStats;
+}
+
+export interface AgentsResponse {
+  period: string;
+  period_start: string;
+  period_end: string;
+  agents: AgentStats[];
+}
+
 export interface RecentExecution {
   id: string;
   project: string;
@@ -211,6 +241,8 @@ export interface ExecutionDetail {
   duration_api_ms: number | null;
   feedback: number | null;
   metadata: string | null;
+  experiment_id?: string;
+  variant?: string;
   created_at: number;
 }
 
@@ -227,7 +259,19 @@ export interface FilterOptions {
   channels: string[];
 }
 
-export type Period = '1h' | '24h' | '7d' | '30d' | '90d' | 'all';
+export type Period =
+  | '1h'
+  | '24h'
+  | '7d'
+  | '30d'
+  | '90d'
+  | 'all'
+  | 'today'
+  | 'yesterday'
+  | 'this_week'
+  | 'last_week'
+  | 'this_month'
+  | 'last_month';
 export type Granularity = 'auto' | 'hour' | 'day' | 'week';
 
 export interface AgentClassifyStats {
@@ -251,6 +295,8 @@ export interface ClassifyStatsResponse {
   avg_duration_ms: number;
   agents: AgentClassifyStats[];
   methods: MethodClassifyStats[];
+  cache_hits: number;
+  cache_saved_ms: number;
 }
 
 export interface ClassifyLogEntry {
@@ -266,6 +312,7 @@ export interface ClassifyLogEntry {
   project: string | null;
   source: string | null;
   requester: string | null;
+  llm_error: string | null;
   created_at: string;
 }
 
@@ -290,12 +337,22 @@ export interface Project {
   classify_model: string;
   classify_timeout: number;
   rate_limit_rpm: number;
+  clarify_threshold: number;
   created_at: number;
   updated_at: number;
 }
 
 export type JsonSchema = Record<string, unknown>;
 
+/** Plain string (weight 1, substring match, `/.../` regex) or a weighted rule */
+export type Keyword =
+  | string
+  | {
+      pattern: string;
+      weight?: number;
+      whole_word?: boolean;
+    };
+
 export interface Agent {
   id: string;
   project_id: string;
@@ -303,8 +360,10 @@ export interface Agent {
   description: string;
   model: string;
   priority: number;
-  keywords: string[];
+  keywords: Keyword[];
+  negative_keywords: Keyword[];
   examples: string[];
+  counterexamples: string[];
   instruction?: string;
   tools?: string[];
   output_schema?: JsonSchema;
@@ -326,6 +385,7 @@ export interface CreateProject {
   classify_model?: string;
   classify_timeout?: number;
   rate_limit_rpm?: number;
+  clarify_threshold?: number;
 }
 
 export interface UpdateProject {
@@ -339,6 +399,7 @@ export interface UpdateProject {
   classify_model?: string;
   classify_timeout?: number;
   rate_limit_rpm?: number;
+  clarify_threshold?: number;
 }
 
 export interface CreateAgent {
@@ -346,8 +407,10 @@ export interface CreateAgent {
   description: string;
   model?: string;
   priority?: number;
-  keywords?: string[];
+  keywords?: Keyword[];
+  negative_keywords?: Keyword[];
   examples?: string[];
+  counterexamples?: string[];
   instruction?: string;
   tools?: string[];
   output_schema?: JsonSchema;
@@ -361,8 +424,10 @@ export interface UpdateAgent {
   description?: string;
   model?: string;
   priority?: number;
-  keywords?: string[];
+  keywords?: Keyword[];
+  negative_keywords?: Keyword[];
   examples?: string[];
+  counterexamples?: string[];
   instruction?: string;
   tools?: string[];
   output_schema?: JsonSchema;
@@ -384,3 +449,45 @@ export interface ClassifyTestResponse {
   matched_keyword?: string;
   duration_ms: number;
 }
+
+export interface ExperimentVariant {
+  name: string;
+  weight: number;
+  instruction?: string;
+  model?: string;
+  tools?: string[];
+}
+
+export interface Experiment {
+  id: string;
+  project_id: string;
+  agent: string;
+  name: string;
+  status: 'running' | 'stopped';
+  variants: ExperimentVariant[];
+  created_at: number;
+  updated_at: number;
+}
+
+export interface Budget {
+  id: string;
+  project_id: string;
+  scope: 'project' | 'agent' | 'requester';
+  target?: string;
+  period: 'daily' | 'weekly' | 'monthly';
+  limit_usd: number;
+  action: 'reject' | 'downgrade' | 'warn';
+  downgrade_model?: string;
+  created_at: number;
+  updated_at: number;
+}
+
+export interface BudgetStatus {
+  budget: Budget;
+  period_start: number;
+  period_end: number;
+  spent_usd: number;
+  remaining_usd: number;
+  usage_percent: number;
+  exceeded: boolean;
+}